@@ -0,0 +1,165 @@
+use async_trait::async_trait;
+use cqrs_es::{EventEnvelope, Query};
+use sha2::{Digest, Sha256};
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::domain::aggregate::ChatRoom;
+use crate::domain::events::ChatEvent;
+
+/// Where attachment bytes are kept, by attachment id. The event store only
+/// records their metadata.
+#[async_trait]
+pub trait BlobStore: Send + Sync {
+    async fn put(&self, blob_id: &Uuid, bytes: &[u8]) -> io::Result<()>;
+
+    /// Fails with `io::ErrorKind::NotFound` for an unknown blob.
+    async fn get(&self, blob_id: &Uuid) -> io::Result<Vec<u8>>;
+
+    /// Removing an unknown blob is not an error.
+    async fn delete(&self, blob_id: &Uuid) -> io::Result<()>;
+}
+
+/// Hex-encoded SHA-256 of a blob, as recorded with its attachment.
+pub fn checksum(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Keeps each blob as a file named after its id in one directory.
+#[derive(Debug, Clone)]
+pub struct LocalBlobStore {
+    root: PathBuf,
+}
+
+impl LocalBlobStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Stores blobs under `ATTACHMENTS_DIR`, or `./attachments` when it is unset.
+    pub fn from_env() -> Self {
+        Self::new(std::env::var("ATTACHMENTS_DIR").unwrap_or_else(|_| "attachments".to_string()))
+    }
+
+    fn path(&self, blob_id: &Uuid) -> PathBuf {
+        self.root.join(blob_id.to_string())
+    }
+}
+
+#[async_trait]
+impl BlobStore for LocalBlobStore {
+    async fn put(&self, blob_id: &Uuid, bytes: &[u8]) -> io::Result<()> {
+        tokio::fs::create_dir_all(&self.root).await?;
+        // Written under a temporary name first so a reader never sees half a file.
+        let partial = self.root.join(format!("{}.partial", blob_id));
+        tokio::fs::write(&partial, bytes).await?;
+        tokio::fs::rename(&partial, self.path(blob_id)).await
+    }
+
+    async fn get(&self, blob_id: &Uuid) -> io::Result<Vec<u8>> {
+        tokio::fs::read(self.path(blob_id)).await
+    }
+
+    async fn delete(&self, blob_id: &Uuid) -> io::Result<()> {
+        match tokio::fs::remove_file(self.path(blob_id)).await {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+}
+
+/// Deletes the bytes of attachments that can no longer be downloaded: those of
+/// expired messages, and uploads discarded before they were ever sent.
+#[derive(Clone)]
+pub struct BlobReaper {
+    blobs: Arc<dyn BlobStore>,
+}
+
+impl BlobReaper {
+    pub fn new(blobs: Arc<dyn BlobStore>) -> Self {
+        Self { blobs }
+    }
+}
+
+#[async_trait]
+impl Query<ChatRoom> for BlobReaper {
+    async fn dispatch(&self, _aggregate_id: &str, events: &[EventEnvelope<ChatRoom>]) {
+        for event_envelope in events {
+            let attachment_ids = match &event_envelope.payload {
+                ChatEvent::MessageExpired { attachment_ids, .. } => attachment_ids.clone(),
+                ChatEvent::AttachmentDiscarded { attachment_id, .. } => vec![*attachment_id],
+                _ => continue,
+            };
+            for attachment_id in attachment_ids {
+                if let Err(e) = self.blobs.delete(&attachment_id).await {
+                    log::warn!("Failed to delete the bytes of attachment {}: {}", attachment_id, e);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_local_store_round_trips_and_deletes_blobs() {
+        let root = std::env::temp_dir().join(format!("blobs-{}", Uuid::new_v4()));
+        let store = LocalBlobStore::new(&root);
+        let blob_id = Uuid::new_v4();
+
+        store.put(&blob_id, b"hello").await.unwrap();
+        assert_eq!(store.get(&blob_id).await.unwrap(), b"hello");
+
+        store.delete(&blob_id).await.unwrap();
+        assert_eq!(store.get(&blob_id).await.unwrap_err().kind(), io::ErrorKind::NotFound);
+        store.delete(&blob_id).await.unwrap();
+
+        assert_eq!(
+            checksum(b"hello"),
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+        tokio::fs::remove_dir_all(root).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reaper_deletes_discarded_and_expired_attachments() {
+        let root = std::env::temp_dir().join(format!("blobs-{}", Uuid::new_v4()));
+        let store = Arc::new(LocalBlobStore::new(&root));
+        let (discarded, expired, kept) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        for blob_id in [discarded, expired, kept] {
+            store.put(&blob_id, b"hello").await.unwrap();
+        }
+
+        let events = [
+            ChatEvent::AttachmentDiscarded {
+                attachment_id: discarded,
+                user_id: "user1".to_string(),
+                timestamp: chrono::Utc::now(),
+            },
+            ChatEvent::MessageExpired {
+                message_id: Uuid::new_v4(),
+                attachment_ids: vec![expired],
+                timestamp: chrono::Utc::now(),
+            },
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(i, payload)| EventEnvelope {
+            aggregate_id: "room".to_string(),
+            sequence: i + 1,
+            payload,
+            metadata: Default::default(),
+        })
+        .collect::<Vec<_>>();
+        BlobReaper::new(store.clone()).dispatch("room", &events).await;
+
+        assert_eq!(store.get(&discarded).await.unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert_eq!(store.get(&expired).await.unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert_eq!(store.get(&kept).await.unwrap(), b"hello");
+        tokio::fs::remove_dir_all(root).await.unwrap();
+    }
+}
@@ -0,0 +1,125 @@
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::bots::{bot_user_id, Bot};
+use crate::domain::commands::ChatCommand;
+use crate::domain::events::ChatEvent;
+
+/// Repeats any message addressed to it as `@echo <text>`. The echo of an
+/// expiring message expires no later than the message itself.
+pub struct EchoBot;
+
+const MENTION: &str = "@echo ";
+
+#[async_trait]
+impl Bot for EchoBot {
+    fn name(&self) -> &str {
+        "echo"
+    }
+
+    async fn on_event(&self, _room_id: Uuid, event: &ChatEvent) -> Vec<ChatCommand> {
+        let ChatEvent::MessageSent { content, expires_at, .. } = event else {
+            return vec![];
+        };
+        let Some(text) = content.strip_prefix(MENTION).map(str::trim).filter(|text| !text.is_empty()) else {
+            return vec![];
+        };
+        let ttl_seconds = match expires_at {
+            Some(expires_at) => match (*expires_at - chrono::Utc::now()).num_seconds() {
+                // Too late to echo before the message is gone.
+                remaining if remaining < 1 => return vec![],
+                remaining => Some(remaining as u64),
+            },
+            None => None,
+        };
+
+        vec![ChatCommand::SendMessage {
+            message_id: Uuid::new_v4(),
+            user_id: bot_user_id(self.name()),
+            content: text.to_string(),
+            timestamp: chrono::Utc::now(),
+            ttl_seconds,
+            attachment_ids: Vec::new(),
+        }]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_chat_framework;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_echo_bot_joins_and_repeats_mentions() {
+        let frameworks = create_chat_framework();
+        let room_id = Uuid::new_v4();
+        let create = ChatCommand::CreateRoom {
+            room_id,
+            name: "General".to_string(),
+            created_by: "user1".to_string(),
+        };
+        frameworks.rooms.execute(&room_id.to_string(), create).await.unwrap();
+        for content in ["hello", "@echo is anyone there?"] {
+            let send = ChatCommand::SendMessage {
+                message_id: Uuid::new_v4(),
+                user_id: "user1".to_string(),
+                content: content.to_string(),
+                timestamp: chrono::Utc::now(),
+                ttl_seconds: None,
+                attachment_ids: Vec::new(),
+            };
+            frameworks.rooms.execute(&room_id.to_string(), send).await.unwrap();
+        }
+
+        let mut room = frameworks.room_views.get_room(&room_id).await.unwrap();
+        for _ in 0..50 {
+            if room.messages.len() == 3 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            room = frameworks.room_views.get_room(&room_id).await.unwrap();
+        }
+
+        let reply = room.messages.last().unwrap();
+        assert_eq!(room.messages.len(), 3);
+        assert_eq!(reply.user_id, "bot:echo");
+        assert_eq!(reply.content, "is anyone there?");
+        assert!(room.participants.iter().any(|p| p.user_id == "bot:echo"));
+    }
+
+    #[tokio::test]
+    async fn test_echo_of_an_expiring_message_expires_with_it() {
+        let frameworks = create_chat_framework();
+        let room_id = Uuid::new_v4();
+        let create = ChatCommand::CreateRoom {
+            room_id,
+            name: "General".to_string(),
+            created_by: "user1".to_string(),
+        };
+        frameworks.rooms.execute(&room_id.to_string(), create).await.unwrap();
+        let send = ChatCommand::SendMessage {
+            message_id: Uuid::new_v4(),
+            user_id: "user1".to_string(),
+            content: "@echo gone in a minute".to_string(),
+            timestamp: chrono::Utc::now(),
+            ttl_seconds: Some(60),
+            attachment_ids: Vec::new(),
+        };
+        frameworks.rooms.execute(&room_id.to_string(), send).await.unwrap();
+
+        let mut room = frameworks.room_views.get_room(&room_id).await.unwrap();
+        for _ in 0..50 {
+            if room.messages.len() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            room = frameworks.room_views.get_room(&room_id).await.unwrap();
+        }
+
+        assert_eq!(room.messages.len(), 2);
+        let (original, reply) = (&room.messages[0], &room.messages[1]);
+        assert_eq!(reply.content, "gone in a minute");
+        assert!(reply.expires_at.unwrap() <= original.expires_at.unwrap());
+    }
+}
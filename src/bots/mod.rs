@@ -0,0 +1,218 @@
+pub mod echo;
+pub mod reminder;
+pub mod slash;
+
+use async_trait::async_trait;
+use cqrs_es::{EventEnvelope, Query};
+use std::sync::{Arc, Weak};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::domain::aggregate::ChatRoom;
+use crate::domain::commands::{ChatCommand, DomainCommand};
+use crate::domain::events::ChatEvent;
+use crate::services::ChatRoomViewRepository;
+use crate::ChatRoomFramework;
+
+/// The participant a bot acts as.
+pub fn bot_user_id(name: &str) -> String {
+    format!("bot:{}", name)
+}
+
+/// A user's invocation of a slash command that a bot answers.
+#[derive(Debug, Clone)]
+pub struct SlashInvocation {
+    pub room_id: Uuid,
+    pub user_id: String,
+    pub username: String,
+    /// The command's name, without the slash.
+    pub command: String,
+    pub args: String,
+}
+
+/// A plugin that reacts to rooms by issuing `ChatCommand`s as its own
+/// participant, `bot:<name>`. A bot joins a room the first time it acts there.
+#[async_trait]
+pub trait Bot: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Slash commands the bot answers, without the slash.
+    fn slash_commands(&self) -> &[&str] {
+        &[]
+    }
+
+    /// Checks an invocation before it is accepted, so the user learns of mistakes
+    /// straight away; the error explains how to use the command.
+    fn check_slash_command(&self, _invocation: &SlashInvocation) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Commands to issue in response to an event committed to a room.
+    async fn on_event(&self, _room_id: Uuid, _event: &ChatEvent) -> Vec<ChatCommand> {
+        vec![]
+    }
+
+    /// Commands to issue when a user invokes one of the bot's slash commands.
+    /// Besides its own, the bot may issue commands as the invoking user.
+    async fn on_slash_command(&self, _invocation: &SlashInvocation) -> Vec<ChatCommand> {
+        vec![]
+    }
+}
+
+enum BotInput {
+    Event { room_id: Uuid, event: ChatEvent },
+    SlashCommand { bot: Arc<dyn Bot>, invocation: SlashInvocation },
+}
+
+/// Forwards committed room events, and slash commands addressed to bots, to the
+/// bot runner. Bots run apart from the command that triggered them, so a slow
+/// bot never holds up a command.
+#[derive(Clone)]
+pub struct BotRunner {
+    bots: Vec<Arc<dyn Bot>>,
+    inputs: mpsc::UnboundedSender<BotInput>,
+}
+
+/// The receiving end of a `BotRunner`, waiting to be started once the room
+/// framework the bots issue commands to exists.
+pub struct BotInbox {
+    bots: Vec<Arc<dyn Bot>>,
+    inputs: mpsc::UnboundedReceiver<BotInput>,
+}
+
+impl BotRunner {
+    pub fn new(bots: Vec<Arc<dyn Bot>>) -> (Self, BotInbox) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let runner = Self {
+            bots: bots.clone(),
+            inputs: sender,
+        };
+        (runner, BotInbox { bots, inputs: receiver })
+    }
+
+    pub fn bots(&self) -> &[Arc<dyn Bot>] {
+        &self.bots
+    }
+
+    pub(crate) fn invoke(&self, bot: Arc<dyn Bot>, invocation: SlashInvocation) {
+        if self.inputs.send(BotInput::SlashCommand { bot, invocation }).is_err() {
+            log::error!("Bot runner has stopped, dropping a slash command");
+        }
+    }
+}
+
+#[async_trait]
+impl Query<ChatRoom> for BotRunner {
+    async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<ChatRoom>]) {
+        let Ok(room_id) = Uuid::parse_str(aggregate_id) else {
+            log::error!("Room aggregate id {} is not a UUID", aggregate_id);
+            return;
+        };
+        for event_envelope in events.iter().filter(|envelope| !envelope.payload.is_private()) {
+            // Bots do not see bots' messages, so they cannot set one another off.
+            if let ChatEvent::MessageSent { user_id, .. } = &event_envelope.payload {
+                if user_id.starts_with("bot:") {
+                    continue;
+                }
+            }
+            let input = BotInput::Event {
+                room_id,
+                event: event_envelope.payload.clone(),
+            };
+            if self.inputs.send(input).is_err() {
+                log::error!("Bot runner has stopped, dropping room events");
+                return;
+            }
+        }
+    }
+}
+
+impl BotInbox {
+    /// Runs the bots on a thread of their own; it stops once the runner is dropped.
+    /// Only a weak reference to the framework is kept, since the framework owns the runner.
+    pub fn start(self, rooms: Weak<ChatRoomFramework>, room_views: Arc<ChatRoomViewRepository>) {
+        std::thread::Builder::new()
+            .name("bot-runner".to_string())
+            .spawn(move || actix_rt::System::new().block_on(self.run(rooms, room_views)))
+            .expect("failed to start the bot runner");
+    }
+
+    async fn run(mut self, rooms: Weak<ChatRoomFramework>, room_views: Arc<ChatRoomViewRepository>) {
+        while let Some(input) = self.inputs.recv().await {
+            match input {
+                BotInput::Event { room_id, event } => {
+                    for bot in &self.bots {
+                        let (bot, event, rooms, room_views) = (bot.clone(), event.clone(), rooms.clone(), room_views.clone());
+                        actix_rt::spawn(async move {
+                            let commands = bot.on_event(room_id, &event).await;
+                            issue(bot.as_ref(), room_id, commands, None, &rooms, &room_views).await;
+                        });
+                    }
+                }
+                BotInput::SlashCommand { bot, invocation } => {
+                    let (rooms, room_views) = (rooms.clone(), room_views.clone());
+                    actix_rt::spawn(async move {
+                        let commands = bot.on_slash_command(&invocation).await;
+                        let invoker = Some(invocation.user_id.as_str());
+                        issue(bot.as_ref(), invocation.room_id, commands, invoker, &rooms, &room_views).await;
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Executes a bot's commands together, joining the room first if it has not yet
+/// and acts as itself. Only a slash command's invoker may be acted for besides.
+async fn issue(
+    bot: &dyn Bot,
+    room_id: Uuid,
+    commands: Vec<ChatCommand>,
+    invoker: Option<&str>,
+    rooms: &Weak<ChatRoomFramework>,
+    room_views: &ChatRoomViewRepository,
+) {
+    let user_id = bot_user_id(bot.name());
+    let (mut commands, foreign): (Vec<ChatCommand>, Vec<ChatCommand>) = commands.into_iter().partition(|command| {
+        let issued_by = command.issued_by();
+        issued_by == Some(user_id.as_str()) || (invoker.is_some() && issued_by == invoker)
+    });
+    if !foreign.is_empty() {
+        log::error!("Bot {} tried to issue commands as someone else; dropping them", bot.name());
+    }
+    if commands.is_empty() {
+        return;
+    }
+    let Some(rooms) = rooms.upgrade() else {
+        return;
+    };
+
+    let acts_as_itself = commands.iter().any(|command| command.issued_by() == Some(user_id.as_str()));
+    let joined = room_views
+        .get_room(&room_id)
+        .await
+        .is_some_and(|room| room.participants.iter().any(|p| p.user_id == user_id));
+    if acts_as_itself && !joined {
+        commands.insert(
+            0,
+            ChatCommand::JoinRoom {
+                user_id: user_id.clone(),
+                username: bot.name().to_string(),
+            },
+        );
+    }
+    let command = if commands.len() == 1 {
+        commands.remove(0)
+    } else {
+        ChatCommand::Batch { commands }
+    };
+
+    if let Err(e) = rooms.execute(&room_id.to_string(), command).await {
+        log::warn!("Bot {} could not act in room {}: {}", bot.name(), room_id, e);
+    }
+}
+
+/// The bots every server runs.
+pub fn builtin_bots() -> Vec<Arc<dyn Bot>> {
+    vec![Arc::new(echo::EchoBot), Arc::new(reminder::ReminderBot)]
+}
@@ -0,0 +1,155 @@
+use async_trait::async_trait;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::bots::{Bot, SlashInvocation};
+use crate::domain::commands::ChatCommand;
+use crate::domain::validation::MAX_MESSAGE_LENGTH;
+
+/// The longest a reminder can be set for.
+const MAX_DELAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Answers `/remind <delay> <text>`, such as `/remind 10m stretch`, by
+/// scheduling a message for the user that posts the text back to them once the
+/// delay has passed. The scheduler delivers it, so reminders survive a restart,
+/// and the user can list and cancel it like their other scheduled messages.
+pub struct ReminderBot;
+
+#[async_trait]
+impl Bot for ReminderBot {
+    fn name(&self) -> &str {
+        "reminder"
+    }
+
+    fn slash_commands(&self) -> &[&str] {
+        &["remind"]
+    }
+
+    fn check_slash_command(&self, invocation: &SlashInvocation) -> Result<(), String> {
+        let Some((_, text)) = parse_reminder(&invocation.args) else {
+            return Err("usage: /remind <delay> <text>, with a delay such as 30s, 10m or 2h of at most 24h".to_string());
+        };
+        if reminder_content(&invocation.username, text).chars().count() > MAX_MESSAGE_LENGTH {
+            return Err(format!("reminders must be at most {} characters, with their prefix", MAX_MESSAGE_LENGTH));
+        }
+        Ok(())
+    }
+
+    async fn on_slash_command(&self, invocation: &SlashInvocation) -> Vec<ChatCommand> {
+        let Some((delay, text)) = parse_reminder(&invocation.args) else {
+            return vec![];
+        };
+        let Ok(delay) = chrono::Duration::from_std(delay) else {
+            return vec![];
+        };
+
+        vec![ChatCommand::ScheduleMessage {
+            message_id: Uuid::new_v4(),
+            user_id: invocation.user_id.clone(),
+            content: reminder_content(&invocation.username, text),
+            deliver_at: chrono::Utc::now() + delay,
+        }]
+    }
+}
+
+/// The message a reminder posts.
+fn reminder_content(username: &str, text: &str) -> String {
+    format!("Reminder for @{}: {}", username, text)
+}
+
+/// Splits `<delay> <text>`, where the delay is a number followed by `s`, `m`
+/// or `h`, into its parts.
+pub fn parse_reminder(args: &str) -> Option<(Duration, &str)> {
+    let (delay, text) = args.trim().split_once(char::is_whitespace)?;
+    let text = text.trim();
+    let unit = match delay.chars().last()? {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        _ => return None,
+    };
+    let amount: u64 = delay[..delay.len() - 1].parse().ok()?;
+    let delay = Duration::from_secs(amount.checked_mul(unit)?);
+    (!text.is_empty() && !delay.is_zero() && delay <= MAX_DELAY).then_some((delay, text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bots::slash::SlashError;
+    use crate::create_chat_framework;
+
+    #[test]
+    fn test_parse_reminder() {
+        assert_eq!(parse_reminder("10m stretch  "), Some((Duration::from_secs(600), "stretch")));
+        assert_eq!(parse_reminder("2h call the bank"), Some((Duration::from_secs(7200), "call the bank")));
+        assert_eq!(parse_reminder("10 stretch"), None);
+        assert_eq!(parse_reminder("0s stretch"), None);
+        assert_eq!(parse_reminder("25h stretch"), None);
+        assert_eq!(parse_reminder("10m"), None);
+    }
+
+    #[tokio::test]
+    async fn test_reminders_are_scheduled_messages() {
+        let frameworks = create_chat_framework();
+        let room_id = Uuid::new_v4();
+        let create = ChatCommand::Batch {
+            commands: vec![
+                ChatCommand::CreateRoom {
+                    room_id,
+                    name: "General".to_string(),
+                    created_by: "user1".to_string(),
+                },
+                ChatCommand::JoinRoom {
+                    user_id: "user2".to_string(),
+                    username: "Bob".to_string(),
+                },
+            ],
+        };
+        frameworks.rooms.execute(&room_id.to_string(), create).await.unwrap();
+        let before = chrono::Utc::now();
+        frameworks.slash_commands.dispatch(room_id, "user2", "/remind 10m stretch").await.unwrap();
+
+        let mut pending = frameworks.scheduler.for_user("user2").await;
+        for _ in 0..50 {
+            if !pending.is_empty() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            pending = frameworks.scheduler.for_user("user2").await;
+        }
+
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].room_id, room_id);
+        assert_eq!(pending[0].content, "Reminder for @Bob: stretch");
+        assert!(pending[0].deliver_at >= before + chrono::Duration::minutes(10));
+        assert!(pending[0].deliver_at <= chrono::Utc::now() + chrono::Duration::minutes(10));
+        let room = frameworks.room_views.get_room(&room_id).await.unwrap();
+        assert!(room.messages.is_empty());
+        assert!(!room.participants.iter().any(|p| p.user_id == "bot:reminder"));
+
+        // The reminder is the user's own, so they can cancel it.
+        let cancel = ChatCommand::CancelScheduledMessage {
+            user_id: "user2".to_string(),
+            message_id: pending[0].message_id,
+        };
+        frameworks.rooms.execute(&room_id.to_string(), cancel).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reminders_too_long_with_their_prefix_are_refused() {
+        let frameworks = create_chat_framework();
+        let room_id = Uuid::new_v4();
+        let create = ChatCommand::CreateRoom {
+            room_id,
+            name: "General".to_string(),
+            created_by: "user1".to_string(),
+        };
+        frameworks.rooms.execute(&room_id.to_string(), create).await.unwrap();
+
+        // Fits in a message, but not once "Reminder for @user1: " is put in front.
+        let text = "x".repeat(MAX_MESSAGE_LENGTH - 15);
+        let result = frameworks.slash_commands.dispatch(room_id, "user1", &format!("/remind 10m {}", text)).await;
+        assert!(matches!(result, Err(SlashError::InvalidArguments { .. })));
+    }
+}
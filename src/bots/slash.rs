@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::bots::{Bot, BotRunner, SlashInvocation};
+use crate::domain::commands::ChatCommand;
+use crate::services::{ChatRoomView, ChatRoomViewRepository, UserInfo};
+
+#[derive(Debug, Error, PartialEq)]
+pub enum SlashError {
+    #[error("Unknown command /{0}")]
+    UnknownCommand(String),
+
+    #[error("/{command}: {message}")]
+    InvalidArguments { command: String, message: String },
+
+    #[error("Room {0} not found")]
+    RoomNotFound(Uuid),
+}
+
+impl SlashError {
+    fn invalid(command: &str, message: impl Into<String>) -> Self {
+        SlashError::InvalidArguments {
+            command: command.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+/// The room and user a slash command was typed by.
+pub struct SlashContext<'a> {
+    pub room: &'a ChatRoomView,
+    pub user_id: &'a str,
+}
+
+impl SlashContext<'_> {
+    pub fn username(&self) -> &str {
+        self.room
+            .participants
+            .iter()
+            .find(|p| p.user_id == self.user_id)
+            .map_or(self.user_id, |p| p.username.as_str())
+    }
+
+    /// Resolves `@name` (or a bare name or user id) to one of the room's participants.
+    pub fn participant(&self, command: &str, mention: &str) -> Result<&UserInfo, SlashError> {
+        let name = mention.trim().trim_start_matches('@');
+        if name.is_empty() {
+            return Err(SlashError::invalid(command, "name a participant, such as @bob"));
+        }
+        if let Some(user) = self.room.participants.iter().find(|p| p.user_id == name) {
+            return Ok(user);
+        }
+        let mut matches = self.room.participants.iter().filter(|p| p.username.eq_ignore_ascii_case(name));
+        match (matches.next(), matches.next()) {
+            (Some(user), None) => Ok(user),
+            (Some(_), Some(_)) => Err(SlashError::invalid(command, format!("@{} is ambiguous; use their user id", name))),
+            (None, _) => Err(SlashError::invalid(command, format!("@{} is not in the room", name))),
+        }
+    }
+}
+
+/// A handler for `/<name> <args>`, turning it into a command issued by the user
+/// who typed it, or into nothing when it is handled elsewhere.
+pub trait SlashCommand: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn execute(&self, context: &SlashContext, args: &str) -> Result<Option<ChatCommand>, SlashError>;
+}
+
+/// What became of a message typed by a user.
+#[derive(Debug)]
+pub enum Dispatch {
+    /// Not a slash command: send this text as an ordinary message.
+    Message(String),
+    /// A slash command, carried out by executing this command.
+    Command(ChatCommand),
+    /// A slash command handed to a bot, which acts on it in the background.
+    Handled,
+}
+
+/// Splits `/name args` into its parts. `//text` escapes the slash and is not a command.
+pub fn parse(content: &str) -> Option<(&str, &str)> {
+    let rest = content.trim_start().strip_prefix('/')?;
+    if rest.starts_with('/') {
+        return None;
+    }
+    let (name, args) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    Some((name, args.trim()))
+}
+
+/// Registered slash commands, consulted for every message typed in the TUI or
+/// sent through the Web API.
+pub struct SlashCommands {
+    room_views: Arc<ChatRoomViewRepository>,
+    handlers: HashMap<String, Arc<dyn SlashCommand>>,
+}
+
+impl SlashCommands {
+    /// The built-in commands together with those answered by the runner's bots.
+    pub fn new(room_views: Arc<ChatRoomViewRepository>, bots: &BotRunner) -> Self {
+        let mut commands = Self {
+            room_views,
+            handlers: HashMap::new(),
+        };
+        commands.register(Topic);
+        commands.register(Kick);
+        commands.register(Me);
+        for bot in bots.bots() {
+            for name in bot.slash_commands() {
+                commands.register(BotCommand {
+                    name: name.to_string(),
+                    bot: bot.clone(),
+                    runner: bots.clone(),
+                });
+            }
+        }
+        commands
+    }
+
+    /// Adds a handler, replacing any registered under the same name.
+    pub fn register(&mut self, handler: impl SlashCommand + 'static) {
+        self.handlers.insert(handler.name().to_string(), Arc::new(handler));
+    }
+
+    pub async fn dispatch(&self, room_id: Uuid, user_id: &str, content: &str) -> Result<Dispatch, SlashError> {
+        let Some((name, args)) = parse(content) else {
+            let content = content.trim_start();
+            return Ok(Dispatch::Message(content.strip_prefix('/').unwrap_or(content).to_string()));
+        };
+        let handler = self
+            .handlers
+            .get(name)
+            .ok_or_else(|| SlashError::UnknownCommand(name.to_string()))?;
+        let room = self
+            .room_views
+            .get_room(&room_id)
+            .await
+            .ok_or(SlashError::RoomNotFound(room_id))?;
+        let context = SlashContext { room: &room, user_id };
+
+        Ok(match handler.execute(&context, args)? {
+            Some(command) => Dispatch::Command(command),
+            None => Dispatch::Handled,
+        })
+    }
+}
+
+/// `/topic <text>` sets the room's topic; without text it clears it.
+struct Topic;
+
+impl SlashCommand for Topic {
+    fn name(&self) -> &str {
+        "topic"
+    }
+
+    fn execute(&self, context: &SlashContext, args: &str) -> Result<Option<ChatCommand>, SlashError> {
+        Ok(Some(ChatCommand::SetTopic {
+            user_id: context.user_id.to_string(),
+            topic: args.to_string(),
+        }))
+    }
+}
+
+/// `/kick @name` removes a participant from the room.
+struct Kick;
+
+impl SlashCommand for Kick {
+    fn name(&self) -> &str {
+        "kick"
+    }
+
+    fn execute(&self, context: &SlashContext, args: &str) -> Result<Option<ChatCommand>, SlashError> {
+        let target = context.participant(self.name(), args)?;
+        Ok(Some(ChatCommand::KickUser {
+            user_id: context.user_id.to_string(),
+            target_user_id: target.user_id.clone(),
+        }))
+    }
+}
+
+/// `/me <action>` sends an action, such as `* alice waves`.
+struct Me;
+
+impl SlashCommand for Me {
+    fn name(&self) -> &str {
+        "me"
+    }
+
+    fn execute(&self, context: &SlashContext, args: &str) -> Result<Option<ChatCommand>, SlashError> {
+        if args.is_empty() {
+            return Err(SlashError::invalid(self.name(), "say what you are doing, such as /me waves"));
+        }
+        Ok(Some(ChatCommand::SendMessage {
+            message_id: Uuid::new_v4(),
+            user_id: context.user_id.to_string(),
+            content: format!("* {} {}", context.username(), args),
+            timestamp: chrono::Utc::now(),
+            ttl_seconds: None,
+            attachment_ids: Vec::new(),
+        }))
+    }
+}
+
+/// A slash command answered by a bot.
+struct BotCommand {
+    name: String,
+    bot: Arc<dyn Bot>,
+    runner: BotRunner,
+}
+
+impl SlashCommand for BotCommand {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn execute(&self, context: &SlashContext, args: &str) -> Result<Option<ChatCommand>, SlashError> {
+        if !context.room.participants.iter().any(|p| p.user_id == context.user_id) {
+            return Err(SlashError::invalid(&self.name, "only participants of the room can use it"));
+        }
+        let invocation = SlashInvocation {
+            room_id: context.room.room_id,
+            user_id: context.user_id.to_string(),
+            username: context.username().to_string(),
+            command: self.name.clone(),
+            args: args.to_string(),
+        };
+        self.bot
+            .check_slash_command(&invocation)
+            .map_err(|message| SlashError::invalid(&self.name, message))?;
+        self.runner.invoke(self.bot.clone(), invocation);
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_chat_framework;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("/kick @bob"), Some(("kick", "@bob")));
+        assert_eq!(parse("/topic"), Some(("topic", "")));
+        assert_eq!(parse("  /me   waves  "), Some(("me", "waves")));
+        assert_eq!(parse("//etc/hosts"), None);
+        assert_eq!(parse("hello /me"), None);
+    }
+
+    #[tokio::test]
+    async fn test_dispatch() {
+        let frameworks = create_chat_framework();
+        let room_id = Uuid::new_v4();
+        let create = ChatCommand::Batch {
+            commands: vec![
+                ChatCommand::CreateRoom {
+                    room_id,
+                    name: "General".to_string(),
+                    created_by: "user1".to_string(),
+                },
+                ChatCommand::JoinRoom {
+                    user_id: "user2".to_string(),
+                    username: "Bob".to_string(),
+                },
+            ],
+        };
+        frameworks.rooms.execute(&room_id.to_string(), create).await.unwrap();
+        let slash = &frameworks.slash_commands;
+
+        match slash.dispatch(room_id, "user1", "/kick @bob").await.unwrap() {
+            Dispatch::Command(ChatCommand::KickUser { target_user_id, .. }) => assert_eq!(target_user_id, "user2"),
+            other => panic!("unexpected dispatch: {:?}", other),
+        }
+        match slash.dispatch(room_id, "user2", "/me waves").await.unwrap() {
+            Dispatch::Command(ChatCommand::SendMessage { content, .. }) => assert_eq!(content, "* Bob waves"),
+            other => panic!("unexpected dispatch: {:?}", other),
+        }
+        match slash.dispatch(room_id, "user1", "//etc/hosts").await.unwrap() {
+            Dispatch::Message(content) => assert_eq!(content, "/etc/hosts"),
+            other => panic!("unexpected dispatch: {:?}", other),
+        }
+        assert!(matches!(slash.dispatch(room_id, "user1", "/remind 10m stretch").await, Ok(Dispatch::Handled)));
+
+        assert_eq!(
+            slash.dispatch(room_id, "user1", "/dance").await.unwrap_err(),
+            SlashError::UnknownCommand("dance".to_string()),
+        );
+        assert!(matches!(
+            slash.dispatch(room_id, "user1", "/kick @carol").await,
+            Err(SlashError::InvalidArguments { .. })
+        ));
+        assert!(matches!(
+            slash.dispatch(room_id, "user1", "/remind soon stretch").await,
+            Err(SlashError::InvalidArguments { .. })
+        ));
+    }
+}
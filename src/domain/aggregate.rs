@@ -5,7 +5,10 @@ use std::collections::{HashMap, HashSet};
 use uuid::Uuid;
 
 use crate::domain::commands::ChatCommand;
-use crate::domain::events::{ChatError, ChatEvent};
+use crate::domain::events::{Attachment, ChatError, ChatEvent};
+use crate::domain::validation::{
+    ValidationErrors, MAX_PARTICIPANTS, MAX_PENDING_ATTACHMENTS_PER_USER, MAX_SCHEDULED_MESSAGES_PER_USER,
+};
 use crate::services::ChatServices;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -14,6 +17,35 @@ pub struct Message {
     pub user_id: String,
     pub content: String,
     pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Set once the message has expired, when its content is cleared.
+    pub expired: bool,
+    #[serde(default)]
+    pub attachment_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledMessage {
+    pub user_id: String,
+    pub content: String,
+    pub deliver_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingAttachment {
+    pub user_id: String,
+    pub attachment: Attachment,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Poll {
+    pub created_by: String,
+    pub options: Vec<String>,
+    pub multiple_choice: bool,
+    pub anonymous: bool,
+    pub closed: bool,
+    /// Each voter's chosen option indexes.
+    pub votes: HashMap<String, Vec<usize>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -25,6 +57,27 @@ pub struct ChatRoom {
     pub usernames: HashMap<String, String>,
     pub messages: Vec<Message>,
     pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub topic: String,
+    pub description: String,
+    pub archived: bool,
+    /// The last message each participant has read.
+    pub read_markers: HashMap<String, Uuid>,
+    /// Minimum seconds between two messages from the same non-moderator; 0 when slow mode is off.
+    pub slow_mode_seconds: u64,
+    /// Seconds messages live before they expire; 0 when they are kept forever.
+    pub message_ttl_seconds: u64,
+    /// Active incoming webhooks and the name their bot posts under.
+    pub incoming_webhooks: HashMap<Uuid, String>,
+    /// Messages waiting for their delivery time, by the id they will be sent with.
+    pub scheduled_messages: HashMap<Uuid, ScheduledMessage>,
+    /// Uploaded attachments that no message has been sent with yet.
+    pub pending_attachments: HashMap<Uuid, PendingAttachment>,
+    pub polls: HashMap<Uuid, Poll>,
+}
+
+/// The participant an incoming webhook posts as.
+pub fn incoming_webhook_user_id(hook_id: &Uuid) -> String {
+    format!("hook:{}", hook_id)
 }
 
 #[async_trait]
@@ -43,6 +96,8 @@ impl Aggregate for ChatRoom {
         command: Self::Command,
         services: &Self::Services,
     ) -> Result<Vec<Self::Event>, Self::Error> {
+        let command = command.validate().map_err(ChatError::Validation)?;
+
         match command {
             ChatCommand::CreateRoom { room_id, name, created_by } => {
                 if self.room_id.is_some() {
@@ -62,10 +117,21 @@ impl Aggregate for ChatRoom {
                     return Err(ChatError::RoomNotFound("Room does not exist".to_string()));
                 }
 
+                if self.archived {
+                    return Err(ChatError::RoomArchived("Cannot join an archived room".to_string()));
+                }
+
                 if self.participants.contains(&user_id) {
                     return Err(ChatError::UserAlreadyInRoom(format!("User {} is already in the room", user_id)));
                 }
 
+                if self.participants.len() >= MAX_PARTICIPANTS {
+                    return Err(ChatError::Validation(ValidationErrors::single(
+                        "participants",
+                        format!("room is full, at most {} participants are allowed", MAX_PARTICIPANTS),
+                    )));
+                }
+
                 Ok(vec![ChatEvent::UserJoined {
                     user_id,
                     username,
@@ -88,22 +154,509 @@ impl Aggregate for ChatRoom {
                 }])
             }
 
-            ChatCommand::SendMessage { message_id, user_id, content, timestamp } => {
+            ChatCommand::KickUser { user_id, target_user_id } => {
+                self.ensure_moderator(&user_id)?;
+
+                if self.is_moderator(&target_user_id) {
+                    return Err(ChatError::InvalidOperation("Moderators cannot be kicked".to_string()));
+                }
+
+                if !self.participants.contains(&target_user_id) {
+                    return Err(ChatError::UserNotInRoom(format!("User {} is not in the room", target_user_id)));
+                }
+
+                // A kick leaves the same trace as leaving, so every projection handles it.
+                Ok(vec![ChatEvent::UserLeft {
+                    user_id: target_user_id,
+                    timestamp: chrono::Utc::now(),
+                }])
+            }
+
+            ChatCommand::SendMessage { message_id, user_id, content, timestamp, ttl_seconds, attachment_ids } => {
                 if self.room_id.is_none() {
                     return Err(ChatError::RoomNotFound("Room does not exist".to_string()));
                 }
 
+                if self.archived {
+                    return Err(ChatError::RoomArchived("Cannot send messages to an archived room".to_string()));
+                }
+
                 if !self.participants.contains(&user_id) {
                     return Err(ChatError::UserNotInRoom(format!("User {} is not in the room", user_id)));
                 }
 
+                if self.scheduled_messages.contains_key(&message_id) || self.message_position(&message_id).is_some() {
+                    return Err(ChatError::InvalidOperation(format!("Message {} already exists", message_id)));
+                }
+
+                // Measured by the server's clock, whatever time the sender claims.
+                if let Some(remaining_seconds) = self.slow_mode_remaining(&user_id, chrono::Utc::now()) {
+                    return Err(ChatError::SlowMode { remaining_seconds });
+                }
+
+                let mut attachments = Vec::new();
+                for attachment_id in attachment_ids {
+                    let Some(pending) = self.pending_attachments.get(&attachment_id) else {
+                        return Err(ChatError::InvalidOperation(format!(
+                            "Attachment {} is not waiting to be sent",
+                            attachment_id
+                        )));
+                    };
+                    if pending.user_id != user_id {
+                        return Err(ChatError::PermissionDenied(format!(
+                            "User {} did not upload attachment {}",
+                            user_id, attachment_id
+                        )));
+                    }
+                    attachments.push(pending.attachment.clone());
+                }
+
                 Ok(vec![ChatEvent::MessageSent {
                     message_id,
                     user_id,
                     content,
                     timestamp,
+                    expires_at: self.expires_at(timestamp, ttl_seconds),
+                    attachments,
+                }])
+            }
+
+            ChatCommand::UploadAttachment { attachment_id, user_id, name, mime_type, size, checksum } => {
+                self.ensure_participant(&user_id)?;
+
+                if self.archived {
+                    return Err(ChatError::RoomArchived("Cannot upload attachments to an archived room".to_string()));
+                }
+
+                if self.pending_attachments.contains_key(&attachment_id) {
+                    return Err(ChatError::InvalidOperation(format!("Attachment {} already exists", attachment_id)));
+                }
+
+                let pending = self.pending_attachments.values().filter(|p| p.user_id == user_id).count();
+                if pending >= MAX_PENDING_ATTACHMENTS_PER_USER {
+                    return Err(ChatError::Validation(ValidationErrors::single(
+                        "attachment_id",
+                        format!(
+                            "at most {} attachments may wait to be sent per user",
+                            MAX_PENDING_ATTACHMENTS_PER_USER
+                        ),
+                    )));
+                }
+
+                Ok(vec![ChatEvent::AttachmentUploaded {
+                    attachment: Attachment {
+                        attachment_id,
+                        name,
+                        mime_type,
+                        size,
+                        checksum,
+                    },
+                    user_id,
+                    timestamp: chrono::Utc::now(),
+                }])
+            }
+
+            ChatCommand::RenameRoom { user_id, name } => {
+                self.ensure_moderator(&user_id)?;
+
+                if self.archived {
+                    return Err(ChatError::RoomArchived("Cannot rename an archived room".to_string()));
+                }
+
+                Ok(vec![ChatEvent::RoomRenamed {
+                    user_id,
+                    name,
+                    timestamp: chrono::Utc::now(),
+                }])
+            }
+
+            ChatCommand::SetTopic { user_id, topic } => {
+                self.ensure_moderator(&user_id)?;
+
+                if self.archived {
+                    return Err(ChatError::RoomArchived("Cannot change the topic of an archived room".to_string()));
+                }
+
+                Ok(vec![ChatEvent::TopicChanged {
+                    user_id,
+                    topic,
+                    timestamp: chrono::Utc::now(),
+                }])
+            }
+
+            ChatCommand::SetDescription { user_id, description } => {
+                self.ensure_moderator(&user_id)?;
+
+                if self.archived {
+                    return Err(ChatError::RoomArchived("Cannot change the description of an archived room".to_string()));
+                }
+
+                Ok(vec![ChatEvent::DescriptionChanged {
+                    user_id,
+                    description,
+                    timestamp: chrono::Utc::now(),
+                }])
+            }
+
+            ChatCommand::ArchiveRoom { user_id } => {
+                self.ensure_owner(&user_id)?;
+
+                if self.archived {
+                    return Err(ChatError::InvalidOperation("Room is already archived".to_string()));
+                }
+
+                Ok(vec![ChatEvent::RoomArchived {
+                    user_id,
+                    timestamp: chrono::Utc::now(),
+                }])
+            }
+
+            ChatCommand::UnarchiveRoom { user_id } => {
+                self.ensure_owner(&user_id)?;
+
+                if !self.archived {
+                    return Err(ChatError::InvalidOperation("Room is not archived".to_string()));
+                }
+
+                Ok(vec![ChatEvent::RoomUnarchived {
+                    user_id,
+                    timestamp: chrono::Utc::now(),
+                }])
+            }
+
+            ChatCommand::MarkRead { user_id, up_to_message_id } => {
+                self.ensure_participant(&user_id)?;
+
+                let position = self.message_position(&up_to_message_id).ok_or_else(|| {
+                    ChatError::InvalidOperation(format!("Message {} is not in the room", up_to_message_id))
+                })?;
+
+                // Read markers only move forward; marking an older message is a no-op.
+                let current = self.read_markers.get(&user_id).and_then(|id| self.message_position(id));
+                if current.is_some_and(|current| current >= position) {
+                    return Ok(vec![]);
+                }
+
+                Ok(vec![ChatEvent::MessagesRead {
+                    user_id,
+                    up_to_message_id,
+                    timestamp: chrono::Utc::now(),
+                }])
+            }
+
+            ChatCommand::SetSlowMode { user_id, interval_seconds } => {
+                self.ensure_moderator(&user_id)?;
+
+                if self.slow_mode_seconds == interval_seconds {
+                    return Ok(vec![]);
+                }
+
+                Ok(vec![ChatEvent::SlowModeChanged {
+                    user_id,
+                    interval_seconds,
+                    timestamp: chrono::Utc::now(),
+                }])
+            }
+
+            ChatCommand::SetMessageTtl { user_id, ttl_seconds } => {
+                self.ensure_moderator(&user_id)?;
+
+                if self.message_ttl_seconds == ttl_seconds {
+                    return Ok(vec![]);
+                }
+
+                Ok(vec![ChatEvent::MessageTtlChanged {
+                    user_id,
+                    ttl_seconds,
+                    timestamp: chrono::Utc::now(),
+                }])
+            }
+
+            ChatCommand::ExpireMessage { message_id } => {
+                if self.room_id.is_none() {
+                    return Err(ChatError::RoomNotFound("Room does not exist".to_string()));
+                }
+
+                let message = self
+                    .message_position(&message_id)
+                    .map(|position| &self.messages[position])
+                    .ok_or_else(|| ChatError::InvalidOperation(format!("Message {} is not in the room", message_id)))?;
+
+                // Expiring twice is harmless.
+                if message.expired {
+                    return Ok(vec![]);
+                }
+
+                let timestamp = chrono::Utc::now();
+                if message.expires_at.is_none_or(|expires_at| expires_at > timestamp) {
+                    return Err(ChatError::InvalidOperation(format!("Message {} has not expired", message_id)));
+                }
+
+                Ok(vec![ChatEvent::MessageExpired {
+                    message_id,
+                    attachment_ids: message.attachment_ids.clone(),
+                    timestamp,
+                }])
+            }
+
+            ChatCommand::DiscardAttachment { attachment_id } => {
+                // Sent in the meantime, or discarded already.
+                let Some(pending) = self.pending_attachments.get(&attachment_id) else {
+                    return Ok(vec![]);
+                };
+
+                Ok(vec![ChatEvent::AttachmentDiscarded {
+                    attachment_id,
+                    user_id: pending.user_id.clone(),
+                    timestamp: chrono::Utc::now(),
+                }])
+            }
+
+            ChatCommand::CreateIncomingWebhook { user_id, hook_id, name, token_hash } => {
+                self.ensure_owner(&user_id)?;
+
+                if self.archived {
+                    return Err(ChatError::RoomArchived("Cannot add webhooks to an archived room".to_string()));
+                }
+
+                if self.incoming_webhooks.contains_key(&hook_id) {
+                    return Err(ChatError::InvalidOperation(format!("Incoming webhook {} already exists", hook_id)));
+                }
+
+                if self.participants.len() >= MAX_PARTICIPANTS {
+                    return Err(ChatError::Validation(ValidationErrors::single(
+                        "participants",
+                        format!("room is full, at most {} participants are allowed", MAX_PARTICIPANTS),
+                    )));
+                }
+
+                let timestamp = chrono::Utc::now();
+                Ok(vec![
+                    ChatEvent::IncomingWebhookCreated {
+                        user_id,
+                        hook_id,
+                        name: name.clone(),
+                        token_hash,
+                        timestamp,
+                    },
+                    ChatEvent::UserJoined {
+                        user_id: incoming_webhook_user_id(&hook_id),
+                        username: name,
+                        timestamp,
+                    },
+                ])
+            }
+
+            ChatCommand::RevokeIncomingWebhook { user_id, hook_id } => {
+                self.ensure_owner(&user_id)?;
+
+                if !self.incoming_webhooks.contains_key(&hook_id) {
+                    return Err(ChatError::InvalidOperation(format!("Incoming webhook {} does not exist", hook_id)));
+                }
+
+                let timestamp = chrono::Utc::now();
+                let mut events = vec![ChatEvent::IncomingWebhookRevoked {
+                    user_id,
+                    hook_id,
+                    timestamp,
+                }];
+                let bot = incoming_webhook_user_id(&hook_id);
+                if self.participants.contains(&bot) {
+                    events.push(ChatEvent::UserLeft { user_id: bot, timestamp });
+                }
+
+                Ok(events)
+            }
+
+            ChatCommand::ScheduleMessage { message_id, user_id, content, deliver_at } => {
+                self.ensure_participant(&user_id)?;
+
+                if self.archived {
+                    return Err(ChatError::RoomArchived("Cannot schedule messages in an archived room".to_string()));
+                }
+
+                if self.scheduled_messages.contains_key(&message_id) || self.message_position(&message_id).is_some() {
+                    return Err(ChatError::InvalidOperation(format!("Message {} already exists", message_id)));
+                }
+
+                let pending = self.scheduled_messages.values().filter(|m| m.user_id == user_id).count();
+                if pending >= MAX_SCHEDULED_MESSAGES_PER_USER {
+                    return Err(ChatError::Validation(ValidationErrors::single(
+                        "message_id",
+                        format!("at most {} scheduled messages may be pending per user", MAX_SCHEDULED_MESSAGES_PER_USER),
+                    )));
+                }
+
+                Ok(vec![ChatEvent::MessageScheduled {
+                    message_id,
+                    user_id,
+                    content,
+                    deliver_at,
+                    timestamp: chrono::Utc::now(),
+                }])
+            }
+
+            ChatCommand::CancelScheduledMessage { user_id, message_id } => {
+                if self.room_id.is_none() {
+                    return Err(ChatError::RoomNotFound("Room does not exist".to_string()));
+                }
+
+                match self.scheduled_messages.get(&message_id) {
+                    Some(scheduled) if scheduled.user_id == user_id => {}
+                    Some(_) => {
+                        return Err(ChatError::PermissionDenied(format!(
+                            "User {} did not schedule message {}",
+                            user_id, message_id
+                        )))
+                    }
+                    None => {
+                        return Err(ChatError::InvalidOperation(format!(
+                            "Message {} is not scheduled",
+                            message_id
+                        )))
+                    }
+                }
+
+                Ok(vec![ChatEvent::ScheduledMessageCancelled {
+                    user_id,
+                    message_id,
+                    timestamp: chrono::Utc::now(),
+                }])
+            }
+
+            ChatCommand::DeliverScheduledMessage { message_id } => {
+                // Already sent or cancelled: nothing left to deliver.
+                let Some(scheduled) = self.scheduled_messages.get(&message_id) else {
+                    return Ok(vec![]);
+                };
+
+                let timestamp = chrono::Utc::now();
+                let reason = if self.archived {
+                    "the room is archived"
+                } else if !self.participants.contains(&scheduled.user_id) {
+                    "the sender has left the room"
+                } else {
+                    return Ok(vec![ChatEvent::MessageSent {
+                        message_id,
+                        user_id: scheduled.user_id.clone(),
+                        content: scheduled.content.clone(),
+                        timestamp,
+                        expires_at: self.expires_at(timestamp, None),
+                        attachments: Vec::new(),
+                    }]);
+                };
+
+                Ok(vec![ChatEvent::ScheduledMessageDropped {
+                    message_id,
+                    reason: reason.to_string(),
+                    timestamp,
+                }])
+            }
+
+            ChatCommand::CreatePoll { poll_id, user_id, question, options, multiple_choice, anonymous } => {
+                self.ensure_participant(&user_id)?;
+
+                if self.archived {
+                    return Err(ChatError::RoomArchived("Cannot create polls in an archived room".to_string()));
+                }
+
+                if self.polls.contains_key(&poll_id) {
+                    return Err(ChatError::InvalidOperation(format!("Poll {} already exists", poll_id)));
+                }
+
+                Ok(vec![ChatEvent::PollCreated {
+                    poll_id,
+                    user_id,
+                    question,
+                    options,
+                    multiple_choice,
+                    anonymous,
+                    timestamp: chrono::Utc::now(),
+                }])
+            }
+
+            ChatCommand::CastVote { poll_id, user_id, options } => {
+                self.ensure_participant(&user_id)?;
+
+                if self.archived {
+                    return Err(ChatError::RoomArchived("Cannot vote in an archived room".to_string()));
+                }
+
+                let poll = self.open_poll(&poll_id)?;
+                if let Some(option) = options.iter().find(|&&option| option >= poll.options.len()) {
+                    return Err(ChatError::Validation(ValidationErrors::single(
+                        "options",
+                        format!("option {} does not exist, the poll has {} options", option, poll.options.len()),
+                    )));
+                }
+
+                if !poll.multiple_choice && options.len() > 1 {
+                    return Err(ChatError::Validation(ValidationErrors::single(
+                        "options",
+                        "the poll allows a single option",
+                    )));
+                }
+
+                if poll.votes.get(&user_id) == Some(&options) {
+                    return Ok(vec![]);
+                }
+
+                Ok(vec![ChatEvent::VoteCast {
+                    poll_id,
+                    user_id,
+                    options,
+                    anonymous: poll.anonymous,
+                    timestamp: chrono::Utc::now(),
+                }])
+            }
+
+            ChatCommand::RetractVote { poll_id, user_id } => {
+                self.ensure_participant(&user_id)?;
+
+                let poll = self.open_poll(&poll_id)?;
+                if !poll.votes.contains_key(&user_id) {
+                    return Err(ChatError::InvalidOperation(format!(
+                        "User {} has not voted in poll {}",
+                        user_id, poll_id
+                    )));
+                }
+
+                Ok(vec![ChatEvent::VoteRetracted {
+                    poll_id,
+                    user_id,
+                    anonymous: poll.anonymous,
+                    timestamp: chrono::Utc::now(),
+                }])
+            }
+
+            ChatCommand::ClosePoll { poll_id, user_id } => {
+                let poll = self.open_poll(&poll_id)?;
+                if poll.created_by != user_id && !self.is_moderator(&user_id) {
+                    return Err(ChatError::PermissionDenied(format!(
+                        "User {} did not create poll {} and is not a moderator of the room",
+                        user_id, poll_id
+                    )));
+                }
+
+                Ok(vec![ChatEvent::PollClosed {
+                    poll_id,
+                    user_id,
+                    timestamp: chrono::Utc::now(),
                 }])
             }
+
+            ChatCommand::Batch { commands } => {
+                let mut room = self.clone();
+                let mut events = Vec::new();
+                for command in commands {
+                    for event in room.handle(command, services).await? {
+                        room.apply(event.clone());
+                        events.push(event);
+                    }
+                }
+
+                Ok(events)
+            }
         }
     }
 
@@ -127,41 +680,249 @@ impl Aggregate for ChatRoom {
                 self.usernames.remove(&user_id);
             }
 
-            ChatEvent::MessageSent { message_id, user_id, content, timestamp } => {
+            ChatEvent::MessageSent { message_id, user_id, content, timestamp, expires_at, attachments } => {
+                self.scheduled_messages.remove(&message_id);
+                for attachment in &attachments {
+                    self.pending_attachments.remove(&attachment.attachment_id);
+                }
                 self.messages.push(Message {
                     id: message_id,
                     user_id,
                     content,
                     timestamp,
+                    expires_at,
+                    expired: false,
+                    attachment_ids: attachments.iter().map(|attachment| attachment.attachment_id).collect(),
                 });
             }
-        }
-    }
-}
 
-#[cfg(test)]
-mod aggregate_tests {
-    use std::vec;
+            ChatEvent::MessageExpired { message_id, .. } => {
+                if let Some(message) = self.messages.iter_mut().find(|message| message.id == message_id) {
+                    message.content.clear();
+                    message.expired = true;
+                }
+            }
 
-    use super::*;
-    use cqrs_es::test::TestFramework;
+            ChatEvent::RoomRenamed { user_id: _, name, timestamp: _ } => {
+                self.name = name;
+            }
 
-    type ChatRoomTestFramework = TestFramework<ChatRoom>;
+            ChatEvent::TopicChanged { user_id: _, topic, timestamp: _ } => {
+                self.topic = topic;
+            }
 
-    #[test]
-    fn test_create_room() {
-        let room_id = Uuid::new_v4();
-        let command = ChatCommand::CreateRoom {
-            room_id,
-            name: "Test Room".to_string(),
-            created_by: "user1".to_string(),
-        };
+            ChatEvent::DescriptionChanged { user_id: _, description, timestamp: _ } => {
+                self.description = description;
+            }
 
-        let expected = ChatEvent::RoomCreated {
-            room_id,
-            name: "Test Room".to_string(),
-            created_by: "user1".to_string(),
-            timestamp: chrono::Utc::now(),
+            ChatEvent::RoomArchived { user_id: _, timestamp: _ } => {
+                self.archived = true;
+            }
+
+            ChatEvent::RoomUnarchived { user_id: _, timestamp: _ } => {
+                self.archived = false;
+            }
+
+            ChatEvent::MessagesRead { user_id, up_to_message_id, timestamp: _ } => {
+                self.read_markers.insert(user_id, up_to_message_id);
+            }
+
+            ChatEvent::SlowModeChanged { user_id: _, interval_seconds, timestamp: _ } => {
+                self.slow_mode_seconds = interval_seconds;
+            }
+
+            ChatEvent::MessageTtlChanged { user_id: _, ttl_seconds, timestamp: _ } => {
+                self.message_ttl_seconds = ttl_seconds;
+            }
+
+            ChatEvent::IncomingWebhookCreated { user_id: _, hook_id, name, token_hash: _, timestamp: _ } => {
+                self.incoming_webhooks.insert(hook_id, name);
+            }
+
+            ChatEvent::IncomingWebhookRevoked { user_id: _, hook_id, timestamp: _ } => {
+                self.incoming_webhooks.remove(&hook_id);
+            }
+
+            ChatEvent::AttachmentUploaded { attachment, user_id, timestamp: _ } => {
+                self.pending_attachments
+                    .insert(attachment.attachment_id, PendingAttachment { user_id, attachment });
+            }
+
+            ChatEvent::AttachmentDiscarded { attachment_id, .. } => {
+                self.pending_attachments.remove(&attachment_id);
+            }
+
+            ChatEvent::MessageScheduled { message_id, user_id, content, deliver_at, timestamp: _ } => {
+                self.scheduled_messages.insert(
+                    message_id,
+                    ScheduledMessage {
+                        user_id,
+                        content,
+                        deliver_at,
+                    },
+                );
+            }
+
+            ChatEvent::ScheduledMessageCancelled { user_id: _, message_id, timestamp: _ }
+            | ChatEvent::ScheduledMessageDropped { message_id, reason: _, timestamp: _ } => {
+                self.scheduled_messages.remove(&message_id);
+            }
+
+            ChatEvent::PollCreated { poll_id, user_id, question: _, options, multiple_choice, anonymous, timestamp: _ } => {
+                self.polls.insert(
+                    poll_id,
+                    Poll {
+                        created_by: user_id,
+                        options,
+                        multiple_choice,
+                        anonymous,
+                        closed: false,
+                        votes: HashMap::new(),
+                    },
+                );
+            }
+
+            ChatEvent::VoteCast { poll_id, user_id, options, anonymous: _, timestamp: _ } => {
+                if let Some(poll) = self.polls.get_mut(&poll_id) {
+                    poll.votes.insert(user_id, options);
+                }
+            }
+
+            ChatEvent::VoteRetracted { poll_id, user_id, anonymous: _, timestamp: _ } => {
+                if let Some(poll) = self.polls.get_mut(&poll_id) {
+                    poll.votes.remove(&user_id);
+                }
+            }
+
+            ChatEvent::PollClosed { poll_id, user_id: _, timestamp: _ } => {
+                if let Some(poll) = self.polls.get_mut(&poll_id) {
+                    poll.closed = true;
+                }
+            }
+        }
+    }
+}
+
+impl ChatRoom {
+    fn message_position(&self, message_id: &Uuid) -> Option<usize> {
+        self.messages.iter().position(|message| &message.id == message_id)
+    }
+
+    /// A poll that still takes votes.
+    fn open_poll(&self, poll_id: &Uuid) -> Result<&Poll, ChatError> {
+        if self.room_id.is_none() {
+            return Err(ChatError::RoomNotFound("Room does not exist".to_string()));
+        }
+
+        match self.polls.get(poll_id) {
+            Some(poll) if poll.closed => Err(ChatError::InvalidOperation(format!("Poll {} is closed", poll_id))),
+            Some(poll) => Ok(poll),
+            None => Err(ChatError::InvalidOperation(format!("Poll {} does not exist", poll_id))),
+        }
+    }
+
+    fn ensure_participant(&self, user_id: &str) -> Result<(), ChatError> {
+        if self.room_id.is_none() {
+            return Err(ChatError::RoomNotFound("Room does not exist".to_string()));
+        }
+
+        if !self.participants.contains(user_id) {
+            return Err(ChatError::UserNotInRoom(format!("User {} is not in the room", user_id)));
+        }
+
+        Ok(())
+    }
+
+    fn ensure_owner(&self, user_id: &str) -> Result<(), ChatError> {
+        if self.room_id.is_none() {
+            return Err(ChatError::RoomNotFound("Room does not exist".to_string()));
+        }
+
+        if self.created_by.as_deref() != Some(user_id) {
+            return Err(ChatError::PermissionDenied(format!("User {} is not the owner of the room", user_id)));
+        }
+
+        Ok(())
+    }
+
+    /// A room's only moderator is its owner, who created it; there is no role
+    /// to make anyone else one. Moderators rename the room, set its topic,
+    /// description, slow mode and message time-to-live, close any poll and are
+    /// never held back by slow mode.
+    fn is_moderator(&self, user_id: &str) -> bool {
+        self.created_by.as_deref() == Some(user_id)
+    }
+
+    fn ensure_moderator(&self, user_id: &str) -> Result<(), ChatError> {
+        if self.room_id.is_none() {
+            return Err(ChatError::RoomNotFound("Room does not exist".to_string()));
+        }
+
+        if !self.is_moderator(user_id) {
+            return Err(ChatError::PermissionDenied(format!("User {} is not a moderator of the room", user_id)));
+        }
+
+        Ok(())
+    }
+
+    /// When a message sent at `timestamp` expires: after the shorter of its own
+    /// time-to-live and the room's, or never when neither is set.
+    fn expires_at(
+        &self,
+        timestamp: chrono::DateTime<chrono::Utc>,
+        ttl_seconds: Option<u64>,
+    ) -> Option<chrono::DateTime<chrono::Utc>> {
+        let room_ttl = (self.message_ttl_seconds > 0).then_some(self.message_ttl_seconds);
+        let ttl_seconds = ttl_seconds.into_iter().chain(room_ttl).min()?;
+        // A timestamp too close to the end of time never expires rather than overflowing.
+        Some(
+            timestamp
+                .checked_add_signed(chrono::Duration::seconds(ttl_seconds as i64))
+                .unwrap_or(chrono::DateTime::<chrono::Utc>::MAX_UTC),
+        )
+    }
+
+    /// Whole seconds, rounded up, that slow mode still holds back a message sent
+    /// by `user_id` at `now`, measured from their previous message.
+    fn slow_mode_remaining(&self, user_id: &str, now: chrono::DateTime<chrono::Utc>) -> Option<u64> {
+        if self.slow_mode_seconds == 0 || self.is_moderator(user_id) {
+            return None;
+        }
+
+        let previous = self.messages.iter().rev().find(|message| message.user_id == user_id)?;
+        let interval = chrono::Duration::seconds(self.slow_mode_seconds as i64);
+        let remaining = interval - (now - previous.timestamp);
+        (remaining > chrono::Duration::zero()).then(|| (remaining.num_milliseconds() as u64).div_ceil(1000))
+    }
+}
+
+#[cfg(test)]
+mod aggregate_tests {
+    use std::vec;
+
+    use super::*;
+    use cqrs_es::test::{AggregateResultValidator, TestFramework};
+
+    type ChatRoomTestFramework = TestFramework<ChatRoom>;
+
+    trait ChatRoomResultValidator {
+        fn then_expect_events_matching(self, matcher: impl FnOnce(&[ChatEvent]) -> bool);
+    }
+
+    impl ChatRoomResultValidator for AggregateResultValidator<ChatRoom> {
+        fn then_expect_events_matching(self, matcher: impl FnOnce(&[ChatEvent]) -> bool) {
+            let events = self.inspect_result().expect("expected events but the command failed");
+            assert!(matcher(&events), "events did not match: {:?}", events);
+        }
+    }
+
+    #[test]
+    fn test_create_room() {
+        let room_id = Uuid::new_v4();
+        let command = ChatCommand::CreateRoom {
+            room_id,
+            name: "Test Room".to_string(),
+            created_by: "user1".to_string(),
         };
 
         ChatRoomTestFramework::with(ChatServices)
@@ -237,6 +998,8 @@ mod aggregate_tests {
             user_id: "user2".to_string(),
             content: "Hello, world!".to_string(),
             timestamp,
+            ttl_seconds: None,
+            attachment_ids: Vec::new(),
         };
 
         ChatRoomTestFramework::with(ChatServices)
@@ -245,11 +1008,13 @@ mod aggregate_tests {
             .then_expect_events_matching(|events| {
                 assert_eq!(events.len(), 1);
                 match &events[0] {
-                    ChatEvent::MessageSent { message_id: m, user_id, content, timestamp: t } => {
+                    ChatEvent::MessageSent { message_id: m, user_id, content, timestamp: t, expires_at, attachments } => {
                         assert_eq!(m, &message_id);
                         assert_eq!(user_id, "user2");
                         assert_eq!(content, "Hello, world!");
                         assert_eq!(t, &timestamp);
+                        assert_eq!(expires_at, &None);
+                        assert!(attachments.is_empty());
                         true
                     }
                     _ => false,
@@ -292,4 +1057,846 @@ mod aggregate_tests {
                 }
             });
     }
+
+    #[test]
+    fn test_rename_room() {
+        let room_id = Uuid::new_v4();
+        let previous = ChatEvent::RoomCreated {
+            room_id,
+            name: "Test Room".to_string(),
+            created_by: "user1".to_string(),
+            timestamp: chrono::Utc::now(),
+        };
+
+        let command = ChatCommand::RenameRoom {
+            user_id: "user1".to_string(),
+            name: "Renamed Room".to_string(),
+        };
+
+        ChatRoomTestFramework::with(ChatServices)
+            .given(vec![previous])
+            .when(command)
+            .then_expect_events_matching(|events| {
+                assert_eq!(events.len(), 1);
+                match &events[0] {
+                    ChatEvent::RoomRenamed { user_id, name, timestamp: _ } => {
+                        assert_eq!(user_id, "user1");
+                        assert_eq!(name, "Renamed Room");
+                        true
+                    }
+                    _ => false,
+                }
+            });
+    }
+
+    #[test]
+    fn test_room_details_require_a_moderator() {
+        let room_id = Uuid::new_v4();
+        let previous_events = vec![
+            ChatEvent::RoomCreated {
+                room_id,
+                name: "Test Room".to_string(),
+                created_by: "user1".to_string(),
+                timestamp: chrono::Utc::now(),
+            },
+            ChatEvent::UserJoined {
+                user_id: "user2".to_string(),
+                username: "User Two".to_string(),
+                timestamp: chrono::Utc::now(),
+            },
+        ];
+        let commands = [
+            ChatCommand::RenameRoom {
+                user_id: "user2".to_string(),
+                name: "Taken Over".to_string(),
+            },
+            ChatCommand::SetTopic {
+                user_id: "user2".to_string(),
+                topic: "Taken over".to_string(),
+            },
+            ChatCommand::SetDescription {
+                user_id: "user2".to_string(),
+                description: "Taken over".to_string(),
+            },
+        ];
+
+        for command in commands {
+            ChatRoomTestFramework::with(ChatServices)
+                .given(previous_events.clone())
+                .when(command)
+                .then_expect_error_message("Permission denied: User user2 is not a moderator of the room");
+        }
+    }
+
+    #[test]
+    fn test_archived_room_details_cannot_change() {
+        let room_id = Uuid::new_v4();
+        let previous_events = vec![
+            ChatEvent::RoomCreated {
+                room_id,
+                name: "Test Room".to_string(),
+                created_by: "user1".to_string(),
+                timestamp: chrono::Utc::now(),
+            },
+            ChatEvent::RoomArchived {
+                user_id: "user1".to_string(),
+                timestamp: chrono::Utc::now(),
+            },
+        ];
+
+        ChatRoomTestFramework::with(ChatServices)
+            .given(previous_events.clone())
+            .when(ChatCommand::RenameRoom {
+                user_id: "user1".to_string(),
+                name: "Renamed Room".to_string(),
+            })
+            .then_expect_error_message("Room is archived: Cannot rename an archived room");
+
+        ChatRoomTestFramework::with(ChatServices)
+            .given(previous_events.clone())
+            .when(ChatCommand::SetTopic {
+                user_id: "user1".to_string(),
+                topic: "Topic".to_string(),
+            })
+            .then_expect_error_message("Room is archived: Cannot change the topic of an archived room");
+
+        ChatRoomTestFramework::with(ChatServices)
+            .given(previous_events)
+            .when(ChatCommand::SetDescription {
+                user_id: "user1".to_string(),
+                description: "Description".to_string(),
+            })
+            .then_expect_error_message("Room is archived: Cannot change the description of an archived room");
+    }
+
+    #[test]
+    fn test_archived_room_rejects_messages_and_joins() {
+        let room_id = Uuid::new_v4();
+        let previous_events = vec![
+            ChatEvent::RoomCreated {
+                room_id,
+                name: "Test Room".to_string(),
+                created_by: "user1".to_string(),
+                timestamp: chrono::Utc::now(),
+            },
+            ChatEvent::RoomArchived {
+                user_id: "user1".to_string(),
+                timestamp: chrono::Utc::now(),
+            },
+        ];
+
+        let send = ChatCommand::SendMessage {
+            message_id: Uuid::new_v4(),
+            user_id: "user1".to_string(),
+            content: "Hello?".to_string(),
+            timestamp: chrono::Utc::now(),
+            ttl_seconds: None,
+            attachment_ids: Vec::new(),
+        };
+
+        ChatRoomTestFramework::with(ChatServices)
+            .given(previous_events.clone())
+            .when(send)
+            .then_expect_error_message("Room is archived: Cannot send messages to an archived room");
+
+        let join = ChatCommand::JoinRoom {
+            user_id: "user2".to_string(),
+            username: "User Two".to_string(),
+        };
+
+        ChatRoomTestFramework::with(ChatServices)
+            .given(previous_events)
+            .when(join)
+            .then_expect_error_message("Room is archived: Cannot join an archived room");
+    }
+
+    #[test]
+    fn test_archive_room_requires_owner() {
+        let room_id = Uuid::new_v4();
+        let previous_events = vec![
+            ChatEvent::RoomCreated {
+                room_id,
+                name: "Test Room".to_string(),
+                created_by: "user1".to_string(),
+                timestamp: chrono::Utc::now(),
+            },
+            ChatEvent::UserJoined {
+                user_id: "user2".to_string(),
+                username: "User Two".to_string(),
+                timestamp: chrono::Utc::now(),
+            },
+        ];
+
+        let command = ChatCommand::ArchiveRoom {
+            user_id: "user2".to_string(),
+        };
+
+        ChatRoomTestFramework::with(ChatServices)
+            .given(previous_events)
+            .when(command)
+            .then_expect_error_message("Permission denied: User user2 is not the owner of the room");
+    }
+    #[test]
+    fn test_send_message_rejects_blank_content() {
+        let room_id = Uuid::new_v4();
+        let previous = ChatEvent::RoomCreated {
+            room_id,
+            name: "Test Room".to_string(),
+            created_by: "user1".to_string(),
+            timestamp: chrono::Utc::now(),
+        };
+
+        let command = ChatCommand::SendMessage {
+            message_id: Uuid::new_v4(),
+            user_id: "user1".to_string(),
+            content: "   ".to_string(),
+            timestamp: chrono::Utc::now(),
+            ttl_seconds: None,
+            attachment_ids: Vec::new(),
+        };
+
+        ChatRoomTestFramework::with(ChatServices)
+            .given(vec![previous])
+            .when(command)
+            .then_expect_error_message("Validation failed: content: must not be empty");
+    }
+
+    #[test]
+    fn test_send_message_rejects_a_reused_message_id() {
+        let room_id = Uuid::new_v4();
+        let message_id = Uuid::new_v4();
+        let previous_events = vec![
+            ChatEvent::RoomCreated {
+                room_id,
+                name: "Test Room".to_string(),
+                created_by: "user1".to_string(),
+                timestamp: chrono::Utc::now(),
+            },
+            ChatEvent::MessageSent {
+                message_id,
+                user_id: "user1".to_string(),
+                content: "Hello".to_string(),
+                timestamp: chrono::Utc::now(),
+                expires_at: None,
+                attachments: Vec::new(),
+            },
+        ];
+        let send = |message_id| ChatCommand::SendMessage {
+            message_id,
+            user_id: "user1".to_string(),
+            content: "Hello again".to_string(),
+            timestamp: chrono::DateTime::<chrono::Utc>::MAX_UTC,
+            ttl_seconds: Some(60),
+            attachment_ids: Vec::new(),
+        };
+
+        ChatRoomTestFramework::with(ChatServices)
+            .given(previous_events.clone())
+            .when(send(message_id))
+            .then_expect_error_message(&format!("Invalid operation: Message {} already exists", message_id));
+
+        // A timestamp at the end of time saturates its expiry instead of overflowing.
+        ChatRoomTestFramework::with(ChatServices)
+            .given(previous_events)
+            .when(send(Uuid::new_v4()))
+            .then_expect_events_matching(|events| {
+                matches!(events, [ChatEvent::MessageSent { expires_at: Some(expires_at), .. }]
+                    if *expires_at == chrono::DateTime::<chrono::Utc>::MAX_UTC)
+            });
+    }
+
+    #[test]
+    fn test_mark_read_only_moves_forward() {
+        let room_id = Uuid::new_v4();
+        let first = Uuid::new_v4();
+        let second = Uuid::new_v4();
+        let message = |message_id| ChatEvent::MessageSent {
+            message_id,
+            user_id: "user1".to_string(),
+            content: "Hello".to_string(),
+            timestamp: chrono::Utc::now(),
+            expires_at: None,
+            attachments: Vec::new(),
+        };
+        let previous_events = vec![
+            ChatEvent::RoomCreated {
+                room_id,
+                name: "Test Room".to_string(),
+                created_by: "user1".to_string(),
+                timestamp: chrono::Utc::now(),
+            },
+            message(first),
+            message(second),
+            ChatEvent::MessagesRead {
+                user_id: "user1".to_string(),
+                up_to_message_id: second,
+                timestamp: chrono::Utc::now(),
+            },
+        ];
+
+        let command = ChatCommand::MarkRead {
+            user_id: "user1".to_string(),
+            up_to_message_id: first,
+        };
+
+        ChatRoomTestFramework::with(ChatServices)
+            .given(previous_events)
+            .when(command)
+            .then_expect_events(vec![]);
+    }
+
+    #[test]
+    fn test_mark_read_rejects_unknown_message() {
+        let room_id = Uuid::new_v4();
+        let previous = ChatEvent::RoomCreated {
+            room_id,
+            name: "Test Room".to_string(),
+            created_by: "user1".to_string(),
+            timestamp: chrono::Utc::now(),
+        };
+        let message_id = Uuid::new_v4();
+
+        let command = ChatCommand::MarkRead {
+            user_id: "user1".to_string(),
+            up_to_message_id: message_id,
+        };
+
+        ChatRoomTestFramework::with(ChatServices)
+            .given(vec![previous])
+            .when(command)
+            .then_expect_error_message(&format!("Invalid operation: Message {} is not in the room", message_id));
+    }
+
+    #[test]
+    fn test_slow_mode_holds_back_non_moderators() {
+        let room_id = Uuid::new_v4();
+        let now = chrono::Utc::now();
+        let message = |user_id: &str, timestamp| ChatEvent::MessageSent {
+            message_id: Uuid::new_v4(),
+            user_id: user_id.to_string(),
+            content: "Hello".to_string(),
+            timestamp,
+            expires_at: None,
+            attachments: Vec::new(),
+        };
+        // The room as it stands with each user's last message sent `seconds_ago`.
+        let previous_events = |seconds_ago: i64| {
+            let sent_at = now - chrono::Duration::milliseconds(seconds_ago * 1000 + 500);
+            vec![
+                ChatEvent::RoomCreated {
+                    room_id,
+                    name: "Test Room".to_string(),
+                    created_by: "user1".to_string(),
+                    timestamp: sent_at,
+                },
+                ChatEvent::UserJoined {
+                    user_id: "user2".to_string(),
+                    username: "User Two".to_string(),
+                    timestamp: sent_at,
+                },
+                ChatEvent::SlowModeChanged {
+                    user_id: "user1".to_string(),
+                    interval_seconds: 30,
+                    timestamp: sent_at,
+                },
+                message("user1", sent_at),
+                message("user2", sent_at),
+            ]
+        };
+        let send = |user_id: &str, timestamp| ChatCommand::SendMessage {
+            message_id: Uuid::new_v4(),
+            user_id: user_id.to_string(),
+            content: "Again".to_string(),
+            timestamp,
+            ttl_seconds: None,
+            attachment_ids: Vec::new(),
+        };
+
+        ChatRoomTestFramework::with(ChatServices)
+            .given(previous_events(10))
+            .when(send("user2", now))
+            .then_expect_error_message(
+                "Slow mode is on: wait 20 more seconds before sending another message",
+            );
+
+        // The server's clock decides, not the time the sender claims.
+        ChatRoomTestFramework::with(ChatServices)
+            .given(previous_events(10))
+            .when(send("user2", now + chrono::Duration::hours(1)))
+            .then_expect_error_message(
+                "Slow mode is on: wait 20 more seconds before sending another message",
+            );
+
+        ChatRoomTestFramework::with(ChatServices)
+            .given(previous_events(30))
+            .when(send("user2", now))
+            .then_expect_events_matching(|events| matches!(events, [ChatEvent::MessageSent { .. }]));
+
+        ChatRoomTestFramework::with(ChatServices)
+            .given(previous_events(0))
+            .when(send("user1", now))
+            .then_expect_events_matching(|events| matches!(events, [ChatEvent::MessageSent { .. }]));
+    }
+
+    #[test]
+    fn test_set_slow_mode_requires_moderator() {
+        let room_id = Uuid::new_v4();
+        let previous_events = vec![
+            ChatEvent::RoomCreated {
+                room_id,
+                name: "Test Room".to_string(),
+                created_by: "user1".to_string(),
+                timestamp: chrono::Utc::now(),
+            },
+            ChatEvent::UserJoined {
+                user_id: "user2".to_string(),
+                username: "User Two".to_string(),
+                timestamp: chrono::Utc::now(),
+            },
+        ];
+
+        let command = ChatCommand::SetSlowMode {
+            user_id: "user2".to_string(),
+            interval_seconds: 30,
+        };
+
+        ChatRoomTestFramework::with(ChatServices)
+            .given(previous_events)
+            .when(command)
+            .then_expect_error_message("Permission denied: User user2 is not a moderator of the room");
+    }
+
+    #[test]
+    fn test_incoming_webhook_bot_joins_and_leaves_with_it() {
+        let room_id = Uuid::new_v4();
+        let hook_id = Uuid::new_v4();
+        let bot = incoming_webhook_user_id(&hook_id);
+        let created = ChatEvent::RoomCreated {
+            room_id,
+            name: "Test Room".to_string(),
+            created_by: "user1".to_string(),
+            timestamp: chrono::Utc::now(),
+        };
+        let hook_created = ChatEvent::IncomingWebhookCreated {
+            user_id: "user1".to_string(),
+            hook_id,
+            name: "CI".to_string(),
+            token_hash: "ab".repeat(32),
+            timestamp: chrono::Utc::now(),
+        };
+
+        let command = ChatCommand::CreateIncomingWebhook {
+            user_id: "user1".to_string(),
+            hook_id,
+            name: "CI".to_string(),
+            token_hash: "ab".repeat(32),
+        };
+        let expected_bot = bot.clone();
+        ChatRoomTestFramework::with(ChatServices)
+            .given(vec![created.clone()])
+            .when(command)
+            .then_expect_events_matching(|events| {
+                matches!(events, [
+                    ChatEvent::IncomingWebhookCreated { .. },
+                    ChatEvent::UserJoined { user_id, username, .. },
+                ] if *user_id == expected_bot && username == "CI")
+            });
+
+        let bot_joined = ChatEvent::UserJoined {
+            user_id: bot.clone(),
+            username: "CI".to_string(),
+            timestamp: chrono::Utc::now(),
+        };
+        let command = ChatCommand::RevokeIncomingWebhook {
+            user_id: "user1".to_string(),
+            hook_id,
+        };
+        ChatRoomTestFramework::with(ChatServices)
+            .given(vec![created.clone(), hook_created.clone(), bot_joined.clone()])
+            .when(command)
+            .then_expect_events_matching(|events| {
+                matches!(events, [
+                    ChatEvent::IncomingWebhookRevoked { .. },
+                    ChatEvent::UserLeft { user_id, .. },
+                ] if *user_id == bot)
+            });
+
+        let command = ChatCommand::RevokeIncomingWebhook {
+            user_id: "user2".to_string(),
+            hook_id,
+        };
+        ChatRoomTestFramework::with(ChatServices)
+            .given(vec![created, hook_created, bot_joined])
+            .when(command)
+            .then_expect_error_message("Permission denied: User user2 is not the owner of the room");
+    }
+
+    #[test]
+    fn test_kick_user_requires_moderator_and_spares_them() {
+        let room_id = Uuid::new_v4();
+        let previous_events = vec![
+            ChatEvent::RoomCreated {
+                room_id,
+                name: "Test Room".to_string(),
+                created_by: "user1".to_string(),
+                timestamp: chrono::Utc::now(),
+            },
+            ChatEvent::UserJoined {
+                user_id: "user2".to_string(),
+                username: "User Two".to_string(),
+                timestamp: chrono::Utc::now(),
+            },
+        ];
+        let kick = |user_id: &str, target_user_id: &str| ChatCommand::KickUser {
+            user_id: user_id.to_string(),
+            target_user_id: target_user_id.to_string(),
+        };
+
+        ChatRoomTestFramework::with(ChatServices)
+            .given(previous_events.clone())
+            .when(kick("user1", "user2"))
+            .then_expect_events_matching(|events| {
+                matches!(events, [ChatEvent::UserLeft { user_id, .. }] if user_id == "user2")
+            });
+
+        ChatRoomTestFramework::with(ChatServices)
+            .given(previous_events.clone())
+            .when(kick("user2", "user1"))
+            .then_expect_error_message("Permission denied: User user2 is not a moderator of the room");
+
+        ChatRoomTestFramework::with(ChatServices)
+            .given(previous_events)
+            .when(kick("user1", "user1"))
+            .then_expect_error_message("Invalid operation: Moderators cannot be kicked");
+    }
+
+    #[test]
+    fn test_scheduled_message_is_dropped_once_its_sender_leaves() {
+        let room_id = Uuid::new_v4();
+        let message_id = Uuid::new_v4();
+        let previous_events = vec![
+            ChatEvent::RoomCreated {
+                room_id,
+                name: "Test Room".to_string(),
+                created_by: "user1".to_string(),
+                timestamp: chrono::Utc::now(),
+            },
+            ChatEvent::UserJoined {
+                user_id: "user2".to_string(),
+                username: "User Two".to_string(),
+                timestamp: chrono::Utc::now(),
+            },
+            ChatEvent::MessageScheduled {
+                message_id,
+                user_id: "user2".to_string(),
+                content: "Later".to_string(),
+                deliver_at: chrono::Utc::now(),
+                timestamp: chrono::Utc::now(),
+            },
+        ];
+
+        ChatRoomTestFramework::with(ChatServices)
+            .given(previous_events.clone())
+            .when(ChatCommand::CancelScheduledMessage {
+                user_id: "user1".to_string(),
+                message_id,
+            })
+            .then_expect_error_message(&format!("Permission denied: User user1 did not schedule message {}", message_id));
+
+        let mut left = previous_events;
+        left.push(ChatEvent::UserLeft {
+            user_id: "user2".to_string(),
+            timestamp: chrono::Utc::now(),
+        });
+        ChatRoomTestFramework::with(ChatServices)
+            .given(left)
+            .when(ChatCommand::DeliverScheduledMessage { message_id })
+            .then_expect_events_matching(|events| {
+                matches!(events, [ChatEvent::ScheduledMessageDropped { reason, .. }] if reason == "the sender has left the room")
+            });
+    }
+
+    #[test]
+    fn test_messages_expire_after_the_shorter_ttl() {
+        let room_id = Uuid::new_v4();
+        let message_id = Uuid::new_v4();
+        let sent_at = chrono::Utc::now();
+        let previous_events = vec![
+            ChatEvent::RoomCreated {
+                room_id,
+                name: "Test Room".to_string(),
+                created_by: "user1".to_string(),
+                timestamp: sent_at,
+            },
+            ChatEvent::MessageTtlChanged {
+                user_id: "user1".to_string(),
+                ttl_seconds: 3600,
+                timestamp: sent_at,
+            },
+        ];
+        let send = |ttl_seconds| ChatCommand::SendMessage {
+            message_id,
+            user_id: "user1".to_string(),
+            content: "Gone soon".to_string(),
+            timestamp: sent_at,
+            ttl_seconds,
+            attachment_ids: Vec::new(),
+        };
+
+        ChatRoomTestFramework::with(ChatServices)
+            .given(previous_events.clone())
+            .when(send(Some(60)))
+            .then_expect_events_matching(|events| {
+                matches!(events, [ChatEvent::MessageSent { expires_at: Some(at), .. }] if *at == sent_at + chrono::Duration::seconds(60))
+            });
+        ChatRoomTestFramework::with(ChatServices)
+            .given(previous_events.clone())
+            .when(send(None))
+            .then_expect_events_matching(|events| {
+                matches!(events, [ChatEvent::MessageSent { expires_at: Some(at), .. }] if *at == sent_at + chrono::Duration::hours(1))
+            });
+
+        let mut sent = previous_events;
+        sent.push(ChatEvent::MessageSent {
+            message_id,
+            user_id: "user1".to_string(),
+            content: "Gone soon".to_string(),
+            timestamp: sent_at,
+            expires_at: Some(sent_at + chrono::Duration::hours(1)),
+            attachments: Vec::new(),
+        });
+        ChatRoomTestFramework::with(ChatServices)
+            .given(sent.clone())
+            .when(ChatCommand::ExpireMessage { message_id })
+            .then_expect_error_message(&format!("Invalid operation: Message {} has not expired", message_id));
+
+        let mut due = sent;
+        if let Some(ChatEvent::MessageSent { expires_at, .. }) = due.last_mut() {
+            *expires_at = Some(sent_at);
+        }
+        ChatRoomTestFramework::with(ChatServices)
+            .given(due.clone())
+            .when(ChatCommand::ExpireMessage { message_id })
+            .then_expect_events_matching(|events| matches!(events, [ChatEvent::MessageExpired { .. }]));
+
+        due.push(ChatEvent::MessageExpired {
+            message_id,
+            attachment_ids: Vec::new(),
+            timestamp: chrono::Utc::now(),
+        });
+        ChatRoomTestFramework::with(ChatServices)
+            .given(due)
+            .when(ChatCommand::ExpireMessage { message_id })
+            .then_expect_events(vec![]);
+    }
+
+    #[test]
+    fn test_messages_carry_only_their_senders_pending_attachments() {
+        let room_id = Uuid::new_v4();
+        let attachment = Attachment {
+            attachment_id: Uuid::new_v4(),
+            name: "notes.txt".to_string(),
+            mime_type: "text/plain".to_string(),
+            size: 5,
+            checksum: "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824".to_string(),
+        };
+        let previous_events = vec![
+            ChatEvent::RoomCreated {
+                room_id,
+                name: "Test Room".to_string(),
+                created_by: "user1".to_string(),
+                timestamp: chrono::Utc::now(),
+            },
+            ChatEvent::UserJoined {
+                user_id: "user2".to_string(),
+                username: "Bob".to_string(),
+                timestamp: chrono::Utc::now(),
+            },
+            ChatEvent::AttachmentUploaded {
+                attachment: attachment.clone(),
+                user_id: "user1".to_string(),
+                timestamp: chrono::Utc::now(),
+            },
+        ];
+        let send = |user_id: &str, attachment_id| ChatCommand::SendMessage {
+            message_id: Uuid::new_v4(),
+            user_id: user_id.to_string(),
+            content: String::new(),
+            timestamp: chrono::Utc::now(),
+            ttl_seconds: None,
+            attachment_ids: vec![attachment_id],
+        };
+
+        ChatRoomTestFramework::with(ChatServices)
+            .given(previous_events.clone())
+            .when(send("user2", attachment.attachment_id))
+            .then_expect_error_message(&format!(
+                "Permission denied: User user2 did not upload attachment {}",
+                attachment.attachment_id
+            ));
+        let expected = vec![attachment.clone()];
+        ChatRoomTestFramework::with(ChatServices)
+            .given(previous_events.clone())
+            .when(send("user1", attachment.attachment_id))
+            .then_expect_events_matching(|events| {
+                matches!(events, [ChatEvent::MessageSent { attachments, .. }] if *attachments == expected)
+            });
+
+        // An upload left waiting is discarded, but one that was sent is kept.
+        let discard = ChatCommand::DiscardAttachment {
+            attachment_id: attachment.attachment_id,
+        };
+        ChatRoomTestFramework::with(ChatServices)
+            .given(previous_events.clone())
+            .when(discard.clone())
+            .then_expect_events_matching(|events| {
+                matches!(events, [ChatEvent::AttachmentDiscarded { user_id, .. }] if user_id == "user1")
+            });
+
+        // Once sent, the upload can't be attached to another message.
+        let message_id = Uuid::new_v4();
+        let mut sent = previous_events;
+        sent.push(ChatEvent::MessageSent {
+            message_id,
+            user_id: "user1".to_string(),
+            content: String::new(),
+            timestamp: chrono::Utc::now(),
+            expires_at: Some(chrono::Utc::now()),
+            attachments: vec![attachment.clone()],
+        });
+        ChatRoomTestFramework::with(ChatServices)
+            .given(sent.clone())
+            .when(send("user1", attachment.attachment_id))
+            .then_expect_error_message(&format!(
+                "Invalid operation: Attachment {} is not waiting to be sent",
+                attachment.attachment_id
+            ));
+        ChatRoomTestFramework::with(ChatServices)
+            .given(sent.clone())
+            .when(discard)
+            .then_expect_events(vec![]);
+
+        // Its message's expiry names it, so its bytes can be deleted.
+        ChatRoomTestFramework::with(ChatServices)
+            .given(sent)
+            .when(ChatCommand::ExpireMessage { message_id })
+            .then_expect_events_matching(|events| {
+                matches!(events, [ChatEvent::MessageExpired { attachment_ids, .. }] if *attachment_ids == vec![attachment.attachment_id])
+            });
+    }
+
+    #[test]
+    fn test_polls_enforce_choice_limits_and_who_may_close_them() {
+        let room_id = Uuid::new_v4();
+        let poll_id = Uuid::new_v4();
+        let previous_events = vec![
+            ChatEvent::RoomCreated {
+                room_id,
+                name: "Test Room".to_string(),
+                created_by: "user1".to_string(),
+                timestamp: chrono::Utc::now(),
+            },
+            ChatEvent::UserJoined {
+                user_id: "user2".to_string(),
+                username: "Bob".to_string(),
+                timestamp: chrono::Utc::now(),
+            },
+            ChatEvent::UserJoined {
+                user_id: "user3".to_string(),
+                username: "Carol".to_string(),
+                timestamp: chrono::Utc::now(),
+            },
+            ChatEvent::PollCreated {
+                poll_id,
+                user_id: "user2".to_string(),
+                question: "Lunch?".to_string(),
+                options: vec!["Pizza".to_string(), "Sushi".to_string()],
+                multiple_choice: false,
+                anonymous: false,
+                timestamp: chrono::Utc::now(),
+            },
+        ];
+        let vote = |options| ChatCommand::CastVote {
+            poll_id,
+            user_id: "user3".to_string(),
+            options,
+        };
+
+        ChatRoomTestFramework::with(ChatServices)
+            .given(previous_events.clone())
+            .when(vote(vec![0, 1]))
+            .then_expect_error_message("Validation failed: options: the poll allows a single option");
+        ChatRoomTestFramework::with(ChatServices)
+            .given(previous_events.clone())
+            .when(vote(vec![2]))
+            .then_expect_error_message("Validation failed: options: option 2 does not exist, the poll has 2 options");
+        ChatRoomTestFramework::with(ChatServices)
+            .given(previous_events.clone())
+            .when(ChatCommand::ClosePoll {
+                poll_id,
+                user_id: "user3".to_string(),
+            })
+            .then_expect_error_message(&format!(
+                "Permission denied: User user3 did not create poll {} and is not a moderator of the room",
+                poll_id
+            ));
+        ChatRoomTestFramework::with(ChatServices)
+            .given(previous_events.clone())
+            .when(ChatCommand::ClosePoll {
+                poll_id,
+                user_id: "user1".to_string(),
+            })
+            .then_expect_events_matching(|events| matches!(events, [ChatEvent::PollClosed { .. }]));
+
+        let mut closed = previous_events;
+        closed.push(ChatEvent::PollClosed {
+            poll_id,
+            user_id: "user2".to_string(),
+            timestamp: chrono::Utc::now(),
+        });
+        ChatRoomTestFramework::with(ChatServices)
+            .given(closed)
+            .when(vote(vec![1]))
+            .then_expect_error_message(&format!("Invalid operation: Poll {} is closed", poll_id));
+    }
+
+    #[test]
+    fn test_batch_produces_all_events_or_none() {
+        let room_id = Uuid::new_v4();
+        let previous = ChatEvent::RoomCreated {
+            room_id,
+            name: "Test Room".to_string(),
+            created_by: "user1".to_string(),
+            timestamp: chrono::Utc::now(),
+        };
+        let join = ChatCommand::JoinRoom {
+            user_id: "user2".to_string(),
+            username: "User Two".to_string(),
+        };
+        let send = ChatCommand::SendMessage {
+            message_id: Uuid::new_v4(),
+            user_id: "user2".to_string(),
+            content: "Hello".to_string(),
+            timestamp: chrono::Utc::now(),
+            ttl_seconds: None,
+            attachment_ids: Vec::new(),
+        };
+
+        // The message is accepted because the join before it has been applied.
+        ChatRoomTestFramework::with(ChatServices)
+            .given(vec![previous.clone()])
+            .when(ChatCommand::Batch {
+                commands: vec![join.clone(), send],
+            })
+            .then_expect_events_matching(|events| {
+                matches!(events, [ChatEvent::UserJoined { .. }, ChatEvent::MessageSent { .. }])
+            });
+
+        ChatRoomTestFramework::with(ChatServices)
+            .given(vec![previous])
+            .when(ChatCommand::Batch {
+                commands: vec![join.clone(), join],
+            })
+            .then_expect_error_message("User already in room: User user2 is already in the room");
+    }
 }
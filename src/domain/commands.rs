@@ -1,7 +1,8 @@
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub enum ChatCommand {
     CreateRoom {
         room_id: Uuid,
@@ -15,10 +16,228 @@ pub enum ChatCommand {
     LeaveRoom {
         user_id: String,
     },
+    /// Removes another participant from the room.
+    KickUser {
+        user_id: String,
+        target_user_id: String,
+    },
     SendMessage {
         message_id: Uuid,
         user_id: String,
         content: String,
         timestamp: chrono::DateTime<chrono::Utc>,
+        /// Seconds until the message expires; the room's message TTL applies when it is shorter.
+        #[serde(default)]
+        ttl_seconds: Option<u64>,
+        /// Attachments the sender uploaded to the room and has not sent yet.
+        #[serde(default)]
+        attachment_ids: Vec<Uuid>,
+    },
+    /// Records a file whose bytes are already in the blob store, so that a later
+    /// `SendMessage` can attach it. Only the Web API's upload endpoint issues it.
+    UploadAttachment {
+        attachment_id: Uuid,
+        user_id: String,
+        name: String,
+        mime_type: String,
+        size: u64,
+        /// Hex-encoded SHA-256 of the file's bytes.
+        checksum: String,
+    },
+    RenameRoom {
+        user_id: String,
+        name: String,
     },
+    SetTopic {
+        user_id: String,
+        topic: String,
+    },
+    SetDescription {
+        user_id: String,
+        description: String,
+    },
+    ArchiveRoom {
+        user_id: String,
+    },
+    UnarchiveRoom {
+        user_id: String,
+    },
+    MarkRead {
+        user_id: String,
+        up_to_message_id: Uuid,
+    },
+    /// Sets the minimum interval between messages from each non-moderator, that
+    /// is everyone but the room's owner; 0 turns slow mode off.
+    SetSlowMode {
+        user_id: String,
+        interval_seconds: u64,
+    },
+    /// Sets how many seconds messages live before they expire; 0 keeps them forever.
+    SetMessageTtl {
+        user_id: String,
+        ttl_seconds: u64,
+    },
+    /// Issued by the expiry task when a message's time-to-live has run out.
+    ExpireMessage {
+        message_id: Uuid,
+    },
+    /// Issued by the expiry task when an upload has waited too long to be sent.
+    DiscardAttachment {
+        attachment_id: Uuid,
+    },
+    /// Creates an incoming webhook whose bot posts into the room under `name`. Only
+    /// the hash of the webhook's token is kept.
+    CreateIncomingWebhook {
+        user_id: String,
+        hook_id: Uuid,
+        name: String,
+        token_hash: String,
+    },
+    RevokeIncomingWebhook {
+        user_id: String,
+        hook_id: Uuid,
+    },
+    /// Sends `content` as a message once `deliver_at` has passed; `message_id` becomes the message's id.
+    ScheduleMessage {
+        message_id: Uuid,
+        user_id: String,
+        content: String,
+        deliver_at: chrono::DateTime<chrono::Utc>,
+    },
+    CancelScheduledMessage {
+        user_id: String,
+        message_id: Uuid,
+    },
+    /// Issued by the scheduler when a scheduled message is due.
+    DeliverScheduledMessage {
+        message_id: Uuid,
+    },
+    /// Opens a poll with 2 to 10 options. In an anonymous poll, tallies are shown
+    /// without who voted for what.
+    CreatePoll {
+        poll_id: Uuid,
+        user_id: String,
+        question: String,
+        options: Vec<String>,
+        multiple_choice: bool,
+        anonymous: bool,
+    },
+    /// Votes for the options at the given indexes, replacing any earlier vote;
+    /// a single-choice poll takes exactly one.
+    CastVote {
+        poll_id: Uuid,
+        user_id: String,
+        options: Vec<usize>,
+    },
+    RetractVote {
+        poll_id: Uuid,
+        user_id: String,
+    },
+    /// Stops voting; allowed for the poll's creator and the room's moderators.
+    ClosePoll {
+        poll_id: Uuid,
+        user_id: String,
+    },
+    /// Handles the commands in order, each seeing the events of the ones before it,
+    /// and commits all of their events together; if any command fails, none are.
+    Batch {
+        #[schema(no_recursion)]
+        commands: Vec<ChatCommand>,
+    },
+}
+
+/// Lets infrastructure such as metrics and rate limiting inspect a command
+/// without knowing its aggregate.
+pub trait DomainCommand {
+    fn command_type(&self) -> String;
+
+    /// The user issuing the command, when it names one. Commands that don't are
+    /// issued either by the aggregate itself or by the system.
+    fn issued_by(&self) -> Option<&str>;
+
+    /// Whether a command that names no issuer is issued by the aggregate itself,
+    /// as with a user's own profile, rather than by the system.
+    fn issued_by_aggregate(&self) -> bool {
+        false
+    }
+
+    /// The individual commands this one stands for: the contents of a batch, or
+    /// just itself.
+    fn parts(&self) -> Vec<&Self>
+    where
+        Self: Sized,
+    {
+        vec![self]
+    }
+}
+
+impl DomainCommand for ChatCommand {
+    fn command_type(&self) -> String {
+        match self {
+            ChatCommand::CreateRoom { .. } => "CreateRoom".to_string(),
+            ChatCommand::JoinRoom { .. } => "JoinRoom".to_string(),
+            ChatCommand::LeaveRoom { .. } => "LeaveRoom".to_string(),
+            ChatCommand::KickUser { .. } => "KickUser".to_string(),
+            ChatCommand::SendMessage { .. } => "SendMessage".to_string(),
+            ChatCommand::UploadAttachment { .. } => "UploadAttachment".to_string(),
+            ChatCommand::RenameRoom { .. } => "RenameRoom".to_string(),
+            ChatCommand::SetTopic { .. } => "SetTopic".to_string(),
+            ChatCommand::SetDescription { .. } => "SetDescription".to_string(),
+            ChatCommand::ArchiveRoom { .. } => "ArchiveRoom".to_string(),
+            ChatCommand::UnarchiveRoom { .. } => "UnarchiveRoom".to_string(),
+            ChatCommand::MarkRead { .. } => "MarkRead".to_string(),
+            ChatCommand::SetSlowMode { .. } => "SetSlowMode".to_string(),
+            ChatCommand::SetMessageTtl { .. } => "SetMessageTtl".to_string(),
+            ChatCommand::ExpireMessage { .. } => "ExpireMessage".to_string(),
+            ChatCommand::DiscardAttachment { .. } => "DiscardAttachment".to_string(),
+            ChatCommand::CreateIncomingWebhook { .. } => "CreateIncomingWebhook".to_string(),
+            ChatCommand::RevokeIncomingWebhook { .. } => "RevokeIncomingWebhook".to_string(),
+            ChatCommand::ScheduleMessage { .. } => "ScheduleMessage".to_string(),
+            ChatCommand::CancelScheduledMessage { .. } => "CancelScheduledMessage".to_string(),
+            ChatCommand::DeliverScheduledMessage { .. } => "DeliverScheduledMessage".to_string(),
+            ChatCommand::CreatePoll { .. } => "CreatePoll".to_string(),
+            ChatCommand::CastVote { .. } => "CastVote".to_string(),
+            ChatCommand::RetractVote { .. } => "RetractVote".to_string(),
+            ChatCommand::ClosePoll { .. } => "ClosePoll".to_string(),
+            ChatCommand::Batch { .. } => "Batch".to_string(),
+        }
+    }
+
+    fn issued_by(&self) -> Option<&str> {
+        match self {
+            ChatCommand::CreateRoom { created_by, .. } => Some(created_by),
+            ChatCommand::JoinRoom { user_id, .. }
+            | ChatCommand::LeaveRoom { user_id }
+            | ChatCommand::KickUser { user_id, .. }
+            | ChatCommand::SendMessage { user_id, .. }
+            | ChatCommand::UploadAttachment { user_id, .. }
+            | ChatCommand::RenameRoom { user_id, .. }
+            | ChatCommand::SetTopic { user_id, .. }
+            | ChatCommand::SetDescription { user_id, .. }
+            | ChatCommand::ArchiveRoom { user_id }
+            | ChatCommand::UnarchiveRoom { user_id }
+            | ChatCommand::MarkRead { user_id, .. }
+            | ChatCommand::SetSlowMode { user_id, .. }
+            | ChatCommand::SetMessageTtl { user_id, .. }
+            | ChatCommand::CreateIncomingWebhook { user_id, .. }
+            | ChatCommand::RevokeIncomingWebhook { user_id, .. }
+            | ChatCommand::ScheduleMessage { user_id, .. }
+            | ChatCommand::CancelScheduledMessage { user_id, .. }
+            | ChatCommand::CreatePoll { user_id, .. }
+            | ChatCommand::CastVote { user_id, .. }
+            | ChatCommand::RetractVote { user_id, .. }
+            | ChatCommand::ClosePoll { user_id, .. } => Some(user_id),
+            ChatCommand::ExpireMessage { .. }
+            | ChatCommand::DiscardAttachment { .. }
+            | ChatCommand::DeliverScheduledMessage { .. }
+            | ChatCommand::Batch { .. } => None,
+        }
+    }
+
+    fn parts(&self) -> Vec<&Self> {
+        match self {
+            ChatCommand::Batch { commands } => commands.iter().collect(),
+            command => vec![command],
+        }
+    }
 }
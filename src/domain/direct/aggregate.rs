@@ -0,0 +1,207 @@
+use async_trait::async_trait;
+use cqrs_es::Aggregate;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::domain::direct::commands::DirectCommand;
+use crate::domain::direct::events::{DirectError, DirectEvent};
+use crate::services::ChatServices;
+
+/// Namespace used to derive conversation ids from the pair of participants.
+const DIRECT_CONVERSATION_NAMESPACE: Uuid = Uuid::from_u128(0x6f1c_2b7e_4d0a_4c3b_9e55_8a1d_0f3e_72c4);
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct DirectMessage {
+    pub id: Uuid,
+    pub sender_id: String,
+    pub content: String,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct DirectConversation {
+    pub conversation_id: Option<Uuid>,
+    pub participants: Vec<String>,
+    pub messages: Vec<DirectMessage>,
+    pub started_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl DirectConversation {
+    /// Returns the id of the conversation between two users. The id does not
+    /// depend on the order of the arguments, so both sides always resolve to
+    /// the same aggregate.
+    pub fn conversation_id(user_a: &str, user_b: &str) -> Uuid {
+        let (first, second) = if user_a <= user_b { (user_a, user_b) } else { (user_b, user_a) };
+        let name = format!("{}\n{}", first, second);
+        Uuid::new_v5(&DIRECT_CONVERSATION_NAMESPACE, name.as_bytes())
+    }
+}
+
+#[async_trait]
+impl Aggregate for DirectConversation {
+    type Command = DirectCommand;
+    type Event = DirectEvent;
+    type Error = DirectError;
+    type Services = ChatServices;
+
+    fn aggregate_type() -> String {
+        "DirectConversation".to_string()
+    }
+
+    async fn handle(
+        &self,
+        command: Self::Command,
+        _services: &Self::Services,
+    ) -> Result<Vec<Self::Event>, Self::Error> {
+        match command {
+            DirectCommand::StartConversation { conversation_id, initiator_id, initiator_name, recipient_id, recipient_name } => {
+                if self.conversation_id.is_some() {
+                    return Err(DirectError::ConversationAlreadyExists(format!("Conversation with ID {} already exists", conversation_id)));
+                }
+
+                if initiator_id == recipient_id {
+                    return Err(DirectError::InvalidOperation("Cannot start a conversation with yourself".to_string()));
+                }
+
+                if conversation_id != Self::conversation_id(&initiator_id, &recipient_id) {
+                    return Err(DirectError::InvalidOperation(format!("Conversation ID {} does not match its participants", conversation_id)));
+                }
+
+                Ok(vec![DirectEvent::ConversationStarted {
+                    conversation_id,
+                    initiator_id,
+                    initiator_name,
+                    recipient_id,
+                    recipient_name,
+                    timestamp: chrono::Utc::now(),
+                }])
+            }
+
+            DirectCommand::SendDirectMessage { message_id, sender_id, content, timestamp } => {
+                if self.conversation_id.is_none() {
+                    return Err(DirectError::ConversationNotFound("Conversation does not exist".to_string()));
+                }
+
+                if !self.participants.contains(&sender_id) {
+                    return Err(DirectError::UserNotInConversation(format!("User {} is not in the conversation", sender_id)));
+                }
+
+                Ok(vec![DirectEvent::DirectMessageSent {
+                    message_id,
+                    sender_id,
+                    content,
+                    timestamp,
+                }])
+            }
+        }
+    }
+
+    fn apply(&mut self, event: Self::Event) {
+        match event {
+            DirectEvent::ConversationStarted { conversation_id, initiator_id, initiator_name: _, recipient_id, recipient_name: _, timestamp } => {
+                self.conversation_id = Some(conversation_id);
+                self.participants = vec![initiator_id, recipient_id];
+                self.started_at = Some(timestamp);
+            }
+
+            DirectEvent::DirectMessageSent { message_id, sender_id, content, timestamp } => {
+                self.messages.push(DirectMessage {
+                    id: message_id,
+                    sender_id,
+                    content,
+                    timestamp,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod aggregate_tests {
+    use super::*;
+    use cqrs_es::test::TestFramework;
+
+    type DirectConversationTestFramework = TestFramework<DirectConversation>;
+
+    fn conversation_started() -> DirectEvent {
+        DirectEvent::ConversationStarted {
+            conversation_id: DirectConversation::conversation_id("alice", "bob"),
+            initiator_id: "alice".to_string(),
+            initiator_name: "Alice".to_string(),
+            recipient_id: "bob".to_string(),
+            recipient_name: "Bob".to_string(),
+            timestamp: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_conversation_id_is_order_independent() {
+        assert_eq!(
+            DirectConversation::conversation_id("alice", "bob"),
+            DirectConversation::conversation_id("bob", "alice"),
+        );
+        assert_ne!(
+            DirectConversation::conversation_id("alice", "bob"),
+            DirectConversation::conversation_id("alice", "carol"),
+        );
+    }
+
+    #[test]
+    fn test_start_conversation_rejects_mismatched_id() {
+        let command = DirectCommand::StartConversation {
+            conversation_id: Uuid::new_v4(),
+            initiator_id: "alice".to_string(),
+            initiator_name: "Alice".to_string(),
+            recipient_id: "bob".to_string(),
+            recipient_name: "Bob".to_string(),
+        };
+
+        let result = DirectConversationTestFramework::with(ChatServices)
+            .given_no_previous_events()
+            .when(command)
+            .inspect_result();
+
+        assert!(matches!(result, Err(DirectError::InvalidOperation(_))));
+    }
+
+    #[test]
+    fn test_send_direct_message() {
+        let message_id = Uuid::new_v4();
+        let timestamp = chrono::Utc::now();
+
+        let command = DirectCommand::SendDirectMessage {
+            message_id,
+            sender_id: "bob".to_string(),
+            content: "Hi Alice".to_string(),
+            timestamp,
+        };
+
+        let events = DirectConversationTestFramework::with(ChatServices)
+            .given(vec![conversation_started()])
+            .when(command)
+            .inspect_result()
+            .unwrap();
+
+        assert_eq!(events, vec![DirectEvent::DirectMessageSent {
+            message_id,
+            sender_id: "bob".to_string(),
+            content: "Hi Alice".to_string(),
+            timestamp,
+        }]);
+    }
+
+    #[test]
+    fn test_outsider_cannot_send_direct_message() {
+        let command = DirectCommand::SendDirectMessage {
+            message_id: Uuid::new_v4(),
+            sender_id: "carol".to_string(),
+            content: "Hello?".to_string(),
+            timestamp: chrono::Utc::now(),
+        };
+
+        DirectConversationTestFramework::with(ChatServices)
+            .given(vec![conversation_started()])
+            .when(command)
+            .then_expect_error_message("User not in conversation: User carol is not in the conversation");
+    }
+}
@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::domain::commands::DomainCommand;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DirectCommand {
+    StartConversation {
+        conversation_id: Uuid,
+        initiator_id: String,
+        initiator_name: String,
+        recipient_id: String,
+        recipient_name: String,
+    },
+    SendDirectMessage {
+        message_id: Uuid,
+        sender_id: String,
+        content: String,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+}
+
+impl DomainCommand for DirectCommand {
+    fn command_type(&self) -> String {
+        match self {
+            DirectCommand::StartConversation { .. } => "StartConversation".to_string(),
+            DirectCommand::SendDirectMessage { .. } => "SendDirectMessage".to_string(),
+        }
+    }
+
+    fn issued_by(&self) -> Option<&str> {
+        match self {
+            DirectCommand::StartConversation { initiator_id, .. } => Some(initiator_id),
+            DirectCommand::SendDirectMessage { sender_id, .. } => Some(sender_id),
+        }
+    }
+}
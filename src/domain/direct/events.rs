@@ -0,0 +1,50 @@
+use cqrs_es::DomainEvent;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum DirectEvent {
+    ConversationStarted {
+        conversation_id: Uuid,
+        initiator_id: String,
+        initiator_name: String,
+        recipient_id: String,
+        recipient_name: String,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+    DirectMessageSent {
+        message_id: Uuid,
+        sender_id: String,
+        content: String,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+}
+
+impl DomainEvent for DirectEvent {
+    fn event_type(&self) -> String {
+        match self {
+            DirectEvent::ConversationStarted { .. } => "ConversationStarted".to_string(),
+            DirectEvent::DirectMessageSent { .. } => "DirectMessageSent".to_string(),
+        }
+    }
+
+    fn event_version(&self) -> String {
+        "1.0".to_string()
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum DirectError {
+    #[error("Conversation already exists: {0}")]
+    ConversationAlreadyExists(String),
+    
+    #[error("Conversation not found: {0}")]
+    ConversationNotFound(String),
+    
+    #[error("User not in conversation: {0}")]
+    UserNotInConversation(String),
+    
+    #[error("Invalid operation: {0}")]
+    InvalidOperation(String),
+}
@@ -1,9 +1,21 @@
 use cqrs_es::DomainEvent;
 use serde::{Deserialize, Serialize};
-use std::fmt::{Display, Formatter};
 use thiserror::Error;
 use uuid::Uuid;
 
+use crate::domain::validation::ValidationErrors;
+
+/// A file attached to a message. Its bytes live in the blob store under `attachment_id`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Attachment {
+    pub attachment_id: Uuid,
+    pub name: String,
+    pub mime_type: String,
+    pub size: u64,
+    /// Hex-encoded SHA-256 of the file's bytes.
+    pub checksum: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum ChatEvent {
     RoomCreated {
@@ -26,9 +38,180 @@ pub enum ChatEvent {
         user_id: String,
         content: String,
         timestamp: chrono::DateTime<chrono::Utc>,
+        /// When the message expires, if it has a time-to-live.
+        #[serde(default)]
+        expires_at: Option<chrono::DateTime<chrono::Utc>>,
+        #[serde(default)]
+        attachments: Vec<Attachment>,
+    },
+    /// A file was uploaded for a message its uploader has yet to send.
+    AttachmentUploaded {
+        attachment: Attachment,
+        user_id: String,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+    /// The message's time-to-live ran out; its content is gone from the room.
+    MessageExpired {
+        message_id: Uuid,
+        /// The message's attachments, whose bytes are deleted with it.
+        #[serde(default)]
+        attachment_ids: Vec<Uuid>,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+    /// An upload was never sent with a message in time; its bytes are deleted.
+    AttachmentDiscarded {
+        attachment_id: Uuid,
+        user_id: String,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+    RoomRenamed {
+        user_id: String,
+        name: String,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+    TopicChanged {
+        user_id: String,
+        topic: String,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+    DescriptionChanged {
+        user_id: String,
+        description: String,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+    RoomArchived {
+        user_id: String,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+    RoomUnarchived {
+        user_id: String,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+    MessagesRead {
+        user_id: String,
+        up_to_message_id: Uuid,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+    SlowModeChanged {
+        user_id: String,
+        interval_seconds: u64,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+    MessageTtlChanged {
+        user_id: String,
+        ttl_seconds: u64,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+    /// Followed by the `UserJoined` of the webhook's bot.
+    IncomingWebhookCreated {
+        user_id: String,
+        hook_id: Uuid,
+        name: String,
+        token_hash: String,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+    /// Followed by the `UserLeft` of the webhook's bot.
+    IncomingWebhookRevoked {
+        user_id: String,
+        hook_id: Uuid,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+    MessageScheduled {
+        message_id: Uuid,
+        user_id: String,
+        content: String,
+        deliver_at: chrono::DateTime<chrono::Utc>,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+    ScheduledMessageCancelled {
+        user_id: String,
+        message_id: Uuid,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+    /// A scheduled message fell due but could no longer be sent.
+    ScheduledMessageDropped {
+        message_id: Uuid,
+        reason: String,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+    PollCreated {
+        poll_id: Uuid,
+        user_id: String,
+        question: String,
+        options: Vec<String>,
+        multiple_choice: bool,
+        anonymous: bool,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+    /// Replaces any earlier vote by the same user. `anonymous` repeats the poll's setting.
+    VoteCast {
+        poll_id: Uuid,
+        user_id: String,
+        options: Vec<usize>,
+        anonymous: bool,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+    VoteRetracted {
+        poll_id: Uuid,
+        user_id: String,
+        anonymous: bool,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+    PollClosed {
+        poll_id: Uuid,
+        user_id: String,
+        timestamp: chrono::DateTime<chrono::Utc>,
     },
 }
 
+impl ChatEvent {
+    /// Events kept out of the live feeds and outbound webhooks: a scheduled
+    /// message stays between its author and the server until it is sent, a
+    /// vote in an anonymous poll would give away who cast it, and an incoming
+    /// webhook's token hash is only for the server to check tokens against.
+    pub fn is_private(&self) -> bool {
+        matches!(
+            self,
+            ChatEvent::MessageScheduled { .. }
+                | ChatEvent::AttachmentUploaded { .. }
+                | ChatEvent::AttachmentDiscarded { .. }
+                | ChatEvent::IncomingWebhookCreated { .. }
+                | ChatEvent::ScheduledMessageCancelled { .. }
+                | ChatEvent::ScheduledMessageDropped { .. }
+                | ChatEvent::VoteCast { anonymous: true, .. }
+                | ChatEvent::VoteRetracted { anonymous: true, .. }
+        )
+    }
+}
+
+/// Every `ChatEvent` type name, as used by filters that select events by type.
+pub const CHAT_EVENT_TYPES: &[&str] = &[
+    "RoomCreated",
+    "UserJoined",
+    "UserLeft",
+    "MessageSent",
+    "MessageExpired",
+    "AttachmentUploaded",
+    "AttachmentDiscarded",
+    "RoomRenamed",
+    "TopicChanged",
+    "DescriptionChanged",
+    "RoomArchived",
+    "RoomUnarchived",
+    "MessagesRead",
+    "SlowModeChanged",
+    "MessageTtlChanged",
+    "IncomingWebhookCreated",
+    "IncomingWebhookRevoked",
+    "MessageScheduled",
+    "ScheduledMessageCancelled",
+    "ScheduledMessageDropped",
+    "PollCreated",
+    "VoteCast",
+    "VoteRetracted",
+    "PollClosed",
+];
+
 impl DomainEvent for ChatEvent {
     fn event_type(&self) -> String {
         match self {
@@ -36,6 +219,26 @@ impl DomainEvent for ChatEvent {
             ChatEvent::UserJoined { .. } => "UserJoined".to_string(),
             ChatEvent::UserLeft { .. } => "UserLeft".to_string(),
             ChatEvent::MessageSent { .. } => "MessageSent".to_string(),
+            ChatEvent::MessageExpired { .. } => "MessageExpired".to_string(),
+            ChatEvent::AttachmentUploaded { .. } => "AttachmentUploaded".to_string(),
+            ChatEvent::AttachmentDiscarded { .. } => "AttachmentDiscarded".to_string(),
+            ChatEvent::RoomRenamed { .. } => "RoomRenamed".to_string(),
+            ChatEvent::TopicChanged { .. } => "TopicChanged".to_string(),
+            ChatEvent::DescriptionChanged { .. } => "DescriptionChanged".to_string(),
+            ChatEvent::RoomArchived { .. } => "RoomArchived".to_string(),
+            ChatEvent::RoomUnarchived { .. } => "RoomUnarchived".to_string(),
+            ChatEvent::MessagesRead { .. } => "MessagesRead".to_string(),
+            ChatEvent::SlowModeChanged { .. } => "SlowModeChanged".to_string(),
+            ChatEvent::MessageTtlChanged { .. } => "MessageTtlChanged".to_string(),
+            ChatEvent::IncomingWebhookCreated { .. } => "IncomingWebhookCreated".to_string(),
+            ChatEvent::IncomingWebhookRevoked { .. } => "IncomingWebhookRevoked".to_string(),
+            ChatEvent::MessageScheduled { .. } => "MessageScheduled".to_string(),
+            ChatEvent::ScheduledMessageCancelled { .. } => "ScheduledMessageCancelled".to_string(),
+            ChatEvent::ScheduledMessageDropped { .. } => "ScheduledMessageDropped".to_string(),
+            ChatEvent::PollCreated { .. } => "PollCreated".to_string(),
+            ChatEvent::VoteCast { .. } => "VoteCast".to_string(),
+            ChatEvent::VoteRetracted { .. } => "VoteRetracted".to_string(),
+            ChatEvent::PollClosed { .. } => "PollClosed".to_string(),
         }
     }
 
@@ -58,9 +261,21 @@ pub enum ChatError {
     #[error("User not in room: {0}")]
     UserNotInRoom(String),
     
+    #[error("Room is archived: {0}")]
+    RoomArchived(String),
+    
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+    
+    #[error("Validation failed: {0}")]
+    Validation(ValidationErrors),
+    
     #[error("Invalid operation: {0}")]
     InvalidOperation(String),
     
+    #[error("Slow mode is on: wait {remaining_seconds} more seconds before sending another message")]
+    SlowMode { remaining_seconds: u64 },
+    
     #[error("Unknown error: {0}")]
     Unknown(String),
 }
@@ -1,3 +1,7 @@
 pub mod aggregate;
 pub mod commands;
+pub mod direct;
 pub mod events;
+pub mod user;
+pub mod validation;
+pub mod webhook;
@@ -0,0 +1,200 @@
+use async_trait::async_trait;
+use cqrs_es::Aggregate;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::user::commands::UserCommand;
+use crate::domain::user::events::{UserError, UserEvent};
+use crate::services::ChatServices;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct User {
+    pub user_id: Option<String>,
+    pub username: String,
+    pub display_name: String,
+    pub password_hash: Option<String>,
+    pub avatar_url: String,
+    pub status: String,
+    pub deactivated: bool,
+    pub registered_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[async_trait]
+impl Aggregate for User {
+    type Command = UserCommand;
+    type Event = UserEvent;
+    type Error = UserError;
+    type Services = ChatServices;
+
+    fn aggregate_type() -> String {
+        "User".to_string()
+    }
+
+    async fn handle(
+        &self,
+        command: Self::Command,
+        _services: &Self::Services,
+    ) -> Result<Vec<Self::Event>, Self::Error> {
+        match command {
+            UserCommand::RegisterUser { user_id, username, display_name, password_hash } => {
+                if self.user_id.is_some() {
+                    return Err(UserError::UserAlreadyExists(format!("User with ID {} already exists", user_id)));
+                }
+
+                Ok(vec![UserEvent::UserRegistered {
+                    user_id,
+                    username,
+                    display_name,
+                    password_hash,
+                    timestamp: chrono::Utc::now(),
+                }])
+            }
+
+            UserCommand::ChangeDisplayName { display_name } => {
+                self.ensure_active()?;
+
+                if display_name == self.display_name {
+                    return Ok(vec![]);
+                }
+
+                Ok(vec![UserEvent::DisplayNameChanged {
+                    display_name,
+                    timestamp: chrono::Utc::now(),
+                }])
+            }
+
+            UserCommand::SetAvatarUrl { avatar_url } => {
+                self.ensure_active()?;
+
+                Ok(vec![UserEvent::AvatarUrlChanged {
+                    avatar_url,
+                    timestamp: chrono::Utc::now(),
+                }])
+            }
+
+            UserCommand::SetStatus { status } => {
+                self.ensure_active()?;
+
+                Ok(vec![UserEvent::StatusChanged {
+                    status,
+                    timestamp: chrono::Utc::now(),
+                }])
+            }
+
+            UserCommand::DeactivateUser => {
+                self.ensure_active()?;
+
+                Ok(vec![UserEvent::UserDeactivated {
+                    timestamp: chrono::Utc::now(),
+                }])
+            }
+        }
+    }
+
+    fn apply(&mut self, event: Self::Event) {
+        match event {
+            UserEvent::UserRegistered { user_id, username, display_name, password_hash, timestamp } => {
+                self.user_id = Some(user_id);
+                self.username = username;
+                self.display_name = display_name;
+                self.password_hash = password_hash;
+                self.registered_at = Some(timestamp);
+            }
+
+            UserEvent::DisplayNameChanged { display_name, timestamp: _ } => {
+                self.display_name = display_name;
+            }
+
+            UserEvent::AvatarUrlChanged { avatar_url, timestamp: _ } => {
+                self.avatar_url = avatar_url;
+            }
+
+            UserEvent::StatusChanged { status, timestamp: _ } => {
+                self.status = status;
+            }
+
+            UserEvent::UserDeactivated { timestamp: _ } => {
+                self.deactivated = true;
+            }
+        }
+    }
+}
+
+impl User {
+    fn ensure_active(&self) -> Result<(), UserError> {
+        let Some(user_id) = &self.user_id else {
+            return Err(UserError::UserNotFound("User does not exist".to_string()));
+        };
+
+        if self.deactivated {
+            return Err(UserError::UserDeactivated(format!("User {} has been deactivated", user_id)));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod aggregate_tests {
+    use super::*;
+    use cqrs_es::test::TestFramework;
+
+    type UserTestFramework = TestFramework<User>;
+
+    fn user_registered() -> UserEvent {
+        UserEvent::UserRegistered {
+            user_id: "user1".to_string(),
+            username: "alice".to_string(),
+            display_name: "Alice".to_string(),
+            password_hash: None,
+            timestamp: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_change_display_name() {
+        let command = UserCommand::ChangeDisplayName {
+            display_name: "Alice Liddell".to_string(),
+        };
+
+        let events = UserTestFramework::with(ChatServices)
+            .given(vec![user_registered()])
+            .when(command)
+            .inspect_result()
+            .unwrap();
+
+        assert_eq!(events.len(), 1);
+        match &events[0] {
+            UserEvent::DisplayNameChanged { display_name, timestamp: _ } => {
+                assert_eq!(display_name, "Alice Liddell");
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_deactivated_user_cannot_change_profile() {
+        let previous_events = vec![
+            user_registered(),
+            UserEvent::UserDeactivated {
+                timestamp: chrono::Utc::now(),
+            },
+        ];
+
+        let command = UserCommand::SetStatus {
+            status: "Back again".to_string(),
+        };
+
+        UserTestFramework::with(ChatServices)
+            .given(previous_events)
+            .when(command)
+            .then_expect_error_message("User is deactivated: User user1 has been deactivated");
+    }
+
+    #[test]
+    fn test_unregistered_user_not_found() {
+        UserTestFramework::with(ChatServices)
+            .given_no_previous_events()
+            .when(UserCommand::DeactivateUser)
+            .then_expect_error_message("User not found: User does not exist");
+    }
+}
@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+
+use crate::domain::commands::DomainCommand;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum UserCommand {
+    RegisterUser {
+        user_id: String,
+        username: String,
+        display_name: String,
+        password_hash: Option<String>,
+    },
+    ChangeDisplayName {
+        display_name: String,
+    },
+    SetAvatarUrl {
+        avatar_url: String,
+    },
+    SetStatus {
+        status: String,
+    },
+    DeactivateUser,
+}
+
+impl DomainCommand for UserCommand {
+    fn command_type(&self) -> String {
+        match self {
+            UserCommand::RegisterUser { .. } => "RegisterUser".to_string(),
+            UserCommand::ChangeDisplayName { .. } => "ChangeDisplayName".to_string(),
+            UserCommand::SetAvatarUrl { .. } => "SetAvatarUrl".to_string(),
+            UserCommand::SetStatus { .. } => "SetStatus".to_string(),
+            UserCommand::DeactivateUser => "DeactivateUser".to_string(),
+        }
+    }
+
+    fn issued_by(&self) -> Option<&str> {
+        None
+    }
+
+    fn issued_by_aggregate(&self) -> bool {
+        // A registration's user id is freshly generated, so it names no one yet.
+        !matches!(self, UserCommand::RegisterUser { .. })
+    }
+}
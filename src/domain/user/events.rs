@@ -0,0 +1,60 @@
+use cqrs_es::DomainEvent;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum UserEvent {
+    UserRegistered {
+        user_id: String,
+        username: String,
+        display_name: String,
+        password_hash: Option<String>,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+    DisplayNameChanged {
+        display_name: String,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+    AvatarUrlChanged {
+        avatar_url: String,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+    StatusChanged {
+        status: String,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+    UserDeactivated {
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+}
+
+impl DomainEvent for UserEvent {
+    fn event_type(&self) -> String {
+        match self {
+            UserEvent::UserRegistered { .. } => "UserRegistered".to_string(),
+            UserEvent::DisplayNameChanged { .. } => "DisplayNameChanged".to_string(),
+            UserEvent::AvatarUrlChanged { .. } => "AvatarUrlChanged".to_string(),
+            UserEvent::StatusChanged { .. } => "StatusChanged".to_string(),
+            UserEvent::UserDeactivated { .. } => "UserDeactivated".to_string(),
+        }
+    }
+
+    fn event_version(&self) -> String {
+        "1.0".to_string()
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum UserError {
+    #[error("User already exists: {0}")]
+    UserAlreadyExists(String),
+    
+    #[error("User not found: {0}")]
+    UserNotFound(String),
+    
+    #[error("User is deactivated: {0}")]
+    UserDeactivated(String),
+    
+    #[error("Invalid operation: {0}")]
+    InvalidOperation(String),
+}
@@ -0,0 +1,566 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::net::IpAddr;
+use utoipa::ToSchema;
+
+use crate::domain::commands::ChatCommand;
+use crate::domain::events::CHAT_EVENT_TYPES;
+use crate::domain::webhook::commands::WebhookCommand;
+
+pub const MAX_ROOM_NAME_LENGTH: usize = 100;
+pub const MAX_USERNAME_LENGTH: usize = 50;
+pub const MAX_USER_ID_LENGTH: usize = 64;
+pub const MAX_TOPIC_LENGTH: usize = 250;
+pub const MAX_DESCRIPTION_LENGTH: usize = 2_000;
+pub const MAX_MESSAGE_LENGTH: usize = 4_000;
+pub const MAX_PARTICIPANTS: usize = 500;
+pub const MAX_SLOW_MODE_INTERVAL_SECONDS: u64 = 6 * 60 * 60;
+pub const MAX_MESSAGE_TTL_SECONDS: u64 = 30 * 24 * 60 * 60;
+pub const MAX_BATCH_COMMANDS: usize = 100;
+pub const MAX_SCHEDULE_AHEAD_DAYS: i64 = 30;
+pub const MAX_SCHEDULED_MESSAGES_PER_USER: usize = 25;
+pub const MAX_ATTACHMENT_NAME_LENGTH: usize = 255;
+pub const MAX_ATTACHMENT_SIZE: u64 = 10 * 1024 * 1024;
+pub const MAX_ATTACHMENTS_PER_MESSAGE: usize = 10;
+pub const MAX_PENDING_ATTACHMENTS_PER_USER: usize = 20;
+pub const MAX_POLL_QUESTION_LENGTH: usize = 300;
+pub const MAX_POLL_OPTION_LENGTH: usize = 100;
+pub const MIN_POLL_OPTIONS: usize = 2;
+pub const MAX_POLL_OPTIONS: usize = 10;
+pub const MAX_WEBHOOK_URL_LENGTH: usize = 2_048;
+pub const MIN_WEBHOOK_SECRET_LENGTH: usize = 16;
+pub const MAX_WEBHOOK_SECRET_LENGTH: usize = 256;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+/// Every field-level problem found in a single command.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct ValidationErrors {
+    pub errors: Vec<FieldError>,
+}
+
+impl ValidationErrors {
+    pub fn single(field: &str, message: impl Into<String>) -> Self {
+        let mut errors = Self::default();
+        errors.add(field, message);
+        errors
+    }
+
+    pub fn add(&mut self, field: &str, message: impl Into<String>) {
+        self.errors.push(FieldError {
+            field: field.to_string(),
+            message: message.into(),
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+impl Display for ValidationErrors {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let fields: Vec<String> = self
+            .errors
+            .iter()
+            .map(|e| format!("{}: {}", e.field, e.message))
+            .collect();
+        write!(f, "{}", fields.join("; "))
+    }
+}
+
+impl ChatCommand {
+    /// Normalizes the command (trimming surrounding whitespace) and checks every
+    /// field against the length and character rules. Rules that depend on the
+    /// state of the room, such as the participant limit, are checked by the aggregate.
+    pub fn validate(self) -> Result<ChatCommand, ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+
+        let command = match self {
+            ChatCommand::CreateRoom { room_id, name, created_by } => ChatCommand::CreateRoom {
+                room_id,
+                name: single_line(&mut errors, "name", &name, MAX_ROOM_NAME_LENGTH, true),
+                created_by: user_id(&mut errors, "created_by", &created_by),
+            },
+            ChatCommand::JoinRoom { user_id: id, username } => ChatCommand::JoinRoom {
+                user_id: user_id(&mut errors, "user_id", &id),
+                username: single_line(&mut errors, "username", &username, MAX_USERNAME_LENGTH, true),
+            },
+            ChatCommand::LeaveRoom { user_id: id } => ChatCommand::LeaveRoom {
+                user_id: user_id(&mut errors, "user_id", &id),
+            },
+            ChatCommand::KickUser { user_id: id, target_user_id } => ChatCommand::KickUser {
+                user_id: user_id(&mut errors, "user_id", &id),
+                target_user_id: user_id(&mut errors, "target_user_id", &target_user_id),
+            },
+            ChatCommand::SendMessage { message_id, user_id: id, content, timestamp, ttl_seconds, attachment_ids } => {
+                if let Some(ttl_seconds) = ttl_seconds {
+                    message_ttl(&mut errors, "ttl_seconds", ttl_seconds, false);
+                }
+                if attachment_ids.len() > MAX_ATTACHMENTS_PER_MESSAGE {
+                    errors.add(
+                        "attachment_ids",
+                        format!("must contain at most {} attachments", MAX_ATTACHMENTS_PER_MESSAGE),
+                    );
+                }
+                let mut seen = std::collections::HashSet::new();
+                if !attachment_ids.iter().all(|attachment_id| seen.insert(attachment_id)) {
+                    errors.add("attachment_ids", "must not repeat an attachment");
+                }
+                // A message may be just its attachments.
+                let required = attachment_ids.is_empty();
+                ChatCommand::SendMessage {
+                    message_id,
+                    user_id: user_id(&mut errors, "user_id", &id),
+                    content: multi_line(&mut errors, "content", &content, MAX_MESSAGE_LENGTH, required),
+                    timestamp,
+                    ttl_seconds,
+                    attachment_ids,
+                }
+            }
+            ChatCommand::UploadAttachment { attachment_id, user_id: id, name, mime_type, size, checksum } => {
+                let name = single_line(&mut errors, "name", &name, MAX_ATTACHMENT_NAME_LENGTH, true);
+                if name.contains(['/', '\\', '"']) {
+                    errors.add("name", "must not contain / \\ or \"");
+                }
+                let valid_mime_type = mime_type.len() <= 127
+                    && mime_type.split_once('/').is_some_and(|(kind, subtype)| {
+                        [kind, subtype].iter().all(|part| {
+                            !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || "!#$&-^_.+".contains(c))
+                        })
+                    });
+                if !valid_mime_type {
+                    errors.add("mime_type", "must be a MIME type such as text/plain");
+                }
+                if size > MAX_ATTACHMENT_SIZE {
+                    errors.add("size", format!("must be at most {} bytes", MAX_ATTACHMENT_SIZE));
+                }
+                if checksum.len() != 64 || !checksum.chars().all(|c| c.is_ascii_hexdigit()) {
+                    errors.add("checksum", "must be a hex-encoded SHA-256 digest");
+                }
+                ChatCommand::UploadAttachment {
+                    attachment_id,
+                    user_id: user_id(&mut errors, "user_id", &id),
+                    name,
+                    mime_type: mime_type.to_ascii_lowercase(),
+                    size,
+                    checksum: checksum.to_ascii_lowercase(),
+                }
+            }
+            ChatCommand::RenameRoom { user_id: id, name } => ChatCommand::RenameRoom {
+                user_id: user_id(&mut errors, "user_id", &id),
+                name: single_line(&mut errors, "name", &name, MAX_ROOM_NAME_LENGTH, true),
+            },
+            ChatCommand::SetTopic { user_id: id, topic } => ChatCommand::SetTopic {
+                user_id: user_id(&mut errors, "user_id", &id),
+                topic: single_line(&mut errors, "topic", &topic, MAX_TOPIC_LENGTH, false),
+            },
+            ChatCommand::SetDescription { user_id: id, description } => ChatCommand::SetDescription {
+                user_id: user_id(&mut errors, "user_id", &id),
+                description: multi_line(&mut errors, "description", &description, MAX_DESCRIPTION_LENGTH, false),
+            },
+            ChatCommand::ArchiveRoom { user_id: id } => ChatCommand::ArchiveRoom {
+                user_id: user_id(&mut errors, "user_id", &id),
+            },
+            ChatCommand::UnarchiveRoom { user_id: id } => ChatCommand::UnarchiveRoom {
+                user_id: user_id(&mut errors, "user_id", &id),
+            },
+            ChatCommand::MarkRead { user_id: id, up_to_message_id } => ChatCommand::MarkRead {
+                user_id: user_id(&mut errors, "user_id", &id),
+                up_to_message_id,
+            },
+            ChatCommand::SetSlowMode { user_id: id, interval_seconds } => {
+                if interval_seconds > MAX_SLOW_MODE_INTERVAL_SECONDS {
+                    errors.add(
+                        "interval_seconds",
+                        format!("must be at most {} seconds", MAX_SLOW_MODE_INTERVAL_SECONDS),
+                    );
+                }
+                ChatCommand::SetSlowMode {
+                    user_id: user_id(&mut errors, "user_id", &id),
+                    interval_seconds,
+                }
+            }
+            ChatCommand::SetMessageTtl { user_id: id, ttl_seconds } => {
+                message_ttl(&mut errors, "ttl_seconds", ttl_seconds, true);
+                ChatCommand::SetMessageTtl {
+                    user_id: user_id(&mut errors, "user_id", &id),
+                    ttl_seconds,
+                }
+            }
+            ChatCommand::ExpireMessage { message_id } => ChatCommand::ExpireMessage { message_id },
+            ChatCommand::DiscardAttachment { attachment_id } => ChatCommand::DiscardAttachment { attachment_id },
+            ChatCommand::CreateIncomingWebhook { user_id: id, hook_id, name, token_hash } => {
+                if token_hash.len() != 64 || !token_hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                    errors.add("token_hash", "must be a hex-encoded SHA-256 digest");
+                }
+                ChatCommand::CreateIncomingWebhook {
+                    user_id: user_id(&mut errors, "user_id", &id),
+                    hook_id,
+                    name: single_line(&mut errors, "name", &name, MAX_USERNAME_LENGTH, true),
+                    token_hash: token_hash.to_ascii_lowercase(),
+                }
+            }
+            ChatCommand::RevokeIncomingWebhook { user_id: id, hook_id } => ChatCommand::RevokeIncomingWebhook {
+                user_id: user_id(&mut errors, "user_id", &id),
+                hook_id,
+            },
+            ChatCommand::ScheduleMessage { message_id, user_id: id, content, deliver_at } => {
+                let now = chrono::Utc::now();
+                if deliver_at <= now {
+                    errors.add("deliver_at", "must be in the future");
+                } else if deliver_at > now + chrono::Duration::days(MAX_SCHEDULE_AHEAD_DAYS) {
+                    errors.add("deliver_at", format!("must be at most {} days ahead", MAX_SCHEDULE_AHEAD_DAYS));
+                }
+                ChatCommand::ScheduleMessage {
+                    message_id,
+                    user_id: user_id(&mut errors, "user_id", &id),
+                    content: multi_line(&mut errors, "content", &content, MAX_MESSAGE_LENGTH, true),
+                    deliver_at,
+                }
+            }
+            ChatCommand::CancelScheduledMessage { user_id: id, message_id } => ChatCommand::CancelScheduledMessage {
+                user_id: user_id(&mut errors, "user_id", &id),
+                message_id,
+            },
+            ChatCommand::DeliverScheduledMessage { message_id } => ChatCommand::DeliverScheduledMessage { message_id },
+            ChatCommand::CreatePoll { poll_id, user_id: id, question, options, multiple_choice, anonymous } => {
+                if !(MIN_POLL_OPTIONS..=MAX_POLL_OPTIONS).contains(&options.len()) {
+                    errors.add(
+                        "options",
+                        format!("must contain between {} and {} options", MIN_POLL_OPTIONS, MAX_POLL_OPTIONS),
+                    );
+                }
+                let options: Vec<String> = options
+                    .iter()
+                    .enumerate()
+                    .map(|(i, option)| single_line(&mut errors, &format!("options[{}]", i), option, MAX_POLL_OPTION_LENGTH, true))
+                    .collect();
+                let mut seen = std::collections::HashSet::new();
+                if !options.iter().all(|option| seen.insert(option.to_lowercase())) {
+                    errors.add("options", "must not repeat an option");
+                }
+                ChatCommand::CreatePoll {
+                    poll_id,
+                    user_id: user_id(&mut errors, "user_id", &id),
+                    question: single_line(&mut errors, "question", &question, MAX_POLL_QUESTION_LENGTH, true),
+                    options,
+                    multiple_choice,
+                    anonymous,
+                }
+            }
+            ChatCommand::CastVote { poll_id, user_id: id, mut options } => {
+                options.sort_unstable();
+                if options.is_empty() {
+                    errors.add("options", "must choose at least one option");
+                } else if options.windows(2).any(|pair| pair[0] == pair[1]) {
+                    errors.add("options", "must not choose an option twice");
+                }
+                ChatCommand::CastVote {
+                    poll_id,
+                    user_id: user_id(&mut errors, "user_id", &id),
+                    options,
+                }
+            }
+            ChatCommand::RetractVote { poll_id, user_id: id } => ChatCommand::RetractVote {
+                poll_id,
+                user_id: user_id(&mut errors, "user_id", &id),
+            },
+            ChatCommand::ClosePoll { poll_id, user_id: id } => ChatCommand::ClosePoll {
+                poll_id,
+                user_id: user_id(&mut errors, "user_id", &id),
+            },
+            ChatCommand::Batch { commands } => {
+                if commands.is_empty() {
+                    errors.add("commands", "must not be empty");
+                } else if commands.len() > MAX_BATCH_COMMANDS {
+                    errors.add("commands", format!("must contain at most {} commands", MAX_BATCH_COMMANDS));
+                }
+                let commands = commands
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, command)| batched(&mut errors, i, command))
+                    .collect();
+                ChatCommand::Batch { commands }
+            }
+        };
+
+        if errors.is_empty() {
+            Ok(command)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl WebhookCommand {
+    /// Checks the target URL, the event filter and the signing secret.
+    pub fn validate(self) -> Result<WebhookCommand, ValidationErrors> {
+        let mut errors = ValidationErrors::default();
+
+        let command = match self {
+            WebhookCommand::RegisterWebhook { webhook_id, room_id, registered_by, url, event_types, secret } => {
+                let url = url.trim().to_string();
+                let host = url
+                    .strip_prefix("https://")
+                    .or_else(|| url.strip_prefix("http://"))
+                    .and_then(|rest| rest.split(['/', '?', '#']).next())
+                    .unwrap_or_default();
+                if host.is_empty() {
+                    errors.add("url", "must be an absolute http or https URL");
+                } else if !is_public_host(host) {
+                    errors.add("url", "must not point at this server or a loopback, link-local or private address");
+                } else if url.chars().count() > MAX_WEBHOOK_URL_LENGTH {
+                    errors.add("url", format!("must be at most {} characters", MAX_WEBHOOK_URL_LENGTH));
+                } else if url.chars().any(|c| c.is_whitespace() || c.is_control()) {
+                    errors.add("url", "must not contain whitespace or control characters");
+                }
+
+                for event_type in &event_types {
+                    if !CHAT_EVENT_TYPES.contains(&event_type.as_str()) {
+                        errors.add("event_types", format!("{} is not a room event type", event_type));
+                    }
+                }
+
+                let length = secret.chars().count();
+                if !(MIN_WEBHOOK_SECRET_LENGTH..=MAX_WEBHOOK_SECRET_LENGTH).contains(&length) {
+                    errors.add(
+                        "secret",
+                        format!(
+                            "must be between {} and {} characters",
+                            MIN_WEBHOOK_SECRET_LENGTH, MAX_WEBHOOK_SECRET_LENGTH
+                        ),
+                    );
+                }
+
+                WebhookCommand::RegisterWebhook {
+                    webhook_id,
+                    room_id,
+                    registered_by: user_id(&mut errors, "registered_by", &registered_by),
+                    url,
+                    event_types,
+                    secret,
+                }
+            }
+            WebhookCommand::RemoveWebhook { user_id: id } => WebhookCommand::RemoveWebhook {
+                user_id: user_id(&mut errors, "user_id", &id),
+            },
+        };
+
+        if errors.is_empty() {
+            Ok(command)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Whether an address is reachable on the public internet, rather than this host,
+/// its private network or its cloud metadata service (169.254.169.254).
+pub fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || a == 0
+                // Carrier-grade NAT, 100.64.0.0/10.
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_address(IpAddr::V4(ip)),
+            None => !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_unique_local()
+                || ip.is_unicast_link_local()
+                || ip.is_multicast()),
+        },
+    }
+}
+
+/// Rejects the names and address literals of a URL's authority that are never public.
+/// Other names are resolved and checked again when a delivery is sent.
+fn is_public_host(authority: &str) -> bool {
+    let host = authority.rsplit('@').next().unwrap_or_default();
+    let host = match host.strip_prefix('[') {
+        Some(rest) => rest.split(']').next().unwrap_or_default(),
+        None => host.split(':').next().unwrap_or_default(),
+    };
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    if host == "localhost" || host.ends_with(".localhost") {
+        return false;
+    }
+    host.parse().map_or(true, is_public_address)
+}
+
+/// Validates a command inside a batch, reporting its errors under `commands[i]`.
+fn batched(errors: &mut ValidationErrors, index: usize, command: ChatCommand) -> ChatCommand {
+    if matches!(command, ChatCommand::Batch { .. }) {
+        errors.add(&format!("commands[{}]", index), "batches cannot be nested");
+        return command;
+    }
+    match command.clone().validate() {
+        Ok(command) => command,
+        Err(inner) => {
+            for error in inner.errors {
+                errors.add(&format!("commands[{}].{}", index, error.field), error.message);
+            }
+            command
+        }
+    }
+}
+
+fn user_id(errors: &mut ValidationErrors, field: &str, value: &str) -> String {
+    let value = value.trim();
+    if value.is_empty() {
+        errors.add(field, "must not be empty");
+    } else if value.chars().count() > MAX_USER_ID_LENGTH {
+        errors.add(field, format!("must be at most {} characters", MAX_USER_ID_LENGTH));
+    } else if !value.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':' | '@')) {
+        errors.add(field, "may only contain letters, digits and - _ . : @");
+    }
+    value.to_string()
+}
+
+/// A time-to-live in seconds; 0 is only allowed where it switches expiry off.
+fn message_ttl(errors: &mut ValidationErrors, field: &str, value: u64, allow_zero: bool) {
+    if value == 0 && !allow_zero {
+        errors.add(field, "must be at least 1 second");
+    } else if value > MAX_MESSAGE_TTL_SECONDS {
+        errors.add(field, format!("must be at most {} seconds", MAX_MESSAGE_TTL_SECONDS));
+    }
+}
+
+fn single_line(errors: &mut ValidationErrors, field: &str, value: &str, max_length: usize, required: bool) -> String {
+    let value = value.trim();
+    check_length(errors, field, value, max_length, required);
+    if value.chars().any(char::is_control) {
+        errors.add(field, "must not contain line breaks or control characters");
+    }
+    value.to_string()
+}
+
+fn multi_line(errors: &mut ValidationErrors, field: &str, value: &str, max_length: usize, required: bool) -> String {
+    let value = value.trim();
+    check_length(errors, field, value, max_length, required);
+    if value.chars().any(|c| c.is_control() && c != '\n' && c != '\t') {
+        errors.add(field, "must not contain control characters");
+    }
+    value.to_string()
+}
+
+fn check_length(errors: &mut ValidationErrors, field: &str, value: &str, max_length: usize, required: bool) {
+    if required && value.is_empty() {
+        errors.add(field, "must not be empty");
+    } else if value.chars().count() > max_length {
+        errors.add(field, format!("must be at most {} characters", max_length));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_validate_trims_fields() {
+        let command = ChatCommand::RenameRoom {
+            user_id: " user1 ".to_string(),
+            name: "  General  ".to_string(),
+        };
+
+        match command.validate().unwrap() {
+            ChatCommand::RenameRoom { user_id, name } => {
+                assert_eq!(user_id, "user1");
+                assert_eq!(name, "General");
+            }
+            other => panic!("unexpected command: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_validate_reports_every_failing_field() {
+        let command = ChatCommand::SendMessage {
+            message_id: Uuid::new_v4(),
+            user_id: "".to_string(),
+            content: "x".repeat(MAX_MESSAGE_LENGTH + 1),
+            timestamp: chrono::Utc::now(),
+            ttl_seconds: None,
+            attachment_ids: Vec::new(),
+        };
+
+        let errors = command.validate().unwrap_err();
+        let fields: Vec<&str> = errors.errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, vec!["user_id", "content"]);
+    }
+
+    #[test]
+    fn test_validate_rejects_control_characters_in_names() {
+        let command = ChatCommand::CreateRoom {
+            room_id: Uuid::new_v4(),
+            name: "Gen\u{7}eral".to_string(),
+            created_by: "user1".to_string(),
+        };
+
+        assert_eq!(
+            command.validate().unwrap_err(),
+            ValidationErrors::single("name", "must not contain line breaks or control characters"),
+        );
+    }
+
+    #[test]
+    fn test_validate_reports_batched_errors_by_index() {
+        let command = ChatCommand::Batch {
+            commands: vec![
+                ChatCommand::LeaveRoom {
+                    user_id: "user1".to_string(),
+                },
+                ChatCommand::SetTopic {
+                    user_id: "".to_string(),
+                    topic: "Topic".to_string(),
+                },
+                ChatCommand::Batch { commands: vec![] },
+            ],
+        };
+
+        let errors = command.validate().unwrap_err();
+        let fields: Vec<&str> = errors.errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, vec!["commands[1].user_id", "commands[2]"]);
+    }
+
+    #[test]
+    fn test_webhook_urls_must_be_public() {
+        let register = |url: &str| WebhookCommand::RegisterWebhook {
+            webhook_id: Uuid::new_v4(),
+            room_id: Uuid::new_v4(),
+            registered_by: "user1".to_string(),
+            url: url.to_string(),
+            event_types: Vec::new(),
+            secret: "0123456789abcdef".to_string(),
+        };
+
+        for url in [
+            "http://localhost:8080/hook",
+            "http://api.localhost/hook",
+            "http://127.0.0.1/hook",
+            "http://user@10.0.0.5:8080/hook",
+            "http://169.254.169.254/latest/meta-data",
+            "http://[::1]/hook",
+            "http://[::ffff:192.168.1.1]/hook",
+            "http://[fd00::1]/hook",
+        ] {
+            assert!(register(url).validate().is_err(), "{} was accepted", url);
+        }
+        assert!(register("https://hooks.example.com/chat").validate().is_ok());
+        assert!(register("https://93.184.216.34:8443/chat").validate().is_ok());
+    }
+}
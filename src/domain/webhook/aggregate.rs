@@ -0,0 +1,141 @@
+use async_trait::async_trait;
+use cqrs_es::Aggregate;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::domain::webhook::commands::WebhookCommand;
+use crate::domain::webhook::events::{WebhookError, WebhookEvent};
+use crate::services::ChatServices;
+
+/// A subscription delivering a room's events to an external URL. Kept apart from
+/// the `ChatRoom` aggregate so the signing secret never appears in the room's
+/// event stream, which is replayed to every participant.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Webhook {
+    pub webhook_id: Option<Uuid>,
+    pub room_id: Option<Uuid>,
+    pub registered_by: String,
+    pub url: String,
+    pub event_types: Vec<String>,
+    pub secret: String,
+    pub removed: bool,
+}
+
+#[async_trait]
+impl Aggregate for Webhook {
+    type Command = WebhookCommand;
+    type Event = WebhookEvent;
+    type Error = WebhookError;
+    type Services = ChatServices;
+
+    fn aggregate_type() -> String {
+        "Webhook".to_string()
+    }
+
+    async fn handle(
+        &self,
+        command: Self::Command,
+        _services: &Self::Services,
+    ) -> Result<Vec<Self::Event>, Self::Error> {
+        let command = command.validate().map_err(WebhookError::Validation)?;
+
+        match command {
+            WebhookCommand::RegisterWebhook { webhook_id, room_id, registered_by, url, event_types, secret } => {
+                if self.webhook_id.is_some() {
+                    return Err(WebhookError::WebhookAlreadyExists(format!("Webhook with ID {} already exists", webhook_id)));
+                }
+
+                Ok(vec![WebhookEvent::WebhookRegistered {
+                    webhook_id,
+                    room_id,
+                    registered_by,
+                    url,
+                    event_types,
+                    secret,
+                    timestamp: chrono::Utc::now(),
+                }])
+            }
+
+            WebhookCommand::RemoveWebhook { user_id } => {
+                if self.webhook_id.is_none() || self.removed {
+                    return Err(WebhookError::WebhookNotFound("Webhook does not exist".to_string()));
+                }
+
+                if self.registered_by != user_id {
+                    return Err(WebhookError::PermissionDenied(format!("User {} did not register the webhook", user_id)));
+                }
+
+                Ok(vec![WebhookEvent::WebhookRemoved {
+                    user_id,
+                    timestamp: chrono::Utc::now(),
+                }])
+            }
+        }
+    }
+
+    fn apply(&mut self, event: Self::Event) {
+        match event {
+            WebhookEvent::WebhookRegistered { webhook_id, room_id, registered_by, url, event_types, secret, timestamp: _ } => {
+                self.webhook_id = Some(webhook_id);
+                self.room_id = Some(room_id);
+                self.registered_by = registered_by;
+                self.url = url;
+                self.event_types = event_types;
+                self.secret = secret;
+            }
+
+            WebhookEvent::WebhookRemoved { user_id: _, timestamp: _ } => {
+                self.removed = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cqrs_es::test::TestFramework;
+
+    type WebhookTestFramework = TestFramework<Webhook>;
+
+    fn register(url: &str, event_types: &[&str]) -> WebhookCommand {
+        WebhookCommand::RegisterWebhook {
+            webhook_id: Uuid::new_v4(),
+            room_id: Uuid::new_v4(),
+            registered_by: "user1".to_string(),
+            url: url.to_string(),
+            event_types: event_types.iter().map(|t| t.to_string()).collect(),
+            secret: "0123456789abcdef".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_register_validates_url_and_event_types() {
+        WebhookTestFramework::with(ChatServices)
+            .given_no_previous_events()
+            .when(register("ftp://example.com/hook", &["MessageSent", "MessageEdited"]))
+            .then_expect_error_message(
+                "Validation failed: url: must be an absolute http or https URL; event_types: MessageEdited is not a room event type",
+            );
+    }
+
+    #[test]
+    fn test_only_the_registrant_can_remove() {
+        let registered = WebhookEvent::WebhookRegistered {
+            webhook_id: Uuid::new_v4(),
+            room_id: Uuid::new_v4(),
+            registered_by: "user1".to_string(),
+            url: "https://example.com/hook".to_string(),
+            event_types: vec![],
+            secret: "0123456789abcdef".to_string(),
+            timestamp: chrono::Utc::now(),
+        };
+
+        WebhookTestFramework::with(ChatServices)
+            .given(vec![registered])
+            .when(WebhookCommand::RemoveWebhook {
+                user_id: "user2".to_string(),
+            })
+            .then_expect_error_message("Permission denied: User user2 did not register the webhook");
+    }
+}
@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::domain::commands::DomainCommand;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum WebhookCommand {
+    RegisterWebhook {
+        webhook_id: Uuid,
+        room_id: Uuid,
+        registered_by: String,
+        url: String,
+        /// Event types to deliver; empty means every event.
+        event_types: Vec<String>,
+        secret: String,
+    },
+    RemoveWebhook {
+        user_id: String,
+    },
+}
+
+impl DomainCommand for WebhookCommand {
+    fn command_type(&self) -> String {
+        match self {
+            WebhookCommand::RegisterWebhook { .. } => "RegisterWebhook".to_string(),
+            WebhookCommand::RemoveWebhook { .. } => "RemoveWebhook".to_string(),
+        }
+    }
+
+    fn issued_by(&self) -> Option<&str> {
+        match self {
+            WebhookCommand::RegisterWebhook { registered_by, .. } => Some(registered_by),
+            WebhookCommand::RemoveWebhook { user_id } => Some(user_id),
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use cqrs_es::DomainEvent;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::domain::validation::ValidationErrors;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum WebhookEvent {
+    WebhookRegistered {
+        webhook_id: Uuid,
+        room_id: Uuid,
+        registered_by: String,
+        url: String,
+        event_types: Vec<String>,
+        secret: String,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+    WebhookRemoved {
+        user_id: String,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    },
+}
+
+impl DomainEvent for WebhookEvent {
+    fn event_type(&self) -> String {
+        match self {
+            WebhookEvent::WebhookRegistered { .. } => "WebhookRegistered".to_string(),
+            WebhookEvent::WebhookRemoved { .. } => "WebhookRemoved".to_string(),
+        }
+    }
+
+    fn event_version(&self) -> String {
+        "1.0".to_string()
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum WebhookError {
+    #[error("Webhook already exists: {0}")]
+    WebhookAlreadyExists(String),
+    
+    #[error("Webhook not found: {0}")]
+    WebhookNotFound(String),
+    
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+    
+    #[error("Validation failed: {0}")]
+    Validation(ValidationErrors),
+}
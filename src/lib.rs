@@ -1,27 +1,187 @@
 #![forbid(unsafe_code)]
 #![deny(clippy::all)]
 
+pub mod blobs;
+pub mod bots;
 pub mod domain;
+pub mod metrics;
+pub mod pipeline;
+pub mod polls;
+pub mod presence;
+pub mod rate_limit;
+pub mod scheduler;
+pub mod search;
 pub mod services;
 pub mod tui;
 pub mod web;
+pub mod webhooks;
 
 use cqrs_es::{CqrsFramework, Query};
 use std::sync::Arc;
 
+use blobs::{BlobReaper, BlobStore, LocalBlobStore};
+use bots::slash::SlashCommands;
+use bots::BotRunner;
 use domain::aggregate::ChatRoom;
-use services::{ChatRoomViewRepository, ChatServices, PostgresEventStore};
+use domain::direct::aggregate::DirectConversation;
+use domain::user::aggregate::User;
+use domain::webhook::aggregate::Webhook;
+use metrics::Metrics;
+use pipeline::CommandPipeline;
+use polls::PollRepository;
+use presence::PresenceTracker;
+use rate_limit::RateLimiter;
+use scheduler::MessageScheduler;
+use search::MessageSearchIndex;
+use services::{
+    ChatRoomViewRepository, ChatServices, CredentialRepository, DirectConversationViewRepository, PostgresEventStore,
+    ReadStateRepository, RoomDirectoryFeed, RoomEventHub, UserViewRepository, UsernameIndex,
+};
+use webhooks::{
+    IncomingWebhookRegistry, RetryPolicy, WebhookDeliveryLog, WebhookDispatcher, WebhookRegistry, WebhookTargets,
+};
 
-pub type ChatRoomFramework = CqrsFramework<ChatRoom, PostgresEventStore>;
+pub type ChatRoomFramework = CommandPipeline<ChatRoom, PostgresEventStore<ChatRoom>>;
+pub type DirectConversationFramework = CommandPipeline<DirectConversation, PostgresEventStore<DirectConversation>>;
+pub type UserFramework = CommandPipeline<User, PostgresEventStore<User>>;
+pub type WebhookFramework = CommandPipeline<Webhook, PostgresEventStore<Webhook>>;
 
-pub fn create_chat_framework() -> (ChatRoomFramework, Arc<ChatRoomViewRepository>) {
-    let event_store = PostgresEventStore::new();
-    let services = ChatServices;
+/// The CQRS frameworks of every aggregate together with their query-side repositories.
+#[derive(Clone)]
+pub struct ChatFrameworks {
+    pub rooms: Arc<ChatRoomFramework>,
+    /// Turns messages such as `/topic` into commands, or hands them to bots.
+    pub slash_commands: Arc<SlashCommands>,
+    pub room_views: Arc<ChatRoomViewRepository>,
+    pub room_events: Arc<RoomEventHub>,
+    pub room_directory: Arc<RoomDirectoryFeed>,
+    /// Read access to the room event store, used to replay history to stream subscribers.
+    pub room_store: Arc<PostgresEventStore<ChatRoom>>,
+    pub search: Arc<MessageSearchIndex>,
+    pub read_states: Arc<ReadStateRepository>,
+    /// Scheduled messages waiting to be sent.
+    pub scheduler: Arc<MessageScheduler>,
+    /// Every room's polls with their live tallies.
+    pub polls: Arc<PollRepository>,
+    /// The bytes of uploaded attachments; room events only record their metadata.
+    pub blobs: Arc<dyn BlobStore>,
+    /// Ephemeral online status and typing indicators, kept outside the event store.
+    pub presence: Arc<PresenceTracker>,
+    pub direct: Arc<DirectConversationFramework>,
+    pub direct_views: Arc<DirectConversationViewRepository>,
+    pub users: Arc<UserFramework>,
+    pub user_views: Arc<UserViewRepository>,
+    pub credentials: Arc<CredentialRepository>,
+    /// Usernames taken or reserved by a registration in progress.
+    pub usernames: Arc<UsernameIndex>,
+    pub webhooks: Arc<WebhookFramework>,
+    pub webhook_registry: Arc<WebhookRegistry>,
+    /// Recent outbound webhook deliveries, including dead letters.
+    pub webhook_deliveries: Arc<WebhookDeliveryLog>,
+    pub incoming_webhooks: Arc<IncomingWebhookRegistry>,
+    pub metrics: Arc<Metrics>,
+    /// Per-command token buckets, shared by the command pipelines and the Web API.
+    pub rate_limiter: Arc<RateLimiter>,
+}
+
+pub fn create_chat_framework() -> ChatFrameworks {
+    let metrics = Arc::new(Metrics::new());
+    let rate_limiter = Arc::new(RateLimiter::from_env());
     let view_repository = Arc::new(ChatRoomViewRepository::new());
-    
-    let query_repo = ChatRoomViewRepository::new();
-    let queries: Vec<Box<dyn Query<ChatRoom>>> = vec![Box::new(query_repo)];
-    let framework = CqrsFramework::new(event_store, queries, services);
-    
-    (framework, view_repository)
+    let room_events = Arc::new(RoomEventHub::new());
+    let room_directory = Arc::new(RoomDirectoryFeed::new());
+    let search = Arc::new(MessageSearchIndex::new());
+    let read_states = Arc::new(ReadStateRepository::new());
+    let scheduler = Arc::new(MessageScheduler::new());
+    let polls = Arc::new(PollRepository::new());
+    let blobs: Arc<dyn BlobStore> = Arc::new(LocalBlobStore::from_env());
+    let webhook_registry = Arc::new(WebhookRegistry::new());
+    let webhook_deliveries = Arc::new(WebhookDeliveryLog::new());
+    let incoming_webhooks = Arc::new(IncomingWebhookRegistry::new());
+    let webhook_dispatcher = WebhookDispatcher::new(
+        webhook_registry.as_ref().clone(),
+        webhook_deliveries.as_ref().clone(),
+        RetryPolicy::default(),
+        WebhookTargets::from_env(),
+    );
+    let (bot_runner, bot_inbox) = BotRunner::new(bots::builtin_bots());
+    // The hub runs after the view so subscribers that re-read the view see the new state.
+    // Metrics runs first so projection lag is measured against every committed event.
+    let queries: Vec<Box<dyn Query<ChatRoom>>> = vec![
+        Box::new(metrics.as_ref().clone()),
+        metrics.observe("room_views", view_repository.as_ref().clone()),
+        metrics.observe("room_events", room_events.as_ref().clone()),
+        metrics.observe("room_directory", room_directory.as_ref().clone()),
+        metrics.observe("search", search.as_ref().clone()),
+        metrics.observe("read_states", read_states.as_ref().clone()),
+        metrics.observe("scheduler", scheduler.as_ref().clone()),
+        metrics.observe("polls", polls.as_ref().clone()),
+        metrics.observe("blobs", BlobReaper::new(blobs.clone())),
+        metrics.observe("webhooks", webhook_dispatcher),
+        metrics.observe("incoming_webhooks", incoming_webhooks.as_ref().clone()),
+        metrics.observe("bots", bot_runner.clone()),
+    ];
+    let room_store = Arc::new(PostgresEventStore::new());
+    let framework = CqrsFramework::new(room_store.as_ref().clone(), queries, ChatServices);
+    let rooms = Arc::new(CommandPipeline::new(framework, metrics.as_ref().clone(), rate_limiter.as_ref().clone()));
+    bot_inbox.start(Arc::downgrade(&rooms), view_repository.clone());
+    scheduler.start(Arc::downgrade(&rooms), room_store.as_ref().clone());
+    let slash_commands = Arc::new(SlashCommands::new(view_repository.clone(), &bot_runner));
+
+    let direct_view_repository = Arc::new(DirectConversationViewRepository::new());
+    let direct_queries: Vec<Box<dyn Query<DirectConversation>>> = vec![
+        Box::new(metrics.as_ref().clone()),
+        metrics.observe("direct_views", direct_view_repository.as_ref().clone()),
+    ];
+    let direct_framework = CqrsFramework::new(PostgresEventStore::new(), direct_queries, ChatServices);
+
+    // Profile changes are projected into the room and conversation views as well.
+    let user_view_repository = Arc::new(UserViewRepository::new());
+    let credential_repository = Arc::new(CredentialRepository::new());
+    let usernames = Arc::new(UsernameIndex::new());
+    let user_queries: Vec<Box<dyn Query<User>>> = vec![
+        Box::new(metrics.as_ref().clone()),
+        metrics.observe("user_views", user_view_repository.as_ref().clone()),
+        metrics.observe("credentials", credential_repository.as_ref().clone()),
+        metrics.observe("usernames", usernames.as_ref().clone()),
+        metrics.observe("room_views", view_repository.as_ref().clone()),
+        metrics.observe("direct_views", direct_view_repository.as_ref().clone()),
+        metrics.observe("search", search.as_ref().clone()),
+    ];
+    let user_framework = CqrsFramework::new(PostgresEventStore::new(), user_queries, ChatServices);
+
+    let webhook_queries: Vec<Box<dyn Query<Webhook>>> = vec![
+        Box::new(metrics.as_ref().clone()),
+        metrics.observe("webhook_registry", webhook_registry.as_ref().clone()),
+    ];
+    let webhook_framework = CqrsFramework::new(PostgresEventStore::new(), webhook_queries, ChatServices);
+
+    let presence = Arc::new(PresenceTracker::new(room_events.clone()));
+
+    ChatFrameworks {
+        rooms,
+        slash_commands,
+        room_views: view_repository,
+        room_events,
+        room_directory,
+        room_store,
+        search,
+        read_states,
+        scheduler,
+        polls,
+        blobs,
+        presence,
+        direct: Arc::new(CommandPipeline::new(direct_framework, metrics.as_ref().clone(), rate_limiter.as_ref().clone())),
+        direct_views: direct_view_repository,
+        users: Arc::new(CommandPipeline::new(user_framework, metrics.as_ref().clone(), rate_limiter.as_ref().clone())),
+        user_views: user_view_repository,
+        credentials: credential_repository,
+        usernames,
+        webhooks: Arc::new(CommandPipeline::new(webhook_framework, metrics.as_ref().clone(), rate_limiter.as_ref().clone())),
+        webhook_registry,
+        webhook_deliveries,
+        incoming_webhooks,
+        metrics,
+        rate_limiter,
+    }
 }
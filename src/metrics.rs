@@ -0,0 +1,233 @@
+use async_trait::async_trait;
+use cqrs_es::{Aggregate, DomainEvent, EventEnvelope, Query};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Upper bounds, in seconds, of the command latency histogram buckets.
+const LATENCY_BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+#[derive(Default)]
+struct Histogram {
+    /// Observations per bucket, not cumulative; `render` accumulates them.
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        if let Some(bucket) = LATENCY_BUCKETS.iter().position(|bound| value <= *bound) {
+            self.buckets[bucket] += 1;
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+#[derive(Default)]
+struct MetricsState {
+    /// (aggregate, command) -> commands executed.
+    executed: BTreeMap<(String, String), u64>,
+    /// (aggregate, command) -> commands that returned an error.
+    failed: BTreeMap<(String, String), u64>,
+    latencies: BTreeMap<(String, String), Histogram>,
+    /// (aggregate, event) -> events committed.
+    events: BTreeMap<(String, String), u64>,
+    /// Aggregate -> events committed, as seen by the first query to run.
+    committed: BTreeMap<String, u64>,
+    /// (aggregate, query) -> events the query has finished processing.
+    processed: BTreeMap<(String, String), u64>,
+}
+
+/// How far a projection trails the events committed for its aggregate.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProjectionLag {
+    pub aggregate: String,
+    pub query: String,
+    pub events: u64,
+}
+
+/// Process-wide counters for commands, events and projections, rendered in the
+/// Prometheus text exposition format.
+///
+/// Registered as the first query of every framework, it counts committed events;
+/// the other queries are wrapped with [`Metrics::observe`] so their progress can
+/// be compared against that count.
+#[derive(Clone, Default)]
+pub struct Metrics {
+    state: Arc<Mutex<MetricsState>>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_command(&self, aggregate: &str, command: &str, succeeded: bool, elapsed: Duration) {
+        let key = (aggregate.to_string(), command.to_string());
+        let mut state = self.state.lock().unwrap();
+        *state.executed.entry(key.clone()).or_default() += 1;
+        if !succeeded {
+            *state.failed.entry(key.clone()).or_default() += 1;
+        }
+        state.latencies.entry(key).or_default().observe(elapsed.as_secs_f64());
+    }
+
+    /// Wraps a query so the events it has processed are tracked under `name`.
+    pub fn observe<A, Q>(&self, name: &str, query: Q) -> Box<dyn Query<A>>
+    where
+        A: Aggregate + 'static,
+        Q: Query<A> + 'static,
+    {
+        Box::new(ObservedQuery {
+            name: name.to_string(),
+            query,
+            metrics: self.clone(),
+        })
+    }
+
+    pub fn projection_lag(&self) -> Vec<ProjectionLag> {
+        let state = self.state.lock().unwrap();
+        state
+            .processed
+            .iter()
+            .map(|((aggregate, query), processed)| ProjectionLag {
+                aggregate: aggregate.clone(),
+                query: query.clone(),
+                events: state.committed.get(aggregate).copied().unwrap_or(0).saturating_sub(*processed),
+            })
+            .collect()
+    }
+
+    pub fn render(&self) -> String {
+        let lag = self.projection_lag();
+        let state = self.state.lock().unwrap();
+        let mut out = String::new();
+
+        let _ = writeln!(out, "# HELP chat_commands_executed_total Commands executed, by aggregate and command type.");
+        let _ = writeln!(out, "# TYPE chat_commands_executed_total counter");
+        for ((aggregate, command), count) in &state.executed {
+            let _ = writeln!(out, "chat_commands_executed_total{{aggregate=\"{}\",command=\"{}\"}} {}", aggregate, command, count);
+        }
+
+        let _ = writeln!(out, "# HELP chat_commands_failed_total Commands rejected or failed, by aggregate and command type.");
+        let _ = writeln!(out, "# TYPE chat_commands_failed_total counter");
+        for ((aggregate, command), count) in &state.failed {
+            let _ = writeln!(out, "chat_commands_failed_total{{aggregate=\"{}\",command=\"{}\"}} {}", aggregate, command, count);
+        }
+
+        let _ = writeln!(out, "# HELP chat_command_duration_seconds Time taken to execute a command.");
+        let _ = writeln!(out, "# TYPE chat_command_duration_seconds histogram");
+        for ((aggregate, command), histogram) in &state.latencies {
+            let labels = format!("aggregate=\"{}\",command=\"{}\"", aggregate, command);
+            let mut cumulative = 0;
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets) {
+                cumulative += count;
+                let _ = writeln!(out, "chat_command_duration_seconds_bucket{{{},le=\"{}\"}} {}", labels, bound, cumulative);
+            }
+            let _ = writeln!(out, "chat_command_duration_seconds_bucket{{{},le=\"+Inf\"}} {}", labels, histogram.count);
+            let _ = writeln!(out, "chat_command_duration_seconds_sum{{{}}} {}", labels, histogram.sum);
+            let _ = writeln!(out, "chat_command_duration_seconds_count{{{}}} {}", labels, histogram.count);
+        }
+
+        let _ = writeln!(out, "# HELP chat_events_committed_total Events committed, by aggregate and event type.");
+        let _ = writeln!(out, "# TYPE chat_events_committed_total counter");
+        for ((aggregate, event), count) in &state.events {
+            let _ = writeln!(out, "chat_events_committed_total{{aggregate=\"{}\",event=\"{}\"}} {}", aggregate, event, count);
+        }
+
+        let _ = writeln!(out, "# HELP chat_projection_lag_events Committed events a query has not processed yet.");
+        let _ = writeln!(out, "# TYPE chat_projection_lag_events gauge");
+        for lag in lag {
+            let _ = writeln!(out, "chat_projection_lag_events{{aggregate=\"{}\",query=\"{}\"}} {}", lag.aggregate, lag.query, lag.events);
+        }
+
+        out
+    }
+}
+
+#[async_trait]
+impl<A: Aggregate> Query<A> for Metrics {
+    async fn dispatch(&self, _aggregate_id: &str, events: &[EventEnvelope<A>]) {
+        let aggregate = A::aggregate_type();
+        let mut state = self.state.lock().unwrap();
+        for event_envelope in events {
+            *state
+                .events
+                .entry((aggregate.clone(), event_envelope.payload.event_type()))
+                .or_default() += 1;
+        }
+        *state.committed.entry(aggregate).or_default() += events.len() as u64;
+    }
+}
+
+struct ObservedQuery<Q> {
+    name: String,
+    query: Q,
+    metrics: Metrics,
+}
+
+#[async_trait]
+impl<A, Q> Query<A> for ObservedQuery<Q>
+where
+    A: Aggregate,
+    Q: Query<A>,
+{
+    async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<A>]) {
+        self.query.dispatch(aggregate_id, events).await;
+        let mut state = self.metrics.state.lock().unwrap();
+        *state
+            .processed
+            .entry((A::aggregate_type(), self.name.clone()))
+            .or_default() += events.len() as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_chat_framework;
+    use crate::domain::commands::ChatCommand;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_commands_events_and_projections_are_counted() {
+        let frameworks = create_chat_framework();
+        let room_id = Uuid::new_v4();
+        let command = ChatCommand::CreateRoom {
+            room_id,
+            name: "General".to_string(),
+            created_by: "user1".to_string(),
+        };
+        frameworks.rooms.execute(&room_id.to_string(), command).await.unwrap();
+        let command = ChatCommand::LeaveRoom {
+            user_id: "user2".to_string(),
+        };
+        assert!(frameworks.rooms.execute(&room_id.to_string(), command).await.is_err());
+
+        let output = frameworks.metrics.render();
+        assert!(output.contains("chat_commands_executed_total{aggregate=\"ChatRoom\",command=\"CreateRoom\"} 1"));
+        assert!(output.contains("chat_commands_failed_total{aggregate=\"ChatRoom\",command=\"LeaveRoom\"} 1"));
+        assert!(!output.contains("chat_commands_failed_total{aggregate=\"ChatRoom\",command=\"CreateRoom\"}"));
+        assert!(output.contains("chat_command_duration_seconds_count{aggregate=\"ChatRoom\",command=\"CreateRoom\"} 1"));
+        assert!(output.contains("chat_events_committed_total{aggregate=\"ChatRoom\",event=\"RoomCreated\"} 1"));
+        assert!(output.contains("chat_projection_lag_events{aggregate=\"ChatRoom\",query=\"room_views\"} 0"));
+        assert!(frameworks.metrics.projection_lag().iter().all(|lag| lag.events == 0));
+    }
+
+    #[test]
+    fn test_histogram_buckets_are_cumulative() {
+        let metrics = Metrics::new();
+        metrics.record_command("ChatRoom", "SendMessage", true, Duration::from_millis(3));
+        metrics.record_command("ChatRoom", "SendMessage", true, Duration::from_millis(30));
+
+        let output = metrics.render();
+        let labels = "aggregate=\"ChatRoom\",command=\"SendMessage\"";
+        assert!(output.contains(&format!("chat_command_duration_seconds_bucket{{{},le=\"0.005\"}} 1", labels)));
+        assert!(output.contains(&format!("chat_command_duration_seconds_bucket{{{},le=\"0.05\"}} 2", labels)));
+        assert!(output.contains(&format!("chat_command_duration_seconds_bucket{{{},le=\"+Inf\"}} 2", labels)));
+    }
+}
@@ -0,0 +1,150 @@
+use cqrs_es::{Aggregate, AggregateError, CqrsFramework, EventStore};
+use std::collections::HashMap;
+use std::time::Instant;
+use thiserror::Error;
+
+use crate::domain::commands::DomainCommand;
+use crate::metrics::Metrics;
+use crate::rate_limit::{RateLimited, RateLimiter};
+
+/// Why the pipeline did not execute a command.
+#[derive(Debug, Error)]
+pub enum CommandError<E: std::error::Error> {
+    #[error(transparent)]
+    RateLimited(#[from] RateLimited),
+
+    #[error(transparent)]
+    Aggregate(#[from] AggregateError<E>),
+}
+
+/// Every command, whichever UI issued it, goes through this wrapper around the
+/// CQRS framework, so cross-cutting concerns such as metrics and rate limiting
+/// apply uniformly.
+pub struct CommandPipeline<A, ES>
+where
+    A: Aggregate,
+    ES: EventStore<A>,
+{
+    framework: CqrsFramework<A, ES>,
+    metrics: Metrics,
+    rate_limiter: RateLimiter,
+}
+
+impl<A, ES> CommandPipeline<A, ES>
+where
+    A: Aggregate,
+    A::Command: DomainCommand,
+    ES: EventStore<A>,
+{
+    pub fn new(framework: CqrsFramework<A, ES>, metrics: Metrics, rate_limiter: RateLimiter) -> Self {
+        Self {
+            framework,
+            metrics,
+            rate_limiter,
+        }
+    }
+
+    pub async fn execute(&self, aggregate_id: &str, command: A::Command) -> Result<(), CommandError<A::Error>> {
+        self.execute_with_metadata(aggregate_id, command, HashMap::new()).await
+    }
+
+    pub async fn execute_with_metadata(
+        &self,
+        aggregate_id: &str,
+        command: A::Command,
+        metadata: HashMap<String, String>,
+    ) -> Result<(), CommandError<A::Error>> {
+        let command_type = command.command_type();
+        let started = Instant::now();
+        let result = match self.check_rate_limits(aggregate_id, &command) {
+            Ok(()) => self
+                .framework
+                .execute_with_metadata(aggregate_id, command, metadata)
+                .await
+                .map_err(CommandError::from),
+            Err(limited) => Err(limited.into()),
+        };
+
+        // Rate-limited commands count as rejected, like those the aggregate refuses.
+        self.metrics
+            .record_command(&A::aggregate_type(), &command_type, result.is_ok(), started.elapsed());
+        result
+    }
+
+    /// A batch counts against the limit of every command in it. Commands the system
+    /// issues, such as expiring a message, are not limited; neither is registering,
+    /// which the web API limits per client address instead.
+    fn check_rate_limits(&self, aggregate_id: &str, command: &A::Command) -> Result<(), RateLimited> {
+        for part in command.parts() {
+            let issued_by = part
+                .issued_by()
+                .or_else(|| part.issued_by_aggregate().then_some(aggregate_id));
+            if let Some(issued_by) = issued_by {
+                self.rate_limiter
+                    .check(&format!("user:{}", issued_by), &part.command_type())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_chat_framework;
+    use crate::domain::commands::ChatCommand;
+    use crate::domain::user::commands::UserCommand;
+    use crate::rate_limit::RateLimiter;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_commands_are_rate_limited_per_user() {
+        let frameworks = create_chat_framework();
+        let room_id = Uuid::new_v4();
+        let command = ChatCommand::CreateRoom {
+            room_id,
+            name: "General".to_string(),
+            created_by: "user1".to_string(),
+        };
+        frameworks.rooms.execute(&room_id.to_string(), command).await.unwrap();
+
+        let burst = RateLimiter::default_limits()["SendMessage"].burst;
+        let send = |user_id: &str| ChatCommand::SendMessage {
+            message_id: Uuid::new_v4(),
+            user_id: user_id.to_string(),
+            content: "hello".to_string(),
+            timestamp: chrono::Utc::now(),
+            ttl_seconds: None,
+            attachment_ids: Vec::new(),
+        };
+        for _ in 0..burst {
+            frameworks.rooms.execute(&room_id.to_string(), send("user1")).await.unwrap();
+        }
+
+        let result = frameworks.rooms.execute(&room_id.to_string(), send("user1")).await;
+        assert!(matches!(result, Err(CommandError::RateLimited(_))));
+        assert!(frameworks
+            .metrics
+            .render()
+            .contains("chat_commands_failed_total{aggregate=\"ChatRoom\",command=\"SendMessage\"} 1"));
+        // Another user's allowance is untouched; they are only rejected for not being in the room.
+        let result = frameworks.rooms.execute(&room_id.to_string(), send("user2")).await;
+        assert!(matches!(result, Err(CommandError::Aggregate(_))));
+    }
+
+    #[tokio::test]
+    async fn test_registrations_are_not_limited_per_generated_user() {
+        let frameworks = create_chat_framework();
+        let burst = RateLimiter::default_limits()["RegisterUser"].burst;
+        for i in 0..=burst {
+            let user_id = Uuid::new_v4().to_string();
+            let command = UserCommand::RegisterUser {
+                user_id: user_id.clone(),
+                username: format!("user{}", i),
+                display_name: format!("User {}", i),
+                password_hash: None,
+            };
+            frameworks.users.execute(&user_id, command).await.unwrap();
+        }
+    }
+}
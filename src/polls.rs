@@ -0,0 +1,254 @@
+use async_trait::async_trait;
+use cqrs_es::{EventEnvelope, Query};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::domain::aggregate::ChatRoom;
+use crate::domain::events::ChatEvent;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PollView {
+    pub poll_id: Uuid,
+    pub room_id: Uuid,
+    pub created_by: String,
+    pub question: String,
+    pub options: Vec<PollOptionView>,
+    pub multiple_choice: bool,
+    pub anonymous: bool,
+    pub closed: bool,
+    /// How many participants have voted; in a multiple-choice poll the option
+    /// tallies can add up to more.
+    pub voters: usize,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PollOptionView {
+    pub text: String,
+    pub votes: usize,
+    /// Who voted for the option, in voting order; always empty in an anonymous poll.
+    pub voters: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+struct PollState {
+    room_id: Uuid,
+    created_by: String,
+    question: String,
+    options: Vec<String>,
+    multiple_choice: bool,
+    anonymous: bool,
+    closed: bool,
+    created_at: chrono::DateTime<chrono::Utc>,
+    /// Each voter's choices, in the order the votes were last cast.
+    ballots: Vec<(String, Vec<usize>)>,
+}
+
+impl PollState {
+    fn view(&self, poll_id: Uuid) -> PollView {
+        let options = self
+            .options
+            .iter()
+            .enumerate()
+            .map(|(index, text)| {
+                let voters: Vec<String> = self
+                    .ballots
+                    .iter()
+                    .filter(|(_, choices)| choices.contains(&index))
+                    .map(|(voter, _)| voter.clone())
+                    .collect();
+                PollOptionView {
+                    text: text.clone(),
+                    votes: voters.len(),
+                    voters: if self.anonymous { Vec::new() } else { voters },
+                }
+            })
+            .collect();
+
+        PollView {
+            poll_id,
+            room_id: self.room_id,
+            created_by: self.created_by.clone(),
+            question: self.question.clone(),
+            options,
+            multiple_choice: self.multiple_choice,
+            anonymous: self.anonymous,
+            closed: self.closed,
+            voters: self.ballots.len(),
+            created_at: self.created_at,
+        }
+    }
+}
+
+/// Every room's polls with their tallies, updated as votes are cast.
+#[derive(Clone, Default)]
+pub struct PollRepository {
+    polls: Arc<RwLock<HashMap<Uuid, PollState>>>,
+}
+
+impl PollRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn get_poll(&self, poll_id: &Uuid) -> Option<PollView> {
+        self.polls.read().await.get(poll_id).map(|poll| poll.view(*poll_id))
+    }
+
+    /// A room's polls, newest first.
+    pub async fn for_room(&self, room_id: &Uuid) -> Vec<PollView> {
+        let polls = self.polls.read().await;
+        let mut views: Vec<PollView> = polls
+            .iter()
+            .filter(|(_, poll)| &poll.room_id == room_id)
+            .map(|(poll_id, poll)| poll.view(*poll_id))
+            .collect();
+        views.sort_by_key(|poll| std::cmp::Reverse(poll.created_at));
+        views
+    }
+
+    /// The options a user voted for, which they may see even in an anonymous poll.
+    pub async fn ballot(&self, poll_id: &Uuid, user_id: &str) -> Option<Vec<usize>> {
+        let polls = self.polls.read().await;
+        let poll = polls.get(poll_id)?;
+        poll.ballots
+            .iter()
+            .find(|(voter, _)| voter == user_id)
+            .map(|(_, choices)| choices.clone())
+    }
+}
+
+#[async_trait]
+impl Query<ChatRoom> for PollRepository {
+    async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<ChatRoom>]) {
+        let Ok(room_id) = Uuid::parse_str(aggregate_id) else {
+            log::error!("Room aggregate id {} is not a UUID", aggregate_id);
+            return;
+        };
+        let mut polls = self.polls.write().await;
+        for event_envelope in events {
+            match &event_envelope.payload {
+                ChatEvent::PollCreated { poll_id, user_id, question, options, multiple_choice, anonymous, timestamp } => {
+                    polls.insert(
+                        *poll_id,
+                        PollState {
+                            room_id,
+                            created_by: user_id.clone(),
+                            question: question.clone(),
+                            options: options.clone(),
+                            multiple_choice: *multiple_choice,
+                            anonymous: *anonymous,
+                            closed: false,
+                            created_at: *timestamp,
+                            ballots: Vec::new(),
+                        },
+                    );
+                }
+
+                ChatEvent::VoteCast { poll_id, user_id, options, .. } => {
+                    if let Some(poll) = polls.get_mut(poll_id) {
+                        poll.ballots.retain(|(voter, _)| voter != user_id);
+                        poll.ballots.push((user_id.clone(), options.clone()));
+                    }
+                }
+
+                ChatEvent::VoteRetracted { poll_id, user_id, .. } => {
+                    if let Some(poll) = polls.get_mut(poll_id) {
+                        poll.ballots.retain(|(voter, _)| voter != user_id);
+                    }
+                }
+
+                ChatEvent::PollClosed { poll_id, .. } => {
+                    if let Some(poll) = polls.get_mut(poll_id) {
+                        poll.closed = true;
+                    }
+                }
+
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_chat_framework;
+    use crate::domain::commands::ChatCommand;
+
+    #[tokio::test]
+    async fn test_tallies_follow_votes_and_hide_anonymous_voters() {
+        let frameworks = create_chat_framework();
+        let room_id = Uuid::new_v4();
+        let poll_id = Uuid::new_v4();
+        let setup = ChatCommand::Batch {
+            commands: vec![
+                ChatCommand::CreateRoom {
+                    room_id,
+                    name: "General".to_string(),
+                    created_by: "user1".to_string(),
+                },
+                ChatCommand::JoinRoom {
+                    user_id: "user2".to_string(),
+                    username: "Bob".to_string(),
+                },
+                ChatCommand::CreatePoll {
+                    poll_id,
+                    user_id: "user1".to_string(),
+                    question: "Lunch?".to_string(),
+                    options: vec!["Pizza".to_string(), "Sushi".to_string(), "Tacos".to_string()],
+                    multiple_choice: true,
+                    anonymous: false,
+                },
+            ],
+        };
+        frameworks.rooms.execute(&room_id.to_string(), setup).await.unwrap();
+        let vote = |user_id: &str, options: Vec<usize>| ChatCommand::CastVote {
+            poll_id,
+            user_id: user_id.to_string(),
+            options,
+        };
+        frameworks.rooms.execute(&room_id.to_string(), vote("user1", vec![0, 1])).await.unwrap();
+        frameworks.rooms.execute(&room_id.to_string(), vote("user2", vec![1])).await.unwrap();
+        frameworks.rooms.execute(&room_id.to_string(), vote("user1", vec![2])).await.unwrap();
+
+        let poll = frameworks.polls.get_poll(&poll_id).await.unwrap();
+        let tallies: Vec<usize> = poll.options.iter().map(|option| option.votes).collect();
+        assert_eq!(tallies, vec![0, 1, 1]);
+        assert_eq!(poll.options[1].voters, vec!["user2".to_string()]);
+        assert_eq!(poll.voters, 2);
+        assert_eq!(frameworks.polls.ballot(&poll_id, "user1").await, Some(vec![2]));
+
+        let retract = ChatCommand::RetractVote {
+            poll_id,
+            user_id: "user2".to_string(),
+        };
+        frameworks.rooms.execute(&room_id.to_string(), retract).await.unwrap();
+        assert_eq!(frameworks.polls.get_poll(&poll_id).await.unwrap().options[1].votes, 0);
+
+        let anonymous_id = Uuid::new_v4();
+        let create = ChatCommand::CreatePoll {
+            poll_id: anonymous_id,
+            user_id: "user2".to_string(),
+            question: "Rate the sprint".to_string(),
+            options: vec!["Good".to_string(), "Bad".to_string()],
+            multiple_choice: false,
+            anonymous: true,
+        };
+        frameworks.rooms.execute(&room_id.to_string(), create).await.unwrap();
+        let vote = ChatCommand::CastVote {
+            poll_id: anonymous_id,
+            user_id: "user1".to_string(),
+            options: vec![0],
+        };
+        frameworks.rooms.execute(&room_id.to_string(), vote).await.unwrap();
+
+        let polls = frameworks.polls.for_room(&room_id).await;
+        assert_eq!(polls[0].poll_id, anonymous_id);
+        assert_eq!(polls[0].options[0].votes, 1);
+        assert!(polls[0].options[0].voters.is_empty());
+    }
+}
@@ -0,0 +1,256 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, RwLock};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::services::{RoomEventHub, RoomFeedItem};
+
+/// How long a presence heartbeat keeps a user online.
+pub const PRESENCE_TTL: Duration = Duration::from_secs(60);
+/// How long a typing notice stays visible unless it is repeated.
+pub const TYPING_TTL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PresenceStatus {
+    Online,
+    Away,
+    Offline,
+}
+
+/// A change in a user's presence, pushed to live subscribers.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PresenceChange {
+    pub user_id: String,
+    pub status: PresenceStatus,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UserPresence {
+    pub user_id: String,
+    pub status: PresenceStatus,
+}
+
+#[derive(Default)]
+struct PresenceState {
+    statuses: HashMap<String, (PresenceStatus, Instant)>,
+    typing: HashMap<(Uuid, String), Instant>,
+    /// How many live sockets each user has open.
+    sockets: HashMap<String, usize>,
+}
+
+/// Online/away status and typing indicators. These signals are ephemeral: they
+/// live in memory with a time-to-live and are never written to the event store.
+#[derive(Clone)]
+pub struct PresenceTracker {
+    state: Arc<RwLock<PresenceState>>,
+    changes: broadcast::Sender<PresenceChange>,
+    hub: Arc<RoomEventHub>,
+    presence_ttl: Duration,
+    typing_ttl: Duration,
+}
+
+impl PresenceTracker {
+    pub fn new(hub: Arc<RoomEventHub>) -> Self {
+        Self::with_ttls(hub, PRESENCE_TTL, TYPING_TTL)
+    }
+
+    pub fn with_ttls(hub: Arc<RoomEventHub>, presence_ttl: Duration, typing_ttl: Duration) -> Self {
+        let (changes, _) = broadcast::channel(256);
+        Self {
+            state: Arc::new(RwLock::new(PresenceState::default())),
+            changes,
+            hub,
+            presence_ttl,
+            typing_ttl,
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<PresenceChange> {
+        self.changes.subscribe()
+    }
+
+    /// How long a heartbeat keeps a user online.
+    pub fn presence_ttl(&self) -> Duration {
+        self.presence_ttl
+    }
+
+    /// Counts a newly opened socket of the user's and marks them online.
+    pub async fn connect(&self, user_id: &str) {
+        *self.state.write().await.sockets.entry(user_id.to_string()).or_default() += 1;
+        self.set_status(user_id, PresenceStatus::Online).await;
+    }
+
+    /// Counts a closed socket of the user's; once their last one has closed they
+    /// go offline straight away rather than when their heartbeat runs out.
+    pub async fn disconnect(&self, user_id: &str) {
+        let mut state = self.state.write().await;
+        let Some(open) = state.sockets.get_mut(user_id) else {
+            return;
+        };
+        *open -= 1;
+        if *open > 0 {
+            return;
+        }
+        state.sockets.remove(user_id);
+        if state.statuses.remove(user_id).is_some() {
+            self.notify(user_id, PresenceStatus::Offline);
+        }
+    }
+
+    /// Records a heartbeat. Subscribers are only notified when the status changes.
+    pub async fn set_status(&self, user_id: &str, status: PresenceStatus) {
+        let mut state = self.state.write().await;
+        if status == PresenceStatus::Offline {
+            if state.statuses.remove(user_id).is_some() {
+                self.notify(user_id, status);
+            }
+            return;
+        }
+
+        let previous = state
+            .statuses
+            .insert(user_id.to_string(), (status, Instant::now() + self.presence_ttl));
+        if previous.is_none_or(|(previous, expires_at)| previous != status || expires_at <= Instant::now()) {
+            self.notify(user_id, status);
+        }
+    }
+
+    pub async fn get_status(&self, user_id: &str) -> PresenceStatus {
+        let state = self.state.read().await;
+        match state.statuses.get(user_id) {
+            Some((status, expires_at)) if *expires_at > Instant::now() => *status,
+            _ => PresenceStatus::Offline,
+        }
+    }
+
+    /// Every user who is currently online or away.
+    pub async fn get_present_users(&self) -> Vec<UserPresence> {
+        let state = self.state.read().await;
+        let now = Instant::now();
+        let mut users: Vec<UserPresence> = state
+            .statuses
+            .iter()
+            .filter(|(_, (_, expires_at))| *expires_at > now)
+            .map(|(user_id, (status, _))| UserPresence {
+                user_id: user_id.clone(),
+                status: *status,
+            })
+            .collect();
+        users.sort_by(|a, b| a.user_id.cmp(&b.user_id));
+        users
+    }
+
+    /// Marks the user as typing in the room and relays the notice to the room's live subscribers.
+    pub async fn set_typing(&self, room_id: Uuid, user_id: &str) {
+        let mut state = self.state.write().await;
+        state
+            .typing
+            .insert((room_id, user_id.to_string()), Instant::now() + self.typing_ttl);
+        self.hub.publish(
+            room_id,
+            RoomFeedItem::Typing {
+                user_id: user_id.to_string(),
+            },
+        );
+    }
+
+    /// Users currently typing in the room.
+    pub async fn get_typing(&self, room_id: &Uuid) -> Vec<String> {
+        let state = self.state.read().await;
+        let now = Instant::now();
+        let mut users: Vec<String> = state
+            .typing
+            .iter()
+            .filter(|((room, _), expires_at)| room == room_id && **expires_at > now)
+            .map(|((_, user_id), _)| user_id.clone())
+            .collect();
+        users.sort();
+        users
+    }
+
+    /// Drops expired entries and tells subscribers about users who went offline.
+    /// Meant to be called periodically.
+    pub async fn sweep(&self) {
+        let mut state = self.state.write().await;
+        let now = Instant::now();
+
+        let expired: Vec<String> = state
+            .statuses
+            .iter()
+            .filter(|(_, (_, expires_at))| *expires_at <= now)
+            .map(|(user_id, _)| user_id.clone())
+            .collect();
+        for user_id in expired {
+            state.statuses.remove(&user_id);
+            self.notify(&user_id, PresenceStatus::Offline);
+        }
+
+        state.typing.retain(|_, expires_at| *expires_at > now);
+    }
+
+    fn notify(&self, user_id: &str, status: PresenceStatus) {
+        let _ = self.changes.send(PresenceChange {
+            user_id: user_id.to_string(),
+            status,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_presence_expires_and_notifies_offline() {
+        let tracker = PresenceTracker::with_ttls(Arc::new(RoomEventHub::new()), Duration::from_millis(20), TYPING_TTL);
+        let mut changes = tracker.subscribe();
+
+        tracker.set_status("user1", PresenceStatus::Online).await;
+        tracker.set_status("user1", PresenceStatus::Online).await;
+        assert_eq!(tracker.get_status("user1").await, PresenceStatus::Online);
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(tracker.get_status("user1").await, PresenceStatus::Offline);
+        tracker.sweep().await;
+
+        assert_eq!(changes.recv().await.unwrap().status, PresenceStatus::Online);
+        assert_eq!(changes.recv().await.unwrap().status, PresenceStatus::Offline);
+        assert!(changes.try_recv().is_err(), "repeated heartbeats must not notify");
+    }
+
+    #[tokio::test]
+    async fn test_closing_the_last_socket_goes_offline() {
+        let tracker = PresenceTracker::new(Arc::new(RoomEventHub::new()));
+        let mut changes = tracker.subscribe();
+
+        tracker.connect("user1").await;
+        tracker.connect("user1").await;
+        tracker.disconnect("user1").await;
+        assert_eq!(tracker.get_status("user1").await, PresenceStatus::Online);
+        tracker.disconnect("user1").await;
+        assert_eq!(tracker.get_status("user1").await, PresenceStatus::Offline);
+
+        assert_eq!(changes.recv().await.unwrap().status, PresenceStatus::Online);
+        assert_eq!(changes.recv().await.unwrap().status, PresenceStatus::Offline);
+        assert!(changes.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_typing_is_relayed_and_expires() {
+        let hub = Arc::new(RoomEventHub::new());
+        let tracker = PresenceTracker::with_ttls(hub.clone(), PRESENCE_TTL, Duration::from_millis(20));
+        let mut events = hub.subscribe();
+        let room_id = Uuid::new_v4();
+
+        tracker.set_typing(room_id, "user1").await;
+        assert_eq!(tracker.get_typing(&room_id).await, vec!["user1".to_string()]);
+        assert!(matches!(events.recv().await.unwrap(), (id, RoomFeedItem::Typing { .. }) if id == room_id));
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert!(tracker.get_typing(&room_id).await.is_empty());
+    }
+}
@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// Limit that applies to every command type without a limit of its own.
+pub const ANY_COMMAND: &str = "*";
+
+/// At most this many buckets are tracked. When a new caller would exceed it, the least
+/// recently used tenth are dropped, so the cost of pruning is spread over many checks.
+const MAX_TRACKED_BUCKETS: usize = 10_000;
+
+/// A token bucket: up to `burst` commands at once, refilled evenly so that
+/// `burst` more are allowed every `period`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub burst: u32,
+    pub period: Duration,
+}
+
+impl RateLimit {
+    pub fn new(burst: u32, period: Duration) -> Self {
+        Self { burst, period }
+    }
+
+    /// Parses `burst/period`, e.g. `20/10s`, `5/1m` or `3/1h`.
+    pub fn parse(value: &str) -> Option<Self> {
+        let (burst, period) = value.split_once('/')?;
+        let burst = burst.trim().parse().ok().filter(|burst| *burst > 0)?;
+        let period = period.trim();
+        let (amount, unit) = period.split_at(period.find(|c: char| !c.is_ascii_digit())?);
+        let seconds = amount.parse::<u64>().ok()?
+            * match unit {
+                "s" => 1,
+                "m" => 60,
+                "h" => 3600,
+                _ => return None,
+            };
+        (seconds > 0).then(|| Self::new(burst, Duration::from_secs(seconds)))
+    }
+
+    fn tokens_per_second(&self) -> f64 {
+        self.burst as f64 / self.period.as_secs_f64()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Error)]
+#[error("Too many {command} commands, retry in {} seconds", retry_after.as_secs())]
+pub struct RateLimited {
+    pub command: String,
+    /// Time until the next command would be accepted, rounded up to whole seconds.
+    pub retry_after: Duration,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token-bucket rate limits per command type. Buckets are keyed by caller, such
+/// as `user:<id>` in the command pipeline or `ip:<address>` in the Web API, so
+/// one noisy caller cannot exhaust another's allowance.
+#[derive(Clone)]
+pub struct RateLimiter {
+    limits: Arc<HashMap<String, RateLimit>>,
+    buckets: Arc<Mutex<HashMap<(String, String), Bucket>>>,
+}
+
+impl RateLimiter {
+    pub fn new(limits: HashMap<String, RateLimit>) -> Self {
+        Self {
+            limits: Arc::new(limits),
+            buckets: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn unlimited() -> Self {
+        Self::new(HashMap::new())
+    }
+
+    /// Limits suited to interactive chat: generous for messages, strict for
+    /// uploads and for creating rooms, conversations and accounts.
+    pub fn default_limits() -> HashMap<String, RateLimit> {
+        HashMap::from([
+            ("SendMessage".to_string(), RateLimit::new(20, Duration::from_secs(10))),
+            ("SendDirectMessage".to_string(), RateLimit::new(20, Duration::from_secs(10))),
+            ("UploadAttachment".to_string(), RateLimit::new(10, Duration::from_secs(60))),
+            ("CreateRoom".to_string(), RateLimit::new(5, Duration::from_secs(60))),
+            ("StartConversation".to_string(), RateLimit::new(10, Duration::from_secs(60))),
+            ("RegisterUser".to_string(), RateLimit::new(5, Duration::from_secs(3600))),
+        ])
+    }
+
+    /// Uses the default limits, overridden by `RATE_LIMITS`, a comma-separated list
+    /// such as `SendMessage=10/10s,*=60/1m`. `*` sets a limit for every other command.
+    pub fn from_env() -> Self {
+        let mut limits = Self::default_limits();
+        if let Ok(value) = std::env::var("RATE_LIMITS") {
+            for entry in value.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+                match entry.split_once('=').and_then(|(command, limit)| Some((command, RateLimit::parse(limit)?))) {
+                    Some((command, limit)) => {
+                        limits.insert(command.trim().to_string(), limit);
+                    }
+                    None => log::warn!("Ignoring malformed RATE_LIMITS entry {:?}", entry),
+                }
+            }
+        }
+        Self::new(limits)
+    }
+
+    pub fn limit_for(&self, command_type: &str) -> Option<RateLimit> {
+        self.limits
+            .get(command_type)
+            .or_else(|| self.limits.get(ANY_COMMAND))
+            .copied()
+    }
+
+    /// Takes a token from the caller's bucket for this command type.
+    pub fn check(&self, key: &str, command_type: &str) -> Result<(), RateLimited> {
+        let Some(limit) = self.limit_for(command_type) else {
+            return Ok(());
+        };
+        let rate = limit.tokens_per_second();
+        let now = Instant::now();
+
+        let mut buckets = self.buckets.lock().unwrap();
+        let key = (key.to_string(), command_type.to_string());
+        if buckets.len() >= MAX_TRACKED_BUCKETS && !buckets.contains_key(&key) {
+            evict_least_recently_used(&mut buckets, MAX_TRACKED_BUCKETS / 10);
+        }
+
+        let bucket = buckets.entry(key).or_insert(Bucket {
+            tokens: limit.burst as f64,
+            updated: now,
+        });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(limit.burst as f64);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            let wait = (1.0 - bucket.tokens) / rate;
+            Err(RateLimited {
+                command: command_type.to_string(),
+                retry_after: Duration::from_secs(wait.ceil().max(1.0) as u64),
+            })
+        }
+    }
+}
+
+/// Drops the `count` buckets used longest ago.
+fn evict_least_recently_used(buckets: &mut HashMap<(String, String), Bucket>, count: usize) {
+    let mut used: Vec<Instant> = buckets.values().map(|bucket| bucket.updated).collect();
+    let count = count.clamp(1, used.len());
+    let (_, cutoff, _) = used.select_nth_unstable(count - 1);
+    let cutoff = *cutoff;
+
+    let mut evicted = 0;
+    buckets.retain(|_, bucket| {
+        if evicted < count && bucket.updated <= cutoff {
+            evicted += 1;
+            false
+        } else {
+            true
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_allows_burst_then_refills() {
+        let limiter = RateLimiter::new(HashMap::from([(
+            "SendMessage".to_string(),
+            RateLimit::new(2, Duration::from_millis(100)),
+        )]));
+
+        assert!(limiter.check("user:1", "SendMessage").is_ok());
+        assert!(limiter.check("user:1", "SendMessage").is_ok());
+        let limited = limiter.check("user:1", "SendMessage").unwrap_err();
+        assert_eq!(limited.retry_after, Duration::from_secs(1));
+
+        assert!(limiter.check("user:2", "SendMessage").is_ok(), "buckets are per caller");
+        assert!(limiter.check("user:1", "JoinRoom").is_ok(), "unlisted commands are unlimited");
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(limiter.check("user:1", "SendMessage").is_ok());
+    }
+
+    #[test]
+    fn test_tracked_buckets_are_capped() {
+        let limiter = RateLimiter::new(HashMap::from([(
+            "SendMessage".to_string(),
+            RateLimit::new(1, Duration::from_secs(60)),
+        )]));
+        assert!(limiter.check("user:first", "SendMessage").is_ok());
+        for caller in 0..MAX_TRACKED_BUCKETS {
+            assert!(limiter.check(&format!("user:{}", caller), "SendMessage").is_ok());
+        }
+
+        assert!(limiter.buckets.lock().unwrap().len() <= MAX_TRACKED_BUCKETS);
+        assert!(
+            limiter.check("user:first", "SendMessage").is_ok(),
+            "the least recently used bucket was evicted"
+        );
+        assert!(limiter.check(&format!("user:{}", MAX_TRACKED_BUCKETS - 1), "SendMessage").is_err());
+    }
+
+    #[test]
+    fn test_parse_limits() {
+        assert_eq!(RateLimit::parse("20/10s"), Some(RateLimit::new(20, Duration::from_secs(10))));
+        assert_eq!(RateLimit::parse("3/1h"), Some(RateLimit::new(3, Duration::from_secs(3600))));
+        assert_eq!(RateLimit::parse("0/1s"), None);
+        assert_eq!(RateLimit::parse("5/0m"), None);
+        assert_eq!(RateLimit::parse("5/1d"), None);
+        assert_eq!(RateLimit::parse("5"), None);
+    }
+}
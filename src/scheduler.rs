@@ -0,0 +1,377 @@
+use async_trait::async_trait;
+use cqrs_es::{AggregateError, EventEnvelope, EventStore, Query};
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::domain::aggregate::ChatRoom;
+use crate::domain::commands::ChatCommand;
+use crate::domain::events::ChatEvent;
+use crate::pipeline::CommandError;
+use crate::services::PostgresEventStore;
+use crate::ChatRoomFramework;
+
+/// How often the scheduler looks for messages that have fallen due or expired.
+const TICK: Duration = Duration::from_secs(1);
+/// How long an upload may wait to be sent with a message before it is discarded.
+pub const PENDING_ATTACHMENT_LIFETIME: chrono::Duration = chrono::Duration::hours(1);
+
+#[derive(Debug, Clone)]
+pub struct PendingMessage {
+    pub message_id: Uuid,
+    pub room_id: Uuid,
+    pub user_id: String,
+    pub content: String,
+    pub deliver_at: chrono::DateTime<chrono::Utc>,
+    pub scheduled_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// A sent message with a time-to-live that has not expired yet.
+#[derive(Debug, Clone)]
+struct ExpiringMessage {
+    room_id: Uuid,
+    expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// An uploaded attachment that no message has been sent with yet.
+#[derive(Debug, Clone)]
+struct PendingUpload {
+    room_id: Uuid,
+    discard_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Scheduled messages still waiting for delivery, sent messages still waiting
+/// to expire and uploads still waiting to be sent, projected from room events,
+/// and the background task that delivers, expires and discards them once due.
+#[derive(Clone, Default)]
+pub struct MessageScheduler {
+    pending: Arc<RwLock<HashMap<Uuid, PendingMessage>>>,
+    expiring: Arc<RwLock<HashMap<Uuid, ExpiringMessage>>>,
+    uploads: Arc<RwLock<HashMap<Uuid, PendingUpload>>>,
+}
+
+impl MessageScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A user's pending messages across every room, soonest first.
+    pub async fn for_user(&self, user_id: &str) -> Vec<PendingMessage> {
+        let pending = self.pending.read().await;
+        let mut messages: Vec<PendingMessage> = pending.values().filter(|m| m.user_id == user_id).cloned().collect();
+        messages.sort_by_key(|m| m.deliver_at);
+        messages
+    }
+
+    async fn due(&self, now: chrono::DateTime<chrono::Utc>) -> Vec<PendingMessage> {
+        let pending = self.pending.read().await;
+        pending.values().filter(|m| m.deliver_at <= now).cloned().collect()
+    }
+
+    async fn forget(&self, message_id: &Uuid) {
+        self.pending.write().await.remove(message_id);
+    }
+
+    /// Messages whose time-to-live has run out, with the room each was sent to.
+    async fn expired(&self, now: chrono::DateTime<chrono::Utc>) -> Vec<(Uuid, Uuid)> {
+        let expiring = self.expiring.read().await;
+        expiring
+            .iter()
+            .filter(|(_, m)| m.expires_at <= now)
+            .map(|(message_id, m)| (*message_id, m.room_id))
+            .collect()
+    }
+
+    /// Uploads that have waited too long to be sent, with the room each was uploaded to.
+    async fn abandoned(&self, now: chrono::DateTime<chrono::Utc>) -> Vec<(Uuid, Uuid)> {
+        let uploads = self.uploads.read().await;
+        uploads
+            .iter()
+            .filter(|(_, upload)| upload.discard_at <= now)
+            .map(|(attachment_id, upload)| (*attachment_id, upload.room_id))
+            .collect()
+    }
+
+    /// Replays every stored room event, so messages scheduled or sent before a restart
+    /// are still delivered and expired.
+    pub async fn rebuild(&self, store: &PostgresEventStore<ChatRoom>) {
+        for aggregate_id in store.aggregate_ids().await {
+            match store.load_events(&aggregate_id).await {
+                Ok(events) => self.dispatch(&aggregate_id, &events).await,
+                Err(e) => log::error!("Could not replay room {} for the scheduler: {}", aggregate_id, e),
+            }
+        }
+    }
+
+    /// Rebuilds the schedule and then delivers due messages and expires old ones on a
+    /// thread of its own, until the framework is dropped.
+    pub fn start(&self, rooms: Weak<ChatRoomFramework>, store: PostgresEventStore<ChatRoom>) {
+        let scheduler = self.clone();
+        std::thread::Builder::new()
+            .name("message-scheduler".to_string())
+            .spawn(move || {
+                actix_rt::System::new().block_on(async move {
+                    scheduler.rebuild(&store).await;
+                    let mut interval = actix_rt::time::interval(TICK);
+                    loop {
+                        interval.tick().await;
+                        let Some(rooms) = rooms.upgrade() else {
+                            return;
+                        };
+                        scheduler.deliver_due(&rooms).await;
+                        scheduler.expire_due(&rooms).await;
+                        scheduler.discard_abandoned(&rooms).await;
+                    }
+                })
+            })
+            .expect("failed to start the message scheduler");
+    }
+
+    async fn deliver_due(&self, rooms: &ChatRoomFramework) {
+        for message in self.due(chrono::Utc::now()).await {
+            let command = ChatCommand::DeliverScheduledMessage {
+                message_id: message.message_id,
+            };
+            match rooms.execute(&message.room_id.to_string(), command).await {
+                Ok(()) => self.forget(&message.message_id).await,
+                // Tried again on the next tick.
+                Err(CommandError::RateLimited(_)) | Err(CommandError::Aggregate(AggregateError::AggregateConflict)) => {}
+                Err(e) => {
+                    log::error!("Could not deliver scheduled message {}: {}", message.message_id, e);
+                    self.forget(&message.message_id).await;
+                }
+            }
+        }
+    }
+
+    async fn expire_due(&self, rooms: &ChatRoomFramework) {
+        for (message_id, room_id) in self.expired(chrono::Utc::now()).await {
+            let command = ChatCommand::ExpireMessage { message_id };
+            match rooms.execute(&room_id.to_string(), command).await {
+                Ok(()) => {}
+                // Tried again on the next tick.
+                Err(CommandError::RateLimited(_)) | Err(CommandError::Aggregate(AggregateError::AggregateConflict)) => {
+                    continue;
+                }
+                Err(e) => log::error!("Could not expire message {}: {}", message_id, e),
+            }
+            self.expiring.write().await.remove(&message_id);
+        }
+    }
+
+    async fn discard_abandoned(&self, rooms: &ChatRoomFramework) {
+        for (attachment_id, room_id) in self.abandoned(chrono::Utc::now()).await {
+            let command = ChatCommand::DiscardAttachment { attachment_id };
+            match rooms.execute(&room_id.to_string(), command).await {
+                Ok(()) => {}
+                // Tried again on the next tick.
+                Err(CommandError::RateLimited(_)) | Err(CommandError::Aggregate(AggregateError::AggregateConflict)) => {
+                    continue;
+                }
+                Err(e) => log::error!("Could not discard attachment {}: {}", attachment_id, e),
+            }
+            self.uploads.write().await.remove(&attachment_id);
+        }
+    }
+}
+
+#[async_trait]
+impl Query<ChatRoom> for MessageScheduler {
+    async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<ChatRoom>]) {
+        let Ok(room_id) = Uuid::parse_str(aggregate_id) else {
+            log::error!("Room aggregate id {} is not a UUID", aggregate_id);
+            return;
+        };
+        let mut pending = self.pending.write().await;
+        let mut expiring = self.expiring.write().await;
+        let mut uploads = self.uploads.write().await;
+        for event_envelope in events {
+            match &event_envelope.payload {
+                ChatEvent::MessageScheduled { message_id, user_id, content, deliver_at, timestamp } => {
+                    pending.insert(
+                        *message_id,
+                        PendingMessage {
+                            message_id: *message_id,
+                            room_id,
+                            user_id: user_id.clone(),
+                            content: content.clone(),
+                            deliver_at: *deliver_at,
+                            scheduled_at: *timestamp,
+                        },
+                    );
+                }
+
+                ChatEvent::MessageSent { message_id, expires_at, attachments, .. } => {
+                    pending.remove(message_id);
+                    for attachment in attachments {
+                        uploads.remove(&attachment.attachment_id);
+                    }
+                    if let Some(expires_at) = expires_at {
+                        expiring.insert(*message_id, ExpiringMessage { room_id, expires_at: *expires_at });
+                    }
+                }
+
+                ChatEvent::MessageExpired { message_id, .. } => {
+                    expiring.remove(message_id);
+                }
+
+                ChatEvent::AttachmentUploaded { attachment, timestamp, .. } => {
+                    uploads.insert(
+                        attachment.attachment_id,
+                        PendingUpload {
+                            room_id,
+                            discard_at: *timestamp + PENDING_ATTACHMENT_LIFETIME,
+                        },
+                    );
+                }
+
+                ChatEvent::AttachmentDiscarded { attachment_id, .. } => {
+                    uploads.remove(attachment_id);
+                }
+
+                ChatEvent::ScheduledMessageCancelled { message_id, .. }
+                | ChatEvent::ScheduledMessageDropped { message_id, .. } => {
+                    pending.remove(message_id);
+                }
+
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_chat_framework;
+
+    #[tokio::test]
+    async fn test_due_messages_are_delivered_and_the_schedule_rebuilt() {
+        let frameworks = create_chat_framework();
+        let room_id = Uuid::new_v4();
+        let message_id = Uuid::new_v4();
+        let create = ChatCommand::CreateRoom {
+            room_id,
+            name: "General".to_string(),
+            created_by: "user1".to_string(),
+        };
+        frameworks.rooms.execute(&room_id.to_string(), create).await.unwrap();
+        let schedule = ChatCommand::ScheduleMessage {
+            message_id,
+            user_id: "user1".to_string(),
+            content: "Good morning".to_string(),
+            deliver_at: chrono::Utc::now() + chrono::Duration::milliseconds(500),
+        };
+        frameworks.rooms.execute(&room_id.to_string(), schedule).await.unwrap();
+
+        assert_eq!(frameworks.scheduler.for_user("user1").await.len(), 1);
+        assert!(frameworks.room_views.get_room(&room_id).await.unwrap().messages.is_empty());
+
+        // A scheduler started on the same store after a restart finds the pending message.
+        let restarted = MessageScheduler::new();
+        restarted.rebuild(&frameworks.room_store).await;
+        assert_eq!(restarted.for_user("user1").await[0].message_id, message_id);
+
+        let mut room = frameworks.room_views.get_room(&room_id).await.unwrap();
+        for _ in 0..50 {
+            if !room.messages.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            room = frameworks.room_views.get_room(&room_id).await.unwrap();
+        }
+        assert_eq!(room.messages[0].id, message_id);
+        assert_eq!(room.messages[0].content, "Good morning");
+        assert!(frameworks.scheduler.for_user("user1").await.is_empty());
+
+        // Delivering twice is harmless.
+        let deliver = ChatCommand::DeliverScheduledMessage { message_id };
+        frameworks.rooms.execute(&room_id.to_string(), deliver).await.unwrap();
+        assert_eq!(frameworks.room_views.get_room(&room_id).await.unwrap().messages.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_messages_are_expired_once_their_ttl_runs_out() {
+        let frameworks = create_chat_framework();
+        let room_id = Uuid::new_v4();
+        let message_id = Uuid::new_v4();
+        let create = ChatCommand::CreateRoom {
+            room_id,
+            name: "General".to_string(),
+            created_by: "user1".to_string(),
+        };
+        frameworks.rooms.execute(&room_id.to_string(), create).await.unwrap();
+        let send = ChatCommand::SendMessage {
+            message_id,
+            user_id: "user1".to_string(),
+            content: "Self-destructing".to_string(),
+            timestamp: chrono::Utc::now(),
+            ttl_seconds: Some(1),
+            attachment_ids: Vec::new(),
+        };
+        frameworks.rooms.execute(&room_id.to_string(), send).await.unwrap();
+
+        let query = crate::search::SearchQuery {
+            text: "self-destructing".to_string(),
+            limit: 10,
+            ..Default::default()
+        };
+        assert_eq!(frameworks.search.search(&query).await.len(), 1);
+
+        let mut room = frameworks.room_views.get_room(&room_id).await.unwrap();
+        for _ in 0..80 {
+            if room.messages[0].expired {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            room = frameworks.room_views.get_room(&room_id).await.unwrap();
+        }
+        assert!(room.messages[0].expired);
+        assert!(room.messages[0].content.is_empty());
+        assert!(frameworks.search.search(&query).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_uploads_left_waiting_are_discarded() {
+        let frameworks = create_chat_framework();
+        let room_id = Uuid::new_v4();
+        let (sent, abandoned) = (Uuid::new_v4(), Uuid::new_v4());
+        let create = ChatCommand::CreateRoom {
+            room_id,
+            name: "General".to_string(),
+            created_by: "user1".to_string(),
+        };
+        frameworks.rooms.execute(&room_id.to_string(), create).await.unwrap();
+        for attachment_id in [sent, abandoned] {
+            let upload = ChatCommand::UploadAttachment {
+                attachment_id,
+                user_id: "user1".to_string(),
+                name: "notes.txt".to_string(),
+                mime_type: "text/plain".to_string(),
+                size: 5,
+                checksum: crate::blobs::checksum(b"hello"),
+            };
+            frameworks.rooms.execute(&room_id.to_string(), upload).await.unwrap();
+        }
+        let send = ChatCommand::SendMessage {
+            message_id: Uuid::new_v4(),
+            user_id: "user1".to_string(),
+            content: String::new(),
+            timestamp: chrono::Utc::now(),
+            ttl_seconds: None,
+            attachment_ids: vec![sent],
+        };
+        frameworks.rooms.execute(&room_id.to_string(), send).await.unwrap();
+
+        let scheduler = &frameworks.scheduler;
+        assert!(scheduler.abandoned(chrono::Utc::now()).await.is_empty());
+        let later = chrono::Utc::now() + PENDING_ATTACHMENT_LIFETIME;
+        assert_eq!(scheduler.abandoned(later).await, vec![(abandoned, room_id)]);
+
+        let discard = ChatCommand::DiscardAttachment { attachment_id: abandoned };
+        frameworks.rooms.execute(&room_id.to_string(), discard).await.unwrap();
+        assert!(scheduler.abandoned(later).await.is_empty());
+    }
+}
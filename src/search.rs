@@ -0,0 +1,340 @@
+use async_trait::async_trait;
+use cqrs_es::{EventEnvelope, Query};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::domain::aggregate::ChatRoom;
+use crate::domain::events::ChatEvent;
+use crate::domain::user::aggregate::User;
+use crate::domain::user::events::UserEvent;
+
+pub const DEFAULT_SEARCH_LIMIT: usize = 20;
+pub const MAX_SEARCH_LIMIT: usize = 100;
+
+/// Snippets longer than this are cut down to a window around the first match.
+const SNIPPET_LENGTH: usize = 160;
+/// How much context to keep in front of the first match when cutting a snippet.
+const SNIPPET_LEAD: usize = 60;
+
+#[derive(Debug, Clone, Default)]
+pub struct SearchQuery {
+    pub text: String,
+    pub room_id: Option<Uuid>,
+    /// Only messages of these rooms, when set; searches are kept to the searcher's rooms.
+    pub room_ids: Option<HashSet<Uuid>>,
+    pub user_id: Option<String>,
+    pub from: Option<chrono::DateTime<chrono::Utc>>,
+    pub to: Option<chrono::DateTime<chrono::Utc>>,
+    pub limit: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchHit {
+    pub message_id: Uuid,
+    pub room_id: Uuid,
+    pub room_name: String,
+    pub user_id: String,
+    pub username: String,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub score: f64,
+    pub snippet: String,
+    /// Byte ranges `[start, end)` of the matched terms within `snippet`.
+    pub highlights: Vec<(usize, usize)>,
+}
+
+#[derive(Debug, Clone)]
+struct IndexedMessage {
+    message_id: Uuid,
+    room_id: Uuid,
+    user_id: String,
+    username: String,
+    content: String,
+    timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Default)]
+struct Index {
+    messages: Vec<IndexedMessage>,
+    /// Term -> (position in `messages` -> occurrences of the term).
+    postings: HashMap<String, HashMap<usize, u32>>,
+    room_names: HashMap<Uuid, String>,
+    usernames: HashMap<String, String>,
+}
+
+/// Inverted index over message contents, kept up to date from room events.
+#[derive(Clone, Default)]
+pub struct MessageSearchIndex {
+    index: Arc<RwLock<Index>>,
+}
+
+impl MessageSearchIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns messages containing every term of the query, best matches first.
+    /// Scores are TF-IDF sums; ties go to the most recent message.
+    pub async fn search(&self, query: &SearchQuery) -> Vec<SearchHit> {
+        let terms: Vec<String> = tokenize(&query.text).into_iter().map(|(term, _, _)| term).collect();
+        if terms.is_empty() {
+            return Vec::new();
+        }
+
+        let index = self.index.read().await;
+        let total = index.messages.len() as f64;
+
+        let mut scores: Option<HashMap<usize, f64>> = None;
+        for term in &terms {
+            let Some(postings) = index.postings.get(term) else {
+                return Vec::new();
+            };
+            let idf = (1.0 + total / postings.len() as f64).ln();
+            let term_scores = postings.iter().map(|(&position, &count)| (position, count as f64 * idf));
+            scores = Some(match scores {
+                None => term_scores.collect(),
+                Some(previous) => term_scores
+                    .filter_map(|(position, score)| previous.get(&position).map(|p| (position, p + score)))
+                    .collect(),
+            });
+        }
+
+        let mut hits: Vec<(usize, f64)> = scores
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(position, _)| query.matches(&index.messages[*position]))
+            .collect();
+        hits.sort_by(|(a, a_score), (b, b_score)| {
+            b_score
+                .total_cmp(a_score)
+                .then_with(|| index.messages[*b].timestamp.cmp(&index.messages[*a].timestamp))
+        });
+
+        hits.into_iter()
+            .take(query.limit.clamp(1, MAX_SEARCH_LIMIT))
+            .map(|(position, score)| {
+                let message = &index.messages[position];
+                let (snippet, highlights) = snippet(&message.content, &terms);
+                SearchHit {
+                    message_id: message.message_id,
+                    room_id: message.room_id,
+                    room_name: index.room_names.get(&message.room_id).cloned().unwrap_or_default(),
+                    user_id: message.user_id.clone(),
+                    username: message.username.clone(),
+                    timestamp: message.timestamp,
+                    score,
+                    snippet,
+                    highlights,
+                }
+            })
+            .collect()
+    }
+}
+
+impl SearchQuery {
+    fn matches(&self, message: &IndexedMessage) -> bool {
+        self.room_id.is_none_or(|room_id| message.room_id == room_id)
+            && self.room_ids.as_ref().is_none_or(|room_ids| room_ids.contains(&message.room_id))
+            && self.user_id.as_ref().is_none_or(|user_id| &message.user_id == user_id)
+            && self.from.is_none_or(|from| message.timestamp >= from)
+            && self.to.is_none_or(|to| message.timestamp <= to)
+    }
+}
+
+#[async_trait]
+impl Query<ChatRoom> for MessageSearchIndex {
+    async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<ChatRoom>]) {
+        let Ok(room_id) = Uuid::parse_str(aggregate_id) else {
+            log::error!("Room aggregate id {} is not a UUID", aggregate_id);
+            return;
+        };
+        let mut index = self.index.write().await;
+
+        for event_envelope in events {
+            match &event_envelope.payload {
+                ChatEvent::RoomCreated { name, .. } | ChatEvent::RoomRenamed { name, .. } => {
+                    index.room_names.insert(room_id, name.clone());
+                }
+                ChatEvent::UserJoined { user_id, username, .. } => {
+                    index.usernames.entry(user_id.clone()).or_insert_with(|| username.clone());
+                }
+                ChatEvent::MessageSent { message_id, user_id, content, timestamp, .. } => {
+                    let position = index.messages.len();
+                    for (term, _, _) in tokenize(content) {
+                        *index.postings.entry(term).or_default().entry(position).or_default() += 1;
+                    }
+                    let username = index.usernames.get(user_id).cloned().unwrap_or_else(|| user_id.clone());
+                    index.messages.push(IndexedMessage {
+                        message_id: *message_id,
+                        room_id,
+                        user_id: user_id.clone(),
+                        username,
+                        content: content.clone(),
+                        timestamp: *timestamp,
+                    });
+                }
+                // Expired messages drop out of the index along with their content.
+                ChatEvent::MessageExpired { message_id, .. } => {
+                    let Some(position) = index.messages.iter().position(|m| m.message_id == *message_id) else {
+                        continue;
+                    };
+                    let content = std::mem::take(&mut index.messages[position].content);
+                    for (term, _, _) in tokenize(&content) {
+                        if let Some(postings) = index.postings.get_mut(&term) {
+                            postings.remove(&position);
+                            if postings.is_empty() {
+                                index.postings.remove(&term);
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Keeps the usernames shown in search hits in sync with user profiles.
+#[async_trait]
+impl Query<User> for MessageSearchIndex {
+    async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<User>]) {
+        for event_envelope in events {
+            let name = match &event_envelope.payload {
+                UserEvent::UserRegistered { display_name, .. } => display_name,
+                UserEvent::DisplayNameChanged { display_name, .. } => display_name,
+                _ => continue,
+            };
+            let mut index = self.index.write().await;
+            index.usernames.insert(aggregate_id.to_string(), name.clone());
+            for message in index.messages.iter_mut().filter(|m| m.user_id == aggregate_id) {
+                message.username = name.clone();
+            }
+        }
+    }
+}
+
+/// Splits text into lowercase alphanumeric terms with their byte ranges.
+fn tokenize(text: &str) -> Vec<(String, usize, usize)> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (offset, c) in text.char_indices().chain(std::iter::once((text.len(), ' '))) {
+        match (start, c.is_alphanumeric()) {
+            (None, true) => start = Some(offset),
+            (Some(begin), false) => {
+                tokens.push((text[begin..offset].to_lowercase(), begin, offset));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    tokens
+}
+
+/// Cuts `content` down to a window around the first matched term and returns it
+/// with the byte ranges of every matched term inside the window.
+fn snippet(content: &str, terms: &[String]) -> (String, Vec<(usize, usize)>) {
+    let matches: Vec<(usize, usize)> = tokenize(content)
+        .into_iter()
+        .filter(|(token, _, _)| terms.contains(token))
+        .map(|(_, start, end)| (start, end))
+        .collect();
+
+    if content.len() <= SNIPPET_LENGTH {
+        return (content.to_string(), matches);
+    }
+
+    let first = matches.first().map(|(start, _)| *start).unwrap_or(0);
+    let start = floor_char_boundary(content, first.saturating_sub(SNIPPET_LEAD));
+    let end = floor_char_boundary(content, (start + SNIPPET_LENGTH).min(content.len()));
+
+    let prefix = if start > 0 { "…" } else { "" };
+    let suffix = if end < content.len() { "…" } else { "" };
+    let shift = prefix.len() as isize - start as isize;
+    let highlights = matches
+        .into_iter()
+        .filter(|(s, e)| *s >= start && *e <= end)
+        .map(|(s, e)| ((s as isize + shift) as usize, (e as isize + shift) as usize))
+        .collect();
+
+    (format!("{}{}{}", prefix, &content[start..end], suffix), highlights)
+}
+
+fn floor_char_boundary(text: &str, mut index: usize) -> usize {
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::commands::ChatCommand;
+    use crate::create_chat_framework;
+
+    async fn send(frameworks: &crate::ChatFrameworks, room_id: Uuid, user_id: &str, content: &str) {
+        let command = ChatCommand::SendMessage {
+            message_id: Uuid::new_v4(),
+            user_id: user_id.to_string(),
+            content: content.to_string(),
+            timestamp: chrono::Utc::now(),
+            ttl_seconds: None,
+            attachment_ids: Vec::new(),
+        };
+        frameworks.rooms.execute(&room_id.to_string(), command).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_search_ranks_and_filters_messages() {
+        let frameworks = create_chat_framework();
+        let room_id = Uuid::new_v4();
+        let command = ChatCommand::CreateRoom {
+            room_id,
+            name: "General".to_string(),
+            created_by: "user1".to_string(),
+        };
+        frameworks.rooms.execute(&room_id.to_string(), command).await.unwrap();
+
+        send(&frameworks, room_id, "user1", "Deploy is done").await;
+        send(&frameworks, room_id, "user1", "deploy, deploy, DEPLOY!").await;
+        send(&frameworks, room_id, "user1", "Lunch anyone?").await;
+
+        let query = SearchQuery {
+            text: "deploy".to_string(),
+            limit: DEFAULT_SEARCH_LIMIT,
+            ..SearchQuery::default()
+        };
+        let hits = frameworks.search.search(&query).await;
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].snippet, "deploy, deploy, DEPLOY!");
+        assert_eq!(hits[0].highlights, vec![(0, 6), (8, 14), (16, 22)]);
+        assert_eq!(hits[1].room_name, "General");
+
+        let query = SearchQuery {
+            text: "deploy done".to_string(),
+            limit: DEFAULT_SEARCH_LIMIT,
+            ..SearchQuery::default()
+        };
+        assert_eq!(frameworks.search.search(&query).await.len(), 1);
+
+        let query = SearchQuery {
+            text: "deploy".to_string(),
+            user_id: Some("user2".to_string()),
+            limit: DEFAULT_SEARCH_LIMIT,
+            ..SearchQuery::default()
+        };
+        assert!(frameworks.search.search(&query).await.is_empty());
+    }
+
+    #[test]
+    fn test_snippet_windows_long_messages() {
+        let content = format!("{} needle {}", "a".repeat(200), "b".repeat(200));
+        let (snippet, highlights) = snippet(&content, &["needle".to_string()]);
+
+        assert!(snippet.starts_with('…') && snippet.ends_with('…'));
+        let (start, end) = highlights[0];
+        assert_eq!(&snippet[start..end], "needle");
+    }
+}
@@ -1,13 +1,18 @@
 use anyhow::Result;
 use async_trait::async_trait;
-use cqrs_es::{EventEnvelope, EventStore, Query};
+use cqrs_es::{Aggregate, AggregateError, EventEnvelope, EventStore, Query};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
 use crate::domain::aggregate::ChatRoom;
-use crate::domain::events::ChatEvent;
+use crate::domain::direct::aggregate::DirectConversation;
+use crate::domain::direct::events::DirectEvent;
+use crate::domain::events::{Attachment, ChatEvent};
+use crate::domain::user::aggregate::User;
+use crate::domain::user::events::UserEvent;
 
 pub struct ChatServices;
 
@@ -32,6 +37,15 @@ impl ChatServices {
 pub struct ChatRoomView {
     pub room_id: Uuid,
     pub name: String,
+    /// The owner, who moderates the room.
+    pub created_by: String,
+    pub topic: String,
+    pub description: String,
+    pub archived: bool,
+    /// Seconds non-moderators must wait between messages; 0 when slow mode is off.
+    pub slow_mode_seconds: u64,
+    /// Seconds messages live before they expire; 0 when they are kept forever.
+    pub message_ttl_seconds: u64,
     pub participants: Vec<UserInfo>,
     pub messages: Vec<MessageView>,
     pub created_at: chrono::DateTime<chrono::Utc>,
@@ -50,8 +64,13 @@ pub struct MessageView {
     pub username: String,
     pub content: String,
     pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Whether the message has expired; its content and attachments are then empty.
+    pub expired: bool,
+    pub attachments: Vec<Attachment>,
 }
 
+#[derive(Clone, Default)]
 pub struct ChatRoomViewRepository {
     views: Arc<RwLock<Vec<ChatRoomView>>>,
 }
@@ -68,10 +87,148 @@ impl ChatRoomViewRepository {
         views.iter().find(|view| &view.room_id == room_id).cloned()
     }
 
+    /// An attachment sent to the room in a message that has not expired.
+    pub async fn get_attachment(&self, room_id: &Uuid, attachment_id: &Uuid) -> Option<Attachment> {
+        let views = self.views.read().await;
+        views
+            .iter()
+            .find(|view| &view.room_id == room_id)?
+            .messages
+            .iter()
+            .flat_map(|message| &message.attachments)
+            .find(|attachment| &attachment.attachment_id == attachment_id)
+            .cloned()
+    }
+
     pub async fn get_all_rooms(&self) -> Vec<ChatRoomView> {
         let views = self.views.read().await;
         views.clone()
     }
+
+    /// The rooms `user_id` takes part in.
+    pub async fn rooms_of(&self, user_id: &str) -> HashSet<Uuid> {
+        let views = self.views.read().await;
+        views
+            .iter()
+            .filter(|view| view.participants.iter().any(|p| p.user_id == user_id))
+            .map(|view| view.room_id)
+            .collect()
+    }
+
+    /// Returns one page of a room's messages in chronological order. With `after` the
+    /// page starts right after that cursor; otherwise it ends right before `before`
+    /// (or at the latest message), so paging backwards from the newest is the default.
+    pub async fn get_messages(&self, room_id: &Uuid, query: &MessageQuery) -> Result<MessagePage, HistoryError> {
+        let views = self.views.read().await;
+        let view = views
+            .iter()
+            .find(|view| &view.room_id == room_id)
+            .ok_or(HistoryError::RoomNotFound(*room_id))?;
+        let messages = &view.messages;
+
+        // Messages are appended in commit order, so a cursor maps to an index range.
+        let start = match &query.after {
+            Some(cursor) => cursor.index_after(messages)?,
+            None => 0,
+        };
+        let end = match &query.before {
+            Some(cursor) => cursor.index_before(messages)?,
+            None => messages.len(),
+        };
+        let end = end.max(start);
+        let limit = query.limit.clamp(1, MAX_PAGE_SIZE);
+
+        let (from, to, has_more) = if query.after.is_some() && query.before.is_none() {
+            let to = (start + limit).min(end);
+            (start, to, to < end)
+        } else {
+            let from = end.saturating_sub(limit).max(start);
+            (from, end, from > start)
+        };
+
+        Ok(MessagePage {
+            messages: messages[from..to].to_vec(),
+            has_more,
+        })
+    }
+}
+
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+pub const MAX_PAGE_SIZE: usize = 200;
+
+/// A position in a room's message history: either a message id or a point in time.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MessageCursor {
+    Message(Uuid),
+    Time(chrono::DateTime<chrono::Utc>),
+}
+
+impl MessageCursor {
+    /// Parses a message id or an RFC 3339 timestamp.
+    pub fn parse(value: &str) -> Option<Self> {
+        if let Ok(id) = Uuid::parse_str(value) {
+            return Some(MessageCursor::Message(id));
+        }
+        chrono::DateTime::parse_from_rfc3339(value)
+            .ok()
+            .map(|time| MessageCursor::Time(time.with_timezone(&chrono::Utc)))
+    }
+
+    /// Index of the first message strictly after the cursor.
+    fn index_after(&self, messages: &[MessageView]) -> Result<usize, HistoryError> {
+        match self {
+            MessageCursor::Message(id) => Self::index_of(messages, id).map(|index| index + 1),
+            MessageCursor::Time(time) => Ok(messages.partition_point(|message| &message.timestamp <= time)),
+        }
+    }
+
+    /// Index one past the last message strictly before the cursor.
+    fn index_before(&self, messages: &[MessageView]) -> Result<usize, HistoryError> {
+        match self {
+            MessageCursor::Message(id) => Self::index_of(messages, id),
+            MessageCursor::Time(time) => Ok(messages.partition_point(|message| &message.timestamp < time)),
+        }
+    }
+
+    fn index_of(messages: &[MessageView], id: &Uuid) -> Result<usize, HistoryError> {
+        messages
+            .iter()
+            .position(|message| &message.id == id)
+            .ok_or(HistoryError::MessageNotFound(*id))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageQuery {
+    pub before: Option<MessageCursor>,
+    pub after: Option<MessageCursor>,
+    pub limit: usize,
+}
+
+impl Default for MessageQuery {
+    fn default() -> Self {
+        Self {
+            before: None,
+            after: None,
+            limit: DEFAULT_PAGE_SIZE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessagePage {
+    pub messages: Vec<MessageView>,
+    /// Whether more messages exist beyond this page in the paging direction.
+    pub has_more: bool,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum HistoryError {
+    #[error("Room with ID {0} not found")]
+    RoomNotFound(Uuid),
+
+    #[error("Message with ID {0} not found")]
+    MessageNotFound(Uuid),
 }
 
 #[async_trait]
@@ -96,6 +253,12 @@ impl ChatRoomViewRepository {
                     let view = ChatRoomView {
                         room_id: *room_id,
                         name: name.clone(),
+                        created_by: created_by.clone(),
+                        topic: String::new(),
+                        description: String::new(),
+                        archived: false,
+                        slow_mode_seconds: 0,
+                        message_ttl_seconds: 0,
                         participants: vec![UserInfo {
                             user_id: created_by.clone(),
                             username: created_by.clone(), // Initially use user_id as username
@@ -121,7 +284,7 @@ impl ChatRoomViewRepository {
                     }
                 }
                 
-                ChatEvent::MessageSent { message_id, user_id, content, timestamp } => {
+                ChatEvent::MessageSent { message_id, user_id, content, timestamp, expires_at, attachments } => {
                     if let Some(view) = views.iter_mut().find(|v| v.room_id.to_string() == aggregate_id) {
                         let username = view.participants
                             .iter()
@@ -135,9 +298,85 @@ impl ChatRoomViewRepository {
                             username,
                             content: content.clone(),
                             timestamp: *timestamp,
+                            expires_at: *expires_at,
+                            expired: false,
+                            attachments: attachments.clone(),
                         });
                     }
                 }
+
+                ChatEvent::MessageExpired { message_id, .. } => {
+                    if let Some(view) = views.iter_mut().find(|v| v.room_id.to_string() == aggregate_id) {
+                        if let Some(message) = view.messages.iter_mut().find(|m| m.id == *message_id) {
+                            message.content.clear();
+                            message.attachments.clear();
+                            message.expired = true;
+                        }
+                    }
+                }
+                
+                ChatEvent::RoomRenamed { user_id: _, name, timestamp: _ } => {
+                    if let Some(view) = views.iter_mut().find(|v| v.room_id.to_string() == aggregate_id) {
+                        view.name = name.clone();
+                    }
+                }
+                
+                ChatEvent::TopicChanged { user_id: _, topic, timestamp: _ } => {
+                    if let Some(view) = views.iter_mut().find(|v| v.room_id.to_string() == aggregate_id) {
+                        view.topic = topic.clone();
+                    }
+                }
+                
+                ChatEvent::DescriptionChanged { user_id: _, description, timestamp: _ } => {
+                    if let Some(view) = views.iter_mut().find(|v| v.room_id.to_string() == aggregate_id) {
+                        view.description = description.clone();
+                    }
+                }
+                
+                ChatEvent::RoomArchived { user_id: _, timestamp: _ } => {
+                    if let Some(view) = views.iter_mut().find(|v| v.room_id.to_string() == aggregate_id) {
+                        view.archived = true;
+                    }
+                }
+                
+                ChatEvent::RoomUnarchived { user_id: _, timestamp: _ } => {
+                    if let Some(view) = views.iter_mut().find(|v| v.room_id.to_string() == aggregate_id) {
+                        view.archived = false;
+                    }
+                }
+                
+                ChatEvent::SlowModeChanged { user_id: _, interval_seconds, timestamp: _ } => {
+                    if let Some(view) = views.iter_mut().find(|v| v.room_id.to_string() == aggregate_id) {
+                        view.slow_mode_seconds = *interval_seconds;
+                    }
+                }
+
+                ChatEvent::MessageTtlChanged { user_id: _, ttl_seconds, timestamp: _ } => {
+                    if let Some(view) = views.iter_mut().find(|v| v.room_id.to_string() == aggregate_id) {
+                        view.message_ttl_seconds = *ttl_seconds;
+                    }
+                }
+                
+                // Read markers are projected by `ReadStateRepository`.
+                ChatEvent::MessagesRead { .. } => {}
+
+                // Incoming webhooks are projected by `IncomingWebhookRegistry`; their bots
+                // join and leave through the `UserJoined` and `UserLeft` that follow.
+                ChatEvent::IncomingWebhookCreated { .. } | ChatEvent::IncomingWebhookRevoked { .. } => {}
+
+                // Scheduled messages are projected by `MessageScheduler` and appear here once sent.
+                ChatEvent::MessageScheduled { .. }
+                | ChatEvent::ScheduledMessageCancelled { .. }
+                | ChatEvent::ScheduledMessageDropped { .. } => {}
+
+                // Attachments appear with the message they are sent with.
+                ChatEvent::AttachmentUploaded { .. } | ChatEvent::AttachmentDiscarded { .. } => {}
+
+                // Polls and their tallies are projected by `PollRepository`.
+                ChatEvent::PollCreated { .. }
+                | ChatEvent::VoteCast { .. }
+                | ChatEvent::VoteRetracted { .. }
+                | ChatEvent::PollClosed { .. } => {}
             }
         }
         
@@ -145,39 +384,701 @@ impl ChatRoomViewRepository {
     }
 }
 
-pub struct PostgresEventStore {
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectConversationView {
+    pub conversation_id: Uuid,
+    pub participants: Vec<UserInfo>,
+    pub messages: Vec<MessageView>,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl DirectConversationView {
+    /// Returns the participant on the other side of the conversation.
+    pub fn other_participant(&self, user_id: &str) -> Option<&UserInfo> {
+        self.participants.iter().find(|p| p.user_id != user_id)
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct DirectConversationViewRepository {
+    views: Arc<RwLock<Vec<DirectConversationView>>>,
 }
 
-impl PostgresEventStore {
+impl DirectConversationViewRepository {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            views: Arc::new(RwLock::new(Vec::new())),
+        }
+    }
+
+    pub async fn get_conversation(&self, conversation_id: &Uuid) -> Option<DirectConversationView> {
+        let views = self.views.read().await;
+        views.iter().find(|view| &view.conversation_id == conversation_id).cloned()
+    }
+
+    pub async fn get_conversations_for_user(&self, user_id: &str) -> Vec<DirectConversationView> {
+        let views = self.views.read().await;
+        views
+            .iter()
+            .filter(|view| view.participants.iter().any(|p| p.user_id == user_id))
+            .cloned()
+            .collect()
     }
 }
 
-use cqrs_es::{Aggregate, AggregateError};
-use std::collections::HashMap;
+#[async_trait]
+impl Query<DirectConversation> for DirectConversationViewRepository {
+    async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<DirectConversation>]) {
+        let result = self.update_view(aggregate_id, events).await;
+        if let Err(e) = result {
+            log::error!("Error updating direct conversation view: {}", e);
+        }
+    }
+}
+
+impl DirectConversationViewRepository {
+    async fn update_view(&self, aggregate_id: &str, events: &[EventEnvelope<DirectConversation>]) -> Result<(), anyhow::Error> {
+        let mut views = self.views.write().await;
+        
+        for event_envelope in events {
+            match &event_envelope.payload {
+                DirectEvent::ConversationStarted { conversation_id, initiator_id, initiator_name, recipient_id, recipient_name, timestamp } => {
+                    views.push(DirectConversationView {
+                        conversation_id: *conversation_id,
+                        participants: vec![
+                            UserInfo {
+                                user_id: initiator_id.clone(),
+                                username: initiator_name.clone(),
+                            },
+                            UserInfo {
+                                user_id: recipient_id.clone(),
+                                username: recipient_name.clone(),
+                            },
+                        ],
+                        messages: Vec::new(),
+                        started_at: *timestamp,
+                    });
+                }
+                
+                DirectEvent::DirectMessageSent { message_id, sender_id, content, timestamp } => {
+                    if let Some(view) = views.iter_mut().find(|v| v.conversation_id.to_string() == aggregate_id) {
+                        let username = view.participants
+                            .iter()
+                            .find(|p| p.user_id == *sender_id)
+                            .map(|p| p.username.clone())
+                            .unwrap_or_else(|| sender_id.clone());
+                        
+                        view.messages.push(MessageView {
+                            id: *message_id,
+                            user_id: sender_id.clone(),
+                            username,
+                            content: content.clone(),
+                            timestamp: *timestamp,
+                            expires_at: None,
+                            expired: false,
+                            attachments: Vec::new(),
+                        });
+                    }
+                }
+            }
+        }
+        
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserView {
+    pub user_id: String,
+    pub username: String,
+    pub display_name: String,
+    pub avatar_url: String,
+    pub status: String,
+    pub deactivated: bool,
+    pub registered_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Clone, Default)]
+pub struct UserViewRepository {
+    views: Arc<RwLock<Vec<UserView>>>,
+}
+
+impl UserViewRepository {
+    pub fn new() -> Self {
+        Self {
+            views: Arc::new(RwLock::new(Vec::new())),
+        }
+    }
+
+    pub async fn get_user(&self, user_id: &str) -> Option<UserView> {
+        let views = self.views.read().await;
+        views.iter().find(|view| view.user_id == user_id).cloned()
+    }
+
+    pub async fn get_user_by_username(&self, username: &str) -> Option<UserView> {
+        let views = self.views.read().await;
+        views.iter().find(|view| view.username == username).cloned()
+    }
+
+    pub async fn get_all_users(&self) -> Vec<UserView> {
+        let views = self.views.read().await;
+        views.clone()
+    }
+}
+
+#[async_trait]
+impl Query<User> for UserViewRepository {
+    async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<User>]) {
+        let mut views = self.views.write().await;
+        
+        for event_envelope in events {
+            match &event_envelope.payload {
+                UserEvent::UserRegistered { user_id, username, display_name, password_hash: _, timestamp } => {
+                    views.push(UserView {
+                        user_id: user_id.clone(),
+                        username: username.clone(),
+                        display_name: display_name.clone(),
+                        avatar_url: String::new(),
+                        status: String::new(),
+                        deactivated: false,
+                        registered_at: *timestamp,
+                    });
+                }
+                
+                UserEvent::DisplayNameChanged { display_name, timestamp: _ } => {
+                    if let Some(view) = views.iter_mut().find(|v| v.user_id == aggregate_id) {
+                        view.display_name = display_name.clone();
+                    }
+                }
+                
+                UserEvent::AvatarUrlChanged { avatar_url, timestamp: _ } => {
+                    if let Some(view) = views.iter_mut().find(|v| v.user_id == aggregate_id) {
+                        view.avatar_url = avatar_url.clone();
+                    }
+                }
+                
+                UserEvent::StatusChanged { status, timestamp: _ } => {
+                    if let Some(view) = views.iter_mut().find(|v| v.user_id == aggregate_id) {
+                        view.status = status.clone();
+                    }
+                }
+                
+                UserEvent::UserDeactivated { timestamp: _ } => {
+                    if let Some(view) = views.iter_mut().find(|v| v.user_id == aggregate_id) {
+                        view.deactivated = true;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Login credentials of registered users. Kept apart from `UserView` so
+/// password hashes are never serialized into API responses.
+#[derive(Debug, Clone)]
+pub struct Credential {
+    pub user_id: String,
+    pub password_hash: String,
+    pub deactivated: bool,
+}
+
+#[derive(Clone, Default)]
+pub struct CredentialRepository {
+    credentials: Arc<RwLock<HashMap<String, Credential>>>,
+}
+
+impl CredentialRepository {
+    pub fn new() -> Self {
+        Self {
+            credentials: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
 
-pub struct ChatAggregateContext {
+    pub async fn get_credential(&self, username: &str) -> Option<Credential> {
+        let credentials = self.credentials.read().await;
+        credentials.get(username).cloned()
+    }
+}
+
+#[async_trait]
+impl Query<User> for CredentialRepository {
+    async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<User>]) {
+        let mut credentials = self.credentials.write().await;
+        
+        for event_envelope in events {
+            match &event_envelope.payload {
+                UserEvent::UserRegistered { user_id, username, display_name: _, password_hash: Some(password_hash), timestamp: _ } => {
+                    credentials.insert(username.clone(), Credential {
+                        user_id: user_id.clone(),
+                        password_hash: password_hash.clone(),
+                        deactivated: false,
+                    });
+                }
+                
+                UserEvent::UserDeactivated { timestamp: _ } => {
+                    if let Some(credential) = credentials.values_mut().find(|c| c.user_id == aggregate_id) {
+                        credential.deactivated = true;
+                    }
+                }
+                
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Which user holds each username. Registration reserves the name here before it
+/// issues `RegisterUser`, so two registrations racing for the same name cannot
+/// both succeed; registered users are projected in as well.
+#[derive(Clone, Default)]
+pub struct UsernameIndex {
+    owners: Arc<RwLock<HashMap<String, String>>>,
+}
+
+impl UsernameIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Claims `username` for `user_id`, unless someone else already holds it.
+    pub async fn reserve(&self, username: &str, user_id: &str) -> bool {
+        let mut owners = self.owners.write().await;
+        owners.entry(username.to_string()).or_insert_with(|| user_id.to_string()) == user_id
+    }
+
+    /// Gives up a reservation whose registration failed.
+    pub async fn release(&self, username: &str, user_id: &str) {
+        let mut owners = self.owners.write().await;
+        if owners.get(username).is_some_and(|owner| owner == user_id) {
+            owners.remove(username);
+        }
+    }
+}
+
+#[async_trait]
+impl Query<User> for UsernameIndex {
+    async fn dispatch(&self, _aggregate_id: &str, events: &[EventEnvelope<User>]) {
+        let mut owners = self.owners.write().await;
+        for event_envelope in events {
+            if let UserEvent::UserRegistered { user_id, username, .. } = &event_envelope.payload {
+                owners.entry(username.clone()).or_insert_with(|| user_id.clone());
+            }
+        }
+    }
+}
+
+/// Keeps the usernames shown in room views in sync with user profiles.
+#[async_trait]
+impl Query<User> for ChatRoomViewRepository {
+    async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<User>]) {
+        for event_envelope in events {
+            if let UserEvent::DisplayNameChanged { display_name, timestamp: _ } = &event_envelope.payload {
+                let mut views = self.views.write().await;
+                for view in views.iter_mut() {
+                    rename_user(&mut view.participants, &mut view.messages, aggregate_id, display_name);
+                }
+            }
+        }
+    }
+}
+
+/// Keeps the usernames shown in direct conversations in sync with user profiles.
+#[async_trait]
+impl Query<User> for DirectConversationViewRepository {
+    async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<User>]) {
+        for event_envelope in events {
+            if let UserEvent::DisplayNameChanged { display_name, timestamp: _ } = &event_envelope.payload {
+                let mut views = self.views.write().await;
+                for view in views.iter_mut() {
+                    rename_user(&mut view.participants, &mut view.messages, aggregate_id, display_name);
+                }
+            }
+        }
+    }
+}
+
+fn rename_user(participants: &mut [UserInfo], messages: &mut [MessageView], user_id: &str, display_name: &str) {
+    for participant in participants.iter_mut().filter(|p| p.user_id == user_id) {
+        participant.username = display_name.to_string();
+    }
+    for message in messages.iter_mut().filter(|m| m.user_id == user_id) {
+        message.username = display_name.to_string();
+    }
+}
+
+/// A user's read position in one of their rooms.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RoomReadState {
+    pub room_id: Uuid,
+    pub last_read_message_id: Option<Uuid>,
+    pub unread_count: usize,
+}
+
+#[derive(Default)]
+struct RoomReadLog {
+    /// Message ids and authors in the order they were sent.
+    messages: Vec<(Uuid, String)>,
+    /// Per participant, how many messages from the start of the room they have read.
+    read_up_to: HashMap<String, usize>,
+}
+
+impl RoomReadLog {
+    fn state(&self, room_id: Uuid, user_id: &str, read: usize) -> RoomReadState {
+        RoomReadState {
+            room_id,
+            last_read_message_id: read.checked_sub(1).map(|index| self.messages[index].0),
+            // A user's own messages never count as unread.
+            unread_count: self.messages[read..].iter().filter(|(_, author)| author != user_id).count(),
+        }
+    }
+}
+
+/// Tracks how far each participant has read in each room. Joining a room or
+/// sending a message counts as having read everything up to that point.
+#[derive(Clone, Default)]
+pub struct ReadStateRepository {
+    rooms: Arc<RwLock<HashMap<Uuid, RoomReadLog>>>,
+}
+
+impl ReadStateRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read state of every room the user currently participates in.
+    pub async fn get_read_states(&self, user_id: &str) -> Vec<RoomReadState> {
+        let rooms = self.rooms.read().await;
+        let mut states: Vec<RoomReadState> = rooms
+            .iter()
+            .filter_map(|(room_id, log)| log.read_up_to.get(user_id).map(|read| log.state(*room_id, user_id, *read)))
+            .collect();
+        states.sort_by_key(|state| state.room_id);
+        states
+    }
+
+    pub async fn get_read_state(&self, room_id: &Uuid, user_id: &str) -> Option<RoomReadState> {
+        let rooms = self.rooms.read().await;
+        let log = rooms.get(room_id)?;
+        log.read_up_to.get(user_id).map(|read| log.state(*room_id, user_id, *read))
+    }
+}
+
+#[async_trait]
+impl Query<ChatRoom> for ReadStateRepository {
+    async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<ChatRoom>]) {
+        let Ok(room_id) = Uuid::parse_str(aggregate_id) else {
+            log::error!("Room aggregate id {} is not a UUID", aggregate_id);
+            return;
+        };
+        let mut rooms = self.rooms.write().await;
+        let log = rooms.entry(room_id).or_default();
+
+        for event_envelope in events {
+            match &event_envelope.payload {
+                ChatEvent::RoomCreated { created_by, .. } => {
+                    log.read_up_to.insert(created_by.clone(), 0);
+                }
+                ChatEvent::UserJoined { user_id, .. } => {
+                    log.read_up_to.insert(user_id.clone(), log.messages.len());
+                }
+                ChatEvent::UserLeft { user_id, .. } => {
+                    log.read_up_to.remove(user_id);
+                }
+                ChatEvent::MessageSent { message_id, user_id, .. } => {
+                    log.messages.push((*message_id, user_id.clone()));
+                    log.read_up_to.insert(user_id.clone(), log.messages.len());
+                }
+                ChatEvent::MessagesRead { user_id, up_to_message_id, .. } => {
+                    if let Some(index) = log.messages.iter().position(|(id, _)| id == up_to_message_id) {
+                        let read = log.read_up_to.entry(user_id.clone()).or_default();
+                        *read = (*read).max(index + 1);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// How many room events a slow subscriber may fall behind before it starts missing them.
+const ROOM_EVENT_BUFFER: usize = 1_024;
+
+/// An item pushed to live subscribers of a room.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RoomFeedItem {
+    /// A committed event together with its sequence number within the room.
+    Event { sequence: usize, event: ChatEvent },
+    /// A user is typing. Typing notices are never persisted.
+    Typing { user_id: String },
+}
+
+/// Fans committed room events out to live subscribers such as WebSocket sessions.
+#[derive(Clone)]
+pub struct RoomEventHub {
+    sender: tokio::sync::broadcast::Sender<(Uuid, RoomFeedItem)>,
+}
+
+impl RoomEventHub {
+    pub fn new() -> Self {
+        let (sender, _) = tokio::sync::broadcast::channel(ROOM_EVENT_BUFFER);
+        Self { sender }
+    }
+
+    /// Subscribes to every room; receivers filter on the room id they are interested in.
+    pub fn subscribe(&self) -> tokio::sync::broadcast::Receiver<(Uuid, RoomFeedItem)> {
+        self.sender.subscribe()
+    }
+
+    pub fn publish(&self, room_id: Uuid, item: RoomFeedItem) {
+        // Sending only fails when nobody is listening, which is fine.
+        let _ = self.sender.send((room_id, item));
+    }
+}
+
+impl Default for RoomEventHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Query<ChatRoom> for RoomEventHub {
+    async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<ChatRoom>]) {
+        let Ok(room_id) = Uuid::parse_str(aggregate_id) else {
+            log::error!("Room aggregate id {} is not a UUID", aggregate_id);
+            return;
+        };
+        for event_envelope in events.iter().filter(|envelope| !envelope.payload.is_private()) {
+            self.publish(
+                room_id,
+                RoomFeedItem::Event {
+                    sequence: event_envelope.sequence,
+                    event: event_envelope.payload.clone(),
+                },
+            );
+        }
+    }
+}
+
+/// How many directory changes are kept for clients resuming with `Last-Event-ID`.
+const DIRECTORY_HISTORY: usize = 1_000;
+
+/// The public summary of a room as shown in the room directory.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RoomSummary {
+    pub room_id: Uuid,
+    pub name: String,
+    pub archived: bool,
+    pub participant_count: usize,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DirectoryChangeKind {
+    RoomCreated,
+    RoomRenamed,
+    RoomArchived,
+    RoomUnarchived,
+    ParticipantsChanged,
+}
+
+impl DirectoryChangeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DirectoryChangeKind::RoomCreated => "room_created",
+            DirectoryChangeKind::RoomRenamed => "room_renamed",
+            DirectoryChangeKind::RoomArchived => "room_archived",
+            DirectoryChangeKind::RoomUnarchived => "room_unarchived",
+            DirectoryChangeKind::ParticipantsChanged => "participants_changed",
+        }
+    }
+}
+
+/// A change to the room directory, numbered by a directory-wide sequence.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectoryChange {
+    pub sequence: u64,
+    pub kind: DirectoryChangeKind,
+    pub room: RoomSummary,
+}
+
+#[derive(Default)]
+struct DirectoryState {
+    rooms: HashMap<Uuid, RoomSummary>,
+    history: std::collections::VecDeque<DirectoryChange>,
+    next_sequence: u64,
+}
+
+/// Projects room events into directory changes and fans them out to live subscribers.
+/// Recent changes are retained so reconnecting clients can catch up.
+#[derive(Clone)]
+pub struct RoomDirectoryFeed {
+    state: Arc<RwLock<DirectoryState>>,
+    sender: tokio::sync::broadcast::Sender<DirectoryChange>,
+}
+
+impl RoomDirectoryFeed {
+    pub fn new() -> Self {
+        let (sender, _) = tokio::sync::broadcast::channel(ROOM_EVENT_BUFFER);
+        Self {
+            state: Arc::new(RwLock::new(DirectoryState::default())),
+            sender,
+        }
+    }
+
+    /// Returns the retained changes after `last_sequence` together with a receiver
+    /// for later ones. Both are taken under the same lock so nothing falls in between.
+    pub async fn subscribe_after(
+        &self,
+        last_sequence: Option<u64>,
+    ) -> (Vec<DirectoryChange>, tokio::sync::broadcast::Receiver<DirectoryChange>) {
+        let state = self.state.read().await;
+        let backlog = match last_sequence {
+            Some(last) => state.history.iter().filter(|c| c.sequence > last).cloned().collect(),
+            None => Vec::new(),
+        };
+        (backlog, self.sender.subscribe())
+    }
+}
+
+impl Default for RoomDirectoryFeed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Query<ChatRoom> for RoomDirectoryFeed {
+    async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<ChatRoom>]) {
+        let Ok(room_id) = Uuid::parse_str(aggregate_id) else {
+            log::error!("Room aggregate id {} is not a UUID", aggregate_id);
+            return;
+        };
+        let mut state = self.state.write().await;
+
+        for event_envelope in events {
+            let kind = match &event_envelope.payload {
+                ChatEvent::RoomCreated { name, .. } => {
+                    state.rooms.insert(
+                        room_id,
+                        RoomSummary {
+                            room_id,
+                            name: name.clone(),
+                            archived: false,
+                            participant_count: 1,
+                        },
+                    );
+                    DirectoryChangeKind::RoomCreated
+                }
+                event => {
+                    let Some(room) = state.rooms.get_mut(&room_id) else {
+                        continue;
+                    };
+                    match event {
+                        ChatEvent::RoomRenamed { name, .. } => {
+                            room.name = name.clone();
+                            DirectoryChangeKind::RoomRenamed
+                        }
+                        ChatEvent::RoomArchived { .. } => {
+                            room.archived = true;
+                            DirectoryChangeKind::RoomArchived
+                        }
+                        ChatEvent::RoomUnarchived { .. } => {
+                            room.archived = false;
+                            DirectoryChangeKind::RoomUnarchived
+                        }
+                        ChatEvent::UserJoined { .. } => {
+                            room.participant_count += 1;
+                            DirectoryChangeKind::ParticipantsChanged
+                        }
+                        ChatEvent::UserLeft { .. } => {
+                            room.participant_count = room.participant_count.saturating_sub(1);
+                            DirectoryChangeKind::ParticipantsChanged
+                        }
+                        _ => continue,
+                    }
+                }
+            };
+
+            state.next_sequence += 1;
+            let change = DirectoryChange {
+                sequence: state.next_sequence,
+                kind,
+                room: state.rooms[&room_id].clone(),
+            };
+            if state.history.len() == DIRECTORY_HISTORY {
+                state.history.pop_front();
+            }
+            state.history.push_back(change.clone());
+            let _ = self.sender.send(change);
+        }
+    }
+}
+
+/// Event store shared by every aggregate in the application. Events are kept
+/// in memory, keyed by aggregate id, until the Postgres backend is wired in.
+pub struct PostgresEventStore<A: Aggregate> {
+    events: Arc<RwLock<HashMap<String, Vec<EventEnvelope<A>>>>>,
+}
+
+impl<A: Aggregate> PostgresEventStore<A> {
+    pub fn new() -> Self {
+        Self {
+            events: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Checks that the store is reachable, for readiness probes.
+    pub async fn ping(&self) -> Result<(), AggregateError<A::Error>> {
+        let _events = self.events.read().await;
+        Ok(())
+    }
+
+    /// Every aggregate with at least one stored event, for rebuilding projections.
+    pub async fn aggregate_ids(&self) -> Vec<String> {
+        self.events.read().await.keys().cloned().collect()
+    }
+}
+
+impl<A: Aggregate> Default for PostgresEventStore<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Clones share the same underlying events, so a clone can be kept for reads
+/// while the original is handed to the CQRS framework.
+impl<A: Aggregate> Clone for PostgresEventStore<A> {
+    fn clone(&self) -> Self {
+        Self {
+            events: self.events.clone(),
+        }
+    }
+}
+
+pub struct StoredAggregateContext<A: Aggregate> {
     aggregate_id: String,
-    events: Vec<EventEnvelope<ChatRoom>>,
-    aggregate: ChatRoom,
+    current_sequence: usize,
+    aggregate: A,
 }
 
-impl cqrs_es::AggregateContext<ChatRoom> for ChatAggregateContext {
-    fn aggregate(&self) -> &ChatRoom {
+impl<A: Aggregate> cqrs_es::AggregateContext<A> for StoredAggregateContext<A> {
+    fn aggregate(&self) -> &A {
         &self.aggregate
     }
 }
 
-impl ChatAggregateContext {
-    fn new(aggregate_id: &str, events: Vec<EventEnvelope<ChatRoom>>) -> Self {
-        let mut aggregate = ChatRoom::default();
-        for event in &events {
-            aggregate.apply(event.payload.clone());
+impl<A: Aggregate> StoredAggregateContext<A> {
+    fn new(aggregate_id: &str, events: Vec<EventEnvelope<A>>) -> Self {
+        let mut aggregate = A::default();
+        let current_sequence = events.len();
+        for event in events {
+            aggregate.apply(event.payload);
         }
         Self {
             aggregate_id: aggregate_id.to_string(),
-            events,
+            current_sequence,
             aggregate,
         }
     }
@@ -187,30 +1088,31 @@ impl ChatAggregateContext {
     }
     
     fn current_sequence(&self) -> usize {
-        self.events.len()
+        self.current_sequence
     }
 }
 
 #[async_trait]
-impl EventStore<ChatRoom> for PostgresEventStore {
-    type AC = ChatAggregateContext;
+impl<A: Aggregate> EventStore<A> for PostgresEventStore<A> {
+    type AC = StoredAggregateContext<A>;
 
-    async fn load_events(&self, _aggregate_id: &str) -> Result<Vec<EventEnvelope<ChatRoom>>, AggregateError<crate::domain::events::ChatError>> {
-        Ok(Vec::new())
+    async fn load_events(&self, aggregate_id: &str) -> Result<Vec<EventEnvelope<A>>, AggregateError<A::Error>> {
+        let events = self.events.read().await;
+        Ok(events.get(aggregate_id).cloned().unwrap_or_default())
     }
 
-    async fn load_aggregate(&self, aggregate_id: &str) -> Result<Self::AC, AggregateError<crate::domain::events::ChatError>> {
+    async fn load_aggregate(&self, aggregate_id: &str) -> Result<Self::AC, AggregateError<A::Error>> {
         let events = self.load_events(aggregate_id).await?;
-        let aggregate_context = ChatAggregateContext::new(aggregate_id, events);
+        let aggregate_context = StoredAggregateContext::new(aggregate_id, events);
         Ok(aggregate_context)
     }
 
     async fn commit(
         &self,
-        events: Vec<crate::domain::events::ChatEvent>,
+        events: Vec<A::Event>,
         aggregate_context: Self::AC,
         metadata: HashMap<String, String>,
-    ) -> Result<Vec<EventEnvelope<ChatRoom>>, AggregateError<crate::domain::events::ChatError>> {
+    ) -> Result<Vec<EventEnvelope<A>>, AggregateError<A::Error>> {
         let aggregate_id = aggregate_context.aggregate_id().to_string();
         let sequence = aggregate_context.current_sequence() + 1;
         
@@ -225,10 +1127,167 @@ impl EventStore<ChatRoom> for PostgresEventStore {
             committed_events.push(envelope);
         }
         
+        let mut stored = self.events.write().await;
+        let stream = stored.entry(aggregate_id.clone()).or_default();
+        if stream.len() != aggregate_context.current_sequence() {
+            return Err(AggregateError::AggregateConflict);
+        }
+        
         for event in &committed_events {
             log::info!("Saving event: {:?} for aggregate: {}", event.payload, aggregate_id);
         }
+        stream.extend(committed_events.iter().cloned());
         
         Ok(committed_events)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::commands::ChatCommand;
+
+    async fn room_with_messages(count: usize) -> (ChatRoomViewRepository, Uuid, Vec<MessageView>) {
+        let repository = ChatRoomViewRepository::new();
+        let room_id = Uuid::new_v4();
+        let start = chrono::Utc::now();
+        let messages: Vec<MessageView> = (0..count)
+            .map(|i| MessageView {
+                id: Uuid::new_v4(),
+                user_id: "user1".to_string(),
+                username: "Alice".to_string(),
+                content: format!("message {}", i),
+                timestamp: start + chrono::Duration::seconds(i as i64),
+                expires_at: None,
+                expired: false,
+                attachments: Vec::new(),
+            })
+            .collect();
+        repository.views.write().await.push(ChatRoomView {
+            room_id,
+            name: "General".to_string(),
+            created_by: "user1".to_string(),
+            topic: String::new(),
+            description: String::new(),
+            archived: false,
+            slow_mode_seconds: 0,
+            message_ttl_seconds: 0,
+            participants: Vec::new(),
+            messages: messages.clone(),
+            created_at: start,
+        });
+        (repository, room_id, messages)
+    }
+
+    fn contents(page: &MessagePage) -> Vec<&str> {
+        page.messages.iter().map(|m| m.content.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_latest_page_by_default() {
+        let (repository, room_id, _) = room_with_messages(5).await;
+        let query = MessageQuery { limit: 2, ..MessageQuery::default() };
+
+        let page = repository.get_messages(&room_id, &query).await.unwrap();
+        assert_eq!(contents(&page), vec!["message 3", "message 4"]);
+        assert!(page.has_more);
+    }
+
+    #[tokio::test]
+    async fn test_page_before_and_after_message() {
+        let (repository, room_id, messages) = room_with_messages(5).await;
+
+        let before = MessageQuery {
+            before: Some(MessageCursor::Message(messages[3].id)),
+            limit: 2,
+            ..MessageQuery::default()
+        };
+        let page = repository.get_messages(&room_id, &before).await.unwrap();
+        assert_eq!(contents(&page), vec!["message 1", "message 2"]);
+        assert!(page.has_more);
+
+        let after = MessageQuery {
+            after: Some(MessageCursor::Message(messages[2].id)),
+            limit: 10,
+            ..MessageQuery::default()
+        };
+        let page = repository.get_messages(&room_id, &after).await.unwrap();
+        assert_eq!(contents(&page), vec!["message 3", "message 4"]);
+        assert!(!page.has_more);
+    }
+
+    #[tokio::test]
+    async fn test_page_after_timestamp_excludes_that_instant() {
+        let (repository, room_id, messages) = room_with_messages(3).await;
+        let query = MessageQuery {
+            after: Some(MessageCursor::Time(messages[0].timestamp)),
+            ..MessageQuery::default()
+        };
+
+        let page = repository.get_messages(&room_id, &query).await.unwrap();
+        assert_eq!(contents(&page), vec!["message 1", "message 2"]);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_cursor_is_reported() {
+        let (repository, room_id, _) = room_with_messages(1).await;
+        let query = MessageQuery {
+            before: Some(MessageCursor::Message(Uuid::new_v4())),
+            ..MessageQuery::default()
+        };
+
+        assert!(matches!(
+            repository.get_messages(&room_id, &query).await,
+            Err(HistoryError::MessageNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_unread_counts_skip_own_messages_and_follow_markers() {
+        let frameworks = crate::create_chat_framework();
+        let room_id = Uuid::new_v4();
+        let execute = |command| {
+            let rooms = frameworks.rooms.clone();
+            async move { rooms.execute(&room_id.to_string(), command).await.unwrap() }
+        };
+        let send = |user_id: &str, message_id| ChatCommand::SendMessage {
+            message_id,
+            user_id: user_id.to_string(),
+            content: "Hello".to_string(),
+            timestamp: chrono::Utc::now(),
+            ttl_seconds: None,
+            attachment_ids: Vec::new(),
+        };
+
+        execute(ChatCommand::CreateRoom {
+            room_id,
+            name: "General".to_string(),
+            created_by: "user1".to_string(),
+        })
+        .await;
+        execute(ChatCommand::JoinRoom {
+            user_id: "user2".to_string(),
+            username: "Bob".to_string(),
+        })
+        .await;
+        let first = Uuid::new_v4();
+        execute(send("user1", first)).await;
+        execute(send("user1", Uuid::new_v4())).await;
+        execute(send("user2", Uuid::new_v4())).await;
+
+        let alice = frameworks.read_states.get_read_state(&room_id, "user1").await.unwrap();
+        assert_eq!(alice.unread_count, 1);
+        let bob = frameworks.read_states.get_read_state(&room_id, "user2").await.unwrap();
+        assert_eq!(bob.unread_count, 0);
+
+        execute(ChatCommand::MarkRead {
+            user_id: "user1".to_string(),
+            up_to_message_id: first,
+        })
+        .await;
+        // The marker never moves back behind the user's own latest message.
+        let alice = frameworks.read_states.get_read_state(&room_id, "user1").await.unwrap();
+        assert_eq!(alice.unread_count, 1);
+        assert_eq!(frameworks.read_states.get_read_states("user1").await.len(), 1);
+    }
+}
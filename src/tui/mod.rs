@@ -1,17 +1,30 @@
-use std::sync::Arc;
+use cqrs_es::AggregateError;
+use cursive::theme::{BaseColor, Color, Effect};
 use cursive::traits::*;
-use cursive::views::{Dialog, EditView, LinearLayout, ListView, Panel, ScrollView, TextView};
+use cursive::utils::markup::StyledString;
+use cursive::views::{
+    Button, Checkbox, Dialog, EditView, LinearLayout, ListView, Panel, ScrollView, SelectView, TextArea, TextView,
+};
 use cursive::Cursive;
 use tokio::runtime::Runtime;
 use uuid::Uuid;
 
+use crate::blobs::checksum;
+use crate::bots::slash::Dispatch;
 use crate::domain::commands::ChatCommand;
-use crate::services::ChatRoomView;
-use crate::ChatRoomFramework;
+use crate::domain::direct::aggregate::DirectConversation;
+use crate::domain::events::{Attachment, ChatError};
+use crate::domain::direct::commands::DirectCommand;
+use crate::domain::user::commands::UserCommand;
+use crate::pipeline::CommandError;
+use crate::polls::PollView;
+use crate::presence::PresenceStatus;
+use crate::search::{SearchQuery, DEFAULT_SEARCH_LIMIT};
+use crate::web::auth::{hash_password, verify_password};
+use crate::ChatFrameworks;
 
 pub struct TuiApp {
-    framework: Arc<ChatRoomFramework>,
-    view_repository: Arc<crate::services::ChatRoomViewRepository>,
+    frameworks: ChatFrameworks,
     runtime: Runtime,
     current_room: Option<Uuid>,
     user_id: String,
@@ -19,18 +32,14 @@ pub struct TuiApp {
 }
 
 impl TuiApp {
-    pub fn new(
-        framework: Arc<ChatRoomFramework>,
-        view_repository: Arc<crate::services::ChatRoomViewRepository>,
-    ) -> Self {
+    pub fn new(frameworks: ChatFrameworks) -> Self {
         let runtime = tokio::runtime::Builder::new_multi_thread()
             .enable_all()
             .build()
             .expect("Failed to create Tokio runtime");
 
         Self {
-            framework,
-            view_repository,
+            frameworks,
             runtime,
             current_room: None,
             user_id: String::new(),
@@ -49,8 +58,7 @@ impl TuiApp {
     }
 
     fn show_login_screen(&self, siv: &mut Cursive) {
-        let framework = self.framework.clone();
-        let view_repository = self.view_repository.clone();
+        let frameworks = self.frameworks.clone();
         let runtime = self.runtime.handle().clone();
         
         siv.add_layer(
@@ -60,27 +68,41 @@ impl TuiApp {
                     LinearLayout::vertical()
                         .child(TextView::new("Enter your username:"))
                         .child(EditView::new().with_name("username"))
+                        .child(TextView::new("Password:"))
+                        .child(EditView::new().secret().with_name("password"))
                         .child(TextView::new(""))
                 )
                 .button("Login", move |s| {
                     let username = s.call_on_name("username", |view: &mut EditView| {
                         view.get_content().to_string()
                     }).unwrap();
+                    let password = s.call_on_name("password", |view: &mut EditView| {
+                        view.get_content().to_string()
+                    }).unwrap();
                     
                     if username.is_empty() {
                         s.add_layer(Dialog::info("Username cannot be empty"));
                         return;
                     }
                     
-                    let user_id = Uuid::new_v4().to_string();
+                    let (user_id, display_name) = match runtime.block_on(sign_in(&frameworks, &username, &password)) {
+                        Ok(user) => user,
+                        Err(message) => {
+                            s.add_layer(Dialog::info(message));
+                            return;
+                        }
+                    };
+                    
+                    runtime.block_on(async {
+                        frameworks.presence.set_status(&user_id, PresenceStatus::Online).await;
+                    });
                     
                     let app = TuiApp {
-                        framework: framework.clone(),
-                        view_repository: view_repository.clone(),
+                        frameworks: frameworks.clone(),
                         runtime: runtime.block_on(async { tokio::runtime::Runtime::new().unwrap() }),
                         current_room: None,
-                        user_id: user_id.clone(),
-                        username: username.clone(),
+                        user_id,
+                        username: display_name,
                     };
                     
                     s.pop_layer();
@@ -90,46 +112,57 @@ impl TuiApp {
     }
 
     fn show_room_list(&self, siv: &mut Cursive) {
-        let framework = self.framework.clone();
-        let view_repository = self.view_repository.clone();
+        let frameworks = self.frameworks.clone();
         let runtime = self.runtime.handle().clone();
         let user_id = self.user_id.clone();
         let username = self.username.clone();
         
-        let rooms = runtime.block_on(async {
-            view_repository.get_all_rooms().await
+        let (rooms, read_states) = runtime.block_on(async {
+            (
+                frameworks.room_views.get_all_rooms().await,
+                frameworks.read_states.get_read_states(&user_id).await,
+            )
         });
         
-        let mut room_list = ListView::new();
+        let mut room_list = LinearLayout::vertical();
         
         for room in &rooms {
             let room_id = room.room_id;
             let room_name = room.name.clone();
             let participants_count = room.participants.len();
             
-            let framework_for_room = framework.clone();
-            let view_repository_for_room = view_repository.clone();
+            let frameworks_for_room = frameworks.clone();
             let runtime_for_room = runtime.clone();
             let user_id_for_room = user_id.clone();
             let username_for_room = username.clone();
             
-            let room_id_clone = room_id;
-            let room_display = format!("{} ({} participants)", room_name, participants_count);
+            let room_display = if room.archived {
+                format!("{} ({} participants, archived)", room_name, participants_count)
+            } else {
+                format!("{} ({} participants)", room_name, participants_count)
+            };
+            
+            let unread_count = read_states
+                .iter()
+                .find(|state| state.room_id == room_id)
+                .map_or(0, |state| state.unread_count);
+            let room_label = if unread_count > 0 {
+                StyledString::styled(format!("{} ({} unread)", room_display, unread_count), Effect::Bold)
+            } else {
+                StyledString::plain(room_display)
+            };
             
             let room_id_inner = room_id;
-            let framework_inner = framework_for_room.clone();
-            let view_repository_inner = view_repository_for_room.clone();
+            let frameworks_inner = frameworks_for_room.clone();
             let runtime_inner = runtime_for_room.clone();
             let user_id_inner = user_id_for_room.clone();
             let username_inner = username_for_room.clone();
             
             let button = cursive::views::Button::new("Join", {
                 let runtime_inner = runtime_inner.clone();
-                let framework_inner = framework_inner.clone();
-                let view_repository_inner = view_repository_inner.clone();
+                let frameworks_inner = frameworks_inner.clone();
                 let user_id_inner = user_id_inner.clone();
                 let username_inner = username_inner.clone();
-                let room_id_inner = room_id_inner;
                 
                 move |s: &mut Cursive| {
                     runtime_inner.block_on(async {
@@ -138,12 +171,11 @@ impl TuiApp {
                             username: username_inner.clone(),
                         };
                         
-                        let _ = framework_inner.execute(&room_id_inner.to_string(), command).await;
+                        let _ = frameworks_inner.rooms.execute(&room_id_inner.to_string(), command).await;
                     });
                     
                     let app = TuiApp {
-                        framework: framework_inner.clone(),
-                        view_repository: view_repository_inner.clone(),
+                        frameworks: frameworks_inner.clone(),
                         runtime: runtime_inner.block_on(async { tokio::runtime::Runtime::new().unwrap() }),
                         current_room: Some(room_id_inner),
                         user_id: user_id_inner.clone(),
@@ -155,11 +187,14 @@ impl TuiApp {
                 }
             });
             
-            room_list.add_child(&room_display, button);
+            room_list.add_child(
+                LinearLayout::horizontal()
+                    .child(TextView::new(room_label).full_width())
+                    .child(button)
+            );
         }
         
-        let framework_for_create = framework.clone();
-        let view_repository_for_create = view_repository.clone();
+        let frameworks_for_create = frameworks.clone();
         let user_id_for_create = user_id.clone();
         let username_for_create = username.clone();
         let runtime_for_create = runtime.clone();
@@ -172,8 +207,7 @@ impl TuiApp {
                         .child(Panel::new(room_list).title("Available Rooms"))
                 )
                 .button("Create Room", move |s| {
-                    let framework_inner = framework_for_create.clone();
-                    let view_repository_inner = view_repository_for_create.clone();
+                    let frameworks_inner = frameworks_for_create.clone();
                     let user_id_inner = user_id_for_create.clone();
                     let username_inner = username_for_create.clone();
                     let runtime_inner = runtime_for_create.clone();
@@ -205,12 +239,11 @@ impl TuiApp {
                                         created_by: user_id_inner.clone(),
                                     };
                                     
-                                    let _ = framework_inner.execute(&room_id.to_string(), command).await;
+                                    let _ = frameworks_inner.rooms.execute(&room_id.to_string(), command).await;
                                 });
                                 
                                 let app = TuiApp {
-                                    framework: framework_inner.clone(),
-                                    view_repository: view_repository_inner.clone(),
+                                    frameworks: frameworks_inner.clone(),
                                     runtime: runtime_inner.block_on(async { tokio::runtime::Runtime::new().unwrap() }),
                                     current_room: Some(room_id),
                                     user_id: user_id_inner.clone(),
@@ -226,6 +259,61 @@ impl TuiApp {
                             })
                     );
                 })
+                .button("Search", {
+                    let frameworks = frameworks.clone();
+                    let runtime = runtime.clone();
+                    let user_id = user_id.clone();
+                    let username = username.clone();
+                    
+                    move |s| {
+                        let app = TuiApp {
+                            frameworks: frameworks.clone(),
+                            runtime: runtime.block_on(async { tokio::runtime::Runtime::new().unwrap() }),
+                            current_room: None,
+                            user_id: user_id.clone(),
+                            username: username.clone(),
+                        };
+                        
+                        app.show_search(s);
+                    }
+                })
+                .button("Direct Messages", {
+                    let frameworks = frameworks.clone();
+                    let runtime = runtime.clone();
+                    let user_id = user_id.clone();
+                    let username = username.clone();
+                    
+                    move |s| {
+                        let app = TuiApp {
+                            frameworks: frameworks.clone(),
+                            runtime: runtime.block_on(async { tokio::runtime::Runtime::new().unwrap() }),
+                            current_room: None,
+                            user_id: user_id.clone(),
+                            username: username.clone(),
+                        };
+                        
+                        s.pop_layer();
+                        app.show_direct_messages(s);
+                    }
+                })
+                .button("Profile", {
+                    let frameworks = frameworks.clone();
+                    let runtime = runtime.clone();
+                    let user_id = user_id.clone();
+                    let username = username.clone();
+                    
+                    move |s| {
+                        let app = TuiApp {
+                            frameworks: frameworks.clone(),
+                            runtime: runtime.block_on(async { tokio::runtime::Runtime::new().unwrap() }),
+                            current_room: None,
+                            user_id: user_id.clone(),
+                            username: username.clone(),
+                        };
+                        
+                        app.show_profile(s);
+                    }
+                })
                 .button("Logout", |s| {
                     s.pop_layer();
                 })
@@ -234,17 +322,27 @@ impl TuiApp {
 
     fn show_chat_room(&self, siv: &mut Cursive) {
         if let Some(room_id) = self.current_room {
-            let framework = self.framework.clone();
-            let view_repository = self.view_repository.clone();
+            let frameworks = self.frameworks.clone();
             let runtime = self.runtime.handle().clone();
             let user_id = self.user_id.clone();
             let username = self.username.clone();
             
             let room = runtime.block_on(async {
-                view_repository.get_room(&room_id).await
+                frameworks.room_views.get_room(&room_id).await
             });
             
             if let Some(room) = room {
+                // Opening a room reads it. This fails harmlessly for users who have not joined.
+                if let Some(last) = room.messages.last() {
+                    let command = ChatCommand::MarkRead {
+                        user_id: user_id.clone(),
+                        up_to_message_id: last.id,
+                    };
+                    let _ = runtime.block_on(async {
+                        frameworks.rooms.execute(&room_id.to_string(), command).await
+                    });
+                }
+                
                 let mut messages = LinearLayout::vertical();
                 
                 for message in &room.messages {
@@ -255,48 +353,134 @@ impl TuiApp {
                     };
                     
                     let timestamp = message.timestamp.format("%H:%M:%S").to_string();
-                    let content = message.content.clone();
+                    let content = if message.expired {
+                        "(message expired)".to_string()
+                    } else {
+                        message.content.clone()
+                    };
                     
                     messages.add_child(TextView::new(format!("[{}] {}: {}", timestamp, sender, content)));
+                    for attachment in &message.attachments {
+                        messages.add_child(TextView::new(format!(
+                            "    📎 {} ({})",
+                            attachment.name,
+                            format_size(attachment.size)
+                        )));
+                    }
                 }
                 
+                let (statuses, typing) = runtime.block_on(async {
+                    frameworks.presence.set_status(&user_id, PresenceStatus::Online).await;
+                    let mut statuses = Vec::new();
+                    for participant in &room.participants {
+                        statuses.push(frameworks.presence.get_status(&participant.user_id).await);
+                    }
+                    (statuses, frameworks.presence.get_typing(&room_id).await)
+                });
+                
                 let mut participants = LinearLayout::vertical();
                 
-                for participant in &room.participants {
-                    participants.add_child(TextView::new(format!("• {}", participant.username)));
+                for (participant, status) in room.participants.iter().zip(statuses) {
+                    let dot = match status {
+                        PresenceStatus::Online => StyledString::styled("● ", Color::Dark(BaseColor::Green)),
+                        PresenceStatus::Away => StyledString::styled("● ", Color::Dark(BaseColor::Yellow)),
+                        PresenceStatus::Offline => StyledString::plain("○ "),
+                    };
+                    let mut label = dot;
+                    label.append_plain(&participant.username);
+                    participants.add_child(TextView::new(label));
                 }
                 
-                let framework_for_input = framework.clone();
-                let view_repository_for_input = view_repository.clone();
+                let typists: Vec<String> = typing
+                    .iter()
+                    .filter(|typist| **typist != user_id)
+                    .map(|typist| {
+                        room.participants
+                            .iter()
+                            .find(|p| &p.user_id == typist)
+                            .map_or_else(|| typist.clone(), |p| p.username.clone())
+                    })
+                    .collect();
+                let typing_notice = match typists.as_slice() {
+                    [] => String::new(),
+                    [one] => format!("{} is typing…", one),
+                    many => format!("{} are typing…", many.join(", ")),
+                };
+                
+                let frameworks_for_input = frameworks.clone();
                 let runtime_for_input = runtime.clone();
                 let user_id_for_input = user_id.clone();
                 let username_for_input = username.clone();
                 let room_id_for_input = room_id;
                 
+                let frameworks_for_typing = frameworks.clone();
+                let runtime_for_typing = runtime.clone();
+                let user_id_for_typing = user_id.clone();
+                
                 let input = EditView::new()
+                    .on_edit(move |_, content, _| {
+                        if !content.is_empty() {
+                            runtime_for_typing.block_on(async {
+                                frameworks_for_typing.presence.set_typing(room_id, &user_id_for_typing).await;
+                            });
+                        }
+                    })
                     .on_submit(move |s, content| {
                         if !content.is_empty() {
                             let message_id = Uuid::new_v4();
                             let timestamp = chrono::Utc::now();
                             
-                            runtime_for_input.block_on(async {
-                                let command = ChatCommand::SendMessage {
+                            let dispatch = runtime_for_input.block_on(
+                                frameworks_for_input
+                                    .slash_commands
+                                    .dispatch(room_id_for_input, &user_id_for_input, content),
+                            );
+                            let command = match dispatch {
+                                Ok(Dispatch::Message(content)) => Some(ChatCommand::SendMessage {
                                     message_id,
                                     user_id: user_id_for_input.clone(),
-                                    content: content.to_string(),
+                                    content,
                                     timestamp,
-                                };
-                                
-                                let _ = framework_for_input.execute(&room_id_for_input.to_string(), command).await;
-                            });
+                                    ttl_seconds: None,
+                                    attachment_ids: Vec::new(),
+                                }),
+                                Ok(Dispatch::Command(command)) => Some(command),
+                                Ok(Dispatch::Handled) => None,
+                                Err(e) => {
+                                    s.add_layer(Dialog::info(e.to_string()));
+                                    return;
+                                }
+                            };
+                            
+                            let result = match command {
+                                Some(command) => runtime_for_input.block_on(async {
+                                    frameworks_for_input.rooms.execute(&room_id_for_input.to_string(), command).await
+                                }),
+                                None => Ok(()),
+                            };
+                            
+                            // Keep the draft so it can be sent again once the limit allows.
+                            let wait = match result {
+                                Err(CommandError::RateLimited(limited)) => Some(limited.retry_after.as_secs()),
+                                Err(CommandError::Aggregate(AggregateError::UserError(ChatError::SlowMode {
+                                    remaining_seconds,
+                                }))) => Some(remaining_seconds),
+                                _ => None,
+                            };
+                            if let Some(seconds) = wait {
+                                s.add_layer(Dialog::info(format!(
+                                    "Slow down! You can send again in {} seconds.",
+                                    seconds
+                                )));
+                                return;
+                            }
                             
                             s.call_on_name("message_input", |view: &mut EditView| {
                                 view.set_content("");
                             });
                             
                             let app = TuiApp {
-                                framework: framework_for_input.clone(),
-                                view_repository: view_repository_for_input.clone(),
+                                frameworks: frameworks_for_input.clone(),
                                 runtime: runtime_for_input.block_on(async { tokio::runtime::Runtime::new().unwrap() }),
                                 current_room: Some(room_id_for_input),
                                 user_id: user_id_for_input.clone(),
@@ -309,23 +493,41 @@ impl TuiApp {
                     })
                     .with_name("message_input");
                 
-                let framework_for_refresh = framework.clone();
-                let view_repository_for_refresh = view_repository.clone();
+                let frameworks_for_refresh = frameworks.clone();
                 let runtime_for_refresh = runtime.clone();
                 let user_id_for_refresh = user_id.clone();
                 let username_for_refresh = username.clone();
                 let room_id_for_refresh = room_id;
                 
-                let framework_for_leave = framework.clone();
-                let view_repository_for_leave = view_repository.clone();
+                let frameworks_for_polls = frameworks.clone();
+                let runtime_for_polls = runtime.clone();
+                let user_id_for_polls = user_id.clone();
+                let username_for_polls = username.clone();
+                let room_id_for_polls = room_id;
+                
+                let frameworks_for_attachments = frameworks.clone();
+                let runtime_for_attachments = runtime.clone();
+                let user_id_for_attachments = user_id.clone();
+                let username_for_attachments = username.clone();
+                
+                let frameworks_for_leave = frameworks.clone();
                 let runtime_for_leave = runtime.clone();
                 let user_id_for_leave = user_id.clone();
                 let username_for_leave = username.clone();
                 let room_id_for_leave = room_id;
                 
+                let mut title = if room.topic.is_empty() {
+                    format!("Chat Room: {}", room.name)
+                } else {
+                    format!("Chat Room: {} | {}", room.name, room.topic)
+                };
+                if room.slow_mode_seconds > 0 {
+                    title.push_str(&format!(" | Slow mode: {}s", room.slow_mode_seconds));
+                }
+                
                 siv.add_layer(
                     Dialog::new()
-                        .title(format!("Chat Room: {}", room.name))
+                        .title(title)
                         .content(
                             LinearLayout::horizontal()
                                 .child(
@@ -338,6 +540,7 @@ impl TuiApp {
                                             .title("Messages")
                                             .full_height()
                                         )
+                                        .child(TextView::new(typing_notice))
                                         .child(Panel::new(input).title("Type your message"))
                                 )
                                 .child(
@@ -348,8 +551,7 @@ impl TuiApp {
                         )
                         .button("Refresh", move |s| {
                             let app = TuiApp {
-                                framework: framework_for_refresh.clone(),
-                                view_repository: view_repository_for_refresh.clone(),
+                                frameworks: frameworks_for_refresh.clone(),
                                 runtime: runtime_for_refresh.block_on(async { tokio::runtime::Runtime::new().unwrap() }),
                                 current_room: Some(room_id_for_refresh),
                                 user_id: user_id_for_refresh.clone(),
@@ -359,18 +561,39 @@ impl TuiApp {
                             s.pop_layer();
                             app.show_chat_room(s);
                         })
+                        .button("Polls", move |s| {
+                            let app = TuiApp {
+                                frameworks: frameworks_for_polls.clone(),
+                                runtime: runtime_for_polls.block_on(async { tokio::runtime::Runtime::new().unwrap() }),
+                                current_room: Some(room_id_for_polls),
+                                user_id: user_id_for_polls.clone(),
+                                username: username_for_polls.clone(),
+                            };
+                            
+                            app.show_polls(s);
+                        })
+                        .button("Attachments", move |s| {
+                            let app = TuiApp {
+                                frameworks: frameworks_for_attachments.clone(),
+                                runtime: runtime_for_attachments.block_on(async { tokio::runtime::Runtime::new().unwrap() }),
+                                current_room: Some(room_id),
+                                user_id: user_id_for_attachments.clone(),
+                                username: username_for_attachments.clone(),
+                            };
+                            
+                            app.show_attachments(s);
+                        })
                         .button("Leave Room", move |s| {
                             runtime_for_leave.block_on(async {
                                 let command = ChatCommand::LeaveRoom {
                                     user_id: user_id_for_leave.clone(),
                                 };
                                 
-                                let _ = framework_for_leave.execute(&room_id_for_leave.to_string(), command).await;
+                                let _ = frameworks_for_leave.rooms.execute(&room_id_for_leave.to_string(), command).await;
                             });
                             
                             let app = TuiApp {
-                                framework: framework_for_leave.clone(),
-                                view_repository: view_repository_for_leave.clone(),
+                                frameworks: frameworks_for_leave.clone(),
                                 runtime: runtime_for_leave.block_on(async { tokio::runtime::Runtime::new().unwrap() }),
                                 current_room: None,
                                 user_id: user_id_for_leave.clone(),
@@ -384,4 +607,837 @@ impl TuiApp {
             }
         }
     }
+    /// Lists the files sent to the current room, newest first, and saves the chosen one to disk.
+    fn show_attachments(&self, siv: &mut Cursive) {
+        let Some(room_id) = self.current_room else {
+            return;
+        };
+        let frameworks = self.frameworks.clone();
+        let runtime = self.runtime.handle().clone();
+        
+        let room = runtime.block_on(async { frameworks.room_views.get_room(&room_id).await });
+        let Some(room) = room else {
+            return;
+        };
+        
+        let mut select = SelectView::new();
+        for message in room.messages.iter().rev() {
+            for attachment in &message.attachments {
+                let label = format!(
+                    "{} ({}) from {} at {}",
+                    attachment.name,
+                    format_size(attachment.size),
+                    message.username,
+                    message.timestamp.format("%Y-%m-%d %H:%M")
+                );
+                select.add_item(label, attachment.clone());
+            }
+        }
+        
+        if select.is_empty() {
+            siv.add_layer(Dialog::info("No attachments have been sent to this room"));
+            return;
+        }
+        
+        select.set_on_submit(move |s, attachment: &Attachment| {
+            let attachment = attachment.clone();
+            let frameworks = frameworks.clone();
+            let runtime = runtime.clone();
+            
+            s.add_layer(
+                Dialog::new()
+                    .title(format!("Save {}", attachment.name))
+                    .content(
+                        LinearLayout::vertical()
+                            .child(TextView::new("Save to:"))
+                            .child(EditView::new().content(attachment.name.clone()).with_name("save_path").fixed_width(50))
+                    )
+                    .button("Save", move |s| {
+                        let path = s
+                            .call_on_name("save_path", |view: &mut EditView| view.get_content().to_string())
+                            .unwrap();
+                        let result = runtime
+                            .block_on(async { frameworks.blobs.get(&attachment.attachment_id).await })
+                            .map_err(|e| format!("Could not read the attachment: {}", e))
+                            .and_then(|bytes| {
+                                if checksum(&bytes) != attachment.checksum {
+                                    return Err("The stored file does not match its checksum".to_string());
+                                }
+                                std::fs::write(&path, bytes).map_err(|e| format!("Could not write {}: {}", path, e))
+                            });
+                        
+                        s.pop_layer();
+                        match result {
+                            Ok(()) => s.add_layer(Dialog::info(format!("Saved to {}", path))),
+                            Err(e) => s.add_layer(Dialog::info(e)),
+                        }
+                    })
+                    .button("Cancel", |s| {
+                        s.pop_layer();
+                    })
+            );
+        });
+        
+        siv.add_layer(
+            Dialog::new()
+                .title("Attachments")
+                .content(ScrollView::new(select).max_height(15).min_width(50))
+                .button("Close", |s| {
+                    s.pop_layer();
+                })
+        );
+    }
+    
+    /// Lists the current room's polls with their tallies, over the chat room.
+    fn show_polls(&self, siv: &mut Cursive) {
+        let Some(room_id) = self.current_room else {
+            return;
+        };
+        let frameworks = self.frameworks.clone();
+        let runtime = self.runtime.handle().clone();
+        let user_id = self.user_id.clone();
+        let username = self.username.clone();
+        
+        let polls = runtime.block_on(async {
+            let mut polls = Vec::new();
+            for poll in frameworks.polls.for_room(&room_id).await {
+                let my_votes = frameworks.polls.ballot(&poll.poll_id, &user_id).await.unwrap_or_default();
+                polls.push((poll, my_votes));
+            }
+            polls
+        });
+        
+        let mut list = LinearLayout::vertical();
+        if polls.is_empty() {
+            list.add_child(TextView::new("No polls yet"));
+        }
+        for (poll, my_votes) in &polls {
+            let poll_id = poll.poll_id;
+            let open = Button::new("Open", {
+                let frameworks = frameworks.clone();
+                let runtime = runtime.clone();
+                let user_id = user_id.clone();
+                let username = username.clone();
+                
+                move |s: &mut Cursive| {
+                    let app = TuiApp {
+                        frameworks: frameworks.clone(),
+                        runtime: runtime.block_on(async { tokio::runtime::Runtime::new().unwrap() }),
+                        current_room: Some(room_id),
+                        user_id: user_id.clone(),
+                        username: username.clone(),
+                    };
+                    
+                    app.show_poll(s, poll_id);
+                }
+            });
+            list.add_child(
+                Panel::new(
+                    LinearLayout::vertical()
+                        .child(TextView::new(poll_tallies(poll, my_votes)))
+                        .child(open)
+                )
+                .title(poll.question.clone())
+            );
+        }
+        
+        let frameworks_for_create = frameworks.clone();
+        let runtime_for_create = runtime.clone();
+        let user_id_for_create = user_id.clone();
+        let username_for_create = username.clone();
+        
+        siv.add_layer(
+            Dialog::new()
+                .title("Polls")
+                .content(ScrollView::new(list).max_height(20).min_width(50))
+                .button("New Poll", move |s| {
+                    let app = TuiApp {
+                        frameworks: frameworks_for_create.clone(),
+                        runtime: runtime_for_create.block_on(async { tokio::runtime::Runtime::new().unwrap() }),
+                        current_room: Some(room_id),
+                        user_id: user_id_for_create.clone(),
+                        username: username_for_create.clone(),
+                    };
+                    
+                    app.show_new_poll(s);
+                })
+                .button("Close", |s| {
+                    s.pop_layer();
+                })
+        );
+    }
+    
+    /// Votes in, retracts from or closes one poll, over the poll list.
+    fn show_poll(&self, siv: &mut Cursive, poll_id: Uuid) {
+        let Some(room_id) = self.current_room else {
+            return;
+        };
+        let frameworks = self.frameworks.clone();
+        let runtime = self.runtime.handle().clone();
+        let user_id = self.user_id.clone();
+        let username = self.username.clone();
+        
+        let (poll, my_votes) = runtime.block_on(async {
+            (
+                frameworks.polls.get_poll(&poll_id).await,
+                frameworks.polls.ballot(&poll_id, &user_id).await.unwrap_or_default(),
+            )
+        });
+        let Some(poll) = poll else {
+            return;
+        };
+        
+        let mut choices = ListView::new();
+        for (index, option) in poll.options.iter().enumerate() {
+            let mut checkbox = Checkbox::new();
+            checkbox.set_checked(my_votes.contains(&index));
+            choices.add_child(&option.text, checkbox.with_name(format!("poll_option_{}", index)));
+        }
+        let option_count = poll.options.len();
+        
+        // Runs a poll command, then shows the updated poll list in place of both dialogs.
+        let execute = move |s: &mut Cursive, command: ChatCommand| {
+            let result = runtime.block_on(async {
+                frameworks.rooms.execute(&room_id.to_string(), command).await
+            });
+            if let Err(e) = result {
+                s.add_layer(Dialog::info(e.to_string()));
+                return;
+            }
+            
+            let app = TuiApp {
+                frameworks: frameworks.clone(),
+                runtime: runtime.block_on(async { tokio::runtime::Runtime::new().unwrap() }),
+                current_room: Some(room_id),
+                user_id: user_id.clone(),
+                username: username.clone(),
+            };
+            
+            s.pop_layer();
+            s.pop_layer();
+            app.show_polls(s);
+        };
+        let execute = std::sync::Arc::new(execute);
+        
+        let vote = execute.clone();
+        let retract = execute.clone();
+        let close = execute;
+        let user_id_for_vote = self.user_id.clone();
+        let user_id_for_retract = self.user_id.clone();
+        let user_id_for_close = self.user_id.clone();
+        
+        let hint = if poll.multiple_choice { "Choose one or more:" } else { "Choose one:" };
+        siv.add_layer(
+            Dialog::new()
+                .title(poll.question.clone())
+                .content(
+                    LinearLayout::vertical()
+                        .child(TextView::new(poll_tallies(&poll, &my_votes)))
+                        .child(TextView::new(hint))
+                        .child(choices)
+                )
+                .button("Vote", move |s| {
+                    let options: Vec<usize> = (0..option_count)
+                        .filter(|index| {
+                            s.call_on_name(&format!("poll_option_{}", index), |view: &mut Checkbox| view.is_checked())
+                                .unwrap_or(false)
+                        })
+                        .collect();
+                    vote(s, ChatCommand::CastVote {
+                        poll_id,
+                        user_id: user_id_for_vote.clone(),
+                        options,
+                    });
+                })
+                .button("Retract", move |s| {
+                    retract(s, ChatCommand::RetractVote {
+                        poll_id,
+                        user_id: user_id_for_retract.clone(),
+                    });
+                })
+                .button("Close Poll", move |s| {
+                    close(s, ChatCommand::ClosePoll {
+                        poll_id,
+                        user_id: user_id_for_close.clone(),
+                    });
+                })
+                .button("Back", |s| {
+                    s.pop_layer();
+                })
+        );
+    }
+    
+    fn show_new_poll(&self, siv: &mut Cursive) {
+        let Some(room_id) = self.current_room else {
+            return;
+        };
+        let frameworks = self.frameworks.clone();
+        let runtime = self.runtime.handle().clone();
+        let user_id = self.user_id.clone();
+        let username = self.username.clone();
+        
+        siv.add_layer(
+            Dialog::new()
+                .title("New Poll")
+                .content(
+                    LinearLayout::vertical()
+                        .child(TextView::new("Question:"))
+                        .child(EditView::new().with_name("poll_question").fixed_width(50))
+                        .child(TextView::new("Options, one per line:"))
+                        .child(TextArea::new().with_name("poll_options").fixed_size((50, 6)))
+                        .child(
+                            ListView::new()
+                                .child("Multiple choice", Checkbox::new().with_name("poll_multiple_choice"))
+                                .child("Anonymous", Checkbox::new().with_name("poll_anonymous"))
+                        )
+                )
+                .button("Create", move |s| {
+                    let question = s
+                        .call_on_name("poll_question", |view: &mut EditView| view.get_content().to_string())
+                        .unwrap();
+                    let options: Vec<String> = s
+                        .call_on_name("poll_options", |view: &mut TextArea| view.get_content().to_string())
+                        .unwrap()
+                        .lines()
+                        .filter(|line| !line.trim().is_empty())
+                        .map(str::to_string)
+                        .collect();
+                    let checked = |s: &mut Cursive, name: &str| {
+                        s.call_on_name(name, |view: &mut Checkbox| view.is_checked()).unwrap()
+                    };
+                    
+                    let command = ChatCommand::CreatePoll {
+                        poll_id: Uuid::new_v4(),
+                        user_id: user_id.clone(),
+                        question,
+                        options,
+                        multiple_choice: checked(s, "poll_multiple_choice"),
+                        anonymous: checked(s, "poll_anonymous"),
+                    };
+                    let result = runtime.block_on(async {
+                        frameworks.rooms.execute(&room_id.to_string(), command).await
+                    });
+                    if let Err(e) = result {
+                        s.add_layer(Dialog::info(e.to_string()));
+                        return;
+                    }
+                    
+                    let app = TuiApp {
+                        frameworks: frameworks.clone(),
+                        runtime: runtime.block_on(async { tokio::runtime::Runtime::new().unwrap() }),
+                        current_room: Some(room_id),
+                        user_id: user_id.clone(),
+                        username: username.clone(),
+                    };
+                    
+                    // Replace the form and the outdated poll list.
+                    s.pop_layer();
+                    s.pop_layer();
+                    app.show_polls(s);
+                })
+                .button("Cancel", |s| {
+                    s.pop_layer();
+                })
+        );
+    }
+    
+    fn show_direct_messages(&self, siv: &mut Cursive) {
+        let frameworks = self.frameworks.clone();
+        let runtime = self.runtime.handle().clone();
+        let user_id = self.user_id.clone();
+        let username = self.username.clone();
+        
+        let (conversations, rooms) = runtime.block_on(async {
+            (
+                frameworks.direct_views.get_conversations_for_user(&user_id).await,
+                frameworks.room_views.get_all_rooms().await,
+            )
+        });
+        
+        let mut conversation_list = ListView::new();
+        
+        for conversation in &conversations {
+            let conversation_id = conversation.conversation_id;
+            let other = conversation
+                .other_participant(&user_id)
+                .map(|p| p.username.clone())
+                .unwrap_or_default();
+            let conversation_display = format!("{} ({} messages)", other, conversation.messages.len());
+            
+            let button = cursive::views::Button::new("Open", {
+                let frameworks = frameworks.clone();
+                let runtime = runtime.clone();
+                let user_id = user_id.clone();
+                let username = username.clone();
+                
+                move |s: &mut Cursive| {
+                    let app = TuiApp {
+                        frameworks: frameworks.clone(),
+                        runtime: runtime.block_on(async { tokio::runtime::Runtime::new().unwrap() }),
+                        current_room: None,
+                        user_id: user_id.clone(),
+                        username: username.clone(),
+                    };
+                    
+                    s.pop_layer();
+                    app.show_direct_conversation(s, conversation_id);
+                }
+            });
+            
+            conversation_list.add_child(&conversation_display, button);
+        }
+        
+        // Anyone we have met in a room can be messaged directly.
+        let mut known_users = SelectView::<(String, String)>::new();
+        let mut seen = std::collections::HashSet::new();
+        for participant in rooms.iter().flat_map(|room| room.participants.iter()) {
+            if participant.user_id != user_id && seen.insert(participant.user_id.clone()) {
+                known_users.add_item(
+                    participant.username.clone(),
+                    (participant.user_id.clone(), participant.username.clone()),
+                );
+            }
+        }
+        
+        let known_users = known_users.on_submit({
+            let frameworks = frameworks.clone();
+            let runtime = runtime.clone();
+            let user_id = user_id.clone();
+            let username = username.clone();
+            
+            move |s: &mut Cursive, (recipient_id, recipient_name): &(String, String)| {
+                let conversation_id = DirectConversation::conversation_id(&user_id, recipient_id);
+                
+                runtime.block_on(async {
+                    let command = DirectCommand::StartConversation {
+                        conversation_id,
+                        initiator_id: user_id.clone(),
+                        initiator_name: username.clone(),
+                        recipient_id: recipient_id.clone(),
+                        recipient_name: recipient_name.clone(),
+                    };
+                    
+                    // Fails harmlessly when the conversation already exists.
+                    let _ = frameworks.direct.execute(&conversation_id.to_string(), command).await;
+                });
+                
+                let app = TuiApp {
+                    frameworks: frameworks.clone(),
+                    runtime: runtime.block_on(async { tokio::runtime::Runtime::new().unwrap() }),
+                    current_room: None,
+                    user_id: user_id.clone(),
+                    username: username.clone(),
+                };
+                
+                s.pop_layer();
+                app.show_direct_conversation(s, conversation_id);
+            }
+        });
+        
+        siv.add_layer(
+            Dialog::new()
+                .title("Direct Messages")
+                .content(
+                    LinearLayout::vertical()
+                        .child(Panel::new(conversation_list).title("Conversations"))
+                        .child(Panel::new(ScrollView::new(known_users)).title("Start a conversation with"))
+                )
+                .button("Back", move |s| {
+                    let app = TuiApp {
+                        frameworks: frameworks.clone(),
+                        runtime: runtime.block_on(async { tokio::runtime::Runtime::new().unwrap() }),
+                        current_room: None,
+                        user_id: user_id.clone(),
+                        username: username.clone(),
+                    };
+                    
+                    s.pop_layer();
+                    app.show_room_list(s);
+                })
+        );
+    }
+
+    fn show_direct_conversation(&self, siv: &mut Cursive, conversation_id: Uuid) {
+        let frameworks = self.frameworks.clone();
+        let runtime = self.runtime.handle().clone();
+        let user_id = self.user_id.clone();
+        let username = self.username.clone();
+        
+        let conversation = runtime.block_on(async {
+            frameworks.direct_views.get_conversation(&conversation_id).await
+        });
+        
+        let Some(conversation) = conversation else {
+            return;
+        };
+        
+        let mut messages = LinearLayout::vertical();
+        
+        for message in &conversation.messages {
+            let sender = if message.user_id == user_id {
+                "You".to_string()
+            } else {
+                message.username.clone()
+            };
+            
+            let timestamp = message.timestamp.format("%H:%M:%S").to_string();
+            messages.add_child(TextView::new(format!("[{}] {}: {}", timestamp, sender, message.content)));
+        }
+        
+        let other = conversation
+            .other_participant(&user_id)
+            .map(|p| p.username.clone())
+            .unwrap_or_default();
+        
+        let input = EditView::new()
+            .on_submit({
+                let frameworks = frameworks.clone();
+                let runtime = runtime.clone();
+                let user_id = user_id.clone();
+                let username = username.clone();
+                
+                move |s, content| {
+                    if content.is_empty() {
+                        return;
+                    }
+                    
+                    runtime.block_on(async {
+                        let command = DirectCommand::SendDirectMessage {
+                            message_id: Uuid::new_v4(),
+                            sender_id: user_id.clone(),
+                            content: content.to_string(),
+                            timestamp: chrono::Utc::now(),
+                        };
+                        
+                        let _ = frameworks.direct.execute(&conversation_id.to_string(), command).await;
+                    });
+                    
+                    let app = TuiApp {
+                        frameworks: frameworks.clone(),
+                        runtime: runtime.block_on(async { tokio::runtime::Runtime::new().unwrap() }),
+                        current_room: None,
+                        user_id: user_id.clone(),
+                        username: username.clone(),
+                    };
+                    
+                    s.pop_layer();
+                    app.show_direct_conversation(s, conversation_id);
+                }
+            })
+            .with_name("direct_message_input");
+        
+        let frameworks_for_refresh = frameworks.clone();
+        let runtime_for_refresh = runtime.clone();
+        let user_id_for_refresh = user_id.clone();
+        let username_for_refresh = username.clone();
+        
+        siv.add_layer(
+            Dialog::new()
+                .title(format!("Direct Messages: {}", other))
+                .content(
+                    LinearLayout::vertical()
+                        .child(
+                            Panel::new(
+                                ScrollView::new(messages)
+                                    .scroll_strategy(cursive::view::ScrollStrategy::StickToBottom)
+                            )
+                            .title("Messages")
+                            .full_height()
+                        )
+                        .child(Panel::new(input).title("Type your message"))
+                )
+                .button("Refresh", move |s| {
+                    let app = TuiApp {
+                        frameworks: frameworks_for_refresh.clone(),
+                        runtime: runtime_for_refresh.block_on(async { tokio::runtime::Runtime::new().unwrap() }),
+                        current_room: None,
+                        user_id: user_id_for_refresh.clone(),
+                        username: username_for_refresh.clone(),
+                    };
+                    
+                    s.pop_layer();
+                    app.show_direct_conversation(s, conversation_id);
+                })
+                .button("Back", move |s| {
+                    let app = TuiApp {
+                        frameworks: frameworks.clone(),
+                        runtime: runtime.block_on(async { tokio::runtime::Runtime::new().unwrap() }),
+                        current_room: None,
+                        user_id: user_id.clone(),
+                        username: username.clone(),
+                    };
+                    
+                    s.pop_layer();
+                    app.show_direct_messages(s);
+                })
+        );
+    }
+    fn show_search(&self, siv: &mut Cursive) {
+        let frameworks = self.frameworks.clone();
+        let runtime = self.runtime.handle().clone();
+        let user_id = self.user_id.clone();
+        let username = self.username.clone();
+        
+        let results = SelectView::<Uuid>::new().on_submit(move |s, room_id: &Uuid| {
+            let app = TuiApp {
+                frameworks: frameworks.clone(),
+                runtime: runtime.block_on(async { tokio::runtime::Runtime::new().unwrap() }),
+                current_room: Some(*room_id),
+                user_id: user_id.clone(),
+                username: username.clone(),
+            };
+            
+            // Close the search dialog and the room list underneath it.
+            s.pop_layer();
+            s.pop_layer();
+            app.show_chat_room(s);
+        });
+        
+        let frameworks = self.frameworks.clone();
+        let runtime = self.runtime.handle().clone();
+        let user_id = self.user_id.clone();
+        
+        siv.add_layer(
+            Dialog::new()
+                .title("Search Messages")
+                .content(
+                    LinearLayout::vertical()
+                        .child(EditView::new().with_name("search_query").fixed_width(50))
+                        .child(ScrollView::new(results.with_name("search_results")).fixed_height(12))
+                )
+                .button("Search", move |s| {
+                    let text = s.call_on_name("search_query", |view: &mut EditView| {
+                        view.get_content().to_string()
+                    }).unwrap();
+                    
+                    let hits = runtime.block_on(async {
+                        let query = SearchQuery {
+                            text,
+                            room_ids: Some(frameworks.room_views.rooms_of(&user_id).await),
+                            limit: DEFAULT_SEARCH_LIMIT,
+                            ..SearchQuery::default()
+                        };
+                        frameworks.search.search(&query).await
+                    });
+                    
+                    s.call_on_name("search_results", |view: &mut SelectView<Uuid>| {
+                        view.clear();
+                        for hit in &hits {
+                            let mut label = StyledString::plain(format!("[{}] {}: ", hit.room_name, hit.username));
+                            let mut last = 0;
+                            for &(start, end) in &hit.highlights {
+                                label.append_plain(&hit.snippet[last..start]);
+                                label.append_styled(&hit.snippet[start..end], Effect::Reverse);
+                                last = end;
+                            }
+                            label.append_plain(&hit.snippet[last..]);
+                            view.add_item(label, hit.room_id);
+                        }
+                    });
+                    
+                    if hits.is_empty() {
+                        s.add_layer(Dialog::info("No messages found"));
+                    }
+                })
+                .button("Close", |s| {
+                    s.pop_layer();
+                })
+        );
+    }
+
+    fn show_profile(&self, siv: &mut Cursive) {
+        let frameworks = self.frameworks.clone();
+        let runtime = self.runtime.handle().clone();
+        let user_id = self.user_id.clone();
+        
+        let user = runtime.block_on(async {
+            frameworks.user_views.get_user(&user_id).await
+        });
+        
+        let Some(user) = user else {
+            return;
+        };
+        
+        siv.add_layer(
+            Dialog::new()
+                .title("Profile")
+                .content(
+                    LinearLayout::vertical()
+                        .child(TextView::new(format!("Username: {}", user.username)))
+                        .child(TextView::new("Display Name:"))
+                        .child(EditView::new().content(user.display_name.clone()).with_name("profile_display_name"))
+                        .child(TextView::new("Status:"))
+                        .child(EditView::new().content(user.status.clone()).with_name("profile_status"))
+                        .child(TextView::new("Avatar URL:"))
+                        .child(EditView::new().content(user.avatar_url.clone()).with_name("profile_avatar_url"))
+                )
+                .button("Save", move |s| {
+                    let read = |s: &mut Cursive, name: &str| {
+                        s.call_on_name(name, |view: &mut EditView| view.get_content().to_string()).unwrap()
+                    };
+                    let display_name = read(s, "profile_display_name");
+                    let status = read(s, "profile_status");
+                    let avatar_url = read(s, "profile_avatar_url");
+                    
+                    if display_name.is_empty() {
+                        s.add_layer(Dialog::info("Display name cannot be empty"));
+                        return;
+                    }
+                    
+                    let mut commands = Vec::new();
+                    if display_name != user.display_name {
+                        commands.push(UserCommand::ChangeDisplayName { display_name: display_name.clone() });
+                    }
+                    if status != user.status {
+                        commands.push(UserCommand::SetStatus { status });
+                    }
+                    if avatar_url != user.avatar_url {
+                        commands.push(UserCommand::SetAvatarUrl { avatar_url });
+                    }
+                    
+                    runtime.block_on(async {
+                        for command in commands {
+                            let _ = frameworks.users.execute(&user_id, command).await;
+                        }
+                    });
+                    
+                    let app = TuiApp {
+                        frameworks: frameworks.clone(),
+                        runtime: runtime.block_on(async { tokio::runtime::Runtime::new().unwrap() }),
+                        current_room: None,
+                        user_id: user_id.clone(),
+                        username: display_name,
+                    };
+                    
+                    s.pop_layer();
+                    s.pop_layer();
+                    app.show_room_list(s);
+                })
+                .button("Cancel", |s| {
+                    s.pop_layer();
+                })
+        );
+    }
+}
+
+/// Logs in as `username`, returning the user's id and display name. Accounts
+/// with a password, including those registered through the Web API, need it;
+/// a new username is registered with the password given, which is required.
+async fn sign_in(frameworks: &ChatFrameworks, username: &str, password: &str) -> Result<(String, String), String> {
+    if let Some(user) = frameworks.user_views.get_user_by_username(username).await {
+        if user.deactivated {
+            return Err("This account has been deactivated".to_string());
+        }
+        if let Some(credential) = frameworks.credentials.get_credential(username).await {
+            if !verify_password(password, &credential.password_hash) {
+                return Err("Invalid username or password".to_string());
+            }
+        }
+        return Ok((user.user_id, user.display_name));
+    }
+
+    if password.is_empty() {
+        return Err("Choose a password to register this username".to_string());
+    }
+    let password_hash = hash_password(password).map_err(|e| format!("Failed to hash password: {}", e))?;
+    let user_id = Uuid::new_v4().to_string();
+    if !frameworks.usernames.reserve(username, &user_id).await {
+        return Err("Invalid username or password".to_string());
+    }
+
+    let command = UserCommand::RegisterUser {
+        user_id: user_id.clone(),
+        username: username.to_string(),
+        display_name: username.to_string(),
+        password_hash: Some(password_hash),
+    };
+    if let Err(e) = frameworks.users.execute(&user_id, command).await {
+        frameworks.usernames.release(username, &user_id).await;
+        return Err(format!("Failed to register: {}", e));
+    }
+
+    Ok((user_id, username.to_string()))
+}
+
+/// A poll's tallies as bars, marking the options the user voted for.
+fn poll_tallies(poll: &PollView, my_votes: &[usize]) -> String {
+    const BAR_WIDTH: usize = 20;
+    let mut lines = Vec::new();
+    let mut flags = Vec::new();
+    if poll.multiple_choice {
+        flags.push("multiple choice");
+    }
+    if poll.anonymous {
+        flags.push("anonymous");
+    }
+    if poll.closed {
+        flags.push("closed");
+    }
+    let voters = if poll.voters == 1 { "1 voter".to_string() } else { format!("{} voters", poll.voters) };
+    lines.push(if flags.is_empty() { voters } else { format!("{} ({})", voters, flags.join(", ")) });
+    
+    for (index, option) in poll.options.iter().enumerate() {
+        let mark = if my_votes.contains(&index) { "✓" } else { " " };
+        let filled = (option.votes * BAR_WIDTH).checked_div(poll.voters).unwrap_or(0);
+        let mut line = format!(
+            "{} {:<20} {}{} {}",
+            mark,
+            option.text,
+            "█".repeat(filled),
+            "░".repeat(BAR_WIDTH - filled),
+            option.votes
+        );
+        if !option.voters.is_empty() {
+            line.push_str(&format!(" ({})", option.voters.join(", ")));
+        }
+        lines.push(line);
+    }
+    lines.join("\n")
+}
+
+/// A byte count in the largest unit that keeps it at least 1.
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 3] = ["KiB", "MiB", "GiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_chat_framework;
+
+    #[tokio::test]
+    async fn test_sign_in_requires_the_password_of_protected_accounts() {
+        let frameworks = create_chat_framework();
+        let register = UserCommand::RegisterUser {
+            user_id: "user1".to_string(),
+            username: "alice".to_string(),
+            display_name: "Alice".to_string(),
+            password_hash: Some(hash_password("correct horse").unwrap()),
+        };
+        frameworks.users.execute("user1", register).await.unwrap();
+
+        assert!(sign_in(&frameworks, "alice", "").await.is_err());
+        assert!(sign_in(&frameworks, "alice", "battery staple").await.is_err());
+        assert_eq!(
+            sign_in(&frameworks, "alice", "correct horse").await.unwrap(),
+            ("user1".to_string(), "Alice".to_string())
+        );
+
+        assert!(sign_in(&frameworks, "bob", "").await.is_err(), "new accounts need a password");
+        let (user_id, _) = sign_in(&frameworks, "bob", "hunter2").await.unwrap();
+        let credential = frameworks.credentials.get_credential("bob").await.unwrap();
+        assert_eq!(credential.user_id, user_id);
+        assert!(sign_in(&frameworks, "bob", "wrong").await.is_err());
+        assert_eq!(sign_in(&frameworks, "bob", "hunter2").await.unwrap().0, user_id);
+    }
 }
@@ -0,0 +1,246 @@
+use std::io;
+use std::sync::Arc;
+
+use actix_web::http::header::{self, ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{web, HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::blobs::{checksum, BlobStore};
+use crate::domain::commands::ChatCommand;
+use crate::domain::events::{Attachment, ChatError};
+use crate::services::ChatRoomViewRepository;
+use crate::web::auth::AuthenticatedUser;
+use crate::web::errors::{ApiError, Problem};
+use crate::web::responses::AttachmentResponse;
+use crate::web::room_for_participant;
+use crate::ChatRoomFramework;
+
+/// MIME types a browser may display in place. Anything else is served as a
+/// download, since an uploaded page or script would otherwise run on our origin.
+const INLINE_MIME_TYPES: &[&str] = &["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+#[derive(Debug, Serialize, Deserialize, IntoParams)]
+pub(crate) struct UploadParams {
+    /// The file name shown to other participants.
+    name: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/rooms/{room_id}/attachments",
+    tag = "rooms",
+    params(("room_id" = Uuid, Path, description = "Room id"), UploadParams),
+    request_body(
+        content = String,
+        content_type = "application/octet-stream",
+        description = "The file's bytes, at most 10 MiB; the Content-Type header becomes its MIME type",
+    ),
+    responses(
+        (status = 201, description = "The stored attachment, to send with a message by its id", body = AttachmentResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller is not in the room", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Room not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Room is archived", body = Problem, content_type = "application/problem+json"),
+        (status = 413, description = "The file is larger than 10 MiB"),
+        (status = 422, description = "Invalid name or MIME type, or too many attachments waiting to be sent", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limit exceeded; see Retry-After", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn upload_attachment(
+    user: AuthenticatedUser,
+    room_id: web::Path<Uuid>,
+    params: web::Query<UploadParams>,
+    request: HttpRequest,
+    body: web::Bytes,
+    blobs: web::Data<Arc<dyn BlobStore>>,
+    framework: web::Data<Arc<ChatRoomFramework>>,
+) -> Result<HttpResponse, ApiError> {
+    let room_id = room_id.into_inner();
+    let attachment_id = Uuid::new_v4();
+    let mime_type = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.split(';').next().unwrap_or_default().trim().to_string())
+        .unwrap_or_else(|| "application/octet-stream".to_string());
+
+    // Validated before the bytes are stored, so a bad name never leaves a blob behind.
+    let command = ChatCommand::UploadAttachment {
+        attachment_id,
+        user_id: user.user_id,
+        name: params.into_inner().name,
+        mime_type,
+        size: body.len() as u64,
+        checksum: checksum(&body),
+    }
+    .validate()
+    .map_err(ChatError::Validation)?;
+    let ChatCommand::UploadAttachment { name, mime_type, size, checksum, .. } = &command else {
+        unreachable!("validation keeps the command's variant");
+    };
+    let attachment = Attachment {
+        attachment_id,
+        name: name.clone(),
+        mime_type: mime_type.clone(),
+        size: *size,
+        checksum: checksum.clone(),
+    };
+
+    blobs
+        .put(&attachment_id, &body)
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to store attachment: {}", e)))?;
+    if let Err(e) = framework.execute(&room_id.to_string(), command).await {
+        if let Err(delete_error) = blobs.delete(&attachment_id).await {
+            log::warn!("Failed to remove rejected attachment {}: {}", attachment_id, delete_error);
+        }
+        return Err(e.into());
+    }
+
+    Ok(HttpResponse::Created().json(AttachmentResponse::from(attachment)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/rooms/{room_id}/attachments/{attachment_id}",
+    tag = "rooms",
+    params(
+        ("room_id" = Uuid, Path, description = "Room id"),
+        ("attachment_id" = Uuid, Path, description = "Attachment id"),
+    ),
+    responses(
+        (status = 200, description = "The attachment's bytes, with its MIME type and file name", body = String, content_type = "application/octet-stream"),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller is not in the room", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No sent, unexpired attachment with this id in the room", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn download_attachment(
+    user: AuthenticatedUser,
+    path: web::Path<(Uuid, Uuid)>,
+    room_views: web::Data<Arc<ChatRoomViewRepository>>,
+    blobs: web::Data<Arc<dyn BlobStore>>,
+) -> Result<HttpResponse, ApiError> {
+    let (room_id, attachment_id) = path.into_inner();
+    room_for_participant(&room_views, room_id, &user.user_id).await?;
+    let not_found = || ApiError::not_found("attachment_not_found", format!("Attachment with ID {} not found", attachment_id));
+    let attachment = room_views.get_attachment(&room_id, &attachment_id).await.ok_or_else(not_found)?;
+
+    let bytes = blobs.get(&attachment_id).await.map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => not_found(),
+        _ => ApiError::internal(format!("Failed to read attachment: {}", e)),
+    })?;
+
+    let disposition = if INLINE_MIME_TYPES.contains(&attachment.mime_type.as_str()) {
+        DispositionType::Inline
+    } else {
+        DispositionType::Attachment
+    };
+    Ok(HttpResponse::Ok()
+        .content_type(attachment.mime_type)
+        .insert_header(ContentDisposition {
+            disposition,
+            parameters: vec![DispositionParam::Filename(attachment.name)],
+        })
+        .insert_header((header::X_CONTENT_TYPE_OPTIONS, "nosniff"))
+        .insert_header((header::ETAG, format!("\"{}\"", attachment.checksum)))
+        .body(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blobs::LocalBlobStore;
+    use crate::create_chat_framework;
+    use crate::web::auth::{authenticate, TokenSigner};
+    use actix_web::http::StatusCode;
+    use actix_web::middleware::from_fn;
+    use actix_web::App;
+
+    #[actix_web::test]
+    async fn test_uploaded_attachment_is_sent_and_served() {
+        use actix_web::test;
+
+        let frameworks = create_chat_framework();
+        let root = std::env::temp_dir().join(format!("attachments-{}", Uuid::new_v4()));
+        let blobs: Arc<dyn BlobStore> = Arc::new(LocalBlobStore::new(&root));
+        let signer = TokenSigner::new("secret");
+        let room_id = Uuid::new_v4();
+        let create = ChatCommand::CreateRoom {
+            room_id,
+            name: "Files".to_string(),
+            created_by: "user1".to_string(),
+        };
+        frameworks.rooms.execute(&room_id.to_string(), create).await.unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(frameworks.rooms.clone()))
+                .app_data(web::Data::new(frameworks.room_views.clone()))
+                .app_data(web::Data::new(blobs.clone()))
+                .app_data(web::Data::new(signer.clone()))
+                .wrap(from_fn(authenticate))
+                .route("/rooms/{room_id}/attachments", web::post().to(upload_attachment))
+                .route("/rooms/{room_id}/attachments/{attachment_id}", web::get().to(download_attachment)),
+        )
+        .await;
+        let upload = |user_id: &str| {
+            test::TestRequest::post()
+                .uri(&format!("/rooms/{}/attachments?name=notes.txt", room_id))
+                .insert_header((header::AUTHORIZATION, format!("Bearer {}", signer.issue(user_id))))
+                .insert_header((header::CONTENT_TYPE, "text/plain; charset=utf-8"))
+                .set_payload("hello")
+                .to_request()
+        };
+
+        let response = test::call_service(&app, upload("stranger")).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let uploaded: serde_json::Value = test::call_and_read_body_json(&app, upload("user1")).await;
+        assert_eq!(uploaded["mime_type"], "text/plain");
+        assert_eq!(uploaded["size"], 5);
+        assert_eq!(uploaded["checksum"], checksum(b"hello"));
+        let attachment_id: Uuid = serde_json::from_value(uploaded["attachment_id"].clone()).unwrap();
+
+        let download = |user_id: &str| {
+            test::TestRequest::get()
+                .uri(&format!("/rooms/{}/attachments/{}", room_id, attachment_id))
+                .insert_header((header::AUTHORIZATION, format!("Bearer {}", signer.issue(user_id))))
+                .to_request()
+        };
+        let response = test::call_service(&app, download("user1")).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let send = ChatCommand::SendMessage {
+            message_id: Uuid::new_v4(),
+            user_id: "user1".to_string(),
+            content: String::new(),
+            timestamp: chrono::Utc::now(),
+            ttl_seconds: None,
+            attachment_ids: vec![attachment_id],
+        };
+        frameworks.rooms.execute(&room_id.to_string(), send).await.unwrap();
+
+        let anonymous = test::TestRequest::get()
+            .uri(&format!("/rooms/{}/attachments/{}", room_id, attachment_id))
+            .to_request();
+        let response = test::call_service(&app, anonymous).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = test::call_service(&app, download("stranger")).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = test::call_service(&app, download("user1")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get(header::CONTENT_TYPE).unwrap(), "text/plain");
+        assert_eq!(response.headers().get(header::X_CONTENT_TYPE_OPTIONS).unwrap(), "nosniff");
+        let disposition = response.headers().get(header::CONTENT_DISPOSITION).unwrap().to_str().unwrap();
+        assert!(disposition.starts_with("attachment"), "only safe types are shown inline: {}", disposition);
+        assert_eq!(test::read_body(response).await, "hello");
+
+        tokio::fs::remove_dir_all(root).await.unwrap();
+    }
+}
@@ -0,0 +1,380 @@
+use std::sync::Arc;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::{web, FromRequest, HttpMessage, HttpRequest, HttpResponse};
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::future::{ready, Ready};
+use thiserror::Error;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::domain::user::commands::UserCommand;
+use crate::services::{CredentialRepository, UserViewRepository, UsernameIndex};
+use crate::web::errors::{ApiError, Problem};
+use crate::UserFramework;
+
+/// How long an issued bearer token stays valid.
+const TOKEN_TTL_HOURS: i64 = 24;
+
+#[derive(Debug, Error)]
+pub enum AuthError {
+    #[error("Missing bearer token")]
+    MissingToken,
+
+    #[error("Malformed token")]
+    MalformedToken,
+
+    #[error("Invalid token signature")]
+    InvalidSignature,
+
+    #[error("Token has expired")]
+    Expired,
+
+    #[error("User is deactivated")]
+    Deactivated,
+}
+
+/// Hashes a password with Argon2 and a random salt, returning the PHC string.
+pub fn hash_password(password: &str) -> Result<String, argon2::password_hash::Error> {
+    let salt = SaltString::generate(&mut OsRng);
+    Ok(Argon2::default().hash_password(password.as_bytes(), &salt)?.to_string())
+}
+
+pub fn verify_password(password: &str, password_hash: &str) -> bool {
+    PasswordHash::new(password_hash)
+        .map(|hash| Argon2::default().verify_password(password.as_bytes(), &hash).is_ok())
+        .unwrap_or(false)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: String,
+    pub exp: i64,
+}
+
+/// Issues and verifies HMAC-SHA256 signed bearer tokens of the form `<claims>.<signature>`,
+/// both parts base64url encoded.
+#[derive(Clone)]
+pub struct TokenSigner {
+    secret: Arc<Vec<u8>>,
+}
+
+impl TokenSigner {
+    pub fn new(secret: impl Into<Vec<u8>>) -> Self {
+        Self {
+            secret: Arc::new(secret.into()),
+        }
+    }
+
+    /// Uses `AUTH_SECRET` when set. Otherwise a random secret is generated, which
+    /// invalidates all tokens on restart.
+    pub fn from_env() -> Self {
+        match std::env::var("AUTH_SECRET") {
+            Ok(secret) if !secret.is_empty() => Self::new(secret),
+            _ => {
+                log::warn!("AUTH_SECRET is not set, using a random secret; tokens will not survive a restart");
+                let mut secret = vec![0u8; 32];
+                rand::thread_rng().fill_bytes(&mut secret);
+                Self::new(secret)
+            }
+        }
+    }
+
+    pub fn issue(&self, user_id: &str) -> String {
+        let claims = Claims {
+            sub: user_id.to_string(),
+            exp: (chrono::Utc::now() + chrono::Duration::hours(TOKEN_TTL_HOURS)).timestamp(),
+        };
+        let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&claims).expect("claims serialize"));
+        let signature = URL_SAFE_NO_PAD.encode(self.mac(payload.as_bytes()).finalize().into_bytes());
+        format!("{}.{}", payload, signature)
+    }
+
+    pub fn verify(&self, token: &str) -> Result<Claims, AuthError> {
+        let (payload, signature) = token.split_once('.').ok_or(AuthError::MalformedToken)?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| AuthError::MalformedToken)?;
+
+        self.mac(payload.as_bytes())
+            .verify_slice(&signature)
+            .map_err(|_| AuthError::InvalidSignature)?;
+
+        let payload = URL_SAFE_NO_PAD.decode(payload).map_err(|_| AuthError::MalformedToken)?;
+        let claims: Claims = serde_json::from_slice(&payload).map_err(|_| AuthError::MalformedToken)?;
+
+        if claims.exp < chrono::Utc::now().timestamp() {
+            return Err(AuthError::Expired);
+        }
+
+        Ok(claims)
+    }
+
+    fn mac(&self, data: &[u8]) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.secret).expect("HMAC accepts keys of any length");
+        mac.update(data);
+        mac
+    }
+}
+
+impl From<AuthError> for ApiError {
+    fn from(error: AuthError) -> Self {
+        let code = match error {
+            AuthError::MissingToken => "missing_token",
+            AuthError::MalformedToken | AuthError::InvalidSignature => "invalid_token",
+            AuthError::Expired => "token_expired",
+            AuthError::Deactivated => "user_deactivated",
+        };
+        ApiError::unauthorized(code, error.to_string())
+    }
+}
+
+impl From<AuthError> for actix_web::Error {
+    fn from(error: AuthError) -> Self {
+        ApiError::from(error).into()
+    }
+}
+
+/// The user on whose behalf a request is made, as established by [`authenticate`].
+#[derive(Debug, Clone)]
+pub struct AuthenticatedUser {
+    pub user_id: String,
+}
+
+impl FromRequest for AuthenticatedUser {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(
+            req.extensions()
+                .get::<AuthenticatedUser>()
+                .cloned()
+                .ok_or_else(|| AuthError::MissingToken.into()),
+        )
+    }
+}
+
+/// Middleware that resolves the `Authorization: Bearer` header (or the
+/// `access_token` query parameter) into an [`AuthenticatedUser`]. Requests
+/// without a token pass through untouched so public routes keep working;
+/// handlers that need a user extract it and get a 401 when it is absent.
+pub async fn authenticate(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let token = req
+        .headers()
+        .get(actix_web::http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::to_string)
+        .or_else(|| query_token(req.query_string()));
+
+    if let Some(token) = token {
+        let signer = req
+            .app_data::<web::Data<TokenSigner>>()
+            .expect("TokenSigner is registered as app data");
+        let claims = signer.verify(&token).map_err(ApiError::from)?;
+
+        if let Some(user_views) = req.app_data::<web::Data<Arc<UserViewRepository>>>() {
+            let deactivated = user_views
+                .get_user(&claims.sub)
+                .await
+                .map(|user| user.deactivated)
+                .unwrap_or(false);
+            if deactivated {
+                return Err(ApiError::from(AuthError::Deactivated).into());
+            }
+        }
+
+        req.extensions_mut().insert(AuthenticatedUser { user_id: claims.sub });
+    }
+
+    next.call(req).await
+}
+
+/// Browsers cannot set headers on WebSocket or EventSource requests, so the
+/// token may also be passed as an `access_token` query parameter.
+fn query_token(query: &str) -> Option<String> {
+    web::Query::<std::collections::HashMap<String, String>>::from_query(query)
+        .ok()
+        .and_then(|params| params.get("access_token").cloned())
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub(crate) struct RegisterRequest {
+    username: String,
+    display_name: String,
+    password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub(crate) struct LoginRequest {
+    username: String,
+    password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub(crate) struct TokenResponse {
+    user_id: String,
+    token: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/register",
+    tag = "auth",
+    request_body = RegisterRequest,
+    responses(
+        (status = 201, description = "Registered; the token is ready to use", body = TokenResponse),
+        (status = 409, description = "Username already taken", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limit exceeded; see Retry-After", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+pub(crate) async fn register(
+    req: web::Json<RegisterRequest>,
+    framework: web::Data<Arc<UserFramework>>,
+    usernames: web::Data<Arc<UsernameIndex>>,
+    signer: web::Data<TokenSigner>,
+) -> Result<HttpResponse, ApiError> {
+    let password_hash = hash_password(&req.password)
+        .map_err(|e| ApiError::internal(format!("Failed to hash password: {}", e)))?;
+
+    let user_id = Uuid::new_v4().to_string();
+
+    // Reserving the name first means a concurrent registration of it gets a conflict
+    // instead of overwriting this account's credentials.
+    if !usernames.reserve(&req.username, &user_id).await {
+        return Err(ApiError::conflict("username_taken", format!("Username {} is already taken", req.username)));
+    }
+
+    let command = UserCommand::RegisterUser {
+        user_id: user_id.clone(),
+        username: req.username.clone(),
+        display_name: req.display_name.clone(),
+        password_hash: Some(password_hash),
+    };
+
+    if let Err(e) = framework.execute(&user_id, command).await {
+        usernames.release(&req.username, &user_id).await;
+        return Err(e.into());
+    }
+
+    let token = signer.issue(&user_id);
+    Ok(HttpResponse::Created().json(TokenResponse { user_id, token }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/login",
+    tag = "auth",
+    request_body = LoginRequest,
+    responses(
+        (status = 200, description = "Logged in", body = TokenResponse),
+        (status = 401, description = "Invalid username or password", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+pub(crate) async fn login(
+    req: web::Json<LoginRequest>,
+    credentials: web::Data<Arc<CredentialRepository>>,
+    signer: web::Data<TokenSigner>,
+) -> Result<HttpResponse, ApiError> {
+    let credential = credentials.get_credential(&req.username).await;
+
+    match credential {
+        Some(credential) if !credential.deactivated && verify_password(&req.password, &credential.password_hash) => {
+            let token = signer.issue(&credential.user_id);
+            Ok(HttpResponse::Ok().json(TokenResponse {
+                user_id: credential.user_id,
+                token,
+            }))
+        }
+        _ => Err(ApiError::unauthorized("invalid_credentials", "Invalid username or password")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_password_hash_round_trip() {
+        let hash = hash_password("correct horse").unwrap();
+        assert!(verify_password("correct horse", &hash));
+        assert!(!verify_password("battery staple", &hash));
+        assert_ne!(hash, hash_password("correct horse").unwrap(), "hashes must be salted");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_registrations_of_a_username_conflict() {
+        use actix_web::ResponseError;
+
+        let frameworks = crate::create_chat_framework();
+        let registrations: Vec<_> = (0..4)
+            .map(|i| {
+                let frameworks = frameworks.clone();
+                tokio::spawn(async move {
+                    let request = RegisterRequest {
+                        username: "alice".to_string(),
+                        display_name: "Alice".to_string(),
+                        password: format!("password {}", i),
+                    };
+                    let result = register(
+                        web::Json(request),
+                        web::Data::new(frameworks.users.clone()),
+                        web::Data::new(frameworks.usernames.clone()),
+                        web::Data::new(TokenSigner::new("secret")),
+                    )
+                    .await;
+                    match result {
+                        Ok(response) => (response.status(), i),
+                        Err(error) => {
+                            assert_eq!(error.code(), "username_taken");
+                            (error.status_code(), i)
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        let mut registered = Vec::new();
+        for registration in registrations {
+            let (status, i) = registration.await.unwrap();
+            match status.as_u16() {
+                201 => registered.push(i),
+                409 => {}
+                other => panic!("unexpected status {}", other),
+            }
+        }
+
+        assert_eq!(registered.len(), 1, "exactly one registration wins");
+        let credential = frameworks.credentials.get_credential("alice").await.unwrap();
+        assert!(verify_password(&format!("password {}", registered[0]), &credential.password_hash));
+        assert_eq!(frameworks.user_views.get_all_users().await.len(), 1);
+    }
+
+    #[test]
+    fn test_token_round_trip() {
+        let signer = TokenSigner::new("secret");
+        let token = signer.issue("user1");
+        assert_eq!(signer.verify(&token).unwrap().sub, "user1");
+    }
+
+    #[test]
+    fn test_token_signed_with_other_secret_is_rejected() {
+        let token = TokenSigner::new("secret").issue("user1");
+        assert!(matches!(
+            TokenSigner::new("other").verify(&token),
+            Err(AuthError::InvalidSignature)
+        ));
+    }
+}
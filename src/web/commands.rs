@@ -0,0 +1,256 @@
+use std::sync::Arc;
+
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::domain::commands::{ChatCommand, DomainCommand};
+use crate::domain::events::ChatError;
+use crate::services::UserViewRepository;
+use crate::web::auth::AuthenticatedUser;
+use crate::web::errors::{ApiError, Problem};
+use crate::ChatRoomFramework;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub(crate) struct BatchRequest {
+    commands: Vec<ChatCommand>,
+}
+
+/// The caller's display name, which they join rooms under.
+async fn profile_name(user_views: &UserViewRepository, user_id: &str) -> String {
+    user_views
+        .get_user(user_id)
+        .await
+        .map(|profile| profile.display_name)
+        .unwrap_or_else(|| user_id.to_string())
+}
+
+/// Replaces the values the server owns rather than the client: a message is
+/// sent now, and a participant joins under their profile name.
+fn with_server_values(command: ChatCommand, username: &str) -> ChatCommand {
+    match command {
+        ChatCommand::SendMessage { message_id, user_id, content, ttl_seconds, attachment_ids, .. } => {
+            ChatCommand::SendMessage {
+                message_id,
+                user_id,
+                content,
+                timestamp: chrono::Utc::now(),
+                ttl_seconds,
+                attachment_ids,
+            }
+        }
+        ChatCommand::JoinRoom { user_id, .. } => ChatCommand::JoinRoom {
+            user_id,
+            username: username.to_string(),
+        },
+        ChatCommand::Batch { commands } => ChatCommand::Batch {
+            commands: commands.into_iter().map(|command| with_server_values(command, username)).collect(),
+        },
+        command => command,
+    }
+}
+
+/// Validates a raw command and checks the caller may issue it against this room:
+/// every command must name the caller as its issuer, and a `CreateRoom` must
+/// create the room in the path. Timestamps and usernames are the server's.
+fn authorize(room_id: Uuid, user_id: &str, username: &str, command: ChatCommand) -> Result<ChatCommand, ApiError> {
+    let command = with_server_values(command, username).validate().map_err(ChatError::Validation)?;
+
+    for part in command.parts() {
+        if part.issued_by() != Some(user_id) {
+            return Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                "permission_denied",
+                format!("{} must be issued by the authenticated user", part.command_type()),
+            ));
+        }
+
+        // Its size and checksum must describe bytes that are actually in the blob store.
+        if let ChatCommand::UploadAttachment { .. } = part {
+            return Err(ApiError::new(
+                StatusCode::FORBIDDEN,
+                "permission_denied",
+                "UploadAttachment is only issued by uploading to /rooms/{room_id}/attachments",
+            ));
+        }
+
+        if let ChatCommand::CreateRoom { room_id: created, .. } = part {
+            if *created != room_id {
+                return Err(ApiError::bad_request(
+                    "room_id_mismatch",
+                    format!("CreateRoom is for room {}, not {}", created, room_id),
+                ));
+            }
+        }
+    }
+
+    Ok(command)
+}
+
+#[utoipa::path(
+    post,
+    path = "/rooms/{room_id}/commands",
+    tag = "commands",
+    params(("room_id" = Uuid, Path, description = "Room id")),
+    request_body = ChatCommand,
+    responses(
+        (status = 200, description = "Command executed", body = String, content_type = "text/plain"),
+        (status = 400, description = "Malformed command, or CreateRoom for another room", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Command is issued by someone else, or the caller lacks permission", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Room not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Command conflicts with the room's state", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Command failed validation", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limit exceeded or slow mode is on; see Retry-After", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn execute_command(
+    user: AuthenticatedUser,
+    room_id: web::Path<Uuid>,
+    command: web::Json<ChatCommand>,
+    framework: web::Data<Arc<ChatRoomFramework>>,
+    user_views: web::Data<Arc<UserViewRepository>>,
+) -> Result<HttpResponse, ApiError> {
+    let room_id = room_id.into_inner();
+    let username = profile_name(&user_views, &user.user_id).await;
+    let command = authorize(room_id, &user.user_id, &username, command.into_inner())?;
+
+    framework.execute(&room_id.to_string(), command).await?;
+
+    Ok(HttpResponse::Ok().body("Command executed successfully"))
+}
+
+#[utoipa::path(
+    post,
+    path = "/rooms/{room_id}/commands/batch",
+    tag = "commands",
+    params(("room_id" = Uuid, Path, description = "Room id")),
+    request_body = BatchRequest,
+    responses(
+        (status = 200, description = "Every command executed and their events committed together", body = String, content_type = "text/plain"),
+        (status = 400, description = "Malformed command, or CreateRoom for another room", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "A command is issued by someone else, or the caller lacks permission", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Room not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "A command conflicts with the room's state; nothing was committed", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "A command failed validation; errors are reported per `commands[i]`", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limit exceeded or slow mode is on; see Retry-After", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn execute_batch(
+    user: AuthenticatedUser,
+    room_id: web::Path<Uuid>,
+    req: web::Json<BatchRequest>,
+    framework: web::Data<Arc<ChatRoomFramework>>,
+    user_views: web::Data<Arc<UserViewRepository>>,
+) -> Result<HttpResponse, ApiError> {
+    let room_id = room_id.into_inner();
+    let username = profile_name(&user_views, &user.user_id).await;
+    let batch = ChatCommand::Batch {
+        commands: req.into_inner().commands,
+    };
+    let command = authorize(room_id, &user.user_id, &username, batch)?;
+
+    framework.execute(&room_id.to_string(), command).await?;
+
+    Ok(HttpResponse::Ok().body("Batch executed successfully"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_chat_framework;
+
+    fn send(user_id: &str, content: &str) -> ChatCommand {
+        ChatCommand::SendMessage {
+            message_id: Uuid::new_v4(),
+            user_id: user_id.to_string(),
+            content: content.to_string(),
+            timestamp: chrono::Utc::now(),
+            ttl_seconds: None,
+            attachment_ids: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_commands_must_be_issued_by_the_caller() {
+        let room_id = Uuid::new_v4();
+        let batch = ChatCommand::Batch {
+            commands: vec![send("user1", "hello"), send("user2", "hello")],
+        };
+        assert_eq!(authorize(room_id, "user1", "Alice", batch).unwrap_err().code(), "permission_denied");
+
+        let create = ChatCommand::CreateRoom {
+            room_id: Uuid::new_v4(),
+            name: "General".to_string(),
+            created_by: "user1".to_string(),
+        };
+        assert_eq!(authorize(room_id, "user1", "Alice", create).unwrap_err().code(), "room_id_mismatch");
+
+        let nested = ChatCommand::Batch {
+            commands: vec![ChatCommand::Batch { commands: vec![] }],
+        };
+        assert_eq!(authorize(room_id, "user1", "Alice", nested).unwrap_err().code(), "validation_failed");
+    }
+
+    #[test]
+    fn test_timestamps_and_usernames_are_set_by_the_server() {
+        let room_id = Uuid::new_v4();
+        let before = chrono::Utc::now();
+        let batch = ChatCommand::Batch {
+            commands: vec![
+                ChatCommand::JoinRoom {
+                    user_id: "user1".to_string(),
+                    username: "Bob".to_string(),
+                },
+                ChatCommand::SendMessage {
+                    message_id: Uuid::new_v4(),
+                    user_id: "user1".to_string(),
+                    content: "from the future".to_string(),
+                    timestamp: chrono::DateTime::<chrono::Utc>::MAX_UTC,
+                    ttl_seconds: Some(60),
+                    attachment_ids: Vec::new(),
+                },
+            ],
+        };
+
+        let ChatCommand::Batch { commands } = authorize(room_id, "user1", "Alice", batch).unwrap() else {
+            panic!("a batch stays a batch");
+        };
+        assert!(matches!(&commands[0], ChatCommand::JoinRoom { username, .. } if username == "Alice"));
+        assert!(matches!(
+            &commands[1],
+            ChatCommand::SendMessage { timestamp, .. } if *timestamp >= before && *timestamp <= chrono::Utc::now()
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_failed_batch_commits_nothing() {
+        let frameworks = create_chat_framework();
+        let room_id = Uuid::new_v4();
+        let create = ChatCommand::CreateRoom {
+            room_id,
+            name: "General".to_string(),
+            created_by: "user1".to_string(),
+        };
+        let leave = ChatCommand::LeaveRoom {
+            user_id: "user1".to_string(),
+        };
+        let batch = ChatCommand::Batch {
+            commands: vec![create.clone(), send("user1", "first"), leave, send("user1", "too late")],
+        };
+        assert!(frameworks.rooms.execute(&room_id.to_string(), batch).await.is_err());
+        assert!(frameworks.room_views.get_room(&room_id).await.is_none());
+
+        let batch = ChatCommand::Batch {
+            commands: vec![create, send("user1", "first"), send("user1", "second")],
+        };
+        frameworks.rooms.execute(&room_id.to_string(), batch).await.unwrap();
+        let room = frameworks.room_views.get_room(&room_id).await.unwrap();
+        assert_eq!(room.messages.len(), 2);
+    }
+}
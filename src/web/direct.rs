@@ -0,0 +1,167 @@
+use std::sync::Arc;
+
+use actix_web::{web, HttpResponse, Responder};
+use cqrs_es::AggregateError;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::domain::direct::aggregate::DirectConversation;
+use crate::domain::direct::commands::DirectCommand;
+use crate::domain::direct::events::DirectError;
+use crate::pipeline::CommandError;
+use crate::services::{DirectConversationViewRepository, UserViewRepository};
+use crate::web::auth::AuthenticatedUser;
+use crate::web::errors::{ApiError, Problem};
+use crate::web::responses::ConversationResponse;
+use crate::DirectConversationFramework;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub(crate) struct StartConversationRequest {
+    recipient_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub(crate) struct SendDirectMessageRequest {
+    content: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/dms",
+    tag = "direct",
+    responses(
+        (status = 200, description = "The caller's conversations", body = [ConversationResponse]),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn get_conversations(
+    user: AuthenticatedUser,
+    view_repository: web::Data<Arc<DirectConversationViewRepository>>,
+) -> impl Responder {
+    let conversations: Vec<ConversationResponse> = view_repository
+        .get_conversations_for_user(&user.user_id)
+        .await
+        .into_iter()
+        .map(Into::into)
+        .collect();
+    HttpResponse::Ok().json(conversations)
+}
+
+#[utoipa::path(
+    get,
+    path = "/dms/{conversation_id}",
+    tag = "direct",
+    params(("conversation_id" = Uuid, Path, description = "Conversation id")),
+    responses(
+        (status = 200, description = "The conversation", body = ConversationResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Conversation not found", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn get_conversation(
+    user: AuthenticatedUser,
+    conversation_id: web::Path<Uuid>,
+    view_repository: web::Data<Arc<DirectConversationViewRepository>>,
+) -> Result<HttpResponse, ApiError> {
+    let conversation_id = conversation_id.into_inner();
+    
+    // Conversations are private to their participants; don't reveal that others exist.
+    match view_repository.get_conversation(&conversation_id).await {
+        Some(conversation) if conversation.participants.iter().any(|p| p.user_id == user.user_id) => {
+            Ok(HttpResponse::Ok().json(ConversationResponse::from(conversation)))
+        }
+        _ => Err(ApiError::not_found(
+            "conversation_not_found",
+            format!("Conversation with ID {} not found", conversation_id),
+        )),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/dms",
+    tag = "direct",
+    request_body = StartConversationRequest,
+    responses(
+        (status = 201, description = "Id of the new conversation", body = Uuid),
+        (status = 200, description = "Id of the existing conversation", body = Uuid),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Recipient not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Conversation cannot be started, as with yourself", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limit exceeded; see Retry-After", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn start_conversation(
+    user: AuthenticatedUser,
+    req: web::Json<StartConversationRequest>,
+    framework: web::Data<Arc<DirectConversationFramework>>,
+    user_views: web::Data<Arc<UserViewRepository>>,
+) -> Result<HttpResponse, ApiError> {
+    let Some(recipient) = user_views.get_user(&req.recipient_id).await else {
+        return Err(ApiError::not_found("user_not_found", format!("User with ID {} not found", req.recipient_id)));
+    };
+    
+    let initiator_name = user_views
+        .get_user(&user.user_id)
+        .await
+        .map(|profile| profile.display_name)
+        .unwrap_or_else(|| user.user_id.clone());
+    
+    let conversation_id = DirectConversation::conversation_id(&user.user_id, &recipient.user_id);
+    
+    let command = DirectCommand::StartConversation {
+        conversation_id,
+        initiator_id: user.user_id,
+        initiator_name,
+        recipient_id: recipient.user_id,
+        recipient_name: recipient.display_name,
+    };
+    
+    match framework.execute(&conversation_id.to_string(), command).await {
+        Ok(_) => Ok(HttpResponse::Created().json(conversation_id)),
+        // The id is derived from the participants, so an existing conversation is simply reused.
+        Err(CommandError::Aggregate(AggregateError::UserError(DirectError::ConversationAlreadyExists(_)))) => Ok(HttpResponse::Ok().json(conversation_id)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/dms/{conversation_id}/messages",
+    tag = "direct",
+    params(("conversation_id" = Uuid, Path, description = "Conversation id")),
+    request_body = SendDirectMessageRequest,
+    responses(
+        (status = 201, description = "Id of the new message", body = Uuid),
+        (status = 403, description = "Caller is not in the conversation", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid message", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Conversation not found", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limit exceeded; see Retry-After", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn send_direct_message(
+    user: AuthenticatedUser,
+    conversation_id: web::Path<Uuid>,
+    req: web::Json<SendDirectMessageRequest>,
+    framework: web::Data<Arc<DirectConversationFramework>>,
+) -> Result<HttpResponse, ApiError> {
+    let conversation_id = conversation_id.into_inner();
+    let message_id = Uuid::new_v4();
+    
+    let command = DirectCommand::SendDirectMessage {
+        message_id,
+        sender_id: user.user_id,
+        content: req.content.clone(),
+        timestamp: chrono::Utc::now(),
+    };
+    
+    framework.execute(&conversation_id.to_string(), command).await?;
+    
+    Ok(HttpResponse::Created().json(message_id))
+}
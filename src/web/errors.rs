@@ -0,0 +1,323 @@
+use actix_web::http::StatusCode;
+use actix_web::{HttpRequest, HttpResponse, ResponseError};
+use cqrs_es::AggregateError;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+use utoipa::ToSchema;
+
+use crate::bots::slash::SlashError;
+use crate::domain::direct::events::DirectError;
+use crate::domain::events::ChatError;
+use crate::domain::user::events::UserError;
+use crate::domain::validation::FieldError;
+use crate::domain::webhook::events::WebhookError;
+use crate::pipeline::CommandError;
+use crate::rate_limit::RateLimited;
+use crate::services::HistoryError;
+
+/// An RFC 7807 problem document. `code` is a stable, machine-readable
+/// identifier clients can match on; `detail` is for humans and may change.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    pub code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub errors: Option<Vec<FieldError>>,
+}
+
+/// Error returned by every Web API handler, rendered as `application/problem+json`.
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    code: &'static str,
+    detail: String,
+    errors: Option<Vec<FieldError>>,
+    /// Sent as `Retry-After` on 429 responses.
+    retry_after: Option<Duration>,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            detail: detail.into(),
+            errors: None,
+            retry_after: None,
+        }
+    }
+
+    pub fn bad_request(code: &'static str, detail: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, code, detail)
+    }
+
+    pub fn unauthorized(code: &'static str, detail: impl Into<String>) -> Self {
+        Self::new(StatusCode::UNAUTHORIZED, code, detail)
+    }
+
+    pub fn not_found(code: &'static str, detail: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, code, detail)
+    }
+
+    pub fn conflict(code: &'static str, detail: impl Into<String>) -> Self {
+        Self::new(StatusCode::CONFLICT, code, detail)
+    }
+
+    pub fn too_many_requests(detail: impl Into<String>, retry_after: Duration) -> Self {
+        Self {
+            retry_after: Some(retry_after),
+            ..Self::new(StatusCode::TOO_MANY_REQUESTS, "rate_limited", detail)
+        }
+    }
+
+    pub fn internal(detail: impl Into<String>) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, "internal_error", detail)
+    }
+
+    pub fn code(&self) -> &'static str {
+        self.code
+    }
+
+    pub fn problem(&self) -> Problem {
+        Problem {
+            problem_type: format!("urn:chat-app:problem:{}", self.code),
+            title: self.status.canonical_reason().unwrap_or("Error").to_string(),
+            status: self.status.as_u16(),
+            detail: self.detail.clone(),
+            code: self.code.to_string(),
+            errors: self.errors.clone(),
+        }
+    }
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.code, self.detail)
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status);
+        if let Some(retry_after) = self.retry_after {
+            response.insert_header((actix_web::http::header::RETRY_AFTER, retry_after.as_secs().to_string()));
+        }
+        response.content_type("application/problem+json").json(self.problem())
+    }
+}
+
+impl From<ChatError> for ApiError {
+    fn from(error: ChatError) -> Self {
+        let detail = error.to_string();
+        match error {
+            ChatError::RoomNotFound(_) => Self::not_found("room_not_found", detail),
+            ChatError::RoomAlreadyExists(_) => Self::conflict("room_already_exists", detail),
+            ChatError::UserAlreadyInRoom(_) => Self::conflict("user_already_in_room", detail),
+            ChatError::UserNotInRoom(_) => Self::new(StatusCode::FORBIDDEN, "user_not_in_room", detail),
+            ChatError::PermissionDenied(_) => Self::new(StatusCode::FORBIDDEN, "permission_denied", detail),
+            ChatError::RoomArchived(_) => Self::conflict("room_archived", detail),
+            ChatError::InvalidOperation(_) => Self::conflict("invalid_operation", detail),
+            ChatError::SlowMode { remaining_seconds } => Self {
+                retry_after: Some(Duration::from_secs(remaining_seconds)),
+                ..Self::new(StatusCode::TOO_MANY_REQUESTS, "slow_mode", detail)
+            },
+            ChatError::Validation(errors) => Self {
+                errors: Some(errors.errors),
+                ..Self::new(StatusCode::UNPROCESSABLE_ENTITY, "validation_failed", detail)
+            },
+            ChatError::Unknown(_) => Self::internal(detail),
+        }
+    }
+}
+
+impl From<DirectError> for ApiError {
+    fn from(error: DirectError) -> Self {
+        let detail = error.to_string();
+        match error {
+            DirectError::ConversationNotFound(_) => Self::not_found("conversation_not_found", detail),
+            DirectError::ConversationAlreadyExists(_) => Self::conflict("conversation_already_exists", detail),
+            DirectError::UserNotInConversation(_) => Self::new(StatusCode::FORBIDDEN, "user_not_in_conversation", detail),
+            DirectError::InvalidOperation(_) => Self::conflict("invalid_operation", detail),
+        }
+    }
+}
+
+impl From<UserError> for ApiError {
+    fn from(error: UserError) -> Self {
+        let detail = error.to_string();
+        match error {
+            UserError::UserNotFound(_) => Self::not_found("user_not_found", detail),
+            UserError::UserAlreadyExists(_) => Self::conflict("user_already_exists", detail),
+            UserError::UserDeactivated(_) => Self::new(StatusCode::FORBIDDEN, "user_deactivated", detail),
+            UserError::InvalidOperation(_) => Self::conflict("invalid_operation", detail),
+        }
+    }
+}
+
+impl From<WebhookError> for ApiError {
+    fn from(error: WebhookError) -> Self {
+        let detail = error.to_string();
+        match error {
+            WebhookError::WebhookNotFound(_) => Self::not_found("webhook_not_found", detail),
+            WebhookError::WebhookAlreadyExists(_) => Self::conflict("webhook_already_exists", detail),
+            WebhookError::PermissionDenied(_) => Self::new(StatusCode::FORBIDDEN, "permission_denied", detail),
+            WebhookError::Validation(errors) => Self {
+                errors: Some(errors.errors),
+                ..Self::new(StatusCode::UNPROCESSABLE_ENTITY, "validation_failed", detail)
+            },
+        }
+    }
+}
+
+impl From<SlashError> for ApiError {
+    fn from(error: SlashError) -> Self {
+        let detail = error.to_string();
+        match error {
+            SlashError::UnknownCommand(_) => Self::bad_request("unknown_slash_command", detail),
+            SlashError::InvalidArguments { .. } => Self::bad_request("invalid_slash_command", detail),
+            SlashError::RoomNotFound(_) => Self::not_found("room_not_found", detail),
+        }
+    }
+}
+
+impl From<HistoryError> for ApiError {
+    fn from(error: HistoryError) -> Self {
+        let detail = error.to_string();
+        match error {
+            HistoryError::RoomNotFound(_) => Self::not_found("room_not_found", detail),
+            HistoryError::MessageNotFound(_) => Self::not_found("message_not_found", detail),
+        }
+    }
+}
+
+impl<T> From<AggregateError<T>> for ApiError
+where
+    T: std::error::Error,
+    ApiError: From<T>,
+{
+    fn from(error: AggregateError<T>) -> Self {
+        match error {
+            AggregateError::UserError(e) => e.into(),
+            AggregateError::AggregateConflict => Self::conflict(
+                "concurrency_conflict",
+                "The resource was modified concurrently, please retry",
+            ),
+            AggregateError::DatabaseConnectionError(e) => {
+                Self::new(StatusCode::SERVICE_UNAVAILABLE, "event_store_unavailable", e.to_string())
+            }
+            AggregateError::DeserializationError(e) | AggregateError::UnexpectedError(e) => Self::internal(e.to_string()),
+        }
+    }
+}
+
+impl From<RateLimited> for ApiError {
+    fn from(error: RateLimited) -> Self {
+        Self::too_many_requests(error.to_string(), error.retry_after)
+    }
+}
+
+impl<T> From<CommandError<T>> for ApiError
+where
+    T: std::error::Error,
+    ApiError: From<T>,
+{
+    fn from(error: CommandError<T>) -> Self {
+        match error {
+            CommandError::RateLimited(e) => e.into(),
+            CommandError::Aggregate(e) => e.into(),
+        }
+    }
+}
+
+/// Renders malformed JSON bodies as problem documents instead of actix's plain-text default.
+pub fn json_error_handler(error: actix_web::error::JsonPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    ApiError::bad_request("malformed_body", error.to_string()).into()
+}
+
+/// Renders unparsable path segments (such as an invalid room id) as problem documents.
+pub fn path_error_handler(error: actix_web::error::PathError, _req: &HttpRequest) -> actix_web::Error {
+    ApiError::not_found("invalid_path", error.to_string()).into()
+}
+
+/// Renders unparsable query strings as problem documents.
+pub fn query_error_handler(error: actix_web::error::QueryPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    ApiError::bad_request("malformed_query", error.to_string()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::validation::ValidationErrors;
+
+    fn status_and_code(error: AggregateError<ChatError>) -> (u16, &'static str) {
+        let error = ApiError::from(error);
+        (error.status_code().as_u16(), error.code())
+    }
+
+    #[test]
+    fn test_chat_errors_map_to_statuses() {
+        assert_eq!(
+            status_and_code(AggregateError::UserError(ChatError::RoomNotFound("x".to_string()))),
+            (404, "room_not_found"),
+        );
+        assert_eq!(
+            status_and_code(AggregateError::UserError(ChatError::UserAlreadyInRoom("x".to_string()))),
+            (409, "user_already_in_room"),
+        );
+        assert_eq!(
+            status_and_code(AggregateError::UserError(ChatError::PermissionDenied("x".to_string()))),
+            (403, "permission_denied"),
+        );
+        assert_eq!(status_and_code(AggregateError::AggregateConflict), (409, "concurrency_conflict"));
+    }
+
+    #[test]
+    fn test_invalid_operations_conflict_in_every_aggregate() {
+        let errors = [
+            ApiError::from(ChatError::InvalidOperation("x".to_string())),
+            ApiError::from(DirectError::InvalidOperation("x".to_string())),
+            ApiError::from(UserError::InvalidOperation("x".to_string())),
+        ];
+        for error in errors {
+            assert_eq!((error.status_code().as_u16(), error.code()), (409, "invalid_operation"));
+        }
+    }
+
+    #[test]
+    fn test_validation_problem_lists_fields() {
+        let error = ApiError::from(ChatError::Validation(ValidationErrors::single("name", "must not be empty")));
+        let problem = error.problem();
+
+        assert_eq!(problem.status, 422);
+        assert_eq!(problem.code, "validation_failed");
+        assert_eq!(problem.problem_type, "urn:chat-app:problem:validation_failed");
+        assert_eq!(problem.errors.unwrap()[0].field, "name");
+    }
+
+    #[test]
+    fn test_rate_limited_sets_retry_after() {
+        let error = ApiError::from(CommandError::<ChatError>::RateLimited(RateLimited {
+            command: "SendMessage".to_string(),
+            retry_after: Duration::from_secs(3),
+        }));
+        let response = error.error_response();
+
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers().get("Retry-After").unwrap(), "3");
+        assert_eq!(error.code(), "rate_limited");
+
+        let error = ApiError::from(ChatError::SlowMode { remaining_seconds: 12 });
+        assert_eq!(error.error_response().status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(error.error_response().headers().get("Retry-After").unwrap(), "12");
+        assert_eq!(error.code(), "slow_mode");
+    }
+}
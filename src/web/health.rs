@@ -0,0 +1,121 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::{web, HttpResponse, Responder};
+use serde::Serialize;
+
+use crate::domain::aggregate::ChatRoom;
+use crate::metrics::{Metrics, ProjectionLag};
+use crate::services::PostgresEventStore;
+
+/// How long readiness waits for the event store before reporting it unavailable.
+const EVENT_STORE_TIMEOUT: Duration = Duration::from_secs(2);
+/// Projections further behind than this many events make the instance unready.
+const MAX_PROJECTION_LAG: u64 = 100;
+
+#[derive(Debug, Serialize)]
+struct Readiness {
+    ready: bool,
+    event_store: &'static str,
+    projections: Vec<ProjectionLag>,
+}
+
+/// Liveness probe: the process is up and serving requests.
+pub(crate) async fn healthz() -> impl Responder {
+    HttpResponse::Ok().body("ok")
+}
+
+/// Readiness probe: the event store answers and no projection is lagging behind.
+pub(crate) async fn readyz(
+    store: web::Data<Arc<PostgresEventStore<ChatRoom>>>,
+    metrics: web::Data<Arc<Metrics>>,
+) -> impl Responder {
+    let event_store = match tokio::time::timeout(EVENT_STORE_TIMEOUT, store.ping()).await {
+        Ok(Ok(())) => "ok",
+        Ok(Err(e)) => {
+            log::warn!("Event store readiness check failed: {}", e);
+            "unavailable"
+        }
+        Err(_) => "timeout",
+    };
+    let projections = metrics.projection_lag();
+
+    let ready = event_store == "ok" && projections.iter().all(|lag| lag.events <= MAX_PROJECTION_LAG);
+    let readiness = Readiness {
+        ready,
+        event_store,
+        projections,
+    };
+    if ready {
+        HttpResponse::Ok().json(readiness)
+    } else {
+        HttpResponse::ServiceUnavailable().json(readiness)
+    }
+}
+
+/// Prometheus scrape endpoint.
+pub(crate) async fn metrics(metrics: web::Data<Arc<Metrics>>) -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4; charset=utf-8")
+        .body(metrics.render())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use cqrs_es::{EventEnvelope, Query};
+    use uuid::Uuid;
+
+    use crate::create_chat_framework;
+    use crate::domain::commands::ChatCommand;
+    use crate::domain::events::ChatEvent;
+
+    #[actix_web::test]
+    async fn test_readiness_reports_projection_lag() {
+        let frameworks = create_chat_framework();
+        let room_id = Uuid::new_v4();
+        let command = ChatCommand::CreateRoom {
+            room_id,
+            name: "General".to_string(),
+            created_by: "user1".to_string(),
+        };
+        frameworks.rooms.execute(&room_id.to_string(), command).await.unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(frameworks.room_store.clone()))
+                .app_data(web::Data::new(frameworks.metrics.clone()))
+                .route("/healthz", web::get().to(healthz))
+                .route("/readyz", web::get().to(readyz))
+                .route("/metrics", web::get().to(metrics)),
+        )
+        .await;
+
+        let response = test::call_service(&app, test::TestRequest::get().uri("/healthz").to_request()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = test::call_service(&app, test::TestRequest::get().uri("/readyz").to_request()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = test::call_and_read_body(&app, test::TestRequest::get().uri("/metrics").to_request()).await;
+        assert!(String::from_utf8(body.to_vec()).unwrap().contains("chat_events_committed_total"));
+
+        // Events counted as committed but never handed to the projections.
+        let events: Vec<EventEnvelope<ChatRoom>> = (0..=MAX_PROJECTION_LAG as usize)
+            .map(|i| EventEnvelope {
+                aggregate_id: room_id.to_string(),
+                sequence: i + 2,
+                payload: ChatEvent::UserLeft {
+                    user_id: "user1".to_string(),
+                    timestamp: chrono::Utc::now(),
+                },
+                metadata: Default::default(),
+            })
+            .collect();
+        frameworks.metrics.dispatch(&room_id.to_string(), &events).await;
+
+        let response = test::call_service(&app, test::TestRequest::get().uri("/readyz").to_request()).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
@@ -0,0 +1,261 @@
+use std::sync::Arc;
+
+use actix_web::{web, HttpResponse};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::domain::aggregate::incoming_webhook_user_id;
+use crate::domain::commands::ChatCommand;
+use crate::services::ChatRoomViewRepository;
+use crate::web::auth::AuthenticatedUser;
+use crate::web::errors::{ApiError, Problem};
+use crate::web::responses::IncomingWebhookResponse;
+use crate::web::webhooks::owned_room;
+use crate::webhooks::{token_hash, IncomingWebhookRegistry};
+use crate::ChatRoomFramework;
+
+/// Where incoming webhooks post, outside the authenticated API: the token in
+/// the path is the only credential.
+pub(super) const INCOMING_WEBHOOK_ROUTE: &str = "/hooks/{token}";
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub(crate) struct CreateIncomingWebhookRequest {
+    /// The name the webhook's messages appear under.
+    name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub(crate) struct CreatedIncomingWebhookResponse {
+    hook_id: Uuid,
+    /// The participant the webhook posts as.
+    user_id: String,
+    /// Shown only once; the server keeps just its hash.
+    token: String,
+    /// Path to POST messages to, relative to the server root.
+    url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct IncomingMessageRequest {
+    text: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/rooms/{room_id}/incoming-webhooks",
+    tag = "webhooks",
+    params(("room_id" = Uuid, Path, description = "Room id")),
+    responses(
+        (status = 200, description = "The room's incoming webhooks", body = [IncomingWebhookResponse]),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller is not the room's owner", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Room not found", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn get_incoming_webhooks(
+    user: AuthenticatedUser,
+    room_id: web::Path<Uuid>,
+    room_views: web::Data<Arc<ChatRoomViewRepository>>,
+    registry: web::Data<Arc<IncomingWebhookRegistry>>,
+) -> Result<HttpResponse, ApiError> {
+    let room_id = room_id.into_inner();
+    owned_room(&room_views, room_id, &user.user_id).await?;
+
+    let hooks: Vec<IncomingWebhookResponse> = registry.for_room(&room_id).await.into_iter().map(Into::into).collect();
+    Ok(HttpResponse::Ok().json(hooks))
+}
+
+#[utoipa::path(
+    post,
+    path = "/rooms/{room_id}/incoming-webhooks",
+    tag = "webhooks",
+    params(("room_id" = Uuid, Path, description = "Room id")),
+    request_body = CreateIncomingWebhookRequest,
+    responses(
+        (status = 201, description = "The new webhook and its token", body = CreatedIncomingWebhookResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller is not the room's owner", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Room not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Room is archived", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid name, or the room is full", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limit exceeded; see Retry-After", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn create_incoming_webhook(
+    user: AuthenticatedUser,
+    room_id: web::Path<Uuid>,
+    req: web::Json<CreateIncomingWebhookRequest>,
+    framework: web::Data<Arc<ChatRoomFramework>>,
+) -> Result<HttpResponse, ApiError> {
+    let room_id = room_id.into_inner();
+    let hook_id = Uuid::new_v4();
+    let mut secret = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut secret);
+    let token = URL_SAFE_NO_PAD.encode(secret);
+
+    let command = ChatCommand::CreateIncomingWebhook {
+        user_id: user.user_id,
+        hook_id,
+        name: req.into_inner().name,
+        token_hash: token_hash(&token),
+    };
+
+    framework.execute(&room_id.to_string(), command).await?;
+
+    Ok(HttpResponse::Created().json(CreatedIncomingWebhookResponse {
+        hook_id,
+        user_id: incoming_webhook_user_id(&hook_id),
+        url: INCOMING_WEBHOOK_ROUTE.replace("{token}", &token),
+        token,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/rooms/{room_id}/incoming-webhooks/{hook_id}/revoke",
+    tag = "webhooks",
+    params(
+        ("room_id" = Uuid, Path, description = "Room id"),
+        ("hook_id" = Uuid, Path, description = "Incoming webhook id"),
+    ),
+    responses(
+        (status = 200, description = "Webhook revoked and its bot removed from the room", body = String, content_type = "text/plain"),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller is not the room's owner", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Room or incoming webhook not found", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limit exceeded; see Retry-After", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn revoke_incoming_webhook(
+    user: AuthenticatedUser,
+    path: web::Path<(Uuid, Uuid)>,
+    room_views: web::Data<Arc<ChatRoomViewRepository>>,
+    registry: web::Data<Arc<IncomingWebhookRegistry>>,
+    framework: web::Data<Arc<ChatRoomFramework>>,
+) -> Result<HttpResponse, ApiError> {
+    let (room_id, hook_id) = path.into_inner();
+    owned_room(&room_views, room_id, &user.user_id).await?;
+    if !registry.for_room(&room_id).await.iter().any(|hook| hook.hook_id == hook_id) {
+        return Err(ApiError::not_found(
+            "incoming_webhook_not_found",
+            format!("Incoming webhook with ID {} not found", hook_id),
+        ));
+    }
+
+    let command = ChatCommand::RevokeIncomingWebhook {
+        user_id: user.user_id,
+        hook_id,
+    };
+
+    framework.execute(&room_id.to_string(), command).await?;
+
+    Ok(HttpResponse::Ok().body("Incoming webhook revoked successfully"))
+}
+
+/// Posts `{"text": "..."}` into the webhook's room as its bot. The bot's
+/// messages are rate limited like any participant's `SendMessage`.
+pub(crate) async fn post_message(
+    token: web::Path<String>,
+    req: web::Json<IncomingMessageRequest>,
+    registry: web::Data<Arc<IncomingWebhookRegistry>>,
+    framework: web::Data<Arc<ChatRoomFramework>>,
+) -> Result<HttpResponse, ApiError> {
+    let hook = registry
+        .find_by_token(&token)
+        .await
+        .ok_or_else(|| ApiError::not_found("incoming_webhook_not_found", "No incoming webhook has this token"))?;
+    let message_id = Uuid::new_v4();
+
+    let command = ChatCommand::SendMessage {
+        message_id,
+        user_id: hook.user_id,
+        content: req.into_inner().text,
+        timestamp: chrono::Utc::now(),
+        ttl_seconds: None,
+        attachment_ids: Vec::new(),
+    };
+
+    framework.execute(&hook.room_id.to_string(), command).await?;
+
+    Ok(HttpResponse::Created().json(message_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_chat_framework;
+    use actix_web::http::StatusCode;
+    use actix_web::App;
+
+    #[actix_web::test]
+    async fn test_incoming_webhook_posts_as_its_bot_until_revoked() {
+        use actix_web::test;
+
+        let frameworks = create_chat_framework();
+        let room_id = Uuid::new_v4();
+        let hook_id = Uuid::new_v4();
+        let create = ChatCommand::Batch {
+            commands: vec![
+                ChatCommand::CreateRoom {
+                    room_id,
+                    name: "Alerts".to_string(),
+                    created_by: "owner".to_string(),
+                },
+                ChatCommand::CreateIncomingWebhook {
+                    user_id: "owner".to_string(),
+                    hook_id,
+                    name: "CI".to_string(),
+                    token_hash: token_hash("secret-token"),
+                },
+            ],
+        };
+        let mut events = frameworks.room_events.subscribe();
+        frameworks.rooms.execute(&room_id.to_string(), create).await.unwrap();
+
+        // The token's hash stays in the event store; subscribers only see the bot join.
+        let mut feed = Vec::new();
+        while let Ok((_, item)) = events.try_recv() {
+            feed.push(serde_json::to_string(&item).unwrap());
+        }
+        assert!(feed.iter().any(|item| item.contains("UserJoined")));
+        assert!(feed.iter().all(|item| !item.contains(&token_hash("secret-token"))));
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(frameworks.rooms.clone()))
+                .app_data(web::Data::new(frameworks.incoming_webhooks.clone()))
+                .route(INCOMING_WEBHOOK_ROUTE, web::post().to(post_message)),
+        )
+        .await;
+        let post = |token: &str| {
+            test::TestRequest::post()
+                .uri(&format!("/hooks/{}", token))
+                .set_json(serde_json::json!({ "text": "Build passed" }))
+                .to_request()
+        };
+
+        assert_eq!(test::call_service(&app, post("secret-token")).await.status(), StatusCode::CREATED);
+        let room = frameworks.room_views.get_room(&room_id).await.unwrap();
+        let bot = incoming_webhook_user_id(&hook_id);
+        assert_eq!(room.messages[0].user_id, bot);
+        assert!(room.participants.iter().any(|p| p.user_id == bot && p.username == "CI"));
+
+        assert_eq!(test::call_service(&app, post("wrong-token")).await.status(), StatusCode::NOT_FOUND);
+
+        let revoke = ChatCommand::RevokeIncomingWebhook {
+            user_id: "owner".to_string(),
+            hook_id,
+        };
+        frameworks.rooms.execute(&room_id.to_string(), revoke).await.unwrap();
+        assert_eq!(test::call_service(&app, post("secret-token")).await.status(), StatusCode::NOT_FOUND);
+        let room = frameworks.room_views.get_room(&room_id).await.unwrap();
+        assert!(room.participants.iter().all(|p| p.user_id != bot));
+    }
+}
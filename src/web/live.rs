@@ -0,0 +1,891 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use actix_web::web::Bytes;
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_ws::{CloseCode, CloseReason, Message, MessageStream, Session};
+use cqrs_es::{DomainEvent, EventEnvelope, EventStore};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::bots::slash::{Dispatch, SlashCommands};
+use crate::domain::aggregate::ChatRoom;
+use crate::domain::commands::ChatCommand;
+use crate::domain::events::ChatEvent;
+use crate::presence::{PresenceChange, PresenceStatus, PresenceTracker};
+use crate::services::{ChatRoomViewRepository, PostgresEventStore, RoomDirectoryFeed, RoomEventHub, RoomFeedItem};
+use crate::web::auth::AuthenticatedUser;
+use crate::web::errors::{ApiError, Problem};
+use crate::web::room_for_participant;
+use crate::ChatRoomFramework;
+
+/// How often an idle event stream sends a comment to keep proxies from closing it.
+const KEEP_ALIVE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15);
+
+/// Frames a WebSocket client may send.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum ClientFrame {
+    Send { content: String },
+    Typing,
+    Presence { status: PresenceStatus },
+}
+
+/// Frames sent to a WebSocket client besides the room feed itself.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerFrame {
+    Error { problem: Problem },
+    Lagged { skipped: u64 },
+    Presence(PresenceChange),
+}
+
+/// Upgrades to a WebSocket that streams the room's events as they are committed,
+/// along with presence changes, and accepts `send`, `typing` and `presence` frames
+/// from the client. A `send` frame may be a slash command, as in `POST .../messages`.
+/// The user counts as online while the socket is open. The server pings the client,
+/// and closes a socket that has sent nothing, not even a pong, for a minute.
+#[allow(clippy::too_many_arguments)]
+#[utoipa::path(
+    get,
+    path = "/rooms/{room_id}/ws",
+    tag = "live",
+    params(("room_id" = Uuid, Path, description = "Room id")),
+    responses(
+        (status = 101, description = "Switching to the WebSocket protocol"),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller is not in the room", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Room not found", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn room_socket(
+    req: HttpRequest,
+    body: web::Payload,
+    user: AuthenticatedUser,
+    room_id: web::Path<Uuid>,
+    framework: web::Data<Arc<ChatRoomFramework>>,
+    slash_commands: web::Data<Arc<SlashCommands>>,
+    view_repository: web::Data<Arc<ChatRoomViewRepository>>,
+    hub: web::Data<Arc<RoomEventHub>>,
+    presence: web::Data<Arc<PresenceTracker>>,
+) -> Result<HttpResponse, ApiError> {
+    let room_id = room_id.into_inner();
+    // Subscribe before checking membership, so a leave committed after the check
+    // is still seen and ends the session.
+    let events = hub.subscribe();
+    room_for_participant(&view_repository, room_id, &user.user_id).await?;
+
+    let (response, session, stream) = actix_ws::handle(&req, body)
+        .map_err(|e| ApiError::bad_request("websocket_handshake_failed", e.to_string()))?;
+
+    let presence_changes = presence.subscribe();
+    presence.connect(&user.user_id).await;
+    actix_web::rt::spawn(run_session(
+        room_id,
+        user.user_id,
+        session,
+        stream,
+        events,
+        presence_changes,
+        framework.get_ref().clone(),
+        slash_commands.get_ref().clone(),
+        view_repository.get_ref().clone(),
+        presence.get_ref().clone(),
+    ));
+
+    Ok(response)
+}
+
+/// Whether the event takes `user_id` out of the room, by leaving or being kicked,
+/// after which their live feeds of it end.
+fn removes_user(event: &ChatEvent, user_id: &str) -> bool {
+    matches!(event, ChatEvent::UserLeft { user_id: left, .. } if left == user_id)
+}
+
+/// Whether `user_id` is currently one of the room's participants.
+async fn takes_part(view_repository: &ChatRoomViewRepository, room_id: Uuid, user_id: &str) -> bool {
+    view_repository
+        .get_room(&room_id)
+        .await
+        .is_some_and(|room| room.participants.iter().any(|p| p.user_id == user_id))
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn run_session(
+    room_id: Uuid,
+    user_id: String,
+    mut session: Session,
+    mut stream: MessageStream,
+    mut events: tokio::sync::broadcast::Receiver<(Uuid, RoomFeedItem)>,
+    mut presence_changes: tokio::sync::broadcast::Receiver<PresenceChange>,
+    framework: Arc<ChatRoomFramework>,
+    slash_commands: Arc<SlashCommands>,
+    view_repository: Arc<ChatRoomViewRepository>,
+    presence: Arc<PresenceTracker>,
+) {
+    // Heartbeats ping the client and keep the user's presence alive for as long
+    // as the client answers; one that stays silent for the presence time-to-live
+    // is taken to be gone.
+    let timeout = presence.presence_ttl();
+    let mut heartbeat = tokio::time::interval(timeout / 2);
+    let mut last_heard = tokio::time::Instant::now();
+    let mut status = PresenceStatus::Online;
+
+    // Every way out of the loop falls through to closing the socket and clearing presence.
+    let reason = loop {
+        tokio::select! {
+            message = stream.recv() => {
+                last_heard = tokio::time::Instant::now();
+                let text = match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Ping(bytes))) => {
+                        if session.pong(&bytes).await.is_err() {
+                            break None;
+                        }
+                        continue;
+                    }
+                    Some(Ok(Message::Close(reason))) => break reason,
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => {
+                        log::warn!("WebSocket protocol error in room {}: {}", room_id, e);
+                        break Some(CloseReason::from(CloseCode::Protocol));
+                    }
+                    None => break None,
+                };
+
+                let result = match parse_frame(&text) {
+                    Ok(frame) => {
+                        if let ClientFrame::Presence { status: new_status } = frame {
+                            status = new_status;
+                        }
+                        handle_frame(room_id, &user_id, frame, &framework, &slash_commands, &presence).await
+                    }
+                    Err(error) => Err(error),
+                };
+                if let Err(error) = result {
+                    let frame = ServerFrame::Error { problem: error.problem() };
+                    if send_json(&mut session, &frame).await.is_err() {
+                        break None;
+                    }
+                }
+            }
+            event = events.recv() => {
+                let sent = match event {
+                    Ok((id, item)) if id == room_id => {
+                        let sent = send_json(&mut session, &item).await;
+                        let removed = matches!(&item, RoomFeedItem::Event { event, .. } if removes_user(event, &user_id));
+                        if sent.is_ok() && removed {
+                            break Some(CloseReason {
+                                code: CloseCode::Policy,
+                                description: Some("no longer a participant of the room".to_string()),
+                            });
+                        }
+                        sent
+                    }
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(skipped)) => send_json(&mut session, &ServerFrame::Lagged { skipped }).await,
+                    Err(RecvError::Closed) => break None,
+                };
+                if sent.is_err() {
+                    break None;
+                }
+            }
+            change = presence_changes.recv() => {
+                let sent = match change {
+                    // A user's presence is only shown in the rooms they take part in.
+                    Ok(change) if !takes_part(&view_repository, room_id, &change.user_id).await => continue,
+                    Ok(change) => send_json(&mut session, &ServerFrame::Presence(change)).await,
+                    Err(RecvError::Lagged(skipped)) => send_json(&mut session, &ServerFrame::Lagged { skipped }).await,
+                    Err(RecvError::Closed) => break None,
+                };
+                if sent.is_err() {
+                    break None;
+                }
+            }
+            _ = heartbeat.tick() => {
+                if last_heard.elapsed() >= timeout {
+                    break Some(CloseReason {
+                        code: CloseCode::Away,
+                        description: Some("heartbeat timed out".to_string()),
+                    });
+                }
+                if session.ping(b"").await.is_err() {
+                    break None;
+                }
+                if status != PresenceStatus::Offline {
+                    presence.set_status(&user_id, status).await;
+                }
+            }
+        }
+    };
+
+    presence.disconnect(&user_id).await;
+    let _ = session.close(reason).await;
+}
+
+fn parse_frame(text: &str) -> Result<ClientFrame, ApiError> {
+    serde_json::from_str(text).map_err(|e| ApiError::bad_request("malformed_frame", e.to_string()))
+}
+
+async fn handle_frame(
+    room_id: Uuid,
+    user_id: &str,
+    frame: ClientFrame,
+    framework: &ChatRoomFramework,
+    slash_commands: &SlashCommands,
+    presence: &PresenceTracker,
+) -> Result<(), ApiError> {
+    match frame {
+        ClientFrame::Send { content } => {
+            let content = match slash_commands.dispatch(room_id, user_id, &content).await? {
+                Dispatch::Message(content) => content,
+                Dispatch::Command(command) => return Ok(framework.execute(&room_id.to_string(), command).await?),
+                Dispatch::Handled => return Ok(()),
+            };
+            let command = ChatCommand::SendMessage {
+                message_id: Uuid::new_v4(),
+                user_id: user_id.to_string(),
+                content,
+                timestamp: chrono::Utc::now(),
+                ttl_seconds: None,
+                attachment_ids: Vec::new(),
+            };
+            framework.execute(&room_id.to_string(), command).await?;
+        }
+        ClientFrame::Typing => presence.set_typing(room_id, user_id).await,
+        ClientFrame::Presence { status } => presence.set_status(user_id, status).await,
+    }
+
+    Ok(())
+}
+
+async fn send_json<T: Serialize>(session: &mut Session, value: &T) -> Result<(), actix_ws::Closed> {
+    let text = serde_json::to_string(value).expect("frames serialize");
+    session.text(text).await
+}
+
+/// Streams a room's events as Server-Sent Events. Each event's `id` is its sequence
+/// within the room, so a client reconnecting with `Last-Event-ID` first receives every
+/// event it missed from the event store and then continues live. Like the WebSocket,
+/// the stream is only open to the room's participants.
+#[utoipa::path(
+    get,
+    path = "/rooms/{room_id}/events",
+    tag = "live",
+    params(("room_id" = Uuid, Path, description = "Room id"), ("Last-Event-ID" = Option<u64>, Header, description = "Resume after this event sequence")),
+    responses(
+        (status = 200, description = "Server-Sent Event stream of room events", body = String, content_type = "text/event-stream"),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller is not in the room", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Room not found", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn room_events(
+    req: HttpRequest,
+    user: AuthenticatedUser,
+    room_id: web::Path<Uuid>,
+    view_repository: web::Data<Arc<ChatRoomViewRepository>>,
+    store: web::Data<Arc<PostgresEventStore<ChatRoom>>>,
+    hub: web::Data<Arc<RoomEventHub>>,
+) -> Result<HttpResponse, ApiError> {
+    let room_id = room_id.into_inner();
+    room_for_participant(&view_repository, room_id, &user.user_id).await?;
+
+    // Subscribe before reading the store so events committed in between are not lost;
+    // any overlap is skipped by sequence below.
+    let mut live = hub.subscribe();
+    let stored = store.load_events(&room_id.to_string()).await?;
+    // The store may already hold a leave the view has not caught up with.
+    if !participant_after(&stored, &user.user_id) {
+        return Err(ApiError::new(
+            actix_web::http::StatusCode::FORBIDDEN,
+            "user_not_in_room",
+            format!("User {} is not in room {}", user.user_id, room_id),
+        ));
+    }
+    let user_id = user.user_id;
+    let mut last_sent = stored.last().map(|envelope| envelope.sequence).unwrap_or(0);
+    let backlog: Vec<Bytes> = match last_event_id(&req) {
+        Some(last) => {
+            let expired = expired_messages(&stored);
+            stored
+                .into_iter()
+                .filter(|envelope| envelope.sequence as u64 > last && !envelope.payload.is_private())
+                .map(|envelope| {
+                    let event = without_expired_content(envelope.payload, &expired);
+                    sse_frame(Some(envelope.sequence as u64), &event.event_type(), &event)
+                })
+                .collect()
+        }
+        None => Vec::new(),
+    };
+
+    let (sender, receiver) = mpsc::channel(64);
+    actix_web::rt::spawn(async move {
+        for frame in backlog {
+            if sender.send(frame).await.is_err() {
+                return;
+            }
+        }
+
+        let mut keep_alive = tokio::time::interval(KEEP_ALIVE_INTERVAL);
+        loop {
+            let frame = tokio::select! {
+                item = live.recv() => match item {
+                    Ok((id, RoomFeedItem::Event { sequence, event })) if id == room_id => {
+                        if sequence <= last_sent {
+                            continue;
+                        }
+                        last_sent = sequence;
+                        let frame = sse_frame(Some(sequence as u64), &event.event_type(), &event);
+                        // A participant who leaves or is kicked sees their leave, then the stream ends.
+                        if removes_user(&event, &user_id) {
+                            let _ = sender.send(frame).await;
+                            return;
+                        }
+                        frame
+                    }
+                    Ok((id, item @ RoomFeedItem::Typing { .. })) if id == room_id => sse_frame(None, "typing", &item),
+                    Ok(_) => continue,
+                    // Ending the stream makes the client reconnect and resume from its last id.
+                    Err(RecvError::Lagged(_)) | Err(RecvError::Closed) => return,
+                },
+                _ = keep_alive.tick() => Bytes::from_static(b": keep-alive\n\n"),
+            };
+            if sender.send(frame).await.is_err() {
+                return;
+            }
+        }
+    });
+
+    Ok(sse_response(receiver))
+}
+
+/// Whether `user_id` is in the room by the end of the stored events.
+fn participant_after(stored: &[EventEnvelope<ChatRoom>], user_id: &str) -> bool {
+    stored.iter().fold(false, |participant, envelope| match &envelope.payload {
+        ChatEvent::RoomCreated { created_by, .. } if created_by == user_id => true,
+        ChatEvent::UserJoined { user_id: joined, .. } if joined == user_id => true,
+        ChatEvent::UserLeft { user_id: left, .. } if left == user_id => false,
+        _ => participant,
+    })
+}
+
+/// Messages that have expired by the end of the stored events.
+fn expired_messages(stored: &[EventEnvelope<ChatRoom>]) -> HashSet<Uuid> {
+    stored
+        .iter()
+        .filter_map(|envelope| match &envelope.payload {
+            ChatEvent::MessageExpired { message_id, .. } => Some(*message_id),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Replays a sent message without its content or attachments once it has expired.
+fn without_expired_content(event: ChatEvent, expired: &HashSet<Uuid>) -> ChatEvent {
+    match event {
+        ChatEvent::MessageSent { message_id, user_id, timestamp, expires_at, .. } if expired.contains(&message_id) => {
+            ChatEvent::MessageSent {
+                message_id,
+                user_id,
+                content: String::new(),
+                timestamp,
+                expires_at,
+                attachments: Vec::new(),
+            }
+        }
+        event => event,
+    }
+}
+
+/// Streams room directory changes (rooms created, renamed, archived and participant
+/// counts) as Server-Sent Events, resumable with `Last-Event-ID`.
+#[utoipa::path(
+    get,
+    path = "/directory/events",
+    tag = "live",
+    params(("Last-Event-ID" = Option<u64>, Header, description = "Resume after this change sequence")),
+    responses(
+        (status = 200, description = "Server-Sent Event stream of directory changes", body = String, content_type = "text/event-stream"),
+    ),
+)]
+pub(crate) async fn directory_events(
+    req: HttpRequest,
+    directory: web::Data<Arc<RoomDirectoryFeed>>,
+) -> HttpResponse {
+    let (backlog, mut live) = directory.subscribe_after(last_event_id(&req)).await;
+    let mut last_sent = backlog.last().map(|change| change.sequence).unwrap_or(0);
+
+    let (sender, receiver) = mpsc::channel(64);
+    actix_web::rt::spawn(async move {
+        for change in backlog {
+            if sender.send(sse_frame(Some(change.sequence), change.kind.as_str(), &change.room)).await.is_err() {
+                return;
+            }
+        }
+
+        let mut keep_alive = tokio::time::interval(KEEP_ALIVE_INTERVAL);
+        loop {
+            let frame = tokio::select! {
+                change = live.recv() => match change {
+                    Ok(change) if change.sequence > last_sent => {
+                        last_sent = change.sequence;
+                        sse_frame(Some(change.sequence), change.kind.as_str(), &change.room)
+                    }
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(_)) | Err(RecvError::Closed) => return,
+                },
+                _ = keep_alive.tick() => Bytes::from_static(b": keep-alive\n\n"),
+            };
+            if sender.send(frame).await.is_err() {
+                return;
+            }
+        }
+    });
+
+    sse_response(receiver)
+}
+
+fn last_event_id(req: &HttpRequest) -> Option<u64> {
+    req.headers()
+        .get("Last-Event-ID")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+}
+
+fn sse_frame<T: Serialize>(id: Option<u64>, event: &str, data: &T) -> Bytes {
+    let data = serde_json::to_string(data).expect("events serialize");
+    let frame = match id {
+        Some(id) => format!("id: {}\nevent: {}\ndata: {}\n\n", id, event, data),
+        None => format!("event: {}\ndata: {}\n\n", event, data),
+    };
+    Bytes::from(frame)
+}
+
+fn sse_response(receiver: mpsc::Receiver<Bytes>) -> HttpResponse {
+    let stream = futures_util::stream::unfold(receiver, |mut receiver| async move {
+        receiver
+            .recv()
+            .await
+            .map(|frame| (Ok::<_, actix_web::Error>(frame), receiver))
+    });
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_chat_framework;
+    use crate::domain::events::ChatEvent;
+    use crate::web::auth::TokenSigner;
+
+    #[tokio::test]
+    async fn test_frames_reach_room_subscribers() {
+        let frameworks = create_chat_framework();
+        let room_id = Uuid::new_v4();
+        frameworks
+            .rooms
+            .execute(
+                &room_id.to_string(),
+                ChatCommand::CreateRoom {
+                    room_id,
+                    name: "General".to_string(),
+                    created_by: "user1".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+
+        let mut events = frameworks.room_events.subscribe();
+        let send = parse_frame(r#"{"type":"send","content":"hello"}"#).unwrap();
+        handle_frame(room_id, "user1", send, &frameworks.rooms, &frameworks.slash_commands, &frameworks.presence).await.unwrap();
+        let typing = parse_frame(r#"{"type":"typing"}"#).unwrap();
+        handle_frame(room_id, "user1", typing, &frameworks.rooms, &frameworks.slash_commands, &frameworks.presence).await.unwrap();
+
+        match events.recv().await.unwrap() {
+            (id, RoomFeedItem::Event { sequence: 2, event: ChatEvent::MessageSent { content, .. } }) => {
+                assert_eq!(id, room_id);
+                assert_eq!(content, "hello");
+            }
+            other => panic!("unexpected item: {:?}", other),
+        }
+        assert!(matches!(events.recv().await.unwrap(), (_, RoomFeedItem::Typing { user_id }) if user_id == "user1"));
+    }
+
+    #[actix_web::test]
+    async fn test_socket_sends_run_slash_commands() {
+        use futures_util::{SinkExt, StreamExt};
+
+        let frameworks = create_chat_framework();
+        let room_id = Uuid::new_v4();
+        create_room(&frameworks, room_id).await;
+        let signer = TokenSigner::new("secret");
+        let (address, handle) = serve_sockets(&frameworks, frameworks.presence.clone(), &signer);
+
+        let (_, mut socket) = awc::Client::new()
+            .ws(format!("ws://{}/rooms/{}/ws", address, room_id))
+            .bearer_auth(signer.issue("user1"))
+            .connect()
+            .await
+            .unwrap();
+        let frame = r#"{"type":"send","content":"/topic Release planning"}"#;
+        socket.send(awc::ws::Message::Text(frame.into())).await.unwrap();
+
+        loop {
+            let frame = tokio::time::timeout(std::time::Duration::from_secs(5), socket.next()).await;
+            let awc::ws::Frame::Text(text) = frame.unwrap().unwrap().unwrap() else {
+                continue;
+            };
+            let item: serde_json::Value = serde_json::from_slice(&text).unwrap();
+            if item["event"].get("MessageSent").is_some() {
+                panic!("the slash command was sent as a message: {}", item);
+            }
+            if item["event"].get("TopicChanged").is_some() {
+                break;
+            }
+        }
+        let room = frameworks.room_views.get_room(&room_id).await.unwrap();
+        assert_eq!(room.topic, "Release planning");
+
+        handle.stop(false).await;
+    }
+
+    #[actix_web::test]
+    async fn test_closed_and_silent_sockets_go_offline() {
+        use crate::presence::TYPING_TTL;
+        use futures_util::{SinkExt, StreamExt};
+        use std::time::Duration;
+
+        let frameworks = create_chat_framework();
+        let room_id = Uuid::new_v4();
+        create_room(&frameworks, room_id).await;
+        let signer = TokenSigner::new("secret");
+        let presence = Arc::new(PresenceTracker::with_ttls(
+            frameworks.room_events.clone(),
+            Duration::from_millis(300),
+            TYPING_TTL,
+        ));
+        let (address, handle) = serve_sockets(&frameworks, presence.clone(), &signer);
+        let connect = || {
+            awc::Client::new()
+                .ws(format!("ws://{}/rooms/{}/ws", address, room_id))
+                .bearer_auth(signer.issue("user1"))
+                .connect()
+        };
+        // Waits for less than the presence time-to-live, so the heartbeat running
+        // out cannot pass for the socket clearing presence.
+        let wait_until_offline = || async {
+            for _ in 0..5 {
+                if presence.get_status("user1").await == PresenceStatus::Offline {
+                    return true;
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+            false
+        };
+
+        let (_, mut socket) = connect().await.unwrap();
+        assert_eq!(presence.get_status("user1").await, PresenceStatus::Online);
+        socket.send(awc::ws::Message::Close(None)).await.unwrap();
+        assert!(wait_until_offline().await, "closing the socket must clear presence");
+
+        // A client that never answers the server's pings is closed once it has
+        // been silent for the presence time-to-live.
+        let (_, mut socket) = connect().await.unwrap();
+        assert_eq!(presence.get_status("user1").await, PresenceStatus::Online);
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        loop {
+            let frame = tokio::time::timeout(Duration::from_secs(5), socket.next()).await;
+            match frame.unwrap().unwrap().unwrap() {
+                awc::ws::Frame::Close(reason) => {
+                    assert_eq!(reason.unwrap().code, CloseCode::Away);
+                    break;
+                }
+                _ => continue,
+            }
+        }
+        assert!(wait_until_offline().await, "a timed out socket must clear presence");
+
+        handle.stop(false).await;
+    }
+
+    #[actix_web::test]
+    async fn test_socket_closes_when_its_user_is_kicked() {
+        use futures_util::StreamExt;
+
+        let frameworks = create_chat_framework();
+        let room_id = Uuid::new_v4();
+        create_room(&frameworks, room_id).await;
+        let join = ChatCommand::JoinRoom {
+            user_id: "user2".to_string(),
+            username: "Bob".to_string(),
+        };
+        frameworks.rooms.execute(&room_id.to_string(), join).await.unwrap();
+        let signer = TokenSigner::new("secret");
+        let (address, handle) = serve_sockets(&frameworks, frameworks.presence.clone(), &signer);
+
+        let (_, mut socket) = awc::Client::new()
+            .ws(format!("ws://{}/rooms/{}/ws", address, room_id))
+            .bearer_auth(signer.issue("user2"))
+            .connect()
+            .await
+            .unwrap();
+        let kick = ChatCommand::KickUser {
+            user_id: "user1".to_string(),
+            target_user_id: "user2".to_string(),
+        };
+        frameworks.rooms.execute(&room_id.to_string(), kick).await.unwrap();
+        let send = parse_frame(r#"{"type":"send","content":"not for Bob"}"#).unwrap();
+        handle_frame(room_id, "user1", send, &frameworks.rooms, &frameworks.slash_commands, &frameworks.presence).await.unwrap();
+
+        let mut saw_leave = false;
+        loop {
+            let frame = tokio::time::timeout(std::time::Duration::from_secs(5), socket.next()).await;
+            match frame.unwrap().unwrap().unwrap() {
+                awc::ws::Frame::Text(text) => {
+                    let item: serde_json::Value = serde_json::from_slice(&text).unwrap();
+                    assert!(item["event"].get("MessageSent").is_none(), "a kicked user got {}", item);
+                    saw_leave |= item["event"].get("UserLeft").is_some();
+                }
+                awc::ws::Frame::Close(reason) => {
+                    assert_eq!(reason.unwrap().code, CloseCode::Policy);
+                    break;
+                }
+                _ => continue,
+            }
+        }
+        assert!(saw_leave, "the user sees their own removal before the socket closes");
+
+        handle.stop(false).await;
+    }
+
+    #[actix_web::test]
+    async fn test_sockets_only_get_presence_of_the_room_participants() {
+        use futures_util::StreamExt;
+
+        let frameworks = create_chat_framework();
+        let room_id = Uuid::new_v4();
+        create_room(&frameworks, room_id).await;
+        let join = ChatCommand::JoinRoom {
+            user_id: "user2".to_string(),
+            username: "Bob".to_string(),
+        };
+        frameworks.rooms.execute(&room_id.to_string(), join).await.unwrap();
+        let signer = TokenSigner::new("secret");
+        let (address, handle) = serve_sockets(&frameworks, frameworks.presence.clone(), &signer);
+
+        let (_, mut socket) = awc::Client::new()
+            .ws(format!("ws://{}/rooms/{}/ws", address, room_id))
+            .bearer_auth(signer.issue("user1"))
+            .connect()
+            .await
+            .unwrap();
+        frameworks.presence.set_status("stranger", PresenceStatus::Online).await;
+        frameworks.presence.set_status("user2", PresenceStatus::Away).await;
+
+        loop {
+            let frame = tokio::time::timeout(std::time::Duration::from_secs(5), socket.next()).await;
+            let awc::ws::Frame::Text(text) = frame.unwrap().unwrap().unwrap() else {
+                continue;
+            };
+            let item: serde_json::Value = serde_json::from_slice(&text).unwrap();
+            if item["type"] != "presence" {
+                continue;
+            }
+            assert_ne!(item["user_id"], "stranger", "presence of a user outside the room was sent");
+            if item["user_id"] == "user2" {
+                assert_eq!(item["status"], "away");
+                break;
+            }
+        }
+
+        handle.stop(false).await;
+    }
+
+    /// Serves the room socket on a local port, as `run` would.
+    fn serve_sockets(
+        frameworks: &crate::ChatFrameworks,
+        presence: Arc<PresenceTracker>,
+        signer: &TokenSigner,
+    ) -> (std::net::SocketAddr, actix_web::dev::ServerHandle) {
+        use crate::web::auth::authenticate;
+        use actix_web::middleware::from_fn;
+        use actix_web::{App, HttpServer};
+
+        let (state, signer) = (frameworks.clone(), signer.clone());
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(web::Data::new(state.rooms.clone()))
+                .app_data(web::Data::new(state.slash_commands.clone()))
+                .app_data(web::Data::new(state.room_views.clone()))
+                .app_data(web::Data::new(state.room_events.clone()))
+                .app_data(web::Data::new(presence.clone()))
+                .app_data(web::Data::new(signer.clone()))
+                .wrap(from_fn(authenticate))
+                .route("/rooms/{room_id}/ws", web::get().to(room_socket))
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let address = server.addrs()[0];
+        let server = server.run();
+        let handle = server.handle();
+        actix_web::rt::spawn(server);
+        (address, handle)
+    }
+
+    #[test]
+    fn test_malformed_frame_is_rejected() {
+        assert_eq!(parse_frame("not json").unwrap_err().code(), "malformed_frame");
+    }
+
+    async fn create_room(frameworks: &crate::ChatFrameworks, room_id: Uuid) {
+        frameworks
+            .rooms
+            .execute(
+                &room_id.to_string(),
+                ChatCommand::CreateRoom {
+                    room_id,
+                    name: "General".to_string(),
+                    created_by: "user1".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_room_events_resume_after_last_event_id() {
+        use crate::web::auth::authenticate;
+        use actix_web::body::MessageBody;
+        use actix_web::middleware::from_fn;
+        use actix_web::{test, App};
+
+        let frameworks = create_chat_framework();
+        let room_id = Uuid::new_v4();
+        create_room(&frameworks, room_id).await;
+        let send = parse_frame(r#"{"type":"send","content":"missed"}"#).unwrap();
+        handle_frame(room_id, "user1", send, &frameworks.rooms, &frameworks.slash_commands, &frameworks.presence).await.unwrap();
+
+        let signer = TokenSigner::new("secret");
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(signer.clone()))
+                .app_data(web::Data::new(frameworks.room_views.clone()))
+                .app_data(web::Data::new(frameworks.room_store.clone()))
+                .app_data(web::Data::new(frameworks.room_events.clone()))
+                .wrap(from_fn(authenticate))
+                .route("/rooms/{room_id}/events", web::get().to(room_events)),
+        )
+        .await;
+
+        let anonymous = test::TestRequest::get().uri(&format!("/rooms/{}/events", room_id)).to_request();
+        assert_eq!(test::call_service(&app, anonymous).await.status(), 401);
+        let stranger = test::TestRequest::get()
+            .uri(&format!("/rooms/{}/events", room_id))
+            .insert_header(("Authorization", format!("Bearer {}", signer.issue("user2"))))
+            .to_request();
+        assert_eq!(test::call_service(&app, stranger).await.status(), 403);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/rooms/{}/events", room_id))
+            .insert_header(("Authorization", format!("Bearer {}", signer.issue("user1"))))
+            .insert_header(("Last-Event-ID", "1"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get("content-type").unwrap(), "text/event-stream");
+
+        let mut body = resp.into_body().boxed();
+        let chunk = futures_util::future::poll_fn(|cx| std::pin::Pin::new(&mut body).poll_next(cx))
+            .await
+            .unwrap()
+            .unwrap();
+        let frame = String::from_utf8(chunk.to_vec()).unwrap();
+        assert!(frame.starts_with("id: 2\nevent: MessageSent\n"), "unexpected frame: {}", frame);
+        assert!(frame.contains("missed"));
+    }
+
+    #[actix_web::test]
+    async fn test_room_events_end_when_the_user_leaves() {
+        use crate::web::auth::authenticate;
+        use actix_web::body::MessageBody;
+        use actix_web::middleware::from_fn;
+        use actix_web::{test, App};
+
+        let frameworks = create_chat_framework();
+        let room_id = Uuid::new_v4();
+        create_room(&frameworks, room_id).await;
+        let join = ChatCommand::JoinRoom {
+            user_id: "user2".to_string(),
+            username: "Bob".to_string(),
+        };
+        frameworks.rooms.execute(&room_id.to_string(), join).await.unwrap();
+        let signer = TokenSigner::new("secret");
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(signer.clone()))
+                .app_data(web::Data::new(frameworks.room_views.clone()))
+                .app_data(web::Data::new(frameworks.room_store.clone()))
+                .app_data(web::Data::new(frameworks.room_events.clone()))
+                .wrap(from_fn(authenticate))
+                .route("/rooms/{room_id}/events", web::get().to(room_events)),
+        )
+        .await;
+        let req = test::TestRequest::get()
+            .uri(&format!("/rooms/{}/events", room_id))
+            .insert_header(("Authorization", format!("Bearer {}", signer.issue("user2"))))
+            .to_request();
+        let mut body = test::call_service(&app, req).await.into_body().boxed();
+
+        let leave = ChatCommand::LeaveRoom { user_id: "user2".to_string() };
+        frameworks.rooms.execute(&room_id.to_string(), leave).await.unwrap();
+        let send = parse_frame(r#"{"type":"send","content":"not for Bob"}"#).unwrap();
+        handle_frame(room_id, "user1", send, &frameworks.rooms, &frameworks.slash_commands, &frameworks.presence).await.unwrap();
+
+        let mut frames = String::new();
+        loop {
+            let chunk = futures_util::future::poll_fn(|cx| std::pin::Pin::new(&mut body).poll_next(cx));
+            match tokio::time::timeout(std::time::Duration::from_secs(5), chunk).await.unwrap() {
+                Some(chunk) => frames.push_str(std::str::from_utf8(&chunk.unwrap()).unwrap()),
+                None => break,
+            }
+        }
+        assert!(frames.contains("event: UserLeft\n"), "unexpected frames: {}", frames);
+        assert!(!frames.contains("not for Bob"), "the stream outlived the user's leave: {}", frames);
+    }
+
+    #[tokio::test]
+    async fn test_directory_replays_changes_after_last_event_id() {
+        let frameworks = create_chat_framework();
+        let room_id = Uuid::new_v4();
+        create_room(&frameworks, room_id).await;
+        frameworks
+            .rooms
+            .execute(
+                &room_id.to_string(),
+                ChatCommand::JoinRoom {
+                    user_id: "user2".to_string(),
+                    username: "Bob".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+
+        let (backlog, _) = frameworks.room_directory.subscribe_after(Some(1)).await;
+        assert_eq!(backlog.len(), 1);
+        assert_eq!(backlog[0].sequence, 2);
+        assert_eq!(backlog[0].kind, crate::services::DirectoryChangeKind::ParticipantsChanged);
+        assert_eq!(backlog[0].room.participant_count, 2);
+
+        let (backlog, _) = frameworks.room_directory.subscribe_after(None).await;
+        assert!(backlog.is_empty());
+    }
+}
@@ -1,45 +1,77 @@
+mod attachments;
+pub mod auth;
+mod commands;
+mod direct;
+pub mod errors;
+mod health;
+mod incoming;
+mod live;
+mod openapi;
+mod polls;
+mod presence;
+mod rate_limit;
+mod responses;
+mod scheduled;
+mod search;
+mod users;
+pub mod versioning;
+mod webhooks;
+
 use std::sync::Arc;
 
-use actix_web::{web, App, HttpResponse, HttpServer, Responder};
+use actix_web::middleware::from_fn;
+use actix_web::http::Method;
+use actix_web::{web, App, FromRequest, Handler, HttpResponse, HttpServer, Responder, Route};
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, OpenApi, ToSchema};
+use utoipa_swagger_ui::SwaggerUi;
 use uuid::Uuid;
 
+use crate::bots::slash::{Dispatch, SlashCommands};
 use crate::domain::commands::ChatCommand;
-use crate::ChatRoomFramework;
+use crate::domain::validation::MAX_ATTACHMENT_SIZE;
+use crate::services::{
+    ChatRoomView, ChatRoomViewRepository, MessageCursor, MessagePage, MessageQuery,
+    UserViewRepository, DEFAULT_PAGE_SIZE,
+};
+use crate::{ChatFrameworks, ChatRoomFramework};
+use auth::{AuthenticatedUser, TokenSigner};
+use errors::{ApiError, Problem};
+use responses::{MessagePageResponse, MessageResponse, ParticipantResponse, RoomSummaryResponse};
+use versioning::Deprecation;
 
 pub struct WebApi {
-    framework: Arc<ChatRoomFramework>,
-    view_repository: Arc<crate::services::ChatRoomViewRepository>,
+    frameworks: ChatFrameworks,
+    token_signer: TokenSigner,
+    trusted_proxies: rate_limit::TrustedProxies,
 }
 
 impl WebApi {
-    pub fn new(
-        framework: Arc<ChatRoomFramework>,
-        view_repository: Arc<crate::services::ChatRoomViewRepository>,
-    ) -> Self {
+    pub fn new(frameworks: ChatFrameworks) -> Self {
         Self {
-            framework,
-            view_repository,
+            frameworks,
+            token_signer: TokenSigner::from_env(),
+            trusted_proxies: rate_limit::TrustedProxies::from_env(),
         }
     }
 
     pub async fn run(self, host: &str, port: u16) -> std::io::Result<()> {
-        let framework = self.framework;
-        let view_repository = self.view_repository.clone();
+        let frameworks = self.frameworks;
+        let token_signer = self.token_signer;
+        let trusted_proxies = self.trusted_proxies;
+
+        // Expire presence regularly so subscribers learn when users go offline.
+        let presence = frameworks.presence.clone();
+        actix_web::rt::spawn(async move {
+            let mut interval = actix_web::rt::time::interval(std::time::Duration::from_secs(5));
+            loop {
+                interval.tick().await;
+                presence.sweep().await;
+            }
+        });
 
         HttpServer::new(move || {
-            let app = App::new();
-            let app = app.app_data(web::Data::new(framework.clone()));
-            let app = app.app_data(web::Data::new(view_repository.clone()));
-            app.service(
-                web::scope("/api")
-                    .route("/rooms", web::get().to(get_rooms))
-                    .route("/rooms", web::post().to(create_room))
-                    .route("/rooms/{room_id}", web::get().to(get_room))
-                    .route("/rooms/{room_id}/join", web::post().to(join_room))
-                    .route("/rooms/{room_id}/leave", web::post().to(leave_room))
-                    .route("/rooms/{room_id}/messages", web::post().to(send_message))
-            )
+            App::new().configure(|cfg| configure_app(cfg, &frameworks, &token_signer, &trusted_proxies))
         })
         .workers(2)
         .bind((host, port))?
@@ -48,118 +80,885 @@ impl WebApi {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Registers the shared state and every route of the server, as `run` serves them.
+fn configure_app(
+    cfg: &mut web::ServiceConfig,
+    frameworks: &ChatFrameworks,
+    token_signer: &TokenSigner,
+    trusted_proxies: &rate_limit::TrustedProxies,
+) {
+    cfg.app_data(web::Data::new(frameworks.rooms.clone()));
+    cfg.app_data(web::Data::new(frameworks.slash_commands.clone()));
+    cfg.app_data(web::Data::new(frameworks.room_views.clone()));
+    cfg.app_data(web::Data::new(frameworks.room_events.clone()));
+    cfg.app_data(web::Data::new(frameworks.room_directory.clone()));
+    cfg.app_data(web::Data::new(frameworks.room_store.clone()));
+    cfg.app_data(web::Data::new(frameworks.search.clone()));
+    cfg.app_data(web::Data::new(frameworks.read_states.clone()));
+    cfg.app_data(web::Data::new(frameworks.scheduler.clone()));
+    cfg.app_data(web::Data::new(frameworks.polls.clone()));
+    cfg.app_data(web::Data::new(frameworks.blobs.clone()));
+    cfg.app_data(web::Data::new(frameworks.presence.clone()));
+    cfg.app_data(web::Data::new(frameworks.direct.clone()));
+    cfg.app_data(web::Data::new(frameworks.direct_views.clone()));
+    cfg.app_data(web::Data::new(frameworks.users.clone()));
+    cfg.app_data(web::Data::new(frameworks.user_views.clone()));
+    cfg.app_data(web::Data::new(frameworks.credentials.clone()));
+    cfg.app_data(web::Data::new(frameworks.usernames.clone()));
+    cfg.app_data(web::Data::new(frameworks.webhooks.clone()));
+    cfg.app_data(web::Data::new(frameworks.webhook_registry.clone()));
+    cfg.app_data(web::Data::new(frameworks.webhook_deliveries.clone()));
+    cfg.app_data(web::Data::new(frameworks.incoming_webhooks.clone()));
+    cfg.app_data(web::Data::new(frameworks.metrics.clone()));
+    cfg.app_data(web::Data::new(frameworks.rate_limiter.clone()));
+    cfg.app_data(web::Data::new(token_signer.clone()));
+    cfg.app_data(web::Data::new(trusted_proxies.clone()));
+    // Raw bodies are only read by attachment uploads.
+    cfg.app_data(web::PayloadConfig::new(MAX_ATTACHMENT_SIZE as usize));
+    cfg.app_data(web::JsonConfig::default().error_handler(errors::json_error_handler));
+    cfg.app_data(web::PathConfig::default().error_handler(errors::path_error_handler));
+    cfg.app_data(web::QueryConfig::default().error_handler(errors::query_error_handler));
+    // Operational endpoints for the orchestrator and Prometheus, outside the versioned API.
+    cfg.route("/healthz", web::get().to(health::healthz))
+        .route("/readyz", web::get().to(health::readyz))
+        .route("/metrics", web::get().to(health::metrics));
+    // Incoming webhooks authenticate with the token in their path rather than a bearer token.
+    cfg.service(
+        web::resource(incoming::INCOMING_WEBHOOK_ROUTE)
+            .wrap(from_fn(rate_limit::limit_by_ip))
+            .route(web::post().to(incoming::post_message)),
+    );
+    // Registered ahead of the /api scopes, which would otherwise claim these paths.
+    // The alias serves the same document as the current version.
+    cfg.service(
+        SwaggerUi::new("/api/v1/docs/{_:.*}").url("/api/v1/openapi.json", openapi::ApiDoc::openapi()),
+    );
+    cfg.service(SwaggerUi::new("/api/docs/{_:.*}").url("/api/openapi.json", openapi::ApiDoc::openapi()));
+    // /api/v1 must come before the bare /api alias, whose scope would match it too.
+    cfg.service(
+        web::scope(versioning::CURRENT_VERSION)
+            .wrap(from_fn(rate_limit::limit_by_ip))
+            .wrap(from_fn(auth::authenticate))
+            .configure(api_routes),
+    );
+    cfg.service(
+        web::scope("/api")
+            .wrap(from_fn(rate_limit::limit_by_ip))
+            .wrap(from_fn(auth::authenticate))
+            .wrap(Deprecation::unversioned_alias().headers())
+            .configure(api_routes),
+    );
+}
+
+/// Every API route, mounted under each supported prefix.
+fn api_routes(cfg: &mut web::ServiceConfig) {
+    for api_route in api_route_table() {
+        cfg.route(api_route.path, api_route.route);
+    }
+}
+
+/// Every API route with the route serving it, so tests and the rate limiter can
+/// walk exactly what the server registers.
+fn api_route_table() -> Vec<ApiRoute> {
+    vec![
+        api_route(Method::POST, "/auth/register", auth::register).issuing("RegisterUser"),
+        api_route(Method::POST, "/auth/login", auth::login),
+        api_route(Method::GET, "/rooms", get_rooms),
+        api_route(Method::POST, "/rooms", create_room).issuing("CreateRoom"),
+        api_route(Method::GET, "/rooms/{room_id}", get_room),
+        api_route(Method::POST, "/rooms/{room_id}/join", join_room).issuing("JoinRoom"),
+        api_route(Method::POST, "/rooms/{room_id}/leave", leave_room).issuing("LeaveRoom"),
+        api_route(Method::GET, "/rooms/{room_id}/messages", get_messages),
+        api_route(Method::POST, "/rooms/{room_id}/messages", send_message).issuing("SendMessage"),
+        api_route(Method::POST, "/rooms/{room_id}/read", mark_read).issuing("MarkRead"),
+        api_route(Method::POST, "/rooms/{room_id}/attachments", attachments::upload_attachment).issuing("UploadAttachment"),
+        api_route(Method::GET, "/rooms/{room_id}/attachments/{attachment_id}", attachments::download_attachment),
+        api_route(Method::POST, "/rooms/{room_id}/scheduled-messages", scheduled::schedule_message).issuing("ScheduleMessage"),
+        api_route(Method::POST, "/rooms/{room_id}/scheduled-messages/{message_id}/cancel", scheduled::cancel_scheduled_message).issuing("CancelScheduledMessage"),
+        api_route(Method::GET, "/rooms/{room_id}/polls", polls::get_polls),
+        api_route(Method::POST, "/rooms/{room_id}/polls", polls::create_poll).issuing("CreatePoll"),
+        api_route(Method::GET, "/rooms/{room_id}/polls/{poll_id}", polls::get_poll),
+        api_route(Method::POST, "/rooms/{room_id}/polls/{poll_id}/vote", polls::cast_vote).issuing("CastVote"),
+        api_route(Method::POST, "/rooms/{room_id}/polls/{poll_id}/retract", polls::retract_vote).issuing("RetractVote"),
+        api_route(Method::POST, "/rooms/{room_id}/polls/{poll_id}/close", polls::close_poll).issuing("ClosePoll"),
+        api_route(Method::GET, "/rooms/{room_id}/typing", presence::get_typing),
+        api_route(Method::POST, "/rooms/{room_id}/typing", presence::start_typing),
+        api_route(Method::GET, "/presence", presence::get_presence),
+        api_route(Method::POST, "/presence", presence::set_presence),
+        api_route(Method::GET, "/rooms/{room_id}/ws", live::room_socket),
+        api_route(Method::GET, "/rooms/{room_id}/events", live::room_events),
+        api_route(Method::GET, "/directory/events", live::directory_events),
+        api_route(Method::GET, "/search", search::search_messages),
+        api_route(Method::POST, "/rooms/{room_id}/rename", rename_room).issuing("RenameRoom"),
+        api_route(Method::POST, "/rooms/{room_id}/topic", set_topic).issuing("SetTopic"),
+        api_route(Method::POST, "/rooms/{room_id}/description", set_description).issuing("SetDescription"),
+        api_route(Method::POST, "/rooms/{room_id}/archive", archive_room).issuing("ArchiveRoom"),
+        api_route(Method::POST, "/rooms/{room_id}/unarchive", unarchive_room).issuing("UnarchiveRoom"),
+        api_route(Method::POST, "/rooms/{room_id}/slow-mode", set_slow_mode).issuing("SetSlowMode"),
+        api_route(Method::POST, "/rooms/{room_id}/message-ttl", set_message_ttl).issuing("SetMessageTtl"),
+        api_route(Method::POST, "/rooms/{room_id}/commands", commands::execute_command).issuing("ExecuteCommand"),
+        api_route(Method::POST, "/rooms/{room_id}/commands/batch", commands::execute_batch).issuing("ExecuteBatch"),
+        api_route(Method::GET, "/rooms/{room_id}/webhooks", webhooks::get_webhooks),
+        api_route(Method::POST, "/rooms/{room_id}/webhooks", webhooks::register_webhook).issuing("RegisterWebhook"),
+        api_route(Method::POST, "/rooms/{room_id}/webhooks/{webhook_id}/remove", webhooks::remove_webhook).issuing("RemoveWebhook"),
+        api_route(Method::GET, "/rooms/{room_id}/webhooks/{webhook_id}/deliveries", webhooks::get_deliveries),
+        api_route(Method::GET, "/rooms/{room_id}/incoming-webhooks", incoming::get_incoming_webhooks),
+        api_route(Method::POST, "/rooms/{room_id}/incoming-webhooks", incoming::create_incoming_webhook).issuing("CreateIncomingWebhook"),
+        api_route(Method::POST, "/rooms/{room_id}/incoming-webhooks/{hook_id}/revoke", incoming::revoke_incoming_webhook).issuing("RevokeIncomingWebhook"),
+        api_route(Method::GET, "/dms", direct::get_conversations),
+        api_route(Method::POST, "/dms", direct::start_conversation).issuing("StartConversation"),
+        api_route(Method::GET, "/dms/{conversation_id}", direct::get_conversation),
+        api_route(Method::POST, "/dms/{conversation_id}/messages", direct::send_direct_message).issuing("SendDirectMessage"),
+        api_route(Method::GET, "/users", users::get_users),
+        api_route(Method::GET, "/users/{user_id}", users::get_user),
+        api_route(Method::GET, "/me", users::get_me),
+        api_route(Method::GET, "/me/rooms", users::get_my_rooms),
+        api_route(Method::GET, "/me/scheduled-messages", scheduled::get_my_scheduled_messages),
+        api_route(Method::POST, "/me/display-name", users::change_display_name).issuing("ChangeDisplayName"),
+        api_route(Method::POST, "/me/avatar", users::set_avatar_url).issuing("SetAvatarUrl"),
+        api_route(Method::POST, "/me/status", users::set_status).issuing("SetStatus"),
+        api_route(Method::POST, "/me/deactivate", users::deactivate_user).issuing("DeactivateUser"),
+    ]
+}
+
+/// A request path for a route pattern, with every parameter filled in.
+#[cfg(test)]
+fn example_uri(pattern: &str) -> String {
+    pattern
+        .split('/')
+        .map(|segment| if segment.starts_with('{') { Uuid::nil().to_string() } else { segment.to_string() })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// An API route: its method and path, the command it issues if any, and the
+/// route serving it.
+struct ApiRoute {
+    method: Method,
+    path: &'static str,
+    /// Requests are limited per client IP with this command's limit before they
+    /// reach the handler. The generic command routes carry any command, so they get
+    /// names of their own; the commands inside are still limited per user by the
+    /// pipeline.
+    command: Option<&'static str>,
+    route: Route,
+}
+
+impl ApiRoute {
+    fn issuing(self, command: &'static str) -> Self {
+        Self {
+            command: Some(command),
+            ..self
+        }
+    }
+}
+
+fn api_route<F, Args>(method: Method, path: &'static str, handler: F) -> ApiRoute
+where
+    F: Handler<Args>,
+    Args: FromRequest + 'static,
+    F::Output: Responder + 'static,
+{
+    ApiRoute {
+        method: method.clone(),
+        path,
+        command: None,
+        route: web::route().method(method).to(handler),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 struct CreateRoomRequest {
     name: String,
-    created_by: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct JoinRoomRequest {
-    user_id: String,
-    username: String,
+/// A room's metadata together with its latest page of messages.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct RoomResponse {
+    room_id: Uuid,
+    name: String,
+    topic: String,
+    description: String,
+    archived: bool,
+    slow_mode_seconds: u64,
+    message_ttl_seconds: u64,
+    participants: Vec<ParticipantResponse>,
+    created_at: chrono::DateTime<chrono::Utc>,
+    messages: Vec<MessageResponse>,
+    has_more_messages: bool,
+}
+
+impl RoomResponse {
+    fn new(room: ChatRoomView, page: MessagePage) -> Self {
+        Self {
+            room_id: room.room_id,
+            name: room.name,
+            topic: room.topic,
+            description: room.description,
+            archived: room.archived,
+            slow_mode_seconds: room.slow_mode_seconds,
+            message_ttl_seconds: room.message_ttl_seconds,
+            participants: room.participants.into_iter().map(Into::into).collect(),
+            created_at: room.created_at,
+            messages: page.messages.into_iter().map(Into::into).collect(),
+            has_more_messages: page.has_more,
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct LeaveRoomRequest {
-    user_id: String,
+#[derive(Debug, Serialize, Deserialize, IntoParams)]
+struct MessagesParams {
+    /// Message id or RFC 3339 timestamp; returns messages older than it.
+    before: Option<String>,
+    /// Message id or RFC 3339 timestamp; returns messages newer than it.
+    after: Option<String>,
+    limit: Option<usize>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl MessagesParams {
+    fn into_query(self) -> Result<MessageQuery, ApiError> {
+        let parse = |value: Option<String>| {
+            value
+                .map(|value| {
+                    MessageCursor::parse(&value).ok_or_else(|| {
+                        ApiError::bad_request(
+                            "invalid_cursor",
+                            format!("{} is neither a message id nor an RFC 3339 timestamp", value),
+                        )
+                    })
+                })
+                .transpose()
+        };
+        Ok(MessageQuery {
+            before: parse(self.before)?,
+            after: parse(self.after)?,
+            limit: self.limit.unwrap_or(DEFAULT_PAGE_SIZE),
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 struct SendMessageRequest {
-    user_id: String,
+    /// Text starting with `/` runs a slash command such as `/topic`; start it with `//` to send a literal slash.
     content: String,
+    /// Seconds until the message expires; the room's message TTL applies when it is shorter.
+    #[serde(default)]
+    ttl_seconds: Option<u64>,
+    /// Ids returned by `POST /rooms/{room_id}/attachments`; with attachments, `content` may be empty.
+    #[serde(default)]
+    attachment_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct MarkReadRequest {
+    up_to_message_id: Uuid,
 }
 
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct RenameRoomRequest {
+    name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct SetTopicRequest {
+    topic: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct SetDescriptionRequest {
+    description: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct SetSlowModeRequest {
+    /// Seconds each non-moderator must wait between messages; 0 turns slow mode off.
+    interval_seconds: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+struct SetMessageTtlRequest {
+    /// Seconds new messages live before they expire; 0 keeps them forever.
+    ttl_seconds: u64,
+}
+
+#[utoipa::path(
+    get,
+    path = "/rooms",
+    tag = "rooms",
+    responses(
+        (status = 200, description = "Every room", body = [RoomSummaryResponse]),
+    ),
+)]
 async fn get_rooms(
-    view_repository: web::Data<Arc<crate::services::ChatRoomViewRepository>>,
+    view_repository: web::Data<Arc<ChatRoomViewRepository>>,
 ) -> impl Responder {
-    let rooms = view_repository.get_all_rooms().await;
+    let rooms: Vec<RoomSummaryResponse> = view_repository.get_all_rooms().await.into_iter().map(Into::into).collect();
     HttpResponse::Ok().json(rooms)
 }
 
+/// The room, if `user_id` takes part in it. A room's history, attachments and
+/// live feeds, and search hits in it, are only open to its participants.
+async fn room_for_participant(
+    view_repository: &ChatRoomViewRepository,
+    room_id: Uuid,
+    user_id: &str,
+) -> Result<ChatRoomView, ApiError> {
+    let room = view_repository
+        .get_room(&room_id)
+        .await
+        .ok_or_else(|| ApiError::not_found("room_not_found", format!("Room with ID {} not found", room_id)))?;
+    if !room.participants.iter().any(|p| p.user_id == user_id) {
+        return Err(ApiError::new(
+            actix_web::http::StatusCode::FORBIDDEN,
+            "user_not_in_room",
+            format!("User {} is not in room {}", user_id, room_id),
+        ));
+    }
+
+    Ok(room)
+}
+
+#[utoipa::path(
+    get,
+    path = "/rooms/{room_id}",
+    tag = "rooms",
+    params(("room_id" = Uuid, Path, description = "Room id")),
+    responses(
+        (status = 200, description = "The room with its latest page of messages", body = RoomResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller is not in the room", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Room not found", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
 async fn get_room(
+    user: AuthenticatedUser,
     room_id: web::Path<Uuid>,
-    view_repository: web::Data<Arc<crate::services::ChatRoomViewRepository>>,
-) -> impl Responder {
+    view_repository: web::Data<Arc<ChatRoomViewRepository>>,
+) -> Result<HttpResponse, ApiError> {
     let room_id = room_id.into_inner();
     
-    match view_repository.get_room(&room_id).await {
-        Some(room) => HttpResponse::Ok().json(room),
-        None => HttpResponse::NotFound().body(format!("Room with ID {} not found", room_id)),
-    }
+    let room = room_for_participant(&view_repository, room_id, &user.user_id).await?;
+    let page = view_repository.get_messages(&room_id, &MessageQuery::default()).await?;
+    
+    Ok(HttpResponse::Ok().json(RoomResponse::new(room, page)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/rooms/{room_id}/messages",
+    tag = "rooms",
+    params(("room_id" = Uuid, Path, description = "Room id"), MessagesParams),
+    responses(
+        (status = 200, description = "A page of messages, oldest first", body = MessagePageResponse),
+        (status = 400, description = "Invalid cursor", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller is not in the room", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Room or cursor message not found", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+async fn get_messages(
+    user: AuthenticatedUser,
+    room_id: web::Path<Uuid>,
+    params: web::Query<MessagesParams>,
+    view_repository: web::Data<Arc<ChatRoomViewRepository>>,
+) -> Result<HttpResponse, ApiError> {
+    let room_id = room_id.into_inner();
+    room_for_participant(&view_repository, room_id, &user.user_id).await?;
+    let query = params.into_inner().into_query()?;
+    
+    let page = view_repository.get_messages(&room_id, &query).await?;
+    
+    Ok(HttpResponse::Ok().json(MessagePageResponse::from(page)))
 }
 
+#[utoipa::path(
+    post,
+    path = "/rooms",
+    tag = "rooms",
+    request_body = CreateRoomRequest,
+    responses(
+        (status = 201, description = "Id of the new room", body = Uuid),
+        (status = 422, description = "Invalid room name", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limit exceeded; see Retry-After", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
 async fn create_room(
+    user: AuthenticatedUser,
     req: web::Json<CreateRoomRequest>,
     framework: web::Data<Arc<ChatRoomFramework>>,
-) -> impl Responder {
+) -> Result<HttpResponse, ApiError> {
     let room_id = Uuid::new_v4();
     
     let command = ChatCommand::CreateRoom {
         room_id,
         name: req.name.clone(),
-        created_by: req.created_by.clone(),
+        created_by: user.user_id,
     };
     
-    match framework.execute(&room_id.to_string(), command).await {
-        Ok(_) => HttpResponse::Created().json(room_id),
-        Err(e) => HttpResponse::InternalServerError().body(format!("Failed to create room: {}", e)),
-    }
+    framework.execute(&room_id.to_string(), command).await?;
+    
+    Ok(HttpResponse::Created().json(room_id))
 }
 
+#[utoipa::path(
+    post,
+    path = "/rooms/{room_id}/join",
+    tag = "rooms",
+    params(("room_id" = Uuid, Path, description = "Room id")),
+    responses(
+        (status = 200, description = "Joined the room", body = String, content_type = "text/plain"),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller is not in the room or lacks permission", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Room not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Command conflicts with the room's state", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Command failed validation", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limit exceeded; see Retry-After", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
 async fn join_room(
+    user: AuthenticatedUser,
     room_id: web::Path<Uuid>,
-    req: web::Json<JoinRoomRequest>,
     framework: web::Data<Arc<ChatRoomFramework>>,
-) -> impl Responder {
+    user_views: web::Data<Arc<UserViewRepository>>,
+) -> Result<HttpResponse, ApiError> {
     let room_id = room_id.into_inner();
     
+    let username = user_views
+        .get_user(&user.user_id)
+        .await
+        .map(|profile| profile.display_name)
+        .unwrap_or_else(|| user.user_id.clone());
+    
     let command = ChatCommand::JoinRoom {
-        user_id: req.user_id.clone(),
-        username: req.username.clone(),
+        user_id: user.user_id,
+        username,
     };
     
-    match framework.execute(&room_id.to_string(), command).await {
-        Ok(_) => HttpResponse::Ok().body("Joined room successfully"),
-        Err(e) => HttpResponse::BadRequest().body(format!("Failed to join room: {}", e)),
-    }
+    framework.execute(&room_id.to_string(), command).await?;
+    
+    Ok(HttpResponse::Ok().body("Joined room successfully"))
 }
 
+#[utoipa::path(
+    post,
+    path = "/rooms/{room_id}/leave",
+    tag = "rooms",
+    params(("room_id" = Uuid, Path, description = "Room id")),
+    responses(
+        (status = 200, description = "Left the room", body = String, content_type = "text/plain"),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller is not in the room or lacks permission", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Room not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Command conflicts with the room's state", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Command failed validation", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limit exceeded; see Retry-After", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
 async fn leave_room(
+    user: AuthenticatedUser,
     room_id: web::Path<Uuid>,
-    req: web::Json<LeaveRoomRequest>,
     framework: web::Data<Arc<ChatRoomFramework>>,
-) -> impl Responder {
+) -> Result<HttpResponse, ApiError> {
     let room_id = room_id.into_inner();
     
     let command = ChatCommand::LeaveRoom {
-        user_id: req.user_id.clone(),
+        user_id: user.user_id,
     };
     
-    match framework.execute(&room_id.to_string(), command).await {
-        Ok(_) => HttpResponse::Ok().body("Left room successfully"),
-        Err(e) => HttpResponse::BadRequest().body(format!("Failed to leave room: {}", e)),
-    }
+    framework.execute(&room_id.to_string(), command).await?;
+    
+    Ok(HttpResponse::Ok().body("Left room successfully"))
 }
 
+#[utoipa::path(
+    post,
+    path = "/rooms/{room_id}/messages",
+    tag = "rooms",
+    params(("room_id" = Uuid, Path, description = "Room id")),
+    request_body = SendMessageRequest,
+    responses(
+        (status = 201, description = "Id of the new message", body = Uuid),
+        (status = 200, description = "Slash command executed, or handed to a bot", body = String, content_type = "text/plain"),
+        (status = 400, description = "Unknown slash command or invalid arguments", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller is not in the room or lacks permission", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Room not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Command conflicts with the room's state", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Command failed validation", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limit exceeded or slow mode is on; see Retry-After", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
 async fn send_message(
+    user: AuthenticatedUser,
     room_id: web::Path<Uuid>,
     req: web::Json<SendMessageRequest>,
     framework: web::Data<Arc<ChatRoomFramework>>,
-) -> impl Responder {
+    slash_commands: web::Data<Arc<SlashCommands>>,
+) -> Result<HttpResponse, ApiError> {
     let room_id = room_id.into_inner();
+    let req = req.into_inner();
+    
+    let content = match slash_commands.dispatch(room_id, &user.user_id, &req.content).await? {
+        Dispatch::Message(content) => content,
+        Dispatch::Command(command) => {
+            framework.execute(&room_id.to_string(), command).await?;
+            return Ok(HttpResponse::Ok().body("Slash command executed successfully"));
+        }
+        Dispatch::Handled => return Ok(HttpResponse::Ok().body("Slash command accepted")),
+    };
     let message_id = Uuid::new_v4();
     
     let command = ChatCommand::SendMessage {
         message_id,
-        user_id: req.user_id.clone(),
-        content: req.content.clone(),
+        user_id: user.user_id,
+        content,
         timestamp: chrono::Utc::now(),
+        ttl_seconds: req.ttl_seconds,
+        attachment_ids: req.attachment_ids,
+    };
+    
+    framework.execute(&room_id.to_string(), command).await?;
+    
+    Ok(HttpResponse::Created().json(message_id))
+}
+
+#[utoipa::path(
+    post,
+    path = "/rooms/{room_id}/rename",
+    tag = "rooms",
+    params(("room_id" = Uuid, Path, description = "Room id")),
+    request_body = RenameRoomRequest,
+    responses(
+        (status = 200, description = "Room renamed", body = String, content_type = "text/plain"),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller is not in the room or lacks permission", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Room not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Command conflicts with the room's state", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Command failed validation", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limit exceeded; see Retry-After", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+async fn rename_room(
+    user: AuthenticatedUser,
+    room_id: web::Path<Uuid>,
+    req: web::Json<RenameRoomRequest>,
+    framework: web::Data<Arc<ChatRoomFramework>>,
+) -> Result<HttpResponse, ApiError> {
+    let room_id = room_id.into_inner();
+    
+    let command = ChatCommand::RenameRoom {
+        user_id: user.user_id,
+        name: req.name.clone(),
+    };
+    
+    framework.execute(&room_id.to_string(), command).await?;
+    
+    Ok(HttpResponse::Ok().body("Room renamed successfully"))
+}
+
+#[utoipa::path(
+    post,
+    path = "/rooms/{room_id}/topic",
+    tag = "rooms",
+    params(("room_id" = Uuid, Path, description = "Room id")),
+    request_body = SetTopicRequest,
+    responses(
+        (status = 200, description = "Topic updated", body = String, content_type = "text/plain"),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller is not in the room or lacks permission", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Room not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Command conflicts with the room's state", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Command failed validation", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limit exceeded; see Retry-After", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+async fn set_topic(
+    user: AuthenticatedUser,
+    room_id: web::Path<Uuid>,
+    req: web::Json<SetTopicRequest>,
+    framework: web::Data<Arc<ChatRoomFramework>>,
+) -> Result<HttpResponse, ApiError> {
+    let room_id = room_id.into_inner();
+    
+    let command = ChatCommand::SetTopic {
+        user_id: user.user_id,
+        topic: req.topic.clone(),
     };
     
-    match framework.execute(&room_id.to_string(), command).await {
-        Ok(_) => HttpResponse::Created().json(message_id),
-        Err(e) => HttpResponse::BadRequest().body(format!("Failed to send message: {}", e)),
+    framework.execute(&room_id.to_string(), command).await?;
+    
+    Ok(HttpResponse::Ok().body("Topic updated successfully"))
+}
+
+#[utoipa::path(
+    post,
+    path = "/rooms/{room_id}/description",
+    tag = "rooms",
+    params(("room_id" = Uuid, Path, description = "Room id")),
+    request_body = SetDescriptionRequest,
+    responses(
+        (status = 200, description = "Description updated", body = String, content_type = "text/plain"),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller is not in the room or lacks permission", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Room not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Command conflicts with the room's state", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Command failed validation", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limit exceeded; see Retry-After", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+async fn set_description(
+    user: AuthenticatedUser,
+    room_id: web::Path<Uuid>,
+    req: web::Json<SetDescriptionRequest>,
+    framework: web::Data<Arc<ChatRoomFramework>>,
+) -> Result<HttpResponse, ApiError> {
+    let room_id = room_id.into_inner();
+    
+    let command = ChatCommand::SetDescription {
+        user_id: user.user_id,
+        description: req.description.clone(),
+    };
+    
+    framework.execute(&room_id.to_string(), command).await?;
+    
+    Ok(HttpResponse::Ok().body("Description updated successfully"))
+}
+
+#[utoipa::path(
+    post,
+    path = "/rooms/{room_id}/archive",
+    tag = "rooms",
+    params(("room_id" = Uuid, Path, description = "Room id")),
+    responses(
+        (status = 200, description = "Room archived", body = String, content_type = "text/plain"),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller is not in the room or lacks permission", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Room not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Command conflicts with the room's state", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Command failed validation", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limit exceeded; see Retry-After", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+async fn archive_room(
+    user: AuthenticatedUser,
+    room_id: web::Path<Uuid>,
+    framework: web::Data<Arc<ChatRoomFramework>>,
+) -> Result<HttpResponse, ApiError> {
+    let room_id = room_id.into_inner();
+    
+    let command = ChatCommand::ArchiveRoom {
+        user_id: user.user_id,
+    };
+    
+    framework.execute(&room_id.to_string(), command).await?;
+    
+    Ok(HttpResponse::Ok().body("Room archived successfully"))
+}
+
+#[utoipa::path(
+    post,
+    path = "/rooms/{room_id}/unarchive",
+    tag = "rooms",
+    params(("room_id" = Uuid, Path, description = "Room id")),
+    responses(
+        (status = 200, description = "Room unarchived", body = String, content_type = "text/plain"),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller is not in the room or lacks permission", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Room not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Command conflicts with the room's state", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Command failed validation", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limit exceeded; see Retry-After", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+async fn unarchive_room(
+    user: AuthenticatedUser,
+    room_id: web::Path<Uuid>,
+    framework: web::Data<Arc<ChatRoomFramework>>,
+) -> Result<HttpResponse, ApiError> {
+    let room_id = room_id.into_inner();
+    
+    let command = ChatCommand::UnarchiveRoom {
+        user_id: user.user_id,
+    };
+    
+    framework.execute(&room_id.to_string(), command).await?;
+    
+    Ok(HttpResponse::Ok().body("Room unarchived successfully"))
+}
+
+#[utoipa::path(
+    post,
+    path = "/rooms/{room_id}/slow-mode",
+    tag = "rooms",
+    params(("room_id" = Uuid, Path, description = "Room id")),
+    request_body = SetSlowModeRequest,
+    responses(
+        (status = 200, description = "Slow mode updated", body = String, content_type = "text/plain"),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller is not a moderator of the room; only its owner is", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Room not found", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Command failed validation", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limit exceeded; see Retry-After", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+async fn set_slow_mode(
+    user: AuthenticatedUser,
+    room_id: web::Path<Uuid>,
+    req: web::Json<SetSlowModeRequest>,
+    framework: web::Data<Arc<ChatRoomFramework>>,
+) -> Result<HttpResponse, ApiError> {
+    let room_id = room_id.into_inner();
+    
+    let command = ChatCommand::SetSlowMode {
+        user_id: user.user_id,
+        interval_seconds: req.interval_seconds,
+    };
+    
+    framework.execute(&room_id.to_string(), command).await?;
+    
+    Ok(HttpResponse::Ok().body("Slow mode updated successfully"))
+}
+
+#[utoipa::path(
+    post,
+    path = "/rooms/{room_id}/message-ttl",
+    tag = "rooms",
+    params(("room_id" = Uuid, Path, description = "Room id")),
+    request_body = SetMessageTtlRequest,
+    responses(
+        (status = 200, description = "Message time-to-live updated", body = String, content_type = "text/plain"),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller is not a moderator of the room; only its owner is", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Room not found", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Command failed validation", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limit exceeded; see Retry-After", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+async fn set_message_ttl(
+    user: AuthenticatedUser,
+    room_id: web::Path<Uuid>,
+    req: web::Json<SetMessageTtlRequest>,
+    framework: web::Data<Arc<ChatRoomFramework>>,
+) -> Result<HttpResponse, ApiError> {
+    let room_id = room_id.into_inner();
+    
+    let command = ChatCommand::SetMessageTtl {
+        user_id: user.user_id,
+        ttl_seconds: req.ttl_seconds,
+    };
+    
+    framework.execute(&room_id.to_string(), command).await?;
+    
+    Ok(HttpResponse::Ok().body("Message time-to-live updated successfully"))
+}
+
+#[utoipa::path(
+    post,
+    path = "/rooms/{room_id}/read",
+    tag = "rooms",
+    params(("room_id" = Uuid, Path, description = "Room id")),
+    request_body = MarkReadRequest,
+    responses(
+        (status = 200, description = "Read marker moved", body = String, content_type = "text/plain"),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller is not in the room or lacks permission", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Room not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Command conflicts with the room's state", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Command failed validation", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limit exceeded; see Retry-After", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+async fn mark_read(
+    user: AuthenticatedUser,
+    room_id: web::Path<Uuid>,
+    req: web::Json<MarkReadRequest>,
+    framework: web::Data<Arc<ChatRoomFramework>>,
+) -> Result<HttpResponse, ApiError> {
+    let room_id = room_id.into_inner();
+    
+    let command = ChatCommand::MarkRead {
+        user_id: user.user_id,
+        up_to_message_id: req.up_to_message_id,
+    };
+    
+    framework.execute(&room_id.to_string(), command).await?;
+    
+    Ok(HttpResponse::Ok().body("Messages marked as read"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_chat_framework;
+    use crate::web::rate_limit::TrustedProxies;
+    use actix_web::http::{header, StatusCode};
+    use actix_web::test;
+
+    #[actix_web::test]
+    async fn test_history_and_search_are_only_open_to_participants() {
+        let frameworks = create_chat_framework();
+        let signer = TokenSigner::new("secret");
+        let room_id = Uuid::new_v4();
+        let create = ChatCommand::CreateRoom {
+            room_id,
+            name: "Private".to_string(),
+            created_by: "user1".to_string(),
+        };
+        frameworks.rooms.execute(&room_id.to_string(), create).await.unwrap();
+        let send = ChatCommand::SendMessage {
+            message_id: Uuid::new_v4(),
+            user_id: "user1".to_string(),
+            content: "the launch code".to_string(),
+            timestamp: chrono::Utc::now(),
+            ttl_seconds: None,
+            attachment_ids: Vec::new(),
+        };
+        frameworks.rooms.execute(&room_id.to_string(), send).await.unwrap();
+
+        let app = test::init_service(
+            App::new().configure(|cfg| configure_app(cfg, &frameworks, &signer, &TrustedProxies::default())),
+        )
+        .await;
+        let get = |uri: String, user_id: Option<&str>| {
+            let request = test::TestRequest::get().uri(&uri);
+            match user_id {
+                Some(user_id) => request
+                    .insert_header((header::AUTHORIZATION, format!("Bearer {}", signer.issue(user_id))))
+                    .to_request(),
+                None => request.to_request(),
+            }
+        };
+
+        for uri in [format!("/api/v1/rooms/{}", room_id), format!("/api/v1/rooms/{}/messages", room_id)] {
+            let response = test::call_service(&app, get(uri.clone(), None)).await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{}", uri);
+            let response = test::call_service(&app, get(uri.clone(), Some("stranger"))).await;
+            assert_eq!(response.status(), StatusCode::FORBIDDEN, "{}", uri);
+            let response = test::call_service(&app, get(uri.clone(), Some("user1"))).await;
+            assert_eq!(response.status(), StatusCode::OK, "{}", uri);
+        }
+
+        let search = "/api/v1/search?q=launch".to_string();
+        let response = test::call_service(&app, get(search.clone(), None)).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let hits: serde_json::Value = test::call_and_read_body_json(&app, get(search.clone(), Some("stranger"))).await;
+        assert_eq!(hits.as_array().unwrap().len(), 0);
+        let hits: serde_json::Value = test::call_and_read_body_json(&app, get(search, Some("user1"))).await;
+        assert_eq!(hits.as_array().unwrap().len(), 1);
     }
 }
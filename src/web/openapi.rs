@@ -0,0 +1,200 @@
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+use super::{attachments, auth, commands, direct, incoming, live, polls, presence, scheduled, search, users, webhooks};
+
+/// OpenAPI document for the current API version, served at `/api/v1/openapi.json`.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "es-rust chat API", version = "1", description = "Event-sourced chat rooms, direct messages and user profiles."),
+    servers((url = "/api/v1")),
+    paths(
+        auth::register,
+        auth::login,
+        super::get_rooms,
+        super::create_room,
+        super::get_room,
+        super::join_room,
+        super::leave_room,
+        super::get_messages,
+        super::send_message,
+        super::mark_read,
+        attachments::upload_attachment,
+        attachments::download_attachment,
+        scheduled::schedule_message,
+        scheduled::cancel_scheduled_message,
+        polls::get_polls,
+        polls::create_poll,
+        polls::get_poll,
+        polls::cast_vote,
+        polls::retract_vote,
+        polls::close_poll,
+        super::rename_room,
+        super::set_topic,
+        super::set_description,
+        super::archive_room,
+        super::unarchive_room,
+        super::set_slow_mode,
+        super::set_message_ttl,
+        commands::execute_command,
+        commands::execute_batch,
+        webhooks::get_webhooks,
+        webhooks::register_webhook,
+        webhooks::remove_webhook,
+        webhooks::get_deliveries,
+        incoming::get_incoming_webhooks,
+        incoming::create_incoming_webhook,
+        incoming::revoke_incoming_webhook,
+        presence::get_typing,
+        presence::start_typing,
+        presence::get_presence,
+        presence::set_presence,
+        live::room_socket,
+        live::room_events,
+        live::directory_events,
+        search::search_messages,
+        direct::get_conversations,
+        direct::start_conversation,
+        direct::get_conversation,
+        direct::send_direct_message,
+        users::get_users,
+        users::get_user,
+        users::get_me,
+        users::get_my_rooms,
+        scheduled::get_my_scheduled_messages,
+        users::change_display_name,
+        users::set_avatar_url,
+        users::set_status,
+        users::deactivate_user,
+    ),
+    modifiers(&BearerAuth),
+    tags(
+        (name = "auth", description = "Registration and login"),
+        (name = "rooms", description = "Chat rooms and their messages"),
+        (name = "polls", description = "Polls inside rooms and their tallies"),
+        (name = "commands", description = "Raw room commands, singly or as an atomic batch"),
+        (name = "webhooks", description = "Outbound webhooks delivering room events, and incoming webhooks posting into rooms"),
+        (name = "presence", description = "Online status and typing indicators"),
+        (name = "live", description = "WebSocket and Server-Sent Event streams"),
+        (name = "search", description = "Full-text message search"),
+        (name = "direct", description = "Direct conversations between two users"),
+        (name = "users", description = "User profiles"),
+    )
+)]
+pub struct ApiDoc;
+
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    /// `(METHOD, path)` for every route `api_routes` registers.
+    fn registered_routes() -> BTreeSet<(String, String)> {
+        crate::web::api_route_table()
+            .into_iter()
+            .map(|route| (route.method.to_string(), route.path.to_string()))
+            .collect()
+    }
+
+    fn documented_routes() -> BTreeSet<(String, String)> {
+        let spec = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        spec["paths"]
+            .as_object()
+            .unwrap()
+            .iter()
+            .flat_map(|(path, item)| {
+                item.as_object()
+                    .unwrap()
+                    .keys()
+                    .filter(|key| ["get", "post", "put", "patch", "delete"].contains(&key.as_str()))
+                    .map(move |method| (method.to_uppercase(), path.clone()))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_spec_matches_registered_routes() {
+        let registered = registered_routes();
+        let documented = documented_routes();
+
+        let undocumented: Vec<_> = registered.difference(&documented).collect();
+        let stale: Vec<_> = documented.difference(&registered).collect();
+        assert!(undocumented.is_empty(), "routes missing from the OpenAPI spec: {:?}", undocumented);
+        assert!(stale.is_empty(), "OpenAPI spec documents unknown routes: {:?}", stale);
+    }
+
+    #[actix_web::test]
+    async fn test_documented_routes_are_served() {
+        use crate::create_chat_framework;
+        use crate::web::auth::TokenSigner;
+        use crate::web::rate_limit::TrustedProxies;
+        use crate::web::versioning::CURRENT_VERSION;
+        use actix_web::http::{Method, StatusCode};
+        use actix_web::{test, web, App, HttpResponse};
+
+        let frameworks = create_chat_framework();
+        // Requests no route accepts land here rather than on a 404 a handler could return too.
+        let app = test::init_service(
+            App::new()
+                .configure(|cfg| {
+                    crate::web::configure_app(cfg, &frameworks, &TokenSigner::new("secret"), &TrustedProxies::default())
+                })
+                .default_service(web::to(HttpResponse::ImATeapot)),
+        )
+        .await;
+
+        for (method, path) in documented_routes() {
+            let pattern = format!("{}{}", CURRENT_VERSION, path);
+            let request = test::TestRequest::default()
+                .method(Method::from_bytes(method.as_bytes()).unwrap())
+                .uri(&crate::web::example_uri(&pattern))
+                .to_request();
+            let response = test::call_service(&app, request).await;
+            assert_ne!(response.status(), StatusCode::IM_A_TEAPOT, "{} {} is not routed", method, path);
+            assert_eq!(response.request().match_pattern(), Some(pattern), "{} {} is routed elsewhere", method, path);
+        }
+    }
+
+    #[test]
+    fn test_spec_declares_bearer_auth() {
+        let spec = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        assert_eq!(spec["components"]["securitySchemes"]["bearer_auth"]["scheme"], "bearer");
+        assert!(spec["components"]["schemas"]["RoomSummaryResponse"].is_object());
+    }
+
+    #[actix_web::test]
+    async fn test_docs_are_served_under_every_api_prefix() {
+        use crate::create_chat_framework;
+        use crate::web::auth::TokenSigner;
+        use crate::web::rate_limit::TrustedProxies;
+        use actix_web::{test, App};
+
+        let frameworks = create_chat_framework();
+        let app = test::init_service(App::new().configure(|cfg| {
+            crate::web::configure_app(cfg, &frameworks, &TokenSigner::new("secret"), &TrustedProxies::default())
+        }))
+        .await;
+
+        for prefix in ["/api/v1", "/api"] {
+            let request = test::TestRequest::get().uri(&format!("{}/openapi.json", prefix)).to_request();
+            let spec: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+            assert!(spec["paths"]["/rooms"]["get"].is_object(), "{} serves the spec", prefix);
+
+            let request = test::TestRequest::get().uri(&format!("{}/docs/", prefix)).to_request();
+            let response = test::call_service(&app, request).await;
+            assert!(response.status().is_success(), "{} serves the docs UI", prefix);
+        }
+    }
+}
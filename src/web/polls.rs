@@ -0,0 +1,256 @@
+use std::sync::Arc;
+
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::domain::commands::ChatCommand;
+use crate::polls::{PollRepository, PollView};
+use crate::services::ChatRoomViewRepository;
+use crate::web::auth::AuthenticatedUser;
+use crate::web::errors::{ApiError, Problem};
+use crate::web::responses::PollResponse;
+use crate::ChatRoomFramework;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub(crate) struct CreatePollRequest {
+    question: String,
+    /// Between 2 and 10 distinct options.
+    options: Vec<String>,
+    /// Whether voters may choose more than one option.
+    #[serde(default)]
+    multiple_choice: bool,
+    /// Whether to hide who voted for what.
+    #[serde(default)]
+    anonymous: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub(crate) struct CastVoteRequest {
+    /// Indexes of the chosen options; exactly one unless the poll is multiple choice.
+    options: Vec<usize>,
+}
+
+/// The poll, if it belongs to the room.
+async fn room_poll(polls: &PollRepository, room_id: Uuid, poll_id: Uuid) -> Result<PollView, ApiError> {
+    polls
+        .get_poll(&poll_id)
+        .await
+        .filter(|poll| poll.room_id == room_id)
+        .ok_or_else(|| ApiError::not_found("poll_not_found", format!("Poll with ID {} not found", poll_id)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/rooms/{room_id}/polls",
+    tag = "polls",
+    params(("room_id" = Uuid, Path, description = "Room id")),
+    responses(
+        (status = 200, description = "The room's polls with their tallies, newest first", body = [PollResponse]),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Room not found", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn get_polls(
+    user: AuthenticatedUser,
+    room_id: web::Path<Uuid>,
+    room_views: web::Data<Arc<ChatRoomViewRepository>>,
+    polls: web::Data<Arc<PollRepository>>,
+) -> Result<HttpResponse, ApiError> {
+    let room_id = room_id.into_inner();
+    if room_views.get_room(&room_id).await.is_none() {
+        return Err(ApiError::not_found("room_not_found", format!("Room with ID {} not found", room_id)));
+    }
+
+    let mut responses = Vec::new();
+    for poll in polls.for_room(&room_id).await {
+        let my_votes = polls.ballot(&poll.poll_id, &user.user_id).await;
+        responses.push(PollResponse::new(poll, my_votes));
+    }
+    Ok(HttpResponse::Ok().json(responses))
+}
+
+#[utoipa::path(
+    get,
+    path = "/rooms/{room_id}/polls/{poll_id}",
+    tag = "polls",
+    params(
+        ("room_id" = Uuid, Path, description = "Room id"),
+        ("poll_id" = Uuid, Path, description = "Poll id"),
+    ),
+    responses(
+        (status = 200, description = "The poll with its tallies", body = PollResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Poll not found", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn get_poll(
+    user: AuthenticatedUser,
+    path: web::Path<(Uuid, Uuid)>,
+    polls: web::Data<Arc<PollRepository>>,
+) -> Result<HttpResponse, ApiError> {
+    let (room_id, poll_id) = path.into_inner();
+    let poll = room_poll(&polls, room_id, poll_id).await?;
+    let my_votes = polls.ballot(&poll_id, &user.user_id).await;
+
+    Ok(HttpResponse::Ok().json(PollResponse::new(poll, my_votes)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/rooms/{room_id}/polls",
+    tag = "polls",
+    params(("room_id" = Uuid, Path, description = "Room id")),
+    request_body = CreatePollRequest,
+    responses(
+        (status = 201, description = "Id of the new poll", body = Uuid),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller is not in the room", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Room not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Room is archived", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid question or options", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limit exceeded; see Retry-After", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn create_poll(
+    user: AuthenticatedUser,
+    room_id: web::Path<Uuid>,
+    req: web::Json<CreatePollRequest>,
+    framework: web::Data<Arc<ChatRoomFramework>>,
+) -> Result<HttpResponse, ApiError> {
+    let room_id = room_id.into_inner();
+    let req = req.into_inner();
+    let poll_id = Uuid::new_v4();
+
+    let command = ChatCommand::CreatePoll {
+        poll_id,
+        user_id: user.user_id,
+        question: req.question,
+        options: req.options,
+        multiple_choice: req.multiple_choice,
+        anonymous: req.anonymous,
+    };
+
+    framework.execute(&room_id.to_string(), command).await?;
+
+    Ok(HttpResponse::Created().json(poll_id))
+}
+
+#[utoipa::path(
+    post,
+    path = "/rooms/{room_id}/polls/{poll_id}/vote",
+    tag = "polls",
+    params(
+        ("room_id" = Uuid, Path, description = "Room id"),
+        ("poll_id" = Uuid, Path, description = "Poll id"),
+    ),
+    request_body = CastVoteRequest,
+    responses(
+        (status = 200, description = "Vote recorded, replacing any earlier one", body = String, content_type = "text/plain"),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller is not in the room", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Room or poll not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "The poll is closed, or the room is archived", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Unknown options, or several in a single-choice poll", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limit exceeded; see Retry-After", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn cast_vote(
+    user: AuthenticatedUser,
+    path: web::Path<(Uuid, Uuid)>,
+    req: web::Json<CastVoteRequest>,
+    polls: web::Data<Arc<PollRepository>>,
+    framework: web::Data<Arc<ChatRoomFramework>>,
+) -> Result<HttpResponse, ApiError> {
+    let (room_id, poll_id) = path.into_inner();
+    room_poll(&polls, room_id, poll_id).await?;
+
+    let command = ChatCommand::CastVote {
+        poll_id,
+        user_id: user.user_id,
+        options: req.into_inner().options,
+    };
+
+    framework.execute(&room_id.to_string(), command).await?;
+
+    Ok(HttpResponse::Ok().body("Vote cast successfully"))
+}
+
+#[utoipa::path(
+    post,
+    path = "/rooms/{room_id}/polls/{poll_id}/retract",
+    tag = "polls",
+    params(
+        ("room_id" = Uuid, Path, description = "Room id"),
+        ("poll_id" = Uuid, Path, description = "Poll id"),
+    ),
+    responses(
+        (status = 200, description = "Vote retracted", body = String, content_type = "text/plain"),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller is not in the room", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Room or poll not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "The poll is closed, or the caller has not voted", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limit exceeded; see Retry-After", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn retract_vote(
+    user: AuthenticatedUser,
+    path: web::Path<(Uuid, Uuid)>,
+    polls: web::Data<Arc<PollRepository>>,
+    framework: web::Data<Arc<ChatRoomFramework>>,
+) -> Result<HttpResponse, ApiError> {
+    let (room_id, poll_id) = path.into_inner();
+    room_poll(&polls, room_id, poll_id).await?;
+
+    let command = ChatCommand::RetractVote {
+        poll_id,
+        user_id: user.user_id,
+    };
+
+    framework.execute(&room_id.to_string(), command).await?;
+
+    Ok(HttpResponse::Ok().body("Vote retracted successfully"))
+}
+
+#[utoipa::path(
+    post,
+    path = "/rooms/{room_id}/polls/{poll_id}/close",
+    tag = "polls",
+    params(
+        ("room_id" = Uuid, Path, description = "Room id"),
+        ("poll_id" = Uuid, Path, description = "Poll id"),
+    ),
+    responses(
+        (status = 200, description = "Poll closed", body = String, content_type = "text/plain"),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller neither created the poll nor moderates the room", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Room or poll not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "The poll is already closed", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limit exceeded; see Retry-After", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn close_poll(
+    user: AuthenticatedUser,
+    path: web::Path<(Uuid, Uuid)>,
+    polls: web::Data<Arc<PollRepository>>,
+    framework: web::Data<Arc<ChatRoomFramework>>,
+) -> Result<HttpResponse, ApiError> {
+    let (room_id, poll_id) = path.into_inner();
+    room_poll(&polls, room_id, poll_id).await?;
+
+    let command = ChatCommand::ClosePoll {
+        poll_id,
+        user_id: user.user_id,
+    };
+
+    framework.execute(&room_id.to_string(), command).await?;
+
+    Ok(HttpResponse::Ok().body("Poll closed successfully"))
+}
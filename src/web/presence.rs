@@ -0,0 +1,104 @@
+use std::sync::Arc;
+
+use actix_web::{web, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::presence::{PresenceStatus, PresenceTracker};
+use crate::services::ChatRoomViewRepository;
+use crate::web::auth::AuthenticatedUser;
+use crate::web::errors::{ApiError, Problem};
+use crate::web::responses::PresenceResponse;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub(crate) struct SetPresenceRequest {
+    status: PresenceStatus,
+}
+
+/// Records a presence heartbeat; clients repeat it to stay online.
+#[utoipa::path(
+    post,
+    path = "/presence",
+    tag = "presence",
+    request_body = SetPresenceRequest,
+    responses(
+        (status = 204, description = "Heartbeat recorded"),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn set_presence(
+    user: AuthenticatedUser,
+    req: web::Json<SetPresenceRequest>,
+    presence: web::Data<Arc<PresenceTracker>>,
+) -> impl Responder {
+    presence.set_status(&user.user_id, req.status).await;
+    HttpResponse::NoContent().finish()
+}
+
+#[utoipa::path(
+    get,
+    path = "/presence",
+    tag = "presence",
+    responses(
+        (status = 200, description = "Users who are online or away", body = [PresenceResponse]),
+    ),
+)]
+pub(crate) async fn get_presence(presence: web::Data<Arc<PresenceTracker>>) -> impl Responder {
+    let users: Vec<PresenceResponse> = presence.get_present_users().await.into_iter().map(Into::into).collect();
+    HttpResponse::Ok().json(users)
+}
+
+#[utoipa::path(
+    post,
+    path = "/rooms/{room_id}/typing",
+    tag = "presence",
+    params(("room_id" = Uuid, Path, description = "Room id")),
+    responses(
+        (status = 204, description = "Typing notice relayed"),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller is not in the room", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Room not found", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn start_typing(
+    user: AuthenticatedUser,
+    room_id: web::Path<Uuid>,
+    view_repository: web::Data<Arc<ChatRoomViewRepository>>,
+    presence: web::Data<Arc<PresenceTracker>>,
+) -> Result<HttpResponse, ApiError> {
+    let room_id = room_id.into_inner();
+
+    let room = view_repository
+        .get_room(&room_id)
+        .await
+        .ok_or_else(|| ApiError::not_found("room_not_found", format!("Room with ID {} not found", room_id)))?;
+    if !room.participants.iter().any(|p| p.user_id == user.user_id) {
+        return Err(ApiError::new(
+            actix_web::http::StatusCode::FORBIDDEN,
+            "user_not_in_room",
+            format!("User {} is not in room {}", user.user_id, room_id),
+        ));
+    }
+
+    presence.set_typing(room_id, &user.user_id).await;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[utoipa::path(
+    get,
+    path = "/rooms/{room_id}/typing",
+    tag = "presence",
+    params(("room_id" = Uuid, Path, description = "Room id")),
+    responses(
+        (status = 200, description = "Ids of users typing in the room", body = [String]),
+    ),
+)]
+pub(crate) async fn get_typing(
+    room_id: web::Path<Uuid>,
+    presence: web::Data<Arc<PresenceTracker>>,
+) -> impl Responder {
+    HttpResponse::Ok().json(presence.get_typing(&room_id.into_inner()).await)
+}
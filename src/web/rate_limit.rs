@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, OnceLock};
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::Method;
+use actix_web::middleware::Next;
+use actix_web::web;
+
+use crate::rate_limit::RateLimiter;
+use crate::web::errors::ApiError;
+use crate::web::api_route_table;
+use crate::web::incoming::INCOMING_WEBHOOK_ROUTE;
+use crate::web::versioning::CURRENT_VERSION;
+
+/// The command a request to `pattern` issues, as named on its route, so it can be
+/// limited per client IP before it reaches a handler.
+fn command_for(method: &Method, pattern: &str) -> Option<&'static str> {
+    static COMMAND_ROUTES: OnceLock<HashMap<(Method, &'static str), &'static str>> = OnceLock::new();
+
+    // Incoming webhooks post messages from outside the API.
+    if method == Method::POST && pattern == INCOMING_WEBHOOK_ROUTE {
+        return Some("SendMessage");
+    }
+    let route = pattern
+        .strip_prefix(CURRENT_VERSION)
+        .or_else(|| pattern.strip_prefix("/api"))?;
+    let routes = COMMAND_ROUTES.get_or_init(|| {
+        api_route_table()
+            .into_iter()
+            .filter_map(|route| Some(((route.method, route.path), route.command?)))
+            .collect()
+    });
+    routes.get(&(method.clone(), route)).copied()
+}
+
+/// Proxies whose `X-Forwarded-For` header is believed. Requests from anyone else
+/// are limited by their own address, since any client can send the header.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TrustedProxies(Vec<IpAddr>);
+
+impl TrustedProxies {
+    pub(crate) fn new(proxies: Vec<IpAddr>) -> Self {
+        Self(proxies)
+    }
+
+    /// Reads `TRUSTED_PROXIES`, a comma-separated list of proxy IP addresses.
+    /// Unset, no proxy is trusted.
+    pub(crate) fn from_env() -> Self {
+        let proxies = std::env::var("TRUSTED_PROXIES").unwrap_or_default();
+        Self::new(
+            proxies
+                .split(',')
+                .map(str::trim)
+                .filter(|entry| !entry.is_empty())
+                .filter_map(|entry| match entry.parse() {
+                    Ok(ip) => Some(ip),
+                    Err(_) => {
+                        log::warn!("Ignoring malformed TRUSTED_PROXIES entry {:?}", entry);
+                        None
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    fn contains(&self, ip: &IpAddr) -> bool {
+        self.0.contains(ip)
+    }
+}
+
+/// The address a request is limited by: its peer, or when the peer is a trusted
+/// proxy, the nearest address in `X-Forwarded-For` that is not one. Addresses
+/// further left were written by the client and could be anything.
+fn client_ip(req: &ServiceRequest) -> Option<IpAddr> {
+    let peer = req.peer_addr()?.ip();
+    let Some(trusted) = req.app_data::<web::Data<TrustedProxies>>() else {
+        return Some(peer);
+    };
+    if !trusted.contains(&peer) {
+        return Some(peer);
+    }
+
+    let mut client = peer;
+    let forwarded = req.headers().get_all("X-Forwarded-For").filter_map(|value| value.to_str().ok());
+    let hops: Vec<&str> = forwarded.flat_map(|value| value.split(',')).map(str::trim).collect();
+    for hop in hops.into_iter().rev() {
+        let Ok(ip) = hop.parse::<IpAddr>() else {
+            break;
+        };
+        client = ip;
+        if !trusted.contains(&ip) {
+            break;
+        }
+    }
+    Some(client)
+}
+
+/// Middleware applying command rate limits per client IP. Authenticated users are
+/// additionally limited per user inside the command pipeline.
+pub async fn limit_by_ip(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let command = req.match_pattern().and_then(|pattern| command_for(req.method(), &pattern));
+
+    if let Some(command) = command {
+        if let Some(limiter) = req.app_data::<web::Data<Arc<RateLimiter>>>() {
+            let ip = client_ip(&req).map_or_else(|| "unknown".to_string(), |ip| ip.to_string());
+            limiter.check(&format!("ip:{}", ip), command).map_err(ApiError::from)?;
+        }
+    }
+
+    next.call(req).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rate_limit::RateLimit;
+    use actix_web::http::StatusCode;
+    use actix_web::middleware::from_fn;
+    use std::time::Duration;
+
+    #[actix_web::test]
+    async fn test_command_routes_are_registered_post_routes() {
+        use crate::create_chat_framework;
+        use crate::web::auth::TokenSigner;
+        use actix_web::{test, App, HttpResponse};
+
+        let frameworks = create_chat_framework();
+        // Requests no route accepts land here rather than on a 404 a handler could return too.
+        let app = test::init_service(
+            App::new()
+                .configure(|cfg| {
+                    crate::web::configure_app(cfg, &frameworks, &TokenSigner::new("secret"), &TrustedProxies::default())
+                })
+                .default_service(web::to(HttpResponse::ImATeapot)),
+        )
+        .await;
+
+        for route in api_route_table() {
+            let Some(command) = route.command else {
+                continue;
+            };
+            let pattern = format!("{}{}", CURRENT_VERSION, route.path);
+            let request = test::TestRequest::post().uri(&crate::web::example_uri(&pattern)).to_request();
+            let response = test::call_service(&app, request).await;
+            assert_ne!(response.status(), StatusCode::IM_A_TEAPOT, "{} is not a POST route", route.path);
+            let matched = response.request().match_pattern();
+            assert_eq!(
+                matched.and_then(|pattern| command_for(&Method::POST, &pattern)),
+                Some(command),
+                "{} is limited as {}",
+                route.path,
+                command
+            );
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_requests_over_the_limit_get_429() {
+        use actix_web::{test, App, HttpResponse};
+
+        let limiter = Arc::new(RateLimiter::new(HashMap::from([(
+            "SendMessage".to_string(),
+            RateLimit::new(1, Duration::from_secs(60)),
+        )])));
+        let app = test::init_service(
+            App::new().app_data(web::Data::new(limiter)).service(
+                web::scope(CURRENT_VERSION)
+                    .wrap(from_fn(limit_by_ip))
+                    .route("/rooms/{room_id}/messages", web::post().to(HttpResponse::Created))
+                    .route("/rooms/{room_id}/messages", web::get().to(HttpResponse::Ok)),
+            ),
+        )
+        .await;
+        let send = || {
+            test::TestRequest::post()
+                .uri("/api/v1/rooms/42/messages")
+                .peer_addr("10.0.0.1:4000".parse().unwrap())
+                .to_request()
+        };
+
+        assert_eq!(test::call_service(&app, send()).await.status(), StatusCode::CREATED);
+        let error = test::try_call_service(&app, send()).await.unwrap_err();
+        let response = error.error_response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers().get("Retry-After").unwrap(), "60");
+
+        let read = test::TestRequest::get().uri("/api/v1/rooms/42/messages").to_request();
+        assert_eq!(test::call_service(&app, read).await.status(), StatusCode::OK, "reads are not limited");
+    }
+
+    #[test]
+    fn test_forwarded_addresses_are_only_believed_from_trusted_proxies() {
+        use actix_web::test::TestRequest;
+
+        let ip = |ip: &str| Some(ip.parse::<IpAddr>().unwrap());
+        let request = |peer: &str, forwarded: &str| {
+            TestRequest::post()
+                .peer_addr(format!("{}:4000", peer).parse().unwrap())
+                .insert_header(("X-Forwarded-For", forwarded))
+        };
+        let proxies = || web::Data::new(TrustedProxies::new(vec!["10.0.0.2".parse().unwrap(), "10.0.0.3".parse().unwrap()]));
+
+        let untrusted = request("10.0.0.1", "1.2.3.4").to_srv_request();
+        assert_eq!(client_ip(&untrusted), ip("10.0.0.1"), "no proxy is trusted by default");
+
+        let spoofed = request("10.0.0.1", "1.2.3.4").app_data(proxies()).to_srv_request();
+        assert_eq!(client_ip(&spoofed), ip("10.0.0.1"), "only trusted peers may forward");
+
+        let chained = request("10.0.0.2", "1.2.3.4, 5.6.7.8, 10.0.0.3").app_data(proxies()).to_srv_request();
+        assert_eq!(client_ip(&chained), ip("5.6.7.8"), "the client wrote everything left of the first proxy");
+    }
+}
@@ -0,0 +1,373 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::domain::events::Attachment;
+use crate::polls::{PollOptionView, PollView};
+use crate::presence::{PresenceStatus, UserPresence};
+use crate::scheduler::PendingMessage;
+use crate::search::SearchHit;
+use crate::services::{ChatRoomView, DirectConversationView, MessagePage, MessageView, UserInfo, UserView};
+use crate::webhooks::{DeliveryRecord, DeliveryStatus, IncomingWebhook, WebhookSubscription};
+
+/// A room as listed in the directory, without its messages.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub(crate) struct RoomSummaryResponse {
+    room_id: Uuid,
+    name: String,
+    topic: String,
+    description: String,
+    archived: bool,
+    slow_mode_seconds: u64,
+    message_ttl_seconds: u64,
+    participants: Vec<ParticipantResponse>,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<ChatRoomView> for RoomSummaryResponse {
+    fn from(room: ChatRoomView) -> Self {
+        Self {
+            room_id: room.room_id,
+            name: room.name,
+            topic: room.topic,
+            description: room.description,
+            archived: room.archived,
+            slow_mode_seconds: room.slow_mode_seconds,
+            message_ttl_seconds: room.message_ttl_seconds,
+            participants: room.participants.into_iter().map(Into::into).collect(),
+            created_at: room.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub(crate) struct ParticipantResponse {
+    user_id: String,
+    username: String,
+}
+
+impl From<UserInfo> for ParticipantResponse {
+    fn from(user: UserInfo) -> Self {
+        Self {
+            user_id: user.user_id,
+            username: user.username,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub(crate) struct MessageResponse {
+    id: Uuid,
+    user_id: String,
+    username: String,
+    /// Empty once the message has expired.
+    content: String,
+    timestamp: chrono::DateTime<chrono::Utc>,
+    expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    expired: bool,
+    /// Empty once the message has expired.
+    attachments: Vec<AttachmentResponse>,
+}
+
+impl From<MessageView> for MessageResponse {
+    fn from(message: MessageView) -> Self {
+        Self {
+            id: message.id,
+            user_id: message.user_id,
+            username: message.username,
+            content: message.content,
+            timestamp: message.timestamp,
+            expires_at: message.expires_at,
+            expired: message.expired,
+            attachments: message.attachments.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// An attachment's metadata; its bytes are served by
+/// `GET /rooms/{room_id}/attachments/{attachment_id}`.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub(crate) struct AttachmentResponse {
+    attachment_id: Uuid,
+    name: String,
+    mime_type: String,
+    size: u64,
+    /// Hex-encoded SHA-256 of the file's bytes.
+    checksum: String,
+}
+
+impl From<Attachment> for AttachmentResponse {
+    fn from(attachment: Attachment) -> Self {
+        Self {
+            attachment_id: attachment.attachment_id,
+            name: attachment.name,
+            mime_type: attachment.mime_type,
+            size: attachment.size,
+            checksum: attachment.checksum,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub(crate) struct MessagePageResponse {
+    messages: Vec<MessageResponse>,
+    /// Whether more messages exist beyond this page in the paging direction.
+    has_more: bool,
+}
+
+impl From<MessagePage> for MessagePageResponse {
+    fn from(page: MessagePage) -> Self {
+        Self {
+            messages: page.messages.into_iter().map(Into::into).collect(),
+            has_more: page.has_more,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub(crate) struct ConversationResponse {
+    conversation_id: Uuid,
+    participants: Vec<ParticipantResponse>,
+    messages: Vec<MessageResponse>,
+    started_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<DirectConversationView> for ConversationResponse {
+    fn from(conversation: DirectConversationView) -> Self {
+        Self {
+            conversation_id: conversation.conversation_id,
+            participants: conversation.participants.into_iter().map(Into::into).collect(),
+            messages: conversation.messages.into_iter().map(Into::into).collect(),
+            started_at: conversation.started_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub(crate) struct UserResponse {
+    user_id: String,
+    username: String,
+    display_name: String,
+    avatar_url: String,
+    status: String,
+    deactivated: bool,
+    registered_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<UserView> for UserResponse {
+    fn from(user: UserView) -> Self {
+        Self {
+            user_id: user.user_id,
+            username: user.username,
+            display_name: user.display_name,
+            avatar_url: user.avatar_url,
+            status: user.status,
+            deactivated: user.deactivated,
+            registered_at: user.registered_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub(crate) struct SearchHitResponse {
+    message_id: Uuid,
+    room_id: Uuid,
+    room_name: String,
+    user_id: String,
+    username: String,
+    timestamp: chrono::DateTime<chrono::Utc>,
+    score: f64,
+    snippet: String,
+    /// Byte ranges `[start, end)` of the matched terms within `snippet`.
+    highlights: Vec<(usize, usize)>,
+}
+
+impl From<SearchHit> for SearchHitResponse {
+    fn from(hit: SearchHit) -> Self {
+        Self {
+            message_id: hit.message_id,
+            room_id: hit.room_id,
+            room_name: hit.room_name,
+            user_id: hit.user_id,
+            username: hit.username,
+            timestamp: hit.timestamp,
+            score: hit.score,
+            snippet: hit.snippet,
+            highlights: hit.highlights,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub(crate) struct PresenceResponse {
+    user_id: String,
+    status: PresenceStatus,
+}
+
+impl From<UserPresence> for PresenceResponse {
+    fn from(presence: UserPresence) -> Self {
+        Self {
+            user_id: presence.user_id,
+            status: presence.status,
+        }
+    }
+}
+
+/// A webhook subscription; the signing secret is never returned.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub(crate) struct WebhookResponse {
+    webhook_id: Uuid,
+    room_id: Uuid,
+    url: String,
+    /// Event types delivered; empty means every event.
+    event_types: Vec<String>,
+    registered_by: String,
+    registered_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<WebhookSubscription> for WebhookResponse {
+    fn from(hook: WebhookSubscription) -> Self {
+        Self {
+            webhook_id: hook.webhook_id,
+            room_id: hook.room_id,
+            url: hook.url,
+            event_types: hook.event_types,
+            registered_by: hook.registered_by,
+            registered_at: hook.registered_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct DeliveryResponse {
+    delivery_id: Uuid,
+    event_type: String,
+    sequence: usize,
+    status: DeliveryStatus,
+    attempts: u32,
+    response_status: Option<u16>,
+    last_error: Option<String>,
+    created_at: chrono::DateTime<chrono::Utc>,
+    updated_at: chrono::DateTime<chrono::Utc>,
+    /// The undelivered body of a dead-lettered delivery.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    payload: Option<serde_json::Value>,
+}
+
+impl From<DeliveryRecord> for DeliveryResponse {
+    fn from(record: DeliveryRecord) -> Self {
+        Self {
+            delivery_id: record.delivery_id,
+            event_type: record.event_type,
+            sequence: record.sequence,
+            status: record.status,
+            attempts: record.attempts,
+            response_status: record.response_status,
+            last_error: record.last_error,
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+            payload: record.payload,
+        }
+    }
+}
+
+/// An incoming webhook; its token is only returned when it is created.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub(crate) struct IncomingWebhookResponse {
+    hook_id: Uuid,
+    room_id: Uuid,
+    name: String,
+    /// The participant the webhook posts as.
+    user_id: String,
+    created_by: String,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<IncomingWebhook> for IncomingWebhookResponse {
+    fn from(hook: IncomingWebhook) -> Self {
+        Self {
+            hook_id: hook.hook_id,
+            room_id: hook.room_id,
+            name: hook.name,
+            user_id: hook.user_id,
+            created_by: hook.created_by,
+            created_at: hook.created_at,
+        }
+    }
+}
+
+/// A message waiting to be sent at `deliver_at`.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub(crate) struct ScheduledMessageResponse {
+    message_id: Uuid,
+    room_id: Uuid,
+    content: String,
+    deliver_at: chrono::DateTime<chrono::Utc>,
+    scheduled_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<PendingMessage> for ScheduledMessageResponse {
+    fn from(message: PendingMessage) -> Self {
+        Self {
+            message_id: message.message_id,
+            room_id: message.room_id,
+            content: message.content,
+            deliver_at: message.deliver_at,
+            scheduled_at: message.scheduled_at,
+        }
+    }
+}
+
+/// A poll with its current tallies, as seen by the caller.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub(crate) struct PollResponse {
+    poll_id: Uuid,
+    room_id: Uuid,
+    created_by: String,
+    question: String,
+    options: Vec<PollOptionResponse>,
+    multiple_choice: bool,
+    anonymous: bool,
+    closed: bool,
+    /// How many participants have voted.
+    voters: usize,
+    /// Indexes of the options the caller voted for; empty if they have not voted.
+    my_votes: Vec<usize>,
+    created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl PollResponse {
+    pub(crate) fn new(poll: PollView, my_votes: Option<Vec<usize>>) -> Self {
+        Self {
+            poll_id: poll.poll_id,
+            room_id: poll.room_id,
+            created_by: poll.created_by,
+            question: poll.question,
+            options: poll.options.into_iter().map(Into::into).collect(),
+            multiple_choice: poll.multiple_choice,
+            anonymous: poll.anonymous,
+            closed: poll.closed,
+            voters: poll.voters,
+            my_votes: my_votes.unwrap_or_default(),
+            created_at: poll.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub(crate) struct PollOptionResponse {
+    text: String,
+    votes: usize,
+    /// Who voted for the option; always empty in an anonymous poll.
+    voters: Vec<String>,
+}
+
+impl From<PollOptionView> for PollOptionResponse {
+    fn from(option: PollOptionView) -> Self {
+        Self {
+            text: option.text,
+            votes: option.votes,
+            voters: option.voters,
+        }
+    }
+}
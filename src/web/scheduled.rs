@@ -0,0 +1,113 @@
+use std::sync::Arc;
+
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::domain::commands::ChatCommand;
+use crate::scheduler::MessageScheduler;
+use crate::web::auth::AuthenticatedUser;
+use crate::web::errors::{ApiError, Problem};
+use crate::web::responses::ScheduledMessageResponse;
+use crate::ChatRoomFramework;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub(crate) struct ScheduleMessageRequest {
+    content: String,
+    /// When to send the message; at most 30 days ahead.
+    deliver_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/rooms/{room_id}/scheduled-messages",
+    tag = "rooms",
+    params(("room_id" = Uuid, Path, description = "Room id")),
+    request_body = ScheduleMessageRequest,
+    responses(
+        (status = 201, description = "Id the message will be sent with", body = Uuid),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller is not in the room", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Room not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Room is archived", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid content or delivery time, or too many pending messages", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limit exceeded; see Retry-After", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn schedule_message(
+    user: AuthenticatedUser,
+    room_id: web::Path<Uuid>,
+    req: web::Json<ScheduleMessageRequest>,
+    framework: web::Data<Arc<ChatRoomFramework>>,
+) -> Result<HttpResponse, ApiError> {
+    let room_id = room_id.into_inner();
+    let req = req.into_inner();
+    let message_id = Uuid::new_v4();
+
+    let command = ChatCommand::ScheduleMessage {
+        message_id,
+        user_id: user.user_id,
+        content: req.content,
+        deliver_at: req.deliver_at,
+    };
+
+    framework.execute(&room_id.to_string(), command).await?;
+
+    Ok(HttpResponse::Created().json(message_id))
+}
+
+#[utoipa::path(
+    post,
+    path = "/rooms/{room_id}/scheduled-messages/{message_id}/cancel",
+    tag = "rooms",
+    params(
+        ("room_id" = Uuid, Path, description = "Room id"),
+        ("message_id" = Uuid, Path, description = "Scheduled message id"),
+    ),
+    responses(
+        (status = 200, description = "Scheduled message cancelled", body = String, content_type = "text/plain"),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "The message was scheduled by someone else", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Room not found", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "The message is not scheduled, or was already sent", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limit exceeded; see Retry-After", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn cancel_scheduled_message(
+    user: AuthenticatedUser,
+    path: web::Path<(Uuid, Uuid)>,
+    framework: web::Data<Arc<ChatRoomFramework>>,
+) -> Result<HttpResponse, ApiError> {
+    let (room_id, message_id) = path.into_inner();
+
+    let command = ChatCommand::CancelScheduledMessage {
+        user_id: user.user_id,
+        message_id,
+    };
+
+    framework.execute(&room_id.to_string(), command).await?;
+
+    Ok(HttpResponse::Ok().body("Scheduled message cancelled successfully"))
+}
+
+#[utoipa::path(
+    get,
+    path = "/me/scheduled-messages",
+    tag = "users",
+    responses(
+        (status = 200, description = "The caller's pending scheduled messages, soonest first", body = [ScheduledMessageResponse]),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn get_my_scheduled_messages(
+    user: AuthenticatedUser,
+    scheduler: web::Data<Arc<MessageScheduler>>,
+) -> HttpResponse {
+    let messages: Vec<ScheduledMessageResponse> =
+        scheduler.for_user(&user.user_id).await.into_iter().map(Into::into).collect();
+    HttpResponse::Ok().json(messages)
+}
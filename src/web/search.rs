@@ -0,0 +1,59 @@
+use std::sync::Arc;
+
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::search::{MessageSearchIndex, SearchQuery, DEFAULT_SEARCH_LIMIT};
+use crate::services::ChatRoomViewRepository;
+use crate::web::auth::AuthenticatedUser;
+use crate::web::errors::{ApiError, Problem};
+use crate::web::responses::SearchHitResponse;
+
+#[derive(Debug, Serialize, Deserialize, IntoParams)]
+pub(crate) struct SearchParams {
+    q: String,
+    room_id: Option<Uuid>,
+    user_id: Option<String>,
+    from: Option<chrono::DateTime<chrono::Utc>>,
+    to: Option<chrono::DateTime<chrono::Utc>>,
+    limit: Option<usize>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/search",
+    tag = "search",
+    params(SearchParams),
+    responses(
+        (status = 200, description = "Matching messages in the caller's rooms, best first", body = [SearchHitResponse]),
+        (status = 400, description = "Missing query", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn search_messages(
+    user: AuthenticatedUser,
+    params: web::Query<SearchParams>,
+    index: web::Data<Arc<MessageSearchIndex>>,
+    room_views: web::Data<Arc<ChatRoomViewRepository>>,
+) -> Result<HttpResponse, ApiError> {
+    let params = params.into_inner();
+    if params.q.trim().is_empty() {
+        return Err(ApiError::bad_request("missing_query", "The q parameter must not be empty"));
+    }
+
+    let query = SearchQuery {
+        text: params.q,
+        room_id: params.room_id,
+        room_ids: Some(room_views.rooms_of(&user.user_id).await),
+        user_id: params.user_id,
+        from: params.from,
+        to: params.to,
+        limit: params.limit.unwrap_or(DEFAULT_SEARCH_LIMIT),
+    };
+
+    let hits: Vec<SearchHitResponse> = index.search(&query).await.into_iter().map(Into::into).collect();
+    Ok(HttpResponse::Ok().json(hits))
+}
@@ -0,0 +1,227 @@
+use std::sync::Arc;
+
+use actix_web::{web, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::domain::user::commands::UserCommand;
+use crate::services::{ChatRoomViewRepository, ReadStateRepository, UserViewRepository};
+use crate::web::auth::AuthenticatedUser;
+use crate::web::errors::{ApiError, Problem};
+use crate::web::responses::UserResponse;
+use crate::UserFramework;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub(crate) struct ChangeDisplayNameRequest {
+    display_name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub(crate) struct SetAvatarUrlRequest {
+    avatar_url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub(crate) struct SetStatusRequest {
+    status: String,
+}
+
+/// One of the caller's rooms with their read position in it.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub(crate) struct MyRoom {
+    room_id: Uuid,
+    name: String,
+    archived: bool,
+    last_read_message_id: Option<Uuid>,
+    unread_count: usize,
+}
+
+#[utoipa::path(
+    get,
+    path = "/users",
+    tag = "users",
+    responses(
+        (status = 200, description = "Every user", body = [UserResponse]),
+    ),
+)]
+pub(crate) async fn get_users(
+    view_repository: web::Data<Arc<UserViewRepository>>,
+) -> impl Responder {
+    let users: Vec<UserResponse> = view_repository.get_all_users().await.into_iter().map(Into::into).collect();
+    HttpResponse::Ok().json(users)
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/{user_id}",
+    tag = "users",
+    params(("user_id" = String, Path, description = "User id")),
+    responses(
+        (status = 200, description = "The user", body = UserResponse),
+        (status = 404, description = "User not found", body = Problem, content_type = "application/problem+json"),
+    ),
+)]
+pub(crate) async fn get_user(
+    user_id: web::Path<String>,
+    view_repository: web::Data<Arc<UserViewRepository>>,
+) -> Result<HttpResponse, ApiError> {
+    let user_id = user_id.into_inner();
+    
+    match view_repository.get_user(&user_id).await {
+        Some(user) => Ok(HttpResponse::Ok().json(UserResponse::from(user))),
+        None => Err(ApiError::not_found("user_not_found", format!("User with ID {} not found", user_id))),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/me",
+    tag = "users",
+    responses(
+        (status = 200, description = "The caller's profile", body = UserResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "User not found", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn get_me(
+    user: AuthenticatedUser,
+    view_repository: web::Data<Arc<UserViewRepository>>,
+) -> Result<HttpResponse, ApiError> {
+    match view_repository.get_user(&user.user_id).await {
+        Some(profile) => Ok(HttpResponse::Ok().json(UserResponse::from(profile))),
+        None => Err(ApiError::not_found("user_not_found", format!("User with ID {} not found", user.user_id))),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/me/rooms",
+    tag = "users",
+    responses(
+        (status = 200, description = "The caller's rooms with unread counts", body = [MyRoom]),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn get_my_rooms(
+    user: AuthenticatedUser,
+    read_states: web::Data<Arc<ReadStateRepository>>,
+    room_views: web::Data<Arc<ChatRoomViewRepository>>,
+) -> impl Responder {
+    let mut rooms = Vec::new();
+    for state in read_states.get_read_states(&user.user_id).await {
+        if let Some(room) = room_views.get_room(&state.room_id).await {
+            rooms.push(MyRoom {
+                room_id: state.room_id,
+                name: room.name,
+                archived: room.archived,
+                last_read_message_id: state.last_read_message_id,
+                unread_count: state.unread_count,
+            });
+        }
+    }
+    HttpResponse::Ok().json(rooms)
+}
+
+#[utoipa::path(
+    post,
+    path = "/me/display-name",
+    tag = "users",
+    request_body = ChangeDisplayNameRequest,
+    responses(
+        (status = 200, description = "Display name updated", body = String, content_type = "text/plain"),
+        (status = 409, description = "Invalid display name", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limit exceeded; see Retry-After", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn change_display_name(
+    user: AuthenticatedUser,
+    req: web::Json<ChangeDisplayNameRequest>,
+    framework: web::Data<Arc<UserFramework>>,
+) -> Result<HttpResponse, ApiError> {
+    let command = UserCommand::ChangeDisplayName {
+        display_name: req.display_name.clone(),
+    };
+    
+    framework.execute(&user.user_id, command).await?;
+    
+    Ok(HttpResponse::Ok().body("Display name updated successfully"))
+}
+
+#[utoipa::path(
+    post,
+    path = "/me/avatar",
+    tag = "users",
+    request_body = SetAvatarUrlRequest,
+    responses(
+        (status = 200, description = "Avatar updated", body = String, content_type = "text/plain"),
+        (status = 409, description = "Invalid avatar URL", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limit exceeded; see Retry-After", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn set_avatar_url(
+    user: AuthenticatedUser,
+    req: web::Json<SetAvatarUrlRequest>,
+    framework: web::Data<Arc<UserFramework>>,
+) -> Result<HttpResponse, ApiError> {
+    let command = UserCommand::SetAvatarUrl {
+        avatar_url: req.avatar_url.clone(),
+    };
+    
+    framework.execute(&user.user_id, command).await?;
+    
+    Ok(HttpResponse::Ok().body("Avatar updated successfully"))
+}
+
+#[utoipa::path(
+    post,
+    path = "/me/status",
+    tag = "users",
+    request_body = SetStatusRequest,
+    responses(
+        (status = 200, description = "Status updated", body = String, content_type = "text/plain"),
+        (status = 409, description = "Invalid status", body = Problem, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limit exceeded; see Retry-After", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn set_status(
+    user: AuthenticatedUser,
+    req: web::Json<SetStatusRequest>,
+    framework: web::Data<Arc<UserFramework>>,
+) -> Result<HttpResponse, ApiError> {
+    let command = UserCommand::SetStatus {
+        status: req.status.clone(),
+    };
+    
+    framework.execute(&user.user_id, command).await?;
+    
+    Ok(HttpResponse::Ok().body("Status updated successfully"))
+}
+
+#[utoipa::path(
+    post,
+    path = "/me/deactivate",
+    tag = "users",
+    responses(
+        (status = 200, description = "User deactivated", body = String, content_type = "text/plain"),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limit exceeded; see Retry-After", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn deactivate_user(
+    user: AuthenticatedUser,
+    framework: web::Data<Arc<UserFramework>>,
+) -> Result<HttpResponse, ApiError> {
+    framework.execute(&user.user_id, UserCommand::DeactivateUser).await?;
+    
+    Ok(HttpResponse::Ok().body("User deactivated successfully"))
+}
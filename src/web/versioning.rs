@@ -0,0 +1,69 @@
+use actix_web::middleware::DefaultHeaders;
+use chrono::{DateTime, TimeZone, Utc};
+
+/// Prefix of the current API version. Clients should use it instead of the bare `/api` alias.
+pub const CURRENT_VERSION: &str = "/api/v1";
+
+/// Marks a retired API prefix. Its responses carry a `Deprecation` header (RFC 9745),
+/// an optional `Sunset` date (RFC 8594) and a `Link` to the version replacing it.
+#[derive(Debug, Clone)]
+pub struct Deprecation {
+    pub since: DateTime<Utc>,
+    pub sunset: Option<DateTime<Utc>>,
+    pub successor: &'static str,
+}
+
+impl Deprecation {
+    /// The unversioned `/api` alias, kept while clients move to `/api/v1`.
+    pub fn unversioned_alias() -> Self {
+        Self {
+            since: Utc.with_ymd_and_hms(2026, 10, 18, 0, 0, 0).unwrap(),
+            sunset: None,
+            successor: CURRENT_VERSION,
+        }
+    }
+
+    pub fn headers(&self) -> DefaultHeaders {
+        let headers = DefaultHeaders::new()
+            .add(("Deprecation", format!("@{}", self.since.timestamp())))
+            .add(("Link", format!("<{}>; rel=\"successor-version\"", self.successor)));
+        match self.sunset {
+            Some(sunset) => headers.add(("Sunset", sunset.format("%a, %d %b %Y %H:%M:%S GMT").to_string())),
+            None => headers,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, web, App, HttpResponse};
+
+    fn routes(cfg: &mut web::ServiceConfig) {
+        cfg.route("/rooms", web::get().to(HttpResponse::Ok));
+    }
+
+    #[actix_web::test]
+    async fn test_alias_serves_routes_with_deprecation_headers() {
+        let deprecation = Deprecation {
+            sunset: Some(Utc.with_ymd_and_hms(2027, 4, 1, 0, 0, 0).unwrap()),
+            ..Deprecation::unversioned_alias()
+        };
+        let app = test::init_service(
+            App::new()
+                .service(web::scope(CURRENT_VERSION).configure(routes))
+                .service(web::scope("/api").wrap(deprecation.headers()).configure(routes)),
+        )
+        .await;
+
+        let current = test::call_service(&app, test::TestRequest::get().uri("/api/v1/rooms").to_request()).await;
+        assert!(current.status().is_success());
+        assert!(current.headers().get("Deprecation").is_none());
+
+        let alias = test::call_service(&app, test::TestRequest::get().uri("/api/rooms").to_request()).await;
+        assert!(alias.status().is_success());
+        assert_eq!(alias.headers().get("Deprecation").unwrap(), format!("@{}", deprecation.since.timestamp()).as_str());
+        assert_eq!(alias.headers().get("Sunset").unwrap(), "Thu, 01 Apr 2027 00:00:00 GMT");
+        assert_eq!(alias.headers().get("Link").unwrap(), "</api/v1>; rel=\"successor-version\"");
+    }
+}
@@ -0,0 +1,189 @@
+use std::sync::Arc;
+
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::domain::webhook::commands::WebhookCommand;
+use crate::services::ChatRoomViewRepository;
+use crate::web::auth::AuthenticatedUser;
+use crate::web::errors::{ApiError, Problem};
+use crate::web::responses::{DeliveryResponse, WebhookResponse};
+use crate::webhooks::{WebhookDeliveryLog, WebhookRegistry, WebhookSubscription};
+use crate::WebhookFramework;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub(crate) struct RegisterWebhookRequest {
+    url: String,
+    /// Event types to deliver, such as `MessageSent`; omit or leave empty for every event.
+    #[serde(default)]
+    event_types: Vec<String>,
+    /// Key for the HMAC-SHA256 signature sent in `X-Webhook-Signature`.
+    secret: String,
+}
+
+/// Only the room's owner may manage its webhooks.
+pub(super) async fn owned_room(
+    room_views: &ChatRoomViewRepository,
+    room_id: Uuid,
+    user_id: &str,
+) -> Result<(), ApiError> {
+    let room = room_views
+        .get_room(&room_id)
+        .await
+        .ok_or_else(|| ApiError::not_found("room_not_found", format!("Room with ID {} not found", room_id)))?;
+    if room.created_by != user_id {
+        return Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            "permission_denied",
+            format!("User {} is not the owner of room {}", user_id, room_id),
+        ));
+    }
+    Ok(())
+}
+
+async fn room_webhook(registry: &WebhookRegistry, room_id: Uuid, webhook_id: Uuid) -> Result<WebhookSubscription, ApiError> {
+    registry
+        .get(&webhook_id)
+        .await
+        .filter(|hook| hook.room_id == room_id)
+        .ok_or_else(|| ApiError::not_found("webhook_not_found", format!("Webhook with ID {} not found", webhook_id)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/rooms/{room_id}/webhooks",
+    tag = "webhooks",
+    params(("room_id" = Uuid, Path, description = "Room id")),
+    responses(
+        (status = 200, description = "The room's webhooks", body = [WebhookResponse]),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller is not the room's owner", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Room not found", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn get_webhooks(
+    user: AuthenticatedUser,
+    room_id: web::Path<Uuid>,
+    room_views: web::Data<Arc<ChatRoomViewRepository>>,
+    registry: web::Data<Arc<WebhookRegistry>>,
+) -> Result<HttpResponse, ApiError> {
+    let room_id = room_id.into_inner();
+    owned_room(&room_views, room_id, &user.user_id).await?;
+
+    let hooks: Vec<WebhookResponse> = registry.for_room(&room_id).await.into_iter().map(Into::into).collect();
+    Ok(HttpResponse::Ok().json(hooks))
+}
+
+#[utoipa::path(
+    post,
+    path = "/rooms/{room_id}/webhooks",
+    tag = "webhooks",
+    params(("room_id" = Uuid, Path, description = "Room id")),
+    request_body = RegisterWebhookRequest,
+    responses(
+        (status = 201, description = "Id of the new webhook", body = Uuid),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller is not the room's owner", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Room not found", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid URL, event type or secret", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limit exceeded; see Retry-After", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn register_webhook(
+    user: AuthenticatedUser,
+    room_id: web::Path<Uuid>,
+    req: web::Json<RegisterWebhookRequest>,
+    room_views: web::Data<Arc<ChatRoomViewRepository>>,
+    framework: web::Data<Arc<WebhookFramework>>,
+) -> Result<HttpResponse, ApiError> {
+    let room_id = room_id.into_inner();
+    owned_room(&room_views, room_id, &user.user_id).await?;
+
+    let req = req.into_inner();
+    let webhook_id = Uuid::new_v4();
+    let command = WebhookCommand::RegisterWebhook {
+        webhook_id,
+        room_id,
+        registered_by: user.user_id,
+        url: req.url,
+        event_types: req.event_types,
+        secret: req.secret,
+    };
+
+    framework.execute(&webhook_id.to_string(), command).await?;
+
+    Ok(HttpResponse::Created().json(webhook_id))
+}
+
+#[utoipa::path(
+    post,
+    path = "/rooms/{room_id}/webhooks/{webhook_id}/remove",
+    tag = "webhooks",
+    params(
+        ("room_id" = Uuid, Path, description = "Room id"),
+        ("webhook_id" = Uuid, Path, description = "Webhook id"),
+    ),
+    responses(
+        (status = 200, description = "Webhook removed", body = String, content_type = "text/plain"),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller is not the room's owner", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Room or webhook not found", body = Problem, content_type = "application/problem+json"),
+        (status = 429, description = "Rate limit exceeded; see Retry-After", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn remove_webhook(
+    user: AuthenticatedUser,
+    path: web::Path<(Uuid, Uuid)>,
+    room_views: web::Data<Arc<ChatRoomViewRepository>>,
+    registry: web::Data<Arc<WebhookRegistry>>,
+    framework: web::Data<Arc<WebhookFramework>>,
+) -> Result<HttpResponse, ApiError> {
+    let (room_id, webhook_id) = path.into_inner();
+    owned_room(&room_views, room_id, &user.user_id).await?;
+    room_webhook(&registry, room_id, webhook_id).await?;
+
+    let command = WebhookCommand::RemoveWebhook {
+        user_id: user.user_id,
+    };
+
+    framework.execute(&webhook_id.to_string(), command).await?;
+
+    Ok(HttpResponse::Ok().body("Webhook removed successfully"))
+}
+
+#[utoipa::path(
+    get,
+    path = "/rooms/{room_id}/webhooks/{webhook_id}/deliveries",
+    tag = "webhooks",
+    params(
+        ("room_id" = Uuid, Path, description = "Room id"),
+        ("webhook_id" = Uuid, Path, description = "Webhook id"),
+    ),
+    responses(
+        (status = 200, description = "Recent deliveries, newest first, including dead letters", body = [DeliveryResponse]),
+        (status = 401, description = "Missing or invalid bearer token", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Caller is not the room's owner", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "Room or webhook not found", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer_auth" = [])),
+)]
+pub(crate) async fn get_deliveries(
+    user: AuthenticatedUser,
+    path: web::Path<(Uuid, Uuid)>,
+    room_views: web::Data<Arc<ChatRoomViewRepository>>,
+    registry: web::Data<Arc<WebhookRegistry>>,
+    deliveries: web::Data<Arc<WebhookDeliveryLog>>,
+) -> Result<HttpResponse, ApiError> {
+    let (room_id, webhook_id) = path.into_inner();
+    owned_room(&room_views, room_id, &user.user_id).await?;
+    room_webhook(&registry, room_id, webhook_id).await?;
+
+    let records: Vec<DeliveryResponse> = deliveries.for_webhook(&webhook_id).into_iter().map(Into::into).collect();
+    Ok(HttpResponse::Ok().json(records))
+}
@@ -0,0 +1,787 @@
+use async_trait::async_trait;
+use cqrs_es::{DomainEvent, EventEnvelope, Query};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, RwLock};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::domain::aggregate::{incoming_webhook_user_id, ChatRoom};
+use crate::domain::events::ChatEvent;
+use crate::domain::validation::is_public_address;
+use crate::domain::webhook::aggregate::Webhook;
+use crate::domain::webhook::events::WebhookEvent;
+
+/// `t=<unix timestamp>,v1=<hex HMAC-SHA256 of "<timestamp>.<body>">`, keyed with the webhook's secret.
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+pub const EVENT_HEADER: &str = "X-Webhook-Event";
+pub const DELIVERY_HEADER: &str = "X-Webhook-Delivery";
+
+/// How long a receiver has to answer before the attempt counts as failed.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Deliveries kept per webhook for the delivery log; older ones are dropped.
+const DELIVERY_LOG_SIZE: usize = 100;
+
+/// Signs a delivery body the way receivers are expected to verify it.
+pub fn signature(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(format!("{}.{}", timestamp, body).as_bytes());
+    format!("t={},v1={}", timestamp, hex(&mac.finalize().into_bytes()))
+}
+
+/// Incoming webhook tokens are stored and looked up by this hash only.
+pub fn token_hash(token: &str) -> String {
+    hex(&Sha256::digest(token.as_bytes()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct WebhookSubscription {
+    pub webhook_id: Uuid,
+    pub room_id: Uuid,
+    pub registered_by: String,
+    pub url: String,
+    pub event_types: Vec<String>,
+    pub secret: String,
+    pub registered_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl WebhookSubscription {
+    fn accepts(&self, event_type: &str) -> bool {
+        self.event_types.is_empty() || self.event_types.iter().any(|t| t == event_type)
+    }
+}
+
+/// Active webhook subscriptions, projected from `Webhook` events.
+#[derive(Clone, Default)]
+pub struct WebhookRegistry {
+    subscriptions: Arc<RwLock<HashMap<Uuid, WebhookSubscription>>>,
+}
+
+impl WebhookRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn get(&self, webhook_id: &Uuid) -> Option<WebhookSubscription> {
+        self.subscriptions.read().await.get(webhook_id).cloned()
+    }
+
+    pub async fn for_room(&self, room_id: &Uuid) -> Vec<WebhookSubscription> {
+        let subscriptions = self.subscriptions.read().await;
+        let mut hooks: Vec<WebhookSubscription> =
+            subscriptions.values().filter(|hook| &hook.room_id == room_id).cloned().collect();
+        hooks.sort_by_key(|hook| hook.registered_at);
+        hooks
+    }
+}
+
+#[async_trait]
+impl Query<Webhook> for WebhookRegistry {
+    async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<Webhook>]) {
+        let mut subscriptions = self.subscriptions.write().await;
+        for event_envelope in events {
+            match &event_envelope.payload {
+                WebhookEvent::WebhookRegistered { webhook_id, room_id, registered_by, url, event_types, secret, timestamp } => {
+                    subscriptions.insert(
+                        *webhook_id,
+                        WebhookSubscription {
+                            webhook_id: *webhook_id,
+                            room_id: *room_id,
+                            registered_by: registered_by.clone(),
+                            url: url.clone(),
+                            event_types: event_types.clone(),
+                            secret: secret.clone(),
+                            registered_at: *timestamp,
+                        },
+                    );
+                }
+
+                WebhookEvent::WebhookRemoved { .. } => {
+                    if let Ok(webhook_id) = Uuid::parse_str(aggregate_id) {
+                        subscriptions.remove(&webhook_id);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IncomingWebhook {
+    pub hook_id: Uuid,
+    pub room_id: Uuid,
+    pub name: String,
+    /// The participant the webhook posts as.
+    pub user_id: String,
+    pub created_by: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Active incoming webhooks by token hash, projected from room events.
+#[derive(Clone, Default)]
+pub struct IncomingWebhookRegistry {
+    hooks: Arc<RwLock<HashMap<String, IncomingWebhook>>>,
+}
+
+impl IncomingWebhookRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn find_by_token(&self, token: &str) -> Option<IncomingWebhook> {
+        self.hooks.read().await.get(&token_hash(token)).cloned()
+    }
+
+    pub async fn for_room(&self, room_id: &Uuid) -> Vec<IncomingWebhook> {
+        let hooks = self.hooks.read().await;
+        let mut hooks: Vec<IncomingWebhook> = hooks.values().filter(|hook| &hook.room_id == room_id).cloned().collect();
+        hooks.sort_by_key(|hook| hook.created_at);
+        hooks
+    }
+}
+
+#[async_trait]
+impl Query<ChatRoom> for IncomingWebhookRegistry {
+    async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<ChatRoom>]) {
+        let Ok(room_id) = Uuid::parse_str(aggregate_id) else {
+            log::error!("Room aggregate id {} is not a UUID", aggregate_id);
+            return;
+        };
+        let mut hooks = self.hooks.write().await;
+        for event_envelope in events {
+            match &event_envelope.payload {
+                ChatEvent::IncomingWebhookCreated { user_id, hook_id, name, token_hash, timestamp } => {
+                    hooks.insert(
+                        token_hash.clone(),
+                        IncomingWebhook {
+                            hook_id: *hook_id,
+                            room_id,
+                            name: name.clone(),
+                            user_id: incoming_webhook_user_id(hook_id),
+                            created_by: user_id.clone(),
+                            created_at: *timestamp,
+                        },
+                    );
+                }
+
+                ChatEvent::IncomingWebhookRevoked { hook_id, .. } => {
+                    hooks.retain(|_, hook| &hook.hook_id != hook_id);
+                }
+
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Failed deliveries are retried with exponential backoff, starting at
+/// `initial_backoff` and doubling up to `max_backoff`, until `max_attempts`
+/// have been made; then the delivery is dead-lettered.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 6,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(300),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The wait after the given failed attempt, counting from 1.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+}
+
+/// Where deliveries may be sent. A receiver's host is resolved before every attempt and
+/// the request goes to the address that was checked, so a name cannot be re-pointed at
+/// this server or its private network in between.
+#[derive(Debug, Clone, Default)]
+pub struct WebhookTargets {
+    /// Hosts exempt from the public address check, for receivers on the private network.
+    allowed_hosts: Vec<String>,
+}
+
+impl WebhookTargets {
+    pub fn new(allowed_hosts: Vec<String>) -> Self {
+        Self { allowed_hosts }
+    }
+
+    /// Reads `WEBHOOK_ALLOWED_HOSTS`, a comma-separated list of host names.
+    pub fn from_env() -> Self {
+        let hosts = std::env::var("WEBHOOK_ALLOWED_HOSTS").unwrap_or_default();
+        Self::new(
+            hosts
+                .split(',')
+                .map(|host| host.trim().to_ascii_lowercase())
+                .filter(|host| !host.is_empty())
+                .collect(),
+        )
+    }
+
+    /// The address to deliver to, or why the URL may not be delivered to.
+    async fn resolve(&self, url: &str) -> Result<SocketAddr, String> {
+        let uri: awc::http::Uri = url.parse().map_err(|e| format!("Invalid URL {}: {}", url, e))?;
+        let host = uri
+            .host()
+            .ok_or_else(|| format!("URL {} has no host", url))?
+            .trim_start_matches('[')
+            .trim_end_matches(']');
+        let port = uri
+            .port_u16()
+            .unwrap_or(if uri.scheme_str() == Some("https") { 443 } else { 80 });
+
+        let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|e| format!("Could not resolve {}: {}", host, e))?
+            .collect();
+        if !self.allowed_hosts.iter().any(|allowed| allowed.eq_ignore_ascii_case(host)) {
+            if let Some(address) = addresses.iter().find(|address| !is_public_address(address.ip())) {
+                return Err(format!("{} resolves to {}, which is not a public address", host, address.ip()));
+            }
+        }
+        addresses
+            .into_iter()
+            .next()
+            .ok_or_else(|| format!("{} has no addresses", host))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Pending,
+    Retrying,
+    Delivered,
+    DeadLettered,
+    /// The message the delivery carried expired before it was delivered; it is not retried.
+    Expired,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeliveryRecord {
+    pub delivery_id: Uuid,
+    pub webhook_id: Uuid,
+    pub event_type: String,
+    pub sequence: usize,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    /// Status code of the last response, if the receiver answered at all.
+    pub response_status: Option<u16>,
+    pub last_error: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+    /// The undelivered body, kept once a delivery is dead-lettered.
+    pub payload: Option<serde_json::Value>,
+    /// The message whose content the delivery carries, if any.
+    pub message_id: Option<Uuid>,
+}
+
+/// The most recent deliveries of every webhook, including dead letters.
+#[derive(Clone, Default)]
+pub struct WebhookDeliveryLog {
+    records: Arc<Mutex<HashMap<Uuid, VecDeque<DeliveryRecord>>>>,
+}
+
+impl WebhookDeliveryLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Deliveries of a webhook, newest first.
+    pub fn for_webhook(&self, webhook_id: &Uuid) -> Vec<DeliveryRecord> {
+        let records = self.records.lock().unwrap();
+        records
+            .get(webhook_id)
+            .map(|log| log.iter().rev().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    fn record(&self, record: DeliveryRecord) {
+        let mut records = self.records.lock().unwrap();
+        let log = records.entry(record.webhook_id).or_default();
+        if log.len() == DELIVERY_LOG_SIZE {
+            log.pop_front();
+        }
+        log.push_back(record);
+    }
+
+    /// Leaves no copy of an expired message: its dead letters lose their body, and its
+    /// deliveries still waiting to be sent are given up.
+    fn expire_message(&self, message_id: &Uuid) {
+        let mut records = self.records.lock().unwrap();
+        let expired = records
+            .values_mut()
+            .flat_map(|log| log.iter_mut())
+            .filter(|record| record.message_id.as_ref() == Some(message_id));
+        for record in expired {
+            record.payload = None;
+            if matches!(record.status, DeliveryStatus::Pending | DeliveryStatus::Retrying) {
+                record.status = DeliveryStatus::Expired;
+                record.updated_at = chrono::Utc::now();
+            }
+        }
+    }
+
+    fn is_expired(&self, delivery: &Delivery) -> bool {
+        let records = self.records.lock().unwrap();
+        records
+            .get(&delivery.webhook_id)
+            .and_then(|log| log.iter().find(|record| record.delivery_id == delivery.delivery_id))
+            .is_some_and(|record| record.status == DeliveryStatus::Expired)
+    }
+
+    /// An expired delivery's record is final.
+    fn update(&self, delivery: &Delivery, update: impl FnOnce(&mut DeliveryRecord)) {
+        let mut records = self.records.lock().unwrap();
+        let record = records
+            .get_mut(&delivery.webhook_id)
+            .and_then(|log| log.iter_mut().find(|record| record.delivery_id == delivery.delivery_id))
+            .filter(|record| record.status != DeliveryStatus::Expired);
+        if let Some(record) = record {
+            update(record);
+            record.updated_at = chrono::Utc::now();
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct DeliveryPayload<'a> {
+    delivery_id: Uuid,
+    webhook_id: Uuid,
+    room_id: Uuid,
+    sequence: usize,
+    event_type: &'a str,
+    event: &'a ChatEvent,
+}
+
+struct Delivery {
+    delivery_id: Uuid,
+    webhook_id: Uuid,
+    url: String,
+    secret: String,
+    event_type: String,
+    body: String,
+}
+
+/// Turns committed room events into deliveries for every matching subscription
+/// and hands them to a background sender, so commands never wait on receivers.
+pub struct WebhookDispatcher {
+    registry: WebhookRegistry,
+    log: WebhookDeliveryLog,
+    deliveries: mpsc::UnboundedSender<Delivery>,
+}
+
+impl WebhookDispatcher {
+    /// Starts the sender on a thread of its own; it stops once the dispatcher is dropped.
+    pub fn new(registry: WebhookRegistry, log: WebhookDeliveryLog, policy: RetryPolicy, targets: WebhookTargets) -> Self {
+        let (deliveries, receiver) = mpsc::unbounded_channel();
+        let sender_log = log.clone();
+        std::thread::Builder::new()
+            .name("webhook-sender".to_string())
+            .spawn(move || actix_rt::System::new().block_on(send_deliveries(receiver, sender_log, policy, targets)))
+            .expect("failed to start the webhook sender");
+        Self {
+            registry,
+            log,
+            deliveries,
+        }
+    }
+}
+
+#[async_trait]
+impl Query<ChatRoom> for WebhookDispatcher {
+    async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<ChatRoom>]) {
+        let Ok(room_id) = Uuid::parse_str(aggregate_id) else {
+            log::error!("Room aggregate id {} is not a UUID", aggregate_id);
+            return;
+        };
+        for event_envelope in events {
+            if let ChatEvent::MessageExpired { message_id, .. } = &event_envelope.payload {
+                self.log.expire_message(message_id);
+            }
+        }
+
+        let hooks = self.registry.for_room(&room_id).await;
+        if hooks.is_empty() {
+            return;
+        }
+
+        for event_envelope in events.iter().filter(|envelope| !envelope.payload.is_private()) {
+            let event_type = event_envelope.payload.event_type();
+            let message_id = match &event_envelope.payload {
+                ChatEvent::MessageSent { message_id, .. } => Some(*message_id),
+                _ => None,
+            };
+            for hook in hooks.iter().filter(|hook| hook.accepts(&event_type)) {
+                let delivery_id = Uuid::new_v4();
+                let payload = DeliveryPayload {
+                    delivery_id,
+                    webhook_id: hook.webhook_id,
+                    room_id,
+                    sequence: event_envelope.sequence,
+                    event_type: &event_type,
+                    event: &event_envelope.payload,
+                };
+                let body = match serde_json::to_string(&payload) {
+                    Ok(body) => body,
+                    Err(e) => {
+                        log::error!("Could not serialize webhook delivery: {}", e);
+                        continue;
+                    }
+                };
+
+                let now = chrono::Utc::now();
+                self.log.record(DeliveryRecord {
+                    delivery_id,
+                    webhook_id: hook.webhook_id,
+                    event_type: event_type.clone(),
+                    sequence: event_envelope.sequence,
+                    status: DeliveryStatus::Pending,
+                    attempts: 0,
+                    response_status: None,
+                    last_error: None,
+                    created_at: now,
+                    updated_at: now,
+                    payload: None,
+                    message_id,
+                });
+                let delivery = Delivery {
+                    delivery_id,
+                    webhook_id: hook.webhook_id,
+                    url: hook.url.clone(),
+                    secret: hook.secret.clone(),
+                    event_type: event_type.clone(),
+                    body,
+                };
+                if self.deliveries.send(delivery).is_err() {
+                    log::error!("Webhook sender has stopped, dropping delivery {}", delivery_id);
+                }
+            }
+        }
+    }
+}
+
+/// Runs on the sender thread; each delivery retries independently so one slow
+/// receiver does not hold up the others.
+async fn send_deliveries(
+    mut receiver: mpsc::UnboundedReceiver<Delivery>,
+    log: WebhookDeliveryLog,
+    policy: RetryPolicy,
+    targets: WebhookTargets,
+) {
+    // Redirects are not followed: their targets would skip the address check.
+    let client = awc::Client::builder().timeout(REQUEST_TIMEOUT).disable_redirects().finish();
+    let targets = Arc::new(targets);
+    while let Some(delivery) = receiver.recv().await {
+        actix_rt::spawn(deliver(client.clone(), delivery, log.clone(), policy, targets.clone()));
+    }
+}
+
+async fn deliver(
+    client: awc::Client,
+    delivery: Delivery,
+    log: WebhookDeliveryLog,
+    policy: RetryPolicy,
+    targets: Arc<WebhookTargets>,
+) {
+    for attempt in 1..=policy.max_attempts {
+        if log.is_expired(&delivery) {
+            return;
+        }
+        let timestamp = chrono::Utc::now().timestamp();
+        let result = match targets.resolve(&delivery.url).await {
+            Ok(address) => client
+                .post(&delivery.url)
+                .address(address)
+                .insert_header(("Content-Type", "application/json"))
+                .insert_header((SIGNATURE_HEADER, signature(&delivery.secret, timestamp, &delivery.body)))
+                .insert_header((EVENT_HEADER, delivery.event_type.as_str()))
+                .insert_header((DELIVERY_HEADER, delivery.delivery_id.to_string()))
+                .send_body(delivery.body.clone())
+                .await
+                .map_err(|e| e.to_string()),
+            Err(error) => Err(error),
+        };
+
+        let (response_status, error) = match result {
+            Ok(response) if response.status().is_success() => {
+                log.update(&delivery, |record| {
+                    record.status = DeliveryStatus::Delivered;
+                    record.attempts = attempt;
+                    record.response_status = Some(response.status().as_u16());
+                    record.last_error = None;
+                });
+                return;
+            }
+            Ok(response) => (
+                Some(response.status().as_u16()),
+                format!("Receiver responded with {}", response.status()),
+            ),
+            Err(error) => (None, error),
+        };
+
+        let dead = attempt == policy.max_attempts;
+        log.update(&delivery, |record| {
+            record.attempts = attempt;
+            record.response_status = response_status;
+            record.last_error = Some(error.clone());
+            record.status = if dead { DeliveryStatus::DeadLettered } else { DeliveryStatus::Retrying };
+            if dead {
+                record.payload = serde_json::from_str(&delivery.body).ok();
+            }
+        });
+        if dead {
+            log::warn!(
+                "Dead-lettered webhook delivery {} to {} after {} attempts: {}",
+                delivery.delivery_id, delivery.url, attempt, error
+            );
+            return;
+        }
+        actix_rt::time::sleep(policy.backoff(attempt)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const SECRET: &str = "0123456789abcdef";
+
+    /// An in-process receiver that fails the first `failures` requests and records every one.
+    #[derive(Clone, Default)]
+    struct Receiver {
+        failures: Arc<AtomicUsize>,
+        requests: Arc<Mutex<Vec<(String, String)>>>,
+    }
+
+    async fn receive(req: HttpRequest, body: String, receiver: web::Data<Receiver>) -> HttpResponse {
+        let signature = req.headers().get(SIGNATURE_HEADER).unwrap().to_str().unwrap().to_string();
+        receiver.requests.lock().unwrap().push((signature, body));
+        let remaining = receiver.failures.load(Ordering::SeqCst);
+        if remaining > 0 {
+            receiver.failures.store(remaining - 1, Ordering::SeqCst);
+            HttpResponse::InternalServerError().finish()
+        } else {
+            HttpResponse::NoContent().finish()
+        }
+    }
+
+    fn start_receiver(failures: usize) -> (Receiver, String) {
+        let receiver = Receiver::default();
+        receiver.failures.store(failures, Ordering::SeqCst);
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let data = web::Data::new(receiver.clone());
+        let server = HttpServer::new(move || App::new().app_data(data.clone()).route("/hook", web::post().to(receive)))
+            .workers(1)
+            .listen(listener)
+            .unwrap()
+            .run();
+        actix_rt::spawn(server);
+        (receiver, url)
+    }
+
+    /// The test receivers listen on the loopback address, which has to be allowed.
+    fn loopback_allowed() -> WebhookTargets {
+        WebhookTargets::new(vec!["127.0.0.1".to_string()])
+    }
+
+    async fn dispatcher(
+        url: &str,
+        event_types: &[&str],
+        policy: RetryPolicy,
+        targets: WebhookTargets,
+    ) -> (WebhookDispatcher, WebhookDeliveryLog, Uuid, Uuid) {
+        let registry = WebhookRegistry::new();
+        let log = WebhookDeliveryLog::new();
+        let (webhook_id, room_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let registered = EventEnvelope::<Webhook> {
+            aggregate_id: webhook_id.to_string(),
+            sequence: 1,
+            payload: WebhookEvent::WebhookRegistered {
+                webhook_id,
+                room_id,
+                registered_by: "user1".to_string(),
+                url: url.to_string(),
+                event_types: event_types.iter().map(|t| t.to_string()).collect(),
+                secret: SECRET.to_string(),
+                timestamp: chrono::Utc::now(),
+            },
+            metadata: Default::default(),
+        };
+        registry.dispatch(&webhook_id.to_string(), &[registered]).await;
+        (WebhookDispatcher::new(registry, log.clone(), policy, targets), log, webhook_id, room_id)
+    }
+
+    fn room_events(room_id: Uuid) -> Vec<EventEnvelope<ChatRoom>> {
+        let joined = ChatEvent::UserJoined {
+            user_id: "user2".to_string(),
+            username: "User Two".to_string(),
+            timestamp: chrono::Utc::now(),
+        };
+        let sent = ChatEvent::MessageSent {
+            message_id: Uuid::new_v4(),
+            user_id: "user2".to_string(),
+            content: "Hello".to_string(),
+            timestamp: chrono::Utc::now(),
+            expires_at: None,
+            attachments: Vec::new(),
+        };
+        [joined, sent]
+            .into_iter()
+            .enumerate()
+            .map(|(i, payload)| EventEnvelope {
+                aggregate_id: room_id.to_string(),
+                sequence: i + 2,
+                payload,
+                metadata: Default::default(),
+            })
+            .collect()
+    }
+
+    async fn wait_for(log: &WebhookDeliveryLog, webhook_id: &Uuid, status: DeliveryStatus) -> DeliveryRecord {
+        for _ in 0..200 {
+            if let Some(record) = log.for_webhook(webhook_id).into_iter().find(|record| record.status == status) {
+                return record;
+            }
+            actix_rt::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("no delivery reached {:?}: {:?}", status, log.for_webhook(webhook_id));
+    }
+
+    fn fast_retries(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(50),
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_matching_events_are_signed_and_retried() {
+        let (receiver, url) = start_receiver(1);
+        let (dispatcher, log, webhook_id, room_id) = dispatcher(&url, &["MessageSent"], fast_retries(3), loopback_allowed()).await;
+
+        dispatcher.dispatch(&room_id.to_string(), &room_events(room_id)).await;
+
+        let record = wait_for(&log, &webhook_id, DeliveryStatus::Delivered).await;
+        assert_eq!(record.event_type, "MessageSent");
+        assert_eq!(record.attempts, 2);
+        assert_eq!(record.response_status, Some(204));
+        assert_eq!(log.for_webhook(&webhook_id).len(), 1, "UserJoined is filtered out");
+
+        let requests = receiver.requests.lock().unwrap().clone();
+        assert_eq!(requests.len(), 2);
+        let (header, body) = &requests[1];
+        let timestamp = header.strip_prefix("t=").unwrap().split(',').next().unwrap().parse().unwrap();
+        assert_eq!(header, &signature(SECRET, timestamp, body));
+        let payload: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(payload["event"]["MessageSent"]["content"], "Hello");
+    }
+
+    #[actix_web::test]
+    async fn test_failing_deliveries_are_dead_lettered() {
+        let (receiver, url) = start_receiver(usize::MAX);
+        let (dispatcher, log, webhook_id, room_id) = dispatcher(&url, &[], fast_retries(3), loopback_allowed()).await;
+
+        dispatcher.dispatch(&room_id.to_string(), &room_events(room_id)[1..]).await;
+
+        let record = wait_for(&log, &webhook_id, DeliveryStatus::DeadLettered).await;
+        assert_eq!(record.attempts, 3);
+        assert_eq!(record.response_status, Some(500));
+        assert_eq!(record.payload.unwrap()["event_type"], "MessageSent");
+        assert_eq!(receiver.requests.lock().unwrap().len(), 3);
+    }
+
+    fn expiry(room_id: Uuid, events: &[EventEnvelope<ChatRoom>]) -> Vec<EventEnvelope<ChatRoom>> {
+        let message_id = events
+            .iter()
+            .find_map(|envelope| match &envelope.payload {
+                ChatEvent::MessageSent { message_id, .. } => Some(*message_id),
+                _ => None,
+            })
+            .unwrap();
+        vec![EventEnvelope {
+            aggregate_id: room_id.to_string(),
+            sequence: 10,
+            payload: ChatEvent::MessageExpired {
+                message_id,
+                attachment_ids: Vec::new(),
+                timestamp: chrono::Utc::now(),
+            },
+            metadata: Default::default(),
+        }]
+    }
+
+    #[actix_web::test]
+    async fn test_expired_messages_leave_no_copies_in_the_log() {
+        let (_, url) = start_receiver(usize::MAX);
+        let (dead_lettering, log, webhook_id, room_id) =
+            dispatcher(&url, &["MessageSent"], fast_retries(2), loopback_allowed()).await;
+        let events = room_events(room_id);
+        dead_lettering.dispatch(&room_id.to_string(), &events).await;
+        assert!(wait_for(&log, &webhook_id, DeliveryStatus::DeadLettered).await.payload.is_some());
+
+        dead_lettering.dispatch(&room_id.to_string(), &expiry(room_id, &events)).await;
+        let record = log.for_webhook(&webhook_id).remove(0);
+        assert_eq!(record.status, DeliveryStatus::DeadLettered);
+        assert!(record.payload.is_none());
+
+        // A delivery still being retried is given up.
+        let (receiver, url) = start_receiver(usize::MAX);
+        let (retrying, log, webhook_id, room_id) =
+            dispatcher(&url, &["MessageSent"], fast_retries(100), loopback_allowed()).await;
+        let events = room_events(room_id);
+        retrying.dispatch(&room_id.to_string(), &events).await;
+        wait_for(&log, &webhook_id, DeliveryStatus::Retrying).await;
+
+        retrying.dispatch(&room_id.to_string(), &expiry(room_id, &events)).await;
+        wait_for(&log, &webhook_id, DeliveryStatus::Expired).await;
+        actix_rt::time::sleep(Duration::from_millis(100)).await;
+        let attempts = receiver.requests.lock().unwrap().len();
+        actix_rt::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(receiver.requests.lock().unwrap().len(), attempts, "no attempts after expiry");
+        assert_eq!(log.for_webhook(&webhook_id)[0].status, DeliveryStatus::Expired);
+    }
+
+    #[actix_web::test]
+    async fn test_private_addresses_are_not_delivered_to() {
+        let (receiver, url) = start_receiver(0);
+        let (dispatcher, log, webhook_id, room_id) = dispatcher(&url, &[], fast_retries(2), WebhookTargets::default()).await;
+
+        dispatcher.dispatch(&room_id.to_string(), &room_events(room_id)[1..]).await;
+
+        let record = wait_for(&log, &webhook_id, DeliveryStatus::DeadLettered).await;
+        assert!(record.last_error.unwrap().contains("not a public address"));
+        assert!(receiver.requests.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_cap() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.backoff(1), Duration::from_secs(1));
+        assert_eq!(policy.backoff(4), Duration::from_secs(8));
+        assert_eq!(policy.backoff(20), Duration::from_secs(300));
+    }
+}